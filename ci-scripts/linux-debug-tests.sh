@@ -29,6 +29,7 @@ popd
 pushd wrench
 cargo build ${CARGOFLAGS} --features env_logger
 OPTIMIZED=0 python script/headless.py reftest
+OPTIMIZED=0 python script/headless.py --sw-compositor reftest
 popd
 
 pushd examples
@@ -17,6 +17,7 @@ CARGOFLAGS=${CARGOFLAGS:-""}  # default to empty if not set
 
 pushd wrench
 python script/headless.py reftest
+python script/headless.py --sw-compositor reftest
 python script/headless.py rawtest
 cargo build ${CARGOFLAGS} --release
 popd
@@ -213,6 +213,8 @@ impl Example for App {
             image: image_mask_key,
             rect: (75, 75).by(100, 100),
             repeat: false,
+            mode: MaskMode::Alpha,
+            composite_op: MaskCompositeOperator::Intersect,
         };
         let complex = ComplexClipRegion::new(
             (50, 50).to(150, 150),
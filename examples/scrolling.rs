@@ -59,6 +59,7 @@ impl Example for App {
                 vec![],
                 None,
                 ScrollSensitivity::ScriptAndInputEvents,
+                OverscrollBehavior::Auto,
                 LayoutVector2D::zero(),
             );
 
@@ -93,6 +94,7 @@ impl Example for App {
                 vec![],
                 None,
                 ScrollSensitivity::ScriptAndInputEvents,
+                OverscrollBehavior::Auto,
                 LayoutVector2D::zero(),
             );
 
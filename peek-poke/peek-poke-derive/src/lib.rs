@@ -81,13 +81,55 @@ fn derive_max_size(s: &Structure) -> TokenStream {
     }
 }
 
+fn derive_layout_hash(s: &Structure) -> TokenStream {
+    // Structs hash their fields in order. Enums also hash their discriminant
+    // type and a marker for each variant, so that moving a field from one
+    // variant to the next changes the hash.
+    let is_struct = is_struct(s);
+    let init = if is_struct {
+        quote! {
+            let hash = peek_poke::hash_layout_str(peek_poke::LAYOUT_HASH_SEED, "struct");
+        }
+    } else {
+        let discriminant_size_type = get_discriminant_size_type(s.variants().len());
+        quote! {
+            let hash = peek_poke::hash_layout_str(peek_poke::LAYOUT_HASH_SEED, "enum");
+            let hash = peek_poke::hash_layout(hash, <#discriminant_size_type>::layout_hash());
+        }
+    };
+
+    let body = s.variants().iter().fold(init, |acc, vi| {
+        let marker = if is_struct {
+            quote!()
+        } else {
+            quote! {
+                let hash = peek_poke::hash_layout_str(hash, "variant");
+            }
+        };
+        vi.bindings().iter().fold(quote!(#acc #marker), |acc, bi| {
+            let ty = &bi.ast().ty;
+            quote! {
+                #acc
+                let hash = peek_poke::hash_layout(hash, <#ty>::layout_hash());
+            }
+        })
+    });
+
+    quote! {
+        fn layout_hash() -> u64 {
+            #body
+            hash
+        }
+    }
+}
+
 fn derive_peek_from_for_enum(s: &mut Structure) -> TokenStream {
     assert!(!is_struct(s));
     s.bind_with(|_| BindStyle::Move);
 
     let num_variants = s.variants().len();
     let discriminant_size_type = get_discriminant_size_type(num_variants);
-    let body = s
+    let variant_arms = |fallible: bool| s
         .variants()
         .iter()
         .enumerate()
@@ -100,25 +142,39 @@ fn derive_peek_from_for_enum(s: &mut Structure) -> TokenStream {
 
             let variant_pat = Index::from(i);
             let poke_exprs = bindings.iter().fold(quote!(), |acc, bi| {
-                quote! {
-                    #acc
-                    let (#bi, bytes) = peek_poke::peek_from_default(bytes);
+                if fallible {
+                    quote! {
+                        #acc
+                        let (#bi, bytes) = peek_poke::try_peek_from_default(bytes)?;
+                    }
+                } else {
+                    quote! {
+                        #acc
+                        let (#bi, bytes) = peek_poke::peek_from_default(bytes);
+                    }
                 }
             });
             let construct = vi.construct(|_, i| {
                 let bi = &bindings[i];
                 quote!(#bi)
             });
+            let result = if fallible {
+                quote!(Some(bytes))
+            } else {
+                quote!(bytes)
+            };
 
             quote! {
                 #acc
                 #variant_pat => {
                     #poke_exprs
                     *output = #construct;
-                    bytes
+                    #result
                 }
             }
         });
+    let body = variant_arms(false);
+    let try_body = variant_arms(true);
 
     let type_name = s.ast().ident.to_string();
     let max_tag_value = num_variants - 1;
@@ -135,6 +191,14 @@ fn derive_peek_from_for_enum(s: &mut Structure) -> TokenStream {
                 }
             }
         }
+
+        unsafe fn try_peek_from(bytes: *const u8, output: *mut Self) -> Option<*const u8> {
+            let (variant, bytes) = peek_poke::peek_from_default::<#discriminant_size_type>(bytes);
+            match variant {
+                #try_body
+                _ => None,
+            }
+        }
     }
 }
 
@@ -150,19 +214,31 @@ fn derive_peek_from_for_struct(s: &mut Structure) -> TokenStream {
             let bytes = <#ty>::peek_from(bytes, #bi);
         }
     });
-
-    let body = quote! {
-        #pat => {
-            #peek_exprs
-            bytes
+    let try_peek_exprs = s.variants()[0].bindings().iter().fold(quote!(), |acc, bi| {
+        let ty = &bi.ast().ty;
+        quote! {
+            #acc
+            let bytes = <#ty>::try_peek_from(bytes, #bi)?;
         }
-    };
+    });
 
     quote! {
         #[inline(always)]
         unsafe fn peek_from(bytes: *const u8, output: *mut Self) -> *const u8 {
             match &mut (*output) {
-                #body
+                #pat => {
+                    #peek_exprs
+                    bytes
+                }
+            }
+        }
+
+        unsafe fn try_peek_from(bytes: *const u8, output: *mut Self) -> Option<*const u8> {
+            match &mut (*output) {
+                #pat => {
+                    #try_peek_exprs
+                    Some(bytes)
+                }
             }
         }
     }
@@ -216,6 +292,7 @@ fn peek_poke_derive(mut s: Structure) -> TokenStream {
 
     let max_size_fn = derive_max_size(&s);
     let poke_into_fn = derive_poke_into(&s);
+    let layout_hash_fn = derive_layout_hash(&s);
     let peek_from_fn = if is_struct(&s) {
         derive_peek_from_for_struct(&mut s)
     } else {
@@ -228,6 +305,7 @@ fn peek_poke_derive(mut s: Structure) -> TokenStream {
         gen unsafe impl peek_poke::Poke for @Self {
             #max_size_fn
             #poke_into_fn
+            #layout_hash_fn
         }
     });
 
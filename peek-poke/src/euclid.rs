@@ -8,8 +8,8 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use crate::{Peek, Poke};
-use euclid::{Point2D, Rect, SideOffsets2D, Size2D, Transform3D, Vector2D};
+use crate::{hash_layout, hash_layout_str, Peek, Poke, LAYOUT_HASH_SEED};
+use euclid::{Point2D, Point3D, Rect, SideOffsets2D, Size2D, Transform3D, Vector2D};
 
 unsafe impl<T: Poke, U> Poke for Point2D<T, U> {
     #[inline(always)]
@@ -22,6 +22,9 @@ unsafe impl<T: Poke, U> Poke for Point2D<T, U> {
         let bytes = self.y.poke_into(bytes);
         bytes
     }
+    fn layout_hash() -> u64 {
+        hash_layout(hash_layout_str(LAYOUT_HASH_SEED, "Point2D"), T::layout_hash())
+    }
 }
 impl<T: Peek, U> Peek for Point2D<T, U> {
     #[inline(always)]
@@ -30,6 +33,45 @@ impl<T: Peek, U> Peek for Point2D<T, U> {
         let bytes = T::peek_from(bytes, &mut (*output).y);
         bytes
     }
+    #[inline(always)]
+    unsafe fn try_peek_from(bytes: *const u8, output: *mut Self) -> Option<*const u8> {
+        let bytes = T::try_peek_from(bytes, &mut (*output).x)?;
+        let bytes = T::try_peek_from(bytes, &mut (*output).y)?;
+        Some(bytes)
+    }
+}
+
+unsafe impl<T: Poke, U> Poke for Point3D<T, U> {
+    #[inline(always)]
+    fn max_size() -> usize {
+        3 * T::max_size()
+    }
+    #[inline(always)]
+    unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
+        let bytes = self.x.poke_into(bytes);
+        let bytes = self.y.poke_into(bytes);
+        let bytes = self.z.poke_into(bytes);
+        bytes
+    }
+    fn layout_hash() -> u64 {
+        hash_layout(hash_layout_str(LAYOUT_HASH_SEED, "Point3D"), T::layout_hash())
+    }
+}
+impl<T: Peek, U> Peek for Point3D<T, U> {
+    #[inline(always)]
+    unsafe fn peek_from(bytes: *const u8, output: *mut Self) -> *const u8 {
+        let bytes = T::peek_from(bytes, &mut (*output).x);
+        let bytes = T::peek_from(bytes, &mut (*output).y);
+        let bytes = T::peek_from(bytes, &mut (*output).z);
+        bytes
+    }
+    #[inline(always)]
+    unsafe fn try_peek_from(bytes: *const u8, output: *mut Self) -> Option<*const u8> {
+        let bytes = T::try_peek_from(bytes, &mut (*output).x)?;
+        let bytes = T::try_peek_from(bytes, &mut (*output).y)?;
+        let bytes = T::try_peek_from(bytes, &mut (*output).z)?;
+        Some(bytes)
+    }
 }
 
 unsafe impl<T: Poke, U> Poke for Rect<T, U> {
@@ -43,6 +85,12 @@ unsafe impl<T: Poke, U> Poke for Rect<T, U> {
         let bytes = self.size.poke_into(bytes);
         bytes
     }
+    fn layout_hash() -> u64 {
+        hash_layout(
+            hash_layout(hash_layout_str(LAYOUT_HASH_SEED, "Rect"), Point2D::<T, U>::layout_hash()),
+            Size2D::<T, U>::layout_hash(),
+        )
+    }
 }
 impl<T: Peek, U> Peek for Rect<T, U> {
     #[inline(always)]
@@ -51,6 +99,12 @@ impl<T: Peek, U> Peek for Rect<T, U> {
         let bytes = Size2D::<T, U>::peek_from(bytes, &mut (*output).size);
         bytes
     }
+    #[inline(always)]
+    unsafe fn try_peek_from(bytes: *const u8, output: *mut Self) -> Option<*const u8> {
+        let bytes = Point2D::<T, U>::try_peek_from(bytes, &mut (*output).origin)?;
+        let bytes = Size2D::<T, U>::try_peek_from(bytes, &mut (*output).size)?;
+        Some(bytes)
+    }
 }
 
 unsafe impl<T: Poke, U> Poke for SideOffsets2D<T, U> {
@@ -66,6 +120,9 @@ unsafe impl<T: Poke, U> Poke for SideOffsets2D<T, U> {
         let bytes = self.left.poke_into(bytes);
         bytes
     }
+    fn layout_hash() -> u64 {
+        hash_layout(hash_layout_str(LAYOUT_HASH_SEED, "SideOffsets2D"), T::layout_hash())
+    }
 }
 impl<T: Peek, U> Peek for SideOffsets2D<T, U> {
     #[inline(always)]
@@ -76,6 +133,14 @@ impl<T: Peek, U> Peek for SideOffsets2D<T, U> {
         let bytes = T::peek_from(bytes, &mut (*output).left);
         bytes
     }
+    #[inline(always)]
+    unsafe fn try_peek_from(bytes: *const u8, output: *mut Self) -> Option<*const u8> {
+        let bytes = T::try_peek_from(bytes, &mut (*output).top)?;
+        let bytes = T::try_peek_from(bytes, &mut (*output).right)?;
+        let bytes = T::try_peek_from(bytes, &mut (*output).bottom)?;
+        let bytes = T::try_peek_from(bytes, &mut (*output).left)?;
+        Some(bytes)
+    }
 }
 
 unsafe impl<T: Poke, U> Poke for Size2D<T, U> {
@@ -89,6 +154,9 @@ unsafe impl<T: Poke, U> Poke for Size2D<T, U> {
         let bytes = self.height.poke_into(bytes);
         bytes
     }
+    fn layout_hash() -> u64 {
+        hash_layout(hash_layout_str(LAYOUT_HASH_SEED, "Size2D"), T::layout_hash())
+    }
 }
 impl<T: Peek, U> Peek for Size2D<T, U> {
     #[inline(always)]
@@ -97,6 +165,12 @@ impl<T: Peek, U> Peek for Size2D<T, U> {
         let bytes = T::peek_from(bytes, &mut (*output).height);
         bytes
     }
+    #[inline(always)]
+    unsafe fn try_peek_from(bytes: *const u8, output: *mut Self) -> Option<*const u8> {
+        let bytes = T::try_peek_from(bytes, &mut (*output).width)?;
+        let bytes = T::try_peek_from(bytes, &mut (*output).height)?;
+        Some(bytes)
+    }
 }
 
 unsafe impl<T: Poke, S, D> Poke for Transform3D<T, S, D> {
@@ -124,6 +198,9 @@ unsafe impl<T: Poke, S, D> Poke for Transform3D<T, S, D> {
         let bytes = self.m44.poke_into(bytes);
         bytes
     }
+    fn layout_hash() -> u64 {
+        hash_layout(hash_layout_str(LAYOUT_HASH_SEED, "Transform3D"), T::layout_hash())
+    }
 }
 impl<T: Peek, S, D> Peek for Transform3D<T, S, D> {
     #[inline(always)]
@@ -146,6 +223,26 @@ impl<T: Peek, S, D> Peek for Transform3D<T, S, D> {
         let bytes = T::peek_from(bytes, &mut (*output).m44);
         bytes
     }
+    #[inline(always)]
+    unsafe fn try_peek_from(bytes: *const u8, output: *mut Self) -> Option<*const u8> {
+        let bytes = T::try_peek_from(bytes, &mut (*output).m11)?;
+        let bytes = T::try_peek_from(bytes, &mut (*output).m12)?;
+        let bytes = T::try_peek_from(bytes, &mut (*output).m13)?;
+        let bytes = T::try_peek_from(bytes, &mut (*output).m14)?;
+        let bytes = T::try_peek_from(bytes, &mut (*output).m21)?;
+        let bytes = T::try_peek_from(bytes, &mut (*output).m22)?;
+        let bytes = T::try_peek_from(bytes, &mut (*output).m23)?;
+        let bytes = T::try_peek_from(bytes, &mut (*output).m24)?;
+        let bytes = T::try_peek_from(bytes, &mut (*output).m31)?;
+        let bytes = T::try_peek_from(bytes, &mut (*output).m32)?;
+        let bytes = T::try_peek_from(bytes, &mut (*output).m33)?;
+        let bytes = T::try_peek_from(bytes, &mut (*output).m34)?;
+        let bytes = T::try_peek_from(bytes, &mut (*output).m41)?;
+        let bytes = T::try_peek_from(bytes, &mut (*output).m42)?;
+        let bytes = T::try_peek_from(bytes, &mut (*output).m43)?;
+        let bytes = T::try_peek_from(bytes, &mut (*output).m44)?;
+        Some(bytes)
+    }
 }
 
 unsafe impl<T: Poke, U> Poke for Vector2D<T, U> {
@@ -159,6 +256,9 @@ unsafe impl<T: Poke, U> Poke for Vector2D<T, U> {
         let bytes = self.y.poke_into(bytes);
         bytes
     }
+    fn layout_hash() -> u64 {
+        hash_layout(hash_layout_str(LAYOUT_HASH_SEED, "Vector2D"), T::layout_hash())
+    }
 }
 impl<T: Peek, U> Peek for Vector2D<T, U> {
     #[inline(always)]
@@ -167,4 +267,10 @@ impl<T: Peek, U> Peek for Vector2D<T, U> {
         let bytes = T::peek_from(bytes, &mut (*output).y);
         bytes
     }
+    #[inline(always)]
+    unsafe fn try_peek_from(bytes: *const u8, output: *mut Self) -> Option<*const u8> {
+        let bytes = T::try_peek_from(bytes, &mut (*output).x)?;
+        let bytes = T::try_peek_from(bytes, &mut (*output).y)?;
+        Some(bytes)
+    }
 }
@@ -46,6 +46,14 @@ pub unsafe fn peek_from_default<T: Default + Peek>(bytes: *const u8) -> (T, *con
     (val, bytes)
 }
 
+/// Like `peek_from_default`, but returns `None` if `bytes` don't hold a valid
+/// representation of `T`, see `Peek::try_peek_from`.
+pub unsafe fn try_peek_from_default<T: Default + Peek>(bytes: *const u8) -> Option<(T, *const u8)> {
+    let mut val = T::default();
+    let bytes = <T>::try_peek_from(bytes, &mut val)?;
+    Some((val, bytes))
+}
+
 /// Peek inplace a `T` from a slice of bytes, returning a slice of the remaining
 /// bytes. `src` must contain at least `T::max_size()` bytes.
 ///
@@ -63,6 +71,21 @@ pub fn peek_from_slice<'a, T: Peek>(src: &'a [u8], dst: &mut T) -> &'a [u8] {
     }
 }
 
+/// Like `peek_from_slice`, but returns `None` instead of panicking if `src` is
+/// too short or doesn't hold a valid representation of `T`, see
+/// `Peek::try_peek_from`.
+pub fn try_peek_from_slice<'a, T: Peek>(src: &'a [u8], dst: &mut T) -> Option<&'a [u8]> {
+    if T::max_size() >= src.len() {
+        return None;
+    }
+    unsafe {
+        let end_ptr = T::try_peek_from(src.as_ptr(), dst)?;
+        let len = end_ptr as usize - src.as_ptr() as usize;
+        assert!(len <= src.len(), "WRDL: Peek::max_size was wrong");
+        Some(slice::from_raw_parts(end_ptr, src.len() - len))
+    }
+}
+
 /// Poke helper to insert a serialized version of `src` at the beginning for `dst`.
 pub fn poke_inplace_slice<T: Poke>(src: &T, dst: &mut [u8]) {
     assert!(T::max_size() <= dst.len(),  "WRDL: buffer too small to write into");
@@ -133,7 +156,7 @@ mod euclid;
 /// # Example
 ///
 /// ```no_run
-/// use peek_poke::Poke;
+/// use peek_poke::{hash_layout, Poke, LAYOUT_HASH_SEED};
 ///
 /// struct Bar {
 ///     a: u32,
@@ -150,6 +173,11 @@ mod euclid;
 ///         let bytes = self.b.poke_into(bytes);
 ///         self.c.poke_into(bytes)
 ///     }
+///     fn layout_hash() -> u64 {
+///         let hash = hash_layout(LAYOUT_HASH_SEED, <u32>::layout_hash());
+///         let hash = hash_layout(hash, <u8>::layout_hash());
+///         hash_layout(hash, <i16>::layout_hash())
+///     }
 /// }
 /// ```
 ///
@@ -185,6 +213,41 @@ pub unsafe trait Poke {
     /// * `bytes` must pointer to at least the number of bytes returned by
     ///   `max_size()`.
     unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8;
+    /// Return a hash of the serialized layout of `Self`: the order and types
+    /// of its fields, and its enum variants. Readers and writers built from
+    /// different type definitions can compare it to detect that they don't
+    /// agree on the encoding. The names of derived types and their fields are
+    /// not part of the hash.
+    ///
+    /// The default implementation only hashes `max_size()`, so it misses any
+    /// change that keeps the size the same. Implementations should override it
+    /// by combining the hashes of their fields with `hash_layout`.
+    fn layout_hash() -> u64 {
+        hash_layout(hash_layout_str(LAYOUT_HASH_SEED, "max_size"), Self::max_size() as u64)
+    }
+}
+
+/// The initial value of a layout hash, see `Poke::layout_hash`.
+pub const LAYOUT_HASH_SEED: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// Mix `value` into a layout hash, using FNV-1a over its bytes.
+#[inline]
+pub fn hash_layout(hash: u64, value: u64) -> u64 {
+    hash_layout_bytes(hash, &value.to_le_bytes())
+}
+
+/// Mix the name of a primitive type, or any other tag, into a layout hash.
+#[inline]
+pub fn hash_layout_str(hash: u64, value: &str) -> u64 {
+    hash_layout_bytes(hash, value.as_bytes())
+}
+
+fn hash_layout_bytes(mut hash: u64, bytes: &[u8]) -> u64 {
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
 }
 
 /// A trait for values that provide deserialization from buffers of bytes.
@@ -235,6 +298,20 @@ pub trait Peek: Poke {
     /// * `bytes` must pointer to at least the number of bytes returned by
     ///   `Poke::max_size()`.
     unsafe fn peek_from(bytes: *const u8, output: *mut Self) -> *const u8;
+
+    /// Like `peek_from`, but returns `None` instead of panicking if `bytes`
+    /// don't hold a valid representation of `Self`, such as an out of range
+    /// enum tag. `output` may be partially written when `None` is returned.
+    ///
+    /// The default implementation calls `peek_from`, which is only correct for
+    /// types that accept any bytes. Types containing enums must override it.
+    ///
+    /// # Safety
+    ///
+    /// Same as `peek_from`.
+    unsafe fn try_peek_from(bytes: *const u8, output: *mut Self) -> Option<*const u8> {
+        Some(Self::peek_from(bytes, output))
+    }
 }
 
 macro_rules! impl_poke_for_deref {
@@ -247,6 +324,9 @@ macro_rules! impl_poke_for_deref {
             unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
                 (**self).poke_into(bytes)
             }
+            fn layout_hash() -> u64 {
+                <T>::layout_hash()
+            }
         }
     }
 }
@@ -265,6 +345,9 @@ macro_rules! impl_for_primitive {
             unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
                 write_verbatim(*self, bytes)
             }
+            fn layout_hash() -> u64 {
+                hash_layout_str(LAYOUT_HASH_SEED, stringify!($ty))
+            }
         }
         impl Peek for $ty {
             #[inline(always)]
@@ -290,6 +373,9 @@ unsafe impl Poke for bool {
     unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
         (*self as u8).poke_into(bytes)
     }
+    fn layout_hash() -> u64 {
+        hash_layout_str(LAYOUT_HASH_SEED, "bool")
+    }
 }
 
 impl Peek for bool {
@@ -311,6 +397,9 @@ unsafe impl<T> Poke for PhantomData<T> {
     unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
         bytes
     }
+    fn layout_hash() -> u64 {
+        hash_layout_str(LAYOUT_HASH_SEED, "PhantomData")
+    }
 }
 
 impl<T> Peek for PhantomData<T> {
@@ -338,6 +427,10 @@ unsafe impl<T: Poke> Poke for Option<T> {
             }
         }
     }
+
+    fn layout_hash() -> u64 {
+        hash_layout(hash_layout_str(LAYOUT_HASH_SEED, "Option"), T::layout_hash())
+    }
 }
 
 impl<T: Default + Peek> Peek for Option<T> {
@@ -357,6 +450,24 @@ impl<T: Default + Peek> Peek for Option<T> {
             _ => unreachable!(),
         }
     }
+
+    #[inline]
+    unsafe fn try_peek_from(bytes: *const u8, output: *mut Self) -> Option<*const u8> {
+        let (variant, bytes) = peek_from_default::<u8>(bytes);
+        match variant {
+            0 => {
+                *output = None;
+                Some(bytes)
+            }
+            1 => {
+                let mut val = T::default();
+                let bytes = T::try_peek_from(bytes, &mut val)?;
+                *output = Some(val);
+                Some(bytes)
+            }
+            _ => None,
+        }
+    }
 }
 
 macro_rules! impl_for_arrays {
@@ -368,11 +479,17 @@ macro_rules! impl_for_arrays {
             unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
                 self.iter().fold(bytes, |bytes, e| e.poke_into(bytes))
             }
+            fn layout_hash() -> u64 {
+                hash_layout(hash_layout(LAYOUT_HASH_SEED, $len), T::layout_hash())
+            }
         }
         impl<T: Peek> Peek for [T; $len] {
             unsafe fn peek_from(bytes: *const u8, output: *mut Self) -> *const u8 {
                 (&mut *output).iter_mut().fold(bytes, |bytes, e| <T>::peek_from(bytes, e))
             }
+            unsafe fn try_peek_from(bytes: *const u8, output: *mut Self) -> Option<*const u8> {
+                (&mut *output).iter_mut().try_fold(bytes, |bytes, e| <T>::try_peek_from(bytes, e))
+            }
         })+
     }
 }
@@ -391,6 +508,9 @@ unsafe impl Poke for () {
     unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
         bytes
     }
+    fn layout_hash() -> u64 {
+        hash_layout_str(LAYOUT_HASH_SEED, "()")
+    }
 }
 impl Peek for () {
     unsafe fn peek_from(bytes: *const u8, output: *mut Self) -> *const u8 {
@@ -410,12 +530,21 @@ macro_rules! impl_for_tuple {
                 $(let bytes = self.$n.poke_into(bytes);)+
                 bytes
             }
+            fn layout_hash() -> u64 {
+                let hash = hash_layout_str(LAYOUT_HASH_SEED, "tuple");
+                $(let hash = hash_layout(hash, <$ty>::layout_hash());)+
+                hash
+            }
         }
         impl<$($ty: Peek),+> Peek for ($($ty,)+) {
             unsafe fn peek_from(bytes: *const u8, output: *mut Self) -> *const u8 {
                 $(let bytes = $ty::peek_from(bytes, &mut (*output).$n);)+
                 bytes
             }
+            unsafe fn try_peek_from(bytes: *const u8, output: *mut Self) -> Option<*const u8> {
+                $(let bytes = $ty::try_peek_from(bytes, &mut (*output).$n)?;)+
+                Some(bytes)
+            }
         }
     }
 }
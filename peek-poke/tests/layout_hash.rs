@@ -0,0 +1,125 @@
+// Copyright 2019 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#![allow(dead_code)]
+
+use peek_poke::{PeekPoke, Poke};
+
+#[test]
+fn test_numbers() {
+    assert_ne!(u32::layout_hash(), i32::layout_hash());
+    assert_ne!(u32::layout_hash(), f32::layout_hash());
+    assert_ne!(u8::layout_hash(), bool::layout_hash());
+    assert_eq!(u32::layout_hash(), u32::layout_hash());
+}
+
+#[test]
+fn test_option() {
+    assert_ne!(Option::<u8>::layout_hash(), u8::layout_hash());
+    assert_ne!(Option::<u8>::layout_hash(), Option::<u16>::layout_hash());
+}
+
+#[test]
+fn test_fixed_size_array() {
+    assert_ne!(<[u32; 4]>::layout_hash(), <[u32; 5]>::layout_hash());
+    assert_ne!(<[u32; 4]>::layout_hash(), <[f32; 4]>::layout_hash());
+}
+
+#[test]
+fn test_struct() {
+    #[derive(PeekPoke)]
+    struct Bar {
+        a: u32,
+        b: u8,
+    }
+
+    #[derive(PeekPoke)]
+    struct Renamed {
+        x: u32,
+        y: u8,
+    }
+
+    #[derive(PeekPoke)]
+    struct Reordered {
+        b: u8,
+        a: u32,
+    }
+
+    #[derive(PeekPoke)]
+    struct Extended {
+        a: u32,
+        b: u8,
+        c: u8,
+    }
+
+    assert_eq!(Bar::layout_hash(), Renamed::layout_hash());
+    assert_ne!(Bar::layout_hash(), Reordered::layout_hash());
+    assert_ne!(Bar::layout_hash(), Extended::layout_hash());
+}
+
+#[test]
+fn test_enum() {
+    #[derive(PeekPoke)]
+    enum TwoVariants {
+        A(u32),
+        B(u8),
+    }
+
+    #[derive(PeekPoke)]
+    enum MovedField {
+        A(u32, u8),
+        B,
+    }
+
+    #[derive(PeekPoke)]
+    enum ThreeVariants {
+        A(u32),
+        B(u8),
+        C,
+    }
+
+    #[derive(PeekPoke)]
+    struct Fields(u32, u8);
+
+    assert_ne!(TwoVariants::layout_hash(), MovedField::layout_hash());
+    assert_ne!(TwoVariants::layout_hash(), ThreeVariants::layout_hash());
+    assert_ne!(TwoVariants::layout_hash(), Fields::layout_hash());
+}
+
+#[test]
+fn test_default() {
+    // Implementations written before `layout_hash` existed only hash their
+    // size.
+    struct Manual(u32);
+
+    unsafe impl Poke for Manual {
+        fn max_size() -> usize {
+            u32::max_size()
+        }
+        unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
+            self.0.poke_into(bytes)
+        }
+    }
+
+    struct ManualU16(u16);
+
+    unsafe impl Poke for ManualU16 {
+        fn max_size() -> usize {
+            u16::max_size()
+        }
+        unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
+            self.0.poke_into(bytes)
+        }
+    }
+
+    assert_eq!(Manual::layout_hash(), Manual::layout_hash());
+    assert_ne!(Manual::layout_hash(), ManualU16::layout_hash());
+    assert_ne!(Manual::layout_hash(), u32::layout_hash());
+}
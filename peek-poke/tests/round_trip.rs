@@ -232,7 +232,7 @@ fn test_generic_enum() {
 #[cfg(all(feature = "extras", feature = "option_copy"))]
 mod extra_tests {
     use super::*;
-    use euclid::{Point2D, Rect, SideOffsets2D, Size2D, Transform3D, Vector2D};
+    use euclid::{Point2D, Point3D, Rect, SideOffsets2D, Size2D, Transform3D, Vector2D};
     use std::mem::size_of;
 
     #[test]
@@ -240,6 +240,9 @@ mod extra_tests {
         the_same(Point2D::<f32>::new(1.0, 2.0));
         assert_eq!(Point2D::<f32>::max_size(), 2 * size_of::<f32>());
 
+        the_same(Point3D::<f32>::new(1.0, 2.0, 3.0));
+        assert_eq!(Point3D::<f32>::max_size(), 3 * size_of::<f32>());
+
         the_same(Rect::<f32>::new(
             Point2D::<f32>::new(0.0, 0.0),
             Size2D::<f32>::new(100.0, 80.0),
@@ -0,0 +1,101 @@
+// Copyright 2019 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use peek_poke::{ensure_red_zone, poke_into_vec, try_peek_from_slice, Peek, PeekPoke, Poke};
+use std::fmt::Debug;
+
+#[derive(Clone, Copy, Debug, PartialEq, PeekPoke)]
+enum Inner {
+    A,
+    B(u16),
+}
+
+impl Default for Inner {
+    fn default() -> Self {
+        Inner::A
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, PeekPoke)]
+struct Outer {
+    a: u8,
+    inner: Inner,
+    opt: Option<Inner>,
+    arr: [Inner; 2],
+    tuple: (u8, Inner),
+}
+
+fn bytes_of<T: Poke>(value: &T) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    poke_into_vec(value, &mut bytes);
+    ensure_red_zone::<T>(&mut bytes);
+    bytes
+}
+
+fn try_round_trip<T: Debug + Default + PartialEq + Peek>(value: T) {
+    let bytes = bytes_of(&value);
+    let mut result = T::default();
+    let rest = try_peek_from_slice(&bytes, &mut result).unwrap();
+    assert_eq!(rest.len(), T::max_size());
+    assert_eq!(result, value);
+}
+
+fn outer() -> Outer {
+    Outer {
+        a: 7,
+        inner: Inner::B(3),
+        opt: Some(Inner::B(5)),
+        arr: [Inner::A, Inner::B(9)],
+        tuple: (1, Inner::B(2)),
+    }
+}
+
+// Offsets of the enum tags in the encoding of `outer()`.
+const INNER_TAG: usize = 1;
+const OPT_TAG: usize = 4;
+const OPT_INNER_TAG: usize = 5;
+const ARR_1_TAG: usize = 9;
+const TUPLE_INNER_TAG: usize = 13;
+
+#[test]
+fn test_try_peek_valid() {
+    try_round_trip(5u32);
+    try_round_trip(Some(Inner::B(4)));
+    try_round_trip(None::<Inner>);
+    try_round_trip(outer());
+}
+
+#[test]
+fn test_try_peek_tag_offsets() {
+    let bytes = bytes_of(&outer());
+    assert_eq!(bytes[INNER_TAG], 1);
+    assert_eq!(bytes[OPT_TAG], 1);
+    assert_eq!(bytes[OPT_INNER_TAG], 1);
+    assert_eq!(bytes[ARR_1_TAG], 1);
+    assert_eq!(bytes[TUPLE_INNER_TAG], 1);
+}
+
+#[test]
+fn test_try_peek_bad_tag() {
+    for &offset in &[INNER_TAG, OPT_TAG, OPT_INNER_TAG, ARR_1_TAG, TUPLE_INNER_TAG] {
+        let mut bytes = bytes_of(&outer());
+        bytes[offset] = 2;
+        let mut result = Outer::default();
+        assert!(try_peek_from_slice(&bytes, &mut result).is_none(), "tag at {}", offset);
+    }
+}
+
+#[test]
+fn test_try_peek_too_short() {
+    let bytes = bytes_of(&outer());
+    let mut result = Outer::default();
+    assert!(try_peek_from_slice(&bytes[.. Outer::max_size()], &mut result).is_none());
+    assert!(try_peek_from_slice(&[], &mut result).is_none());
+}
@@ -18,12 +18,17 @@ use crate::vertex_types::*;
 const SHADER_IMPORT: &str = "#include ";
 const SHADER_KIND_FRAGMENT: &str = "#define WR_FRAGMENT_SHADER\n";
 const SHADER_KIND_VERTEX: &str = "#define WR_VERTEX_SHADER\n";
+const SHADER_KIND_COMPUTE: &str = "#define WR_COMPUTE_SHADER\n";
 const SHADER_PREFIX: &str = "#define WR_MAX_VERTEX_TEXTURE_WIDTH 1024U\n";
 const SHADER_VERSION_VK: &'static str = "#version 450\n";
 const VK_EXTENSIONS: &'static str = "#extension GL_ARB_shading_language_420pack : enable\n\
                                      #extension GL_ARB_explicit_attrib_location : enable\n\
                                      #extension GL_ARB_separate_shader_objects : enable\n";
 
+// Compute shaders have no vertex input or graphics pipeline state, so they are
+// not listed in shaders.ron and only get a .comp stage.
+const COMPUTE_SHADERS: &[&str] = &["cs_blur_compute"];
+
 // https://www.khronos.org/registry/vulkan/specs/1.1-extensions/html/vkspec.html#features-limits
 const MAX_INPUT_ATTRIBUTES: u32 = 16;
 
@@ -168,6 +173,21 @@ fn create_shaders(out_dir: &str, shaders: &HashMap<String, String>) -> Vec<Strin
             file_names.push(fs_name);
         }
     }
+
+    for shader_name in COMPUTE_SHADERS {
+        let mut cs_source = String::new();
+        cs_source.push_str(SHADER_VERSION_VK);
+        cs_source.push_str(SHADER_KIND_COMPUTE);
+        cs_source.push_str(format!("//Source: {}.glsl\n", shader_name).as_str());
+        if let Some(source) = get_shader_source(shader_name, shaders) {
+            parse_shader_source(&source, shaders, &mut cs_source);
+        }
+
+        let cs_name = format!("{}.comp", shader_name);
+        let mut cs_file = File::create(Path::new(out_dir).join(&cs_name)).unwrap();
+        write!(cs_file, "{}", cs_source).unwrap();
+        file_names.push(cs_name);
+    }
     file_names
 }
 
@@ -492,7 +512,7 @@ fn create_vertex_buffer_descriptors(file_name: &str) -> Vec<VertexBufferDesc> {
             rate: VertexInputRate::Vertex,
         }
     ];
-    if file_name.starts_with("cs_blur") {
+    if file_name.starts_with("cs_blur") || file_name.starts_with("cs_dual_filter") {
         descriptors.push(
             VertexBufferDesc {
                 binding: 1,
@@ -548,6 +568,22 @@ fn create_vertex_buffer_descriptors(file_name: &str) -> Vec<VertexBufferDesc> {
                 rate: VertexInputRate::Instance(1),
             }
         );
+    } else if file_name.starts_with("pf_vector_stencil") {
+        descriptors.push(
+            VertexBufferDesc {
+                binding: 1,
+                stride: mem::size_of::<VectorStencilInstance>() as _,
+                rate: VertexInputRate::Instance(1),
+            }
+        );
+    } else if file_name.starts_with("pf_vector_cover") {
+        descriptors.push(
+            VertexBufferDesc {
+                binding: 1,
+                stride: mem::size_of::<VectorCoverInstance>() as _,
+                rate: VertexInputRate::Instance(1),
+            }
+        );
     } else if file_name.starts_with("composite") {
         descriptors.push(
             VertexBufferDesc {
@@ -613,8 +649,11 @@ fn compile_glsl_to_spirv(file_name_vector: Vec<String>, out_dir: &str, shader_fi
     let mut requirements = HashMap::new();
     for (_index, file_name) in file_name_vector.iter().enumerate() {
         let file_path = Path::new(&out_dir).join(&file_name);
-        if let Some(req) = process_glsl_for_spirv(&file_path, &file_name) {
-            requirements.insert(file_name.trim_end_matches(".vert").to_owned(), req);
+        // Compute shaders are written with explicit Vulkan layouts already.
+        if !file_name.ends_with(".comp") {
+            if let Some(req) = process_glsl_for_spirv(&file_path, &file_name) {
+                requirements.insert(file_name.trim_end_matches(".vert").to_owned(), req);
+            }
         }
         let file_name = [file_name, ".spv"].concat();
         let spirv_file_path = Path::new(&out_dir).join(&file_name);
@@ -26,6 +26,11 @@ flat varying int vTableAddress;
 flat varying vec4 vFloodColor;
 flat varying ivec4 vFuncs;
 flat varying mat3 vColorMat;
+// XY = origin of the lookup table in normalized texture coordinates,
+// ZW = size of a texel in normalized texture coordinates.
+flat varying vec4 vLutUvRect;
+// X = layer index of the lookup table, Y = number of entries per axis.
+flat varying vec2 vLutLayerAndSize;
 
 #ifdef WR_VERTEX_SHADER
 
@@ -142,6 +147,14 @@ void brush_vs(
             vFloodColor = fetch_from_gpu_cache_1(prim_user_data.z);
             break;
         }
+        case 15: {
+            // 3D color lookup table
+            ImageResource lut = fetch_image_resource(prim_user_data.z);
+            vec2 lut_texture_size = vec2(textureSize(sColor1, 0).xy);
+            vLutUvRect = vec4(lut.uv_rect.p0, vec2(1.0)) / lut_texture_size.xyxy;
+            vLutLayerAndSize = vec2(lut.layer, float(prim_user_data.w));
+            break;
+        }
         default: break;
     }
 }
@@ -180,6 +193,26 @@ vec3 LinearToSrgb(vec3 color) {
     return if_then_else(lessThanEqual(color, vec3(0.0031308)), c1, c2);
 }
 
+// The table is stored as a row of blue slices, each indexed by red along x
+// and green along y. Red and green are interpolated by the sampler, and the
+// two nearest blue slices are blended here.
+vec3 ColorLut3D(vec3 color) {
+    float size = vLutLayerAndSize.y;
+    vec3 scaled = clamp(color, vec3(0.0), vec3(1.0)) * (size - 1.0);
+    float slice0 = floor(scaled.b);
+    float slice1 = min(slice0 + 1.0, size - 1.0);
+
+    // Offset to the texel centers so that the sampler interpolates between
+    // neighbouring entries.
+    vec2 texel = scaled.rg + vec2(0.5);
+    vec2 uv0 = vLutUvRect.xy + (texel + vec2(slice0 * size, 0.0)) * vLutUvRect.zw;
+    vec2 uv1 = vLutUvRect.xy + (texel + vec2(slice1 * size, 0.0)) * vLutUvRect.zw;
+
+    vec3 c0 = texture(sColor1, vec3(uv0, vLutLayerAndSize.x)).rgb;
+    vec3 c1 = texture(sColor1, vec3(uv1, vLutLayerAndSize.x)).rgb;
+    return mix(c0, c1, scaled.b - slice0);
+}
+
 // This function has to be factored out due to the following issue:
 // https://github.com/servo/webrender/wiki/Driver-issues#bug-1532245---switch-statement-inside-control-flow-inside-switch-statement-fails-to-compile-on-some-android-phones
 // (and now the words "default: default:" so angle_shader_validation.rs passes)
@@ -288,6 +321,9 @@ Fragment brush_fs() {
             color = vFloodColor.rgb;
             alpha = vFloodColor.a;
             break;
+        case 15:
+            color = ColorLut3D(color);
+            break;
         default:
             color = vColorMat * color + vColorOffset;
     }
@@ -0,0 +1,133 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#define VECS_PER_SPECIFIC_BRUSH 2
+
+#define PI                  3.141592653589793
+
+#include shared,prim_shared,brush
+
+flat varying HIGHP_FS_ADDRESS int vGradientAddress;
+flat varying float vGradientRepeat;
+
+flat varying vec2 vCenter;
+flat varying float vAngle;
+flat varying float vStartOffset;
+flat varying float vEndOffset;
+
+varying vec2 vPos;
+flat varying vec2 vRepeatedSize;
+
+varying vec2 vLocalPos;
+flat varying vec2 vTileRepeat;
+
+#ifdef WR_VERTEX_SHADER
+
+struct ConicGradient {
+    vec4 center_start_end_offset;
+    float angle;
+    int extend_mode;
+    vec2 stretch_size;
+};
+
+ConicGradient fetch_conic_gradient(int address) {
+    vec4 data[2] = fetch_from_gpu_cache_2(address);
+    return ConicGradient(
+        data[0],
+        data[1].x,
+        int(data[1].y),
+        data[1].zw
+    );
+}
+
+void brush_vs(
+    VertexInfo vi,
+    int prim_address,
+    RectWithSize local_rect,
+    RectWithSize segment_rect,
+    ivec4 prim_user_data,
+    int segment_user_data,
+    mat4 transform,
+    PictureTask pic_task,
+    int brush_flags,
+    vec4 texel_rect
+) {
+    ConicGradient gradient = fetch_conic_gradient(prim_address);
+
+    if ((brush_flags & BRUSH_FLAG_SEGMENT_RELATIVE) != 0) {
+        vPos = (vi.local_pos - segment_rect.p0) / segment_rect.size;
+        vPos = vPos * (texel_rect.zw - texel_rect.xy) + texel_rect.xy;
+        vPos = vPos * local_rect.size;
+    } else {
+        vPos = vi.local_pos - local_rect.p0;
+    }
+
+    vCenter = gradient.center_start_end_offset.xy;
+    vStartOffset = gradient.center_start_end_offset.z;
+    vEndOffset = gradient.center_start_end_offset.w;
+    vAngle = gradient.angle;
+
+    vec2 tile_repeat = local_rect.size / gradient.stretch_size;
+    vRepeatedSize = gradient.stretch_size;
+
+    vGradientAddress = prim_user_data.x;
+
+    // Whether to repeat the gradient instead of clamping.
+    vGradientRepeat = float(gradient.extend_mode != EXTEND_MODE_CLAMP);
+
+    if (alpha_pass) {
+        vTileRepeat = tile_repeat.xy;
+        vLocalPos = vi.local_pos;
+    } else {
+        vTileRepeat = vec2(0.0);
+        vLocalPos = vec2(0.0);
+    }
+}
+#endif
+
+#ifdef WR_FRAGMENT_SHADER
+Fragment brush_fs() {
+
+    vec2 pos;
+    if (alpha_pass) {
+        // Handle top and left inflated edges (see brush_image).
+        vec2 local_pos = max(vPos, vec2(0.0));
+
+        // Apply potential horizontal and vertical repetitions.
+        pos = mod(local_pos, vRepeatedSize);
+
+        vec2 prim_size = vRepeatedSize * vTileRepeat;
+        // Handle bottom and right inflated edges (see brush_image).
+        if (local_pos.x >= prim_size.x) {
+            pos.x = vRepeatedSize.x;
+        }
+        if (local_pos.y >= prim_size.y) {
+            pos.y = vRepeatedSize.y;
+        }
+    } else {
+        // Apply potential horizontal and vertical repetitions.
+        pos = mod(vPos, vRepeatedSize);
+    }
+
+    // The gradient line starts at the top and goes clockwise, so rotate
+    // the angle returned by atan (which starts at the positive x axis)
+    // by a quarter turn and subtract the start angle.
+    vec2 current_dir = pos - vCenter;
+    float current_angle = atan(current_dir.y, current_dir.x) + (PI / 2.0 - vAngle);
+    float offset = fract(current_angle / (2.0 * PI));
+
+    // Map the [0, 1] turn onto the normalized stops.
+    offset = (offset - vStartOffset) / (vEndOffset - vStartOffset);
+
+    vec4 color = sample_gradient(vGradientAddress,
+                                 offset,
+                                 vGradientRepeat);
+
+    if (alpha_pass) {
+        color *= init_transform_fs(vLocalPos);
+    }
+
+    return Fragment(color);
+}
+#endif
@@ -0,0 +1,99 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// Compute shader version of cs_blur, only used by the gfx backend.
+//
+// Each work group blurs a run of WORK_GROUP_SIZE texels of a single row
+// (or column) of the blur task. The texels of the run, together with the
+// blur support on both ends, are staged in shared memory first so that
+// every source texel is only fetched once per work group.
+
+// Has to match MAX_COMPUTE_BLUR_SUPPORT and WORK_GROUP_SIZE in
+// device/gfx/compute.rs.
+#define MAX_SUPPORT         32
+#define WORK_GROUP_SIZE     128
+
+#define DIR_HORIZONTAL      0
+#define DIR_VERTICAL        1
+
+layout(local_size_x = WORK_GROUP_SIZE, local_size_y = 1, local_size_z = 1) in;
+
+layout(set = 0, binding = 0) uniform sampler2DArray sSource;
+layout(set = 0, binding = 1) writeonly uniform image2DArray sDestination;
+
+layout(push_constant) uniform BlurParams {
+    // Origin and size of the source task rect.
+    ivec4 src_rect;
+    // Origin and size of the blur task rect.
+    ivec4 dest_rect;
+    // Size of the blur region in xy, source and destination layers in zw.
+    ivec4 region_and_layers;
+    // Blur direction and support.
+    ivec2 direction_and_support;
+    float sigma;
+} uParams;
+
+shared vec4 sTexels[WORK_GROUP_SIZE + 2 * MAX_SUPPORT];
+
+void main(void) {
+    bool vertical = uParams.direction_and_support.x == DIR_VERTICAL;
+    int support = uParams.direction_and_support.y;
+
+    // Position of the output texel along the blur direction, and the
+    // row (or column) this work group is blurring.
+    int along = int(gl_GlobalInvocationID.x);
+    int across = int(gl_WorkGroupID.y);
+    int run_start = int(gl_WorkGroupID.x) * WORK_GROUP_SIZE;
+
+    ivec2 src_min = uParams.src_rect.xy;
+    ivec2 src_max = src_min + uParams.region_and_layers.xy - ivec2(1);
+    int src_layer = uParams.region_and_layers.z;
+
+    // Neighbouring texels are clamped to the blur region, like cs_blur does.
+    for (int i = int(gl_LocalInvocationID.x); i < WORK_GROUP_SIZE + 2 * support; i += WORK_GROUP_SIZE) {
+        int offset = run_start + i - support;
+        ivec2 pos = vertical ? ivec2(across, offset) : ivec2(offset, across);
+        pos = clamp(src_min + pos, src_min, src_max);
+        sTexels[i] = texelFetch(sSource, ivec3(pos, src_layer), 0);
+    }
+
+    barrier();
+
+    ivec2 local_pos = vertical ? ivec2(across, along) : ivec2(along, across);
+    if (any(greaterThanEqual(local_pos, uParams.dest_rect.zw))) {
+        return;
+    }
+
+    vec4 original_color = texelFetch(sSource, ivec3(src_min + local_pos, src_layer), 0);
+    vec4 result = original_color;
+
+    if (support != 0) {
+        float sigma = uParams.sigma;
+
+        // Incremental Gaussian Coefficent Calculation (See GPU Gems 3 pp. 877 - 889)
+        vec3 gauss_coefficient;
+        gauss_coefficient.x = 1.0 / (sqrt(2.0 * 3.14159265) * sigma);
+        gauss_coefficient.y = exp(-0.5 / (sigma * sigma));
+        gauss_coefficient.z = gauss_coefficient.y * gauss_coefficient.y;
+
+        float gauss_coefficient_total = gauss_coefficient.x;
+        vec4 avg_color = original_color * gauss_coefficient.x;
+        gauss_coefficient.xy *= gauss_coefficient.yz;
+
+        int center = int(gl_LocalInvocationID.x) + support;
+        for (int i = 1; i <= support; i++) {
+            avg_color += (sTexels[center - i] + sTexels[center + i]) * gauss_coefficient.x;
+            gauss_coefficient_total += 2.0 * gauss_coefficient.x;
+            gauss_coefficient.xy *= gauss_coefficient.yz;
+        }
+
+        result = avg_color / gauss_coefficient_total;
+    }
+
+    imageStore(
+        sDestination,
+        ivec3(uParams.dest_rect.xy + local_pos, uParams.region_and_layers.w),
+        result
+    );
+}
@@ -10,15 +10,17 @@ varying vec2 vClipMaskImageUv;
 flat varying vec4 vClipMaskUvRect;
 flat varying vec4 vClipMaskUvInnerRect;
 flat varying float vLayer;
+flat varying float vMaskMode;
 
 #ifdef WR_VERTEX_SHADER
 struct ImageMaskData {
     vec2 local_mask_size;
+    float mode;
 };
 
 ImageMaskData fetch_mask_data(ivec2 address) {
     vec4 data = fetch_from_gpu_cache_1_direct(address);
-    ImageMaskData mask_data = ImageMaskData(data.xy);
+    ImageMaskData mask_data = ImageMaskData(data.xy, data.z);
     return mask_data;
 }
 
@@ -41,6 +43,7 @@ void main(void) {
     );
     vLocalPos = vi.local_pos;
     vLayer = res.layer;
+    vMaskMode = mask.mode;
     vClipMaskImageUv = (vi.local_pos.xy - cmi.tile_rect.p0 * vi.local_pos.w) / cmi.tile_rect.size;
 
     vec2 texture_size = vec2(textureSize(sColor0, 0));
@@ -52,6 +55,11 @@ void main(void) {
 #endif
 
 #ifdef WR_FRAGMENT_SHADER
+
+// Has to match MaskMode in display_item.rs.
+#define MASK_MODE_ALPHA         0
+#define MASK_MODE_LUMINANCE     1
+
 void main(void) {
     vec2 local_pos = vLocalPos.xy / vLocalPos.w;
     float alpha = vLocalPos.w > 0.0 ? init_transform_fs(local_pos) : 0.0;
@@ -59,15 +67,25 @@ void main(void) {
     // TODO: Handle repeating masks?
     vec2 clamped_mask_uv = clamp(vClipMaskImageUv, vec2(0.0, 0.0), vLocalPos.ww);
 
-    // Ensure we don't draw outside of our tile.
+    // Ensure we don't draw over the other tiles of the mask. Outside of the
+    // mask there is no coverage, which is written out since the mask
+    // composite operators depend on it.
     // FIXME(emilio): Can we do this earlier?
-    if (clamped_mask_uv != vClipMaskImageUv)
+    if (clamped_mask_uv != vClipMaskImageUv && alpha > 0.0)
         discard;
 
     vec2 source_uv = clamp(
         clamped_mask_uv / vLocalPos.w * vClipMaskUvRect.zw + vClipMaskUvRect.xy,
         vClipMaskUvInnerRect.xy, vClipMaskUvInnerRect.zw);
-    float clip_alpha = texture(sColor0, vec3(source_uv, vLayer)).r; //careful: texture has type A8
+    vec4 mask_color = texture(sColor0, vec3(source_uv, vLayer));
+    float clip_alpha;
+    if (int(vMaskMode) == MASK_MODE_LUMINANCE) {
+        // The color is premultiplied, so this already accounts for the alpha
+        // of the mask, as the luminance mask mode requires.
+        clip_alpha = dot(mask_color.rgb, vec3(0.2125, 0.7154, 0.0721));
+    } else {
+        clip_alpha = mask_color.r; //careful: texture has type A8
+    }
     oFragColor = vec4(alpha * clip_alpha, 1.0, 1.0, 1.0);
 }
 #endif
@@ -0,0 +1,117 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// A single level of a dual filter blur pyramid (Marius Bjørge, "Bandwidth-
+// Efficient Rendering", SIGGRAPH 2015). Down passes halve the size of the
+// source while blurring it, up passes double it again. Both rely on bilinear
+// filtering to average several texels with each fetch.
+
+#include shared,prim_shared
+
+varying vec3 vUv;
+flat varying vec4 vUvRect;
+// Size of half a source texel in uv space, scaled by the offset scale of the task.
+flat varying vec2 vHalfTexel;
+flat varying int vPass;
+
+#ifdef WR_VERTEX_SHADER
+
+in int aBlurRenderTaskAddress;
+in int aBlurSourceTaskAddress;
+// Unused, kept to share the vertex layout with cs_blur.
+in int aBlurDirection;
+
+struct DualFilterTask {
+    RenderTaskCommonData common_data;
+    int pass;
+    vec2 offset_scale;
+};
+
+DualFilterTask fetch_dual_filter_task(int address) {
+    RenderTaskData task_data = fetch_render_task_data(address);
+
+    DualFilterTask task = DualFilterTask(
+        task_data.common_data,
+        int(task_data.user_data.x),
+        task_data.user_data.yz
+    );
+
+    return task;
+}
+
+void main(void) {
+    DualFilterTask blur_task = fetch_dual_filter_task(aBlurRenderTaskAddress);
+    RenderTaskCommonData src_task = fetch_render_task_common_data(aBlurSourceTaskAddress);
+
+    RectWithSize src_rect = src_task.task_rect;
+    RectWithSize target_rect = blur_task.common_data.task_rect;
+
+    vec2 texture_size;
+    if (color_target) {
+        texture_size = vec2(textureSize(sPrevPassColor, 0).xy);
+    } else {
+        texture_size = vec2(textureSize(sPrevPassAlpha, 0).xy);
+    }
+
+    vUv.z = src_task.texture_layer_index;
+    vPass = blur_task.pass;
+    vHalfTexel = 0.5 * blur_task.offset_scale / texture_size;
+
+    vUvRect = vec4(src_rect.p0 + vec2(0.5),
+                   src_rect.p0 + src_rect.size - vec2(0.5));
+    vUvRect /= texture_size.xyxy;
+
+    vec2 pos = target_rect.p0 + target_rect.size * aPosition.xy;
+
+    vec2 uv0 = src_rect.p0 / texture_size;
+    vec2 uv1 = (src_rect.p0 + src_rect.size) / texture_size;
+    vUv.xy = mix(uv0, uv1, aPosition.xy);
+
+    gl_Position = uTransform * vec4(pos, 0.0, 1.0);
+}
+#endif
+
+#ifdef WR_FRAGMENT_SHADER
+
+#define PASS_DOWN   0
+#define PASS_UP     1
+
+vec4 sample_source(vec2 offset) {
+    vec3 uv = vec3(clamp(vUv.xy + offset, vUvRect.xy, vUvRect.zw), vUv.z);
+    if (color_target) {
+        return texture(sPrevPassColor, uv);
+    } else {
+        return vec4(texture(sPrevPassAlpha, uv).r);
+    }
+}
+
+void main(void) {
+    vec2 d = vHalfTexel;
+    vec4 result;
+
+    if (vPass == PASS_DOWN) {
+        // The center, plus the four diagonal neighbours, each of which
+        // averages a 2x2 block of source texels.
+        result = sample_source(vec2(0.0)) * 4.0;
+        result += sample_source(vec2(-d.x, -d.y) * 2.0);
+        result += sample_source(vec2( d.x, -d.y) * 2.0);
+        result += sample_source(vec2(-d.x,  d.y) * 2.0);
+        result += sample_source(vec2( d.x,  d.y) * 2.0);
+        result /= 8.0;
+    } else {
+        // A tent over the surrounding source texels.
+        result = sample_source(vec2(-d.x * 2.0, 0.0));
+        result += sample_source(vec2(d.x * 2.0, 0.0));
+        result += sample_source(vec2(0.0, -d.y * 2.0));
+        result += sample_source(vec2(0.0, d.y * 2.0));
+        result += sample_source(vec2(-d.x, -d.y)) * 2.0;
+        result += sample_source(vec2( d.x, -d.y)) * 2.0;
+        result += sample_source(vec2(-d.x,  d.y)) * 2.0;
+        result += sample_source(vec2( d.x,  d.y)) * 2.0;
+        result /= 12.0;
+    }
+
+    oFragColor = result;
+}
+#endif
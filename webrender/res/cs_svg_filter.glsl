@@ -6,6 +6,7 @@
 
 varying vec3 vInput1Uv;
 varying vec3 vInput2Uv;
+varying vec2 vLocalPos;
 flat varying vec4 vInput1UvRect;
 flat varying vec4 vInput2UvRect;
 flat varying int vFilterInputCount;
@@ -28,6 +29,14 @@ flat varying mat3 vColorMat;
 #define FILTER_COMPONENT_TRANSFER   8
 #define FILTER_IDENTITY             9
 #define FILTER_COMPOSITE            10
+#define FILTER_MORPHOLOGY           11
+#define FILTER_CONVOLVE_MATRIX      12
+#define FILTER_TURBULENCE           13
+#define FILTER_DISPLACEMENT_MAP     14
+#define FILTER_DIFFUSE_LIGHTING     15
+#define FILTER_SPECULAR_LIGHTING    16
+#define FILTER_IMAGE                17
+#define FILTER_TILE                 18
 
 #define COMPOSITE_OVER       0
 #define COMPOSITE_IN         1
@@ -37,6 +46,30 @@ flat varying mat3 vColorMat;
 #define COMPOSITE_LIGHTER    5
 #define COMPOSITE_ARITHMETIC 6
 
+#define MORPHOLOGY_ERODE     0
+#define MORPHOLOGY_DILATE    1
+
+// Must match MAX_MORPHOLOGY_RADIUS in display_item.rs
+#define MAX_MORPHOLOGY_RADIUS       100
+// Must match MAX_CONVOLVE_MATRIX_ORDER in display_item.rs
+#define MAX_CONVOLVE_MATRIX_ORDER   5
+
+#define EDGE_MODE_DUPLICATE  0
+#define EDGE_MODE_WRAP       1
+#define EDGE_MODE_NONE       2
+
+#define LIGHT_SOURCE_DISTANT 0
+#define LIGHT_SOURCE_POINT   1
+#define LIGHT_SOURCE_SPOT    2
+
+#define TURBULENCE_FRACTAL_NOISE    0
+#define TURBULENCE_TURBULENCE       1
+
+// Must match MAX_TURBULENCE_OCTAVES in display_item.rs
+#define MAX_TURBULENCE_OCTAVES      10
+// Must match TURBULENCE_LATTICE_SIZE in render_task.rs
+#define TURBULENCE_LATTICE_SIZE     256
+
 #ifdef WR_VERTEX_SHADER
 
 in int aFilterRenderTaskAddress;
@@ -156,6 +189,78 @@ void main(void) {
               vFilterData0 = fetch_from_gpu_cache_1_direct(aFilterExtraDataAddress);
             }
             break;
+        case FILTER_MORPHOLOGY: {
+            // Each pass runs along a single axis, so only one of the radii
+            // is non-zero.
+            vec2 input_texture_size = vec2(textureSize(sColor0, 0).xy);
+            vec2 radius = filter_task.user_data.xy;
+            vFilterData0 = vec4(sign(radius) / input_texture_size, vec2(0.0));
+            vData = ivec4(aFilterGenericInt, int(max(radius.x, radius.y)), 0, 0);
+
+            RectWithSize input_rect = input_1_task.task_rect;
+            vFilterData1 = vec4(input_rect.p0, input_rect.p0 + input_rect.size) / input_texture_size.xyxy;
+            break;
+        }
+        case FILTER_CONVOLVE_MATRIX:
+            vData = ivec4(aFilterExtraDataAddress, 0, 0);
+            // order.xy, target.xy
+            vFilterData0 = fetch_from_gpu_cache_1_direct(aFilterExtraDataAddress);
+            // divisor, bias, edge mode, preserve alpha
+            vFilterData1 = fetch_from_gpu_cache_1_direct(aFilterExtraDataAddress + ivec2(1, 0));
+            break;
+        case FILTER_DISPLACEMENT_MAP: {
+            // The x and y channel selectors are packed in the generic int.
+            vData = ivec4((aFilterGenericInt >> 4) & 0xf, aFilterGenericInt & 0xf, 0, 0);
+            vec2 input_texture_size = vec2(textureSize(sColor0, 0).xy);
+            vFilterData0 = vec4(vec2(filter_task.user_data.x) / input_texture_size, vec2(0.0));
+
+            RectWithSize input_rect = input_1_task.task_rect;
+            vFilterData1 = vec4(input_rect.p0, input_rect.p0 + input_rect.size) / input_texture_size.xyxy;
+            break;
+        }
+        case FILTER_DIFFUSE_LIGHTING:
+        case FILTER_SPECULAR_LIGHTING:
+            vData = ivec4(aFilterExtraDataAddress, aFilterGenericInt, 0);
+            // surface scale, diffuse or specular constant, specular exponent
+            vFilterData0 = fetch_from_gpu_cache_1_direct(aFilterExtraDataAddress);
+            // lighting color
+            vFilterData1 = fetch_from_gpu_cache_1_direct(aFilterExtraDataAddress + ivec2(1, 0));
+            // The light source is positioned in local space. The user data
+            // holds the local position of the task origin and the local size
+            // of a device pixel.
+            vLocalPos = filter_task.user_data.xy + target_rect.size * aPosition.xy * filter_task.user_data.z;
+            break;
+        case FILTER_TURBULENCE:
+            vData = ivec4(aFilterExtraDataAddress, aFilterGenericInt, 0);
+            // base frequency.xy, octave count, stitch tiles
+            vFilterData0 = fetch_from_gpu_cache_1_direct(aFilterExtraDataAddress);
+            // stitch width, height, wrap x, wrap y
+            vFilterData1 = fetch_from_gpu_cache_1_direct(aFilterExtraDataAddress + ivec2(1, 0));
+            // The user data holds the local position of the task origin
+            // and the local size of a device pixel.
+            vLocalPos = filter_task.user_data.xy + target_rect.size * aPosition.xy * filter_task.user_data.z;
+            break;
+        case FILTER_IMAGE: {
+            // The local rect the image is drawn in.
+            vFilterData1 = fetch_from_gpu_cache_1_direct(aFilterExtraDataAddress);
+            vLocalPos = filter_task.user_data.xy + target_rect.size * aPosition.xy * filter_task.user_data.z;
+
+            // The image task is sized to the rect, so it is stretched over it.
+            vec2 input_texture_size = vec2(textureSize(sColor0, 0).xy);
+            RectWithSize input_rect = input_1_task.task_rect;
+            vFilterData0 = vec4(input_rect.p0, input_rect.size) / input_texture_size.xyxy;
+            break;
+        }
+        case FILTER_TILE: {
+            // The local rect that is repeated, mapped to the uv space of the
+            // input, which lies in local space just like this task.
+            vec4 tile_rect = fetch_from_gpu_cache_1_direct(aFilterExtraDataAddress);
+            vec2 input_texture_size = vec2(textureSize(sColor0, 0).xy);
+            RectWithSize input_rect = input_1_task.task_rect;
+            vec2 tile_origin = input_rect.p0 + (tile_rect.xy - filter_task.user_data.xy) / filter_task.user_data.z;
+            vFilterData0 = vec4(tile_origin, tile_rect.zw / filter_task.user_data.z) / input_texture_size.xyxy;
+            break;
+        }
         default:
             break;
     }
@@ -512,6 +617,260 @@ vec4 sampleInUvRect(sampler2DArray samp, vec3 uv, vec4 uvRect) {
     return texture(samp, vec3(clamped, uv.z), 0.0);
 }
 
+// Morphology Filter
+
+// Takes the component-wise minimum (erode) or maximum (dilate) of the
+// premultiplied input over a line of 2 * radius + 1 pixels along the axis
+// of the current pass.
+vec4 Morphology(int op, int radius) {
+    vec4 result = op == MORPHOLOGY_ERODE ? vec4(1.0) : vec4(0.0);
+
+    for (int i = 0; i <= 2 * MAX_MORPHOLOGY_RADIUS; i++) {
+        if (i > 2 * radius) {
+            break;
+        }
+        vec2 uv = vInput1Uv.xy + float(i - radius) * vFilterData0.xy;
+        vec4 texel = sampleInUvRect(sColor0, vec3(uv, vInput1Uv.z), vInput1UvRect);
+        texel *= point_inside_rect(uv, vFilterData1.xy, vFilterData1.zw);
+        result = op == MORPHOLOGY_ERODE ? min(result, texel) : max(result, texel);
+    }
+
+    return result;
+}
+
+// Convolve Matrix Filter
+
+// The kernel is stored in the gpu cache after the two header blocks, four
+// values per block, in the order it was specified. Per the spec it is
+// applied rotated by 180 degrees.
+vec4 ConvolveMatrix(vec4 Ca) {
+    ivec2 order = ivec2(vFilterData0.xy);
+    ivec2 target = ivec2(vFilterData0.zw);
+    float divisor = vFilterData1.x;
+    float bias = vFilterData1.y;
+    int edge_mode = int(vFilterData1.z);
+    bool preserve_alpha = vFilterData1.w != 0.0;
+
+    vec2 texel_size = vec2(1.0) / vec2(textureSize(sColor0, 0).xy);
+    // The full extent of the input, rather than the rect of texel centers.
+    vec4 input_rect = vInput1UvRect + vec4(-0.5, -0.5, 0.5, 0.5) * texel_size.xyxy;
+
+    vec4 sum = vec4(0.0);
+    for (int y = 0; y < MAX_CONVOLVE_MATRIX_ORDER; y++) {
+        if (y >= order.y) {
+            break;
+        }
+        for (int x = 0; x < MAX_CONVOLVE_MATRIX_ORDER; x++) {
+            if (x >= order.x) {
+                break;
+            }
+
+            int k = (order.y - 1 - y) * order.x + (order.x - 1 - x);
+            vec4 weights = fetch_from_gpu_cache_1_direct(vData.xy + ivec2(2 + k / 4, 0));
+
+            vec2 uv = vInput1Uv.xy + vec2(x - target.x, y - target.y) * texel_size;
+            vec4 texel;
+            if (edge_mode == EDGE_MODE_WRAP) {
+                uv = input_rect.xy + mod(uv - input_rect.xy, input_rect.zw - input_rect.xy);
+                texel = sampleInUvRect(sColor0, vec3(uv, vInput1Uv.z), vInput1UvRect);
+            } else {
+                texel = sampleInUvRect(sColor0, vec3(uv, vInput1Uv.z), vInput1UvRect);
+                if (edge_mode == EDGE_MODE_NONE) {
+                    texel *= point_inside_rect(uv, input_rect.xy, input_rect.zw);
+                }
+            }
+
+            if (preserve_alpha && texel.a != 0.0) {
+                texel.rgb /= texel.a;
+            }
+
+            sum += weights[k % 4] * texel;
+        }
+    }
+
+    vec4 result;
+    if (preserve_alpha) {
+        result.rgb = clamp(sum.rgb / divisor + vec3(bias), vec3(0.0), vec3(1.0));
+        result.a = Ca.a;
+        result.rgb *= result.a;
+    } else {
+        result.a = clamp(sum.a / divisor + bias, 0.0, 1.0);
+        result.rgb = clamp(sum.rgb / divisor + vec3(bias * result.a), vec3(0.0), vec3(result.a));
+    }
+    return result;
+}
+
+// Turbulence Filter
+
+// The lattice selectors are stored in the gpu cache after the two header
+// blocks, four per block, followed by two blocks of gradients per lattice
+// entry holding the gradients of the red and green, then the blue and
+// alpha channels.
+int TurbulenceLatticeSelector(int index) {
+    vec4 texel = fetch_from_gpu_cache_1_direct(vData.xy + ivec2(2 + index / 4, 0));
+    return int(texel[index % 4]);
+}
+
+// Returns the dot products of the gradients of each channel at a lattice
+// entry with the offset from that lattice point.
+vec4 TurbulenceGradientDot(int index, vec2 offset) {
+    ivec2 address = vData.xy + ivec2(2 + TURBULENCE_LATTICE_SIZE / 4 + 2 * index, 0);
+    vec4 rg = fetch_from_gpu_cache_1_direct(address);
+    vec4 ba = fetch_from_gpu_cache_1_direct(address + ivec2(1, 0));
+    return vec4(dot(rg.xy, offset), dot(rg.zw, offset), dot(ba.xy, offset), dot(ba.zw, offset));
+}
+
+// Perlin noise of all four channels, as in the feTurbulence specification.
+vec4 TurbulenceNoise(vec2 pos, bool stitch, ivec4 stitch_info) {
+    ivec2 b0 = ivec2(floor(pos));
+    ivec2 b1 = b0 + ivec2(1);
+    vec2 r0 = pos - floor(pos);
+    vec2 r1 = r0 - vec2(1.0);
+
+    // If stitching, wrap the lattice points around the tile.
+    if (stitch) {
+        if (b0.x >= stitch_info.z) {
+            b0.x -= stitch_info.x;
+        }
+        if (b1.x >= stitch_info.z) {
+            b1.x -= stitch_info.x;
+        }
+        if (b0.y >= stitch_info.w) {
+            b0.y -= stitch_info.y;
+        }
+        if (b1.y >= stitch_info.w) {
+            b1.y -= stitch_info.y;
+        }
+    }
+
+    int mask = TURBULENCE_LATTICE_SIZE - 1;
+    b0 &= ivec2(mask);
+    b1 &= ivec2(mask);
+
+    int i = TurbulenceLatticeSelector(b0.x);
+    int j = TurbulenceLatticeSelector(b1.x);
+    int b00 = TurbulenceLatticeSelector((i + b0.y) & mask);
+    int b10 = TurbulenceLatticeSelector((j + b0.y) & mask);
+    int b01 = TurbulenceLatticeSelector((i + b1.y) & mask);
+    int b11 = TurbulenceLatticeSelector((j + b1.y) & mask);
+
+    vec2 s = r0 * r0 * (3.0 - 2.0 * r0);
+
+    vec4 a = mix(TurbulenceGradientDot(b00, r0),
+                 TurbulenceGradientDot(b10, vec2(r1.x, r0.y)),
+                 s.x);
+    vec4 b = mix(TurbulenceGradientDot(b01, vec2(r0.x, r1.y)),
+                 TurbulenceGradientDot(b11, r1),
+                 s.x);
+    return mix(a, b, s.y);
+}
+
+vec4 Turbulence(int noise_type) {
+    vec2 pos = vLocalPos * vFilterData0.xy;
+    int octave_count = int(vFilterData0.z);
+    bool stitch = vFilterData0.w != 0.0;
+    ivec4 stitch_info = ivec4(vFilterData1);
+
+    vec4 sum = vec4(0.0);
+    float ratio = 1.0;
+    for (int octave = 0; octave < MAX_TURBULENCE_OCTAVES; octave++) {
+        if (octave >= octave_count) {
+            break;
+        }
+        vec4 noise = TurbulenceNoise(pos, stitch, stitch_info);
+        if (noise_type == TURBULENCE_TURBULENCE) {
+            noise = abs(noise);
+        }
+        sum += noise / ratio;
+
+        pos *= 2.0;
+        ratio *= 2.0;
+        stitch_info *= 2;
+    }
+
+    if (noise_type == TURBULENCE_FRACTAL_NOISE) {
+        sum = (sum + vec4(1.0)) * 0.5;
+    }
+    return clamp(sum, vec4(0.0), vec4(1.0));
+}
+
+// Lighting Filters
+
+float LightingInputAlpha(vec2 offset, vec2 texel_size) {
+    vec2 uv = vInput1Uv.xy + offset * texel_size;
+    return sampleInUvRect(sColor0, vec3(uv, vInput1Uv.z), vInput1UvRect).a;
+}
+
+// The surface normal of the input alpha, seen as a height map. This is the
+// Sobel filter given by the spec for interior pixels. Clamping the samples
+// to the input approximates the kernels it gives for edge pixels.
+vec3 SurfaceNormal(float surface_scale) {
+    vec2 texel_size = vec2(1.0) / vec2(textureSize(sColor0, 0).xy);
+
+    float top_left = LightingInputAlpha(vec2(-1.0, -1.0), texel_size);
+    float top = LightingInputAlpha(vec2(0.0, -1.0), texel_size);
+    float top_right = LightingInputAlpha(vec2(1.0, -1.0), texel_size);
+    float left = LightingInputAlpha(vec2(-1.0, 0.0), texel_size);
+    float right = LightingInputAlpha(vec2(1.0, 0.0), texel_size);
+    float bottom_left = LightingInputAlpha(vec2(-1.0, 1.0), texel_size);
+    float bottom = LightingInputAlpha(vec2(0.0, 1.0), texel_size);
+    float bottom_right = LightingInputAlpha(vec2(1.0, 1.0), texel_size);
+
+    vec2 normal = -surface_scale * 0.25 * vec2(
+        (top_right + 2.0 * right + bottom_right) - (top_left + 2.0 * left + bottom_left),
+        (bottom_left + 2.0 * bottom + bottom_right) - (top_left + 2.0 * top + top_right)
+    );
+    return normalize(vec3(normal, 1.0));
+}
+
+// The lighting gpu data is two header blocks, held in vFilterData0 and
+// vFilterData1, followed by two blocks describing the light source:
+// Distant: the unit vector towards the light.
+// Point: the position of the light.
+// Spot: the position of the light and the cosine of the limiting cone angle,
+// then the unit vector the light points in and its specular exponent.
+vec4 Lighting(vec4 Ca, bool specular) {
+    float surface_scale = vFilterData0.x;
+    float lighting_constant = vFilterData0.y;
+    float specular_exponent = vFilterData0.z;
+    vec3 light_color = vFilterData1.rgb;
+    vec4 light_data0 = fetch_from_gpu_cache_1_direct(vData.xy + ivec2(2, 0));
+    vec4 light_data1 = fetch_from_gpu_cache_1_direct(vData.xy + ivec2(3, 0));
+
+    vec3 N = SurfaceNormal(surface_scale);
+
+    vec3 L;
+    if (vData.z == LIGHT_SOURCE_DISTANT) {
+        L = light_data0.xyz;
+    } else {
+        vec3 surface = vec3(vLocalPos, surface_scale * Ca.a);
+        L = normalize(light_data0.xyz - surface);
+        if (vData.z == LIGHT_SOURCE_SPOT) {
+            float cos_angle = -dot(L, light_data1.xyz);
+            if (cos_angle < light_data0.w) {
+                light_color = vec3(0.0);
+            } else {
+                light_color *= pow(max(cos_angle, 0.0), light_data1.w);
+            }
+        }
+    }
+
+    vec4 result;
+    if (specular) {
+        vec3 H = normalize(L + vec3(0.0, 0.0, 1.0));
+        float intensity = lighting_constant * pow(max(dot(N, H), 0.0), specular_exponent);
+        result.rgb = clamp(intensity * light_color, vec3(0.0), vec3(1.0));
+        // The result is transparent where no light is reflected. Since the
+        // alpha is the largest channel, it is already premultiplied.
+        result.a = max(result.r, max(result.g, result.b));
+    } else {
+        float intensity = lighting_constant * max(dot(N, L), 0.0);
+        result.rgb = clamp(intensity * light_color, vec3(0.0), vec3(1.0));
+        result.a = 1.0;
+    }
+    return result;
+}
+
 void main(void) {
     vec4 Ca = vec4(0.0, 0.0, 0.0, 0.0);
     vec4 Cb = vec4(0.0, 0.0, 0.0, 0.0);
@@ -579,6 +938,52 @@ void main(void) {
             result = composite(Ca, Cb, vData.x);
             needsPremul = false;
             break;
+        case FILTER_MORPHOLOGY:
+            result = Morphology(vData.x, vData.y);
+            needsPremul = false;
+            break;
+        case FILTER_CONVOLVE_MATRIX:
+            result = ConvolveMatrix(Ca);
+            needsPremul = false;
+            break;
+        case FILTER_TURBULENCE:
+            result = Turbulence(vData.z);
+            break;
+        case FILTER_DIFFUSE_LIGHTING:
+            result = Lighting(Ca, false);
+            needsPremul = false;
+            break;
+        case FILTER_SPECULAR_LIGHTING:
+            result = Lighting(Ca, true);
+            needsPremul = false;
+            break;
+        case FILTER_IMAGE: {
+            vec2 imagePos = (vLocalPos - vFilterData1.xy) / vFilterData1.zw;
+            vec2 imageUv = vFilterData0.xy + imagePos * vFilterData0.zw;
+            result = sampleInUvRect(sColor0, vec3(imageUv, vInput1Uv.z), vInput1UvRect);
+            result *= point_inside_rect(imagePos, vec2(0.0), vec2(1.0));
+            needsPremul = false;
+            break;
+        }
+        case FILTER_TILE: {
+            if (any(lessThanEqual(vFilterData0.zw, vec2(0.0)))) {
+                result = vec4(0.0);
+            } else {
+                vec2 tileUv = vFilterData0.xy + mod(vInput1Uv.xy - vFilterData0.xy, vFilterData0.zw);
+                result = sampleInUvRect(sColor0, vec3(tileUv, vInput1Uv.z), vInput1UvRect);
+            }
+            needsPremul = false;
+            break;
+        }
+        case FILTER_DISPLACEMENT_MAP: {
+            // The displacement map is read from the unpremultiplied second input.
+            vec2 displacement = vec2(Cb[vData.x], Cb[vData.y]) - vec2(0.5);
+            vec2 displacedUv = vInput1Uv.xy + displacement * vFilterData0.xy;
+            result = sampleInUvRect(sColor0, vec3(displacedUv, vInput1Uv.z), vInput1UvRect);
+            result *= point_inside_rect(displacedUv, vFilterData1.xy, vFilterData1.zw);
+            needsPremul = false;
+            break;
+        }
         default:
             break;
     }
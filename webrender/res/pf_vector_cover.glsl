@@ -2,76 +2,122 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-#include shared
+#include shared,prim_shared
 
-#ifdef WR_VERTEX_SHADER
+// Has to match WINDING_SCALE in pf_vector_stencil.
+#define WINDING_SCALE       0.25
 
-in ivec4 aTargetRect;
-in ivec2 aStencilOrigin;
-in int aSubpixel;
-in int aPad;
+#define FILL_RULE_NONZERO   0
+#define FILL_RULE_EVENODD   1
 
-out vec2 vStencilUV;
-flat out int vSubpixel;
+#define PAINT_KIND_COLOR            0
+#define PAINT_KIND_LINEAR_GRADIENT  1
 
-void main(void) {
-    vec4 targetRect = vec4(aTargetRect);
-    vec2 stencilOrigin = vec2(aStencilOrigin);
+varying vec3 vStencilUv;
+varying vec2 vLocalPos;
 
-    vec2 targetOffset = mix(vec2(0.0), targetRect.zw, aPosition.xy);
-    vec2 targetPosition = targetRect.xy + targetOffset;
-    vec2 stencilOffset = targetOffset * vec2(aSubpixel == 0 ? 1.0 : 3.0, 1.0);
-    vec2 stencilPosition = stencilOrigin + stencilOffset;
+flat varying int vFillRule;
+flat varying int vPaintKind;
+flat varying vec4 vColor;
 
-    gl_Position = uTransform * vec4(targetPosition, aPosition.z, 1.0);
-    vStencilUV = stencilPosition;
-    vSubpixel = aSubpixel;
+flat varying HIGHP_FS_ADDRESS int vGradientAddress;
+flat varying float vGradientRepeat;
+flat varying vec2 vStartPoint;
+flat varying vec2 vScaledDir;
+
+#ifdef WR_VERTEX_SHADER
+
+in vec4 aTaskRect;
+in int aStencilTaskAddress;
+in int aFillRule;
+in int aPaintAddress;
+in int aStopsAddress;
+
+struct PathPaint {
+    vec4 color_or_points;
+    vec2 local_size;
+    int extend_mode;
+    int kind;
+};
+
+PathPaint fetch_path_paint(int address) {
+    vec4 data[2] = fetch_from_gpu_cache_2(address);
+    return PathPaint(
+        data[0],
+        data[1].xy,
+        int(data[1].z),
+        int(data[1].w)
+    );
+}
+
+void main(void) {
+    RenderTaskCommonData stencil_task = fetch_render_task_common_data(aStencilTaskAddress);
+    PathPaint paint = fetch_path_paint(aPaintAddress);
+
+    vec2 texture_size = vec2(textureSize(sPrevPassColor, 0).xy);
+    vec2 stencil_pos = stencil_task.task_rect.p0 + stencil_task.task_rect.size * aPosition.xy;
+    vStencilUv = vec3(stencil_pos / texture_size, stencil_task.texture_layer_index);
+
+    vLocalPos = paint.local_size * aPosition.xy;
+
+    vFillRule = aFillRule;
+    vPaintKind = paint.kind;
+
+    switch (paint.kind) {
+        case PAINT_KIND_LINEAR_GRADIENT: {
+            vec2 start_point = paint.color_or_points.xy;
+            vec2 dir = paint.color_or_points.zw - start_point;
+            vStartPoint = start_point;
+            vScaledDir = dir / dot(dir, dir);
+            vGradientAddress = aStopsAddress;
+            vGradientRepeat = float(paint.extend_mode != EXTEND_MODE_CLAMP);
+            vColor = vec4(0.0);
+            break;
+        }
+        default:
+            vStartPoint = vec2(0.0);
+            vScaledDir = vec2(0.0);
+            vGradientAddress = 0;
+            vGradientRepeat = 0.0;
+            vColor = paint.color_or_points;
+            break;
+    }
+
+    vec2 pos = aTaskRect.xy + aTaskRect.zw * aPosition.xy;
+    gl_Position = uTransform * vec4(pos, 0.0, 1.0);
 }
 
 #endif
 
 #ifdef WR_FRAGMENT_SHADER
 
-#define LCD_FILTER_FACTOR_0     (86.0 / 255.0)
-#define LCD_FILTER_FACTOR_1     (77.0 / 255.0)
-#define LCD_FILTER_FACTOR_2     (8.0  / 255.0)
-
-in vec2 vStencilUV;
-flat in int vSubpixel;
-
-/// Applies a slight horizontal blur to reduce color fringing on LCD screens
-/// when performing subpixel AA.
-///
-/// The algorithm should be identical to that of FreeType:
-/// https://www.freetype.org/freetype2/docs/reference/ft2-lcd_filtering.html
-float lcdFilter(float shadeL2, float shadeL1, float shade0, float shadeR1, float shadeR2) {
-    return LCD_FILTER_FACTOR_2 * shadeL2 +
-        LCD_FILTER_FACTOR_1 * shadeL1 +
-        LCD_FILTER_FACTOR_0 * shade0 +
-        LCD_FILTER_FACTOR_1 * shadeR1 +
-        LCD_FILTER_FACTOR_2 * shadeR2;
-}
-
 void main(void) {
-    ivec2 stencilUV = ivec2(vStencilUV);
-    float shade0 = abs(TEXEL_FETCH(sColor0, stencilUV, 0, ivec2(0, 0)).r);
+    vec4 stencil = texture(sPrevPassColor, vStencilUv);
+    float winding = abs(stencil.r - stencil.g) / WINDING_SCALE;
+
+    float coverage;
+    switch (vFillRule) {
+        case FILL_RULE_EVENODD:
+            coverage = 1.0 - abs(1.0 - mod(winding, 2.0));
+            break;
+        default:
+            coverage = min(winding, 1.0);
+            break;
+    }
 
-    if (vSubpixel == 0) {
-        oFragColor = vec4(shade0);
-        return;
+    vec4 color;
+    switch (vPaintKind) {
+        case PAINT_KIND_LINEAR_GRADIENT: {
+            float offset = dot(vLocalPos - vStartPoint, vScaledDir);
+            color = sample_gradient(vGradientAddress, offset, vGradientRepeat);
+            break;
+        }
+        default:
+            color = vColor;
+            break;
     }
 
-    vec3 shadeL = abs(vec3(TEXEL_FETCH(sColor0, stencilUV, 0, ivec2(-1, 0)).r,
-                           TEXEL_FETCH(sColor0, stencilUV, 0, ivec2(-2, 0)).r,
-                           TEXEL_FETCH(sColor0, stencilUV, 0, ivec2(-3, 0)).r));
-    vec3 shadeR = abs(vec3(TEXEL_FETCH(sColor0, stencilUV, 0, ivec2(1, 0)).r,
-                           TEXEL_FETCH(sColor0, stencilUV, 0, ivec2(2, 0)).r,
-                           TEXEL_FETCH(sColor0, stencilUV, 0, ivec2(3, 0)).r));
-
-    oFragColor = vec4(lcdFilter(shadeL.z, shadeL.y, shadeL.x, shade0,   shadeR.x),
-                      lcdFilter(shadeL.y, shadeL.x, shade0,   shadeR.x, shadeR.y),
-                      lcdFilter(shadeL.x, shade0,   shadeR.x, shadeR.y, shadeR.z),
-                      1.0);
+    oFragColor = color * coverage;
 }
 
 #endif
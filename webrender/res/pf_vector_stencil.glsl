@@ -4,72 +4,55 @@
 
 #include shared
 
+// Accumulates the signed area covered by each segment of a path outline,
+// so that the cover pass can derive a winding number per pixel. Positive
+// area goes to the red channel and negative area to the green channel,
+// since the target is unsigned. Both are scaled down so that a few
+// overlapping subpaths can be accumulated before saturating.
+#define WINDING_SCALE   0.25
+
+// Segment end points, relative to the current pixel center.
+varying vec2 vFrom;
+varying vec2 vCtrl;
+varying vec2 vTo;
+
 #ifdef WR_VERTEX_SHADER
 
+// Quadratic segment, in device pixels relative to the task origin. The
+// segment is expected to be monotonic in x.
 in vec2 aFromPosition;
 in vec2 aCtrlPosition;
 in vec2 aToPosition;
-in vec2 aFromNormal;
-in vec2 aCtrlNormal;
-in vec2 aToNormal;
-in int aPathID;
-in int aPad;
-
-out vec2 vFrom;
-out vec2 vCtrl;
-out vec2 vTo;
+in vec4 aTaskRect;
 
 void main(void) {
-    // Unpack.
-    int pathID = int(aPathID);
-
-    ivec2 pathAddress = ivec2(0.0, aPathID);
-    mat2 transformLinear = mat2(TEXEL_FETCH(sColor1, pathAddress, 0, ivec2(0, 0)));
-    vec2 transformTranslation = TEXEL_FETCH(sColor1, pathAddress, 0, ivec2(1, 0)).xy;
-
-    vec4 miscInfo = TEXEL_FETCH(sColor1, pathAddress, 0, ivec2(2, 0));
-    float rectHeight = miscInfo.y;
-    vec2 emboldenAmount = miscInfo.zw * 0.5;
-
-    // TODO(pcwalton): Hint positions.
-    vec2 from = aFromPosition;
-    vec2 ctrl = aCtrlPosition;
-    vec2 to = aToPosition;
+    vec2 from = aTaskRect.xy + aFromPosition;
+    vec2 ctrl = aTaskRect.xy + aCtrlPosition;
+    vec2 to = aTaskRect.xy + aToPosition;
 
-    // Embolden as necessary.
-    from -= aFromNormal * emboldenAmount;
-    ctrl -= aCtrlNormal * emboldenAmount;
-    to -= aToNormal * emboldenAmount;
+    vec2 task_p0 = aTaskRect.xy;
+    vec2 task_p1 = aTaskRect.xy + aTaskRect.zw;
 
-    // Perform the transform.
-    from = transformLinear * from + transformTranslation;
-    ctrl = transformLinear * ctrl + transformTranslation;
-    to = transformLinear * to + transformTranslation;
-
-    // Choose correct quadrant for rotation.
-    vec2 corner = vec2(0.0, rectHeight) + transformTranslation;
-
-    // Compute edge vectors. De Casteljau subdivide if necessary.
-    // TODO(pcwalton): Actually do the two-pass rendering.
-
-    // Compute position and dilate. If too thin, discard to avoid artefacts.
+    // Cover the horizontal extent of the segment, from its top down to the
+    // bottom of the task, since every pixel below the segment is affected.
+    // Vertical segments don't cover any area, so collapse them.
     vec2 position;
-    if (abs(from.x - to.x) < 0.0001)
-        position.x = 0.0;
-    else if (aPosition.x < 0.5)
+    if (abs(from.x - to.x) < 0.0001) {
+        position.x = task_p0.x;
+    } else if (aPosition.x < 0.5) {
         position.x = floor(min(min(from.x, to.x), ctrl.x));
-    else
+    } else {
         position.x = ceil(max(max(from.x, to.x), ctrl.x));
-    if (aPosition.y < 0.5)
+    }
+    if (aPosition.y < 0.5) {
         position.y = floor(min(min(from.y, to.y), ctrl.y));
-    else
-        position.y = corner.y;
+    } else {
+        position.y = task_p1.y;
+    }
+    position = clamp(position, task_p0, task_p1);
 
-    // Compute final position and depth.
-    vec4 clipPosition = uTransform * vec4(position, aPosition.z, 1.0);
+    gl_Position = uTransform * vec4(position, 0.0, 1.0);
 
-    // Finish up.
-    gl_Position = clipPosition;
     vFrom = from - position;
     vCtrl = ctrl - position;
     vTo = to - position;
@@ -79,14 +62,13 @@ void main(void) {
 
 #ifdef WR_FRAGMENT_SHADER
 
-uniform sampler2D uAreaLUT;
-
-in vec2 vFrom;
-in vec2 vCtrl;
-in vec2 vTo;
+// Integral of clamp(x, 0, 1) from 0 to x.
+float integrate_coverage(float x) {
+    float c = clamp(x, 0.0, 1.0);
+    return 0.5 * c * c + max(x - 1.0, 0.0);
+}
 
 void main(void) {
-    // Unpack.
     vec2 from = vFrom, ctrl = vCtrl, to = vTo;
 
     // Determine winding, and sort into a consistent order so we only need to find one root below.
@@ -96,16 +78,29 @@ void main(void) {
 
     // Shoot a vertical ray toward the curve.
     vec2 window = clamp(vec2(from.x, to.x), -0.5, 0.5);
-    float offset = mix(window.x, window.y, 0.5) - left.x;
+    float mid = mix(window.x, window.y, 0.5);
+    float offset = mid - left.x;
     float t = offset / (v0.x + sqrt(v1.x * offset - v0.x * (offset - v0.x)));
 
     // Compute position and derivative to form a line approximation.
     float y = mix(mix(left.y, ctrl.y, t), mix(ctrl.y, right.y, t), t);
     float d = mix(v0.y, v1.y, t) / mix(v0.x, v1.x, t);
 
-    // Look up area under that line, and scale horizontally to the window size.
-    float dX = window.x - window.y;
-    oFragColor = vec4(texture(sColor0, vec2(y + 8.0, abs(d * dX)) / 16.0).r * dX);
+    // Compute the average coverage of the part of the pixel below that line,
+    // over the horizontal window covered by the segment.
+    float f0 = 0.5 - (y + d * (min(window.x, window.y) - mid));
+    float f1 = 0.5 - (y + d * (max(window.x, window.y) - mid));
+    float coverage;
+    if (abs(f1 - f0) < 0.0001) {
+        coverage = clamp(f0, 0.0, 1.0);
+    } else {
+        coverage = (integrate_coverage(f1) - integrate_coverage(f0)) / (f1 - f0);
+    }
+
+    // Scale horizontally to the window size.
+    float area = coverage * (window.x - window.y);
+
+    oFragColor = vec4(max(area, 0.0), max(-area, 0.0), 0.0, 0.0) * WINDING_SCALE;
 }
 
 #endif
@@ -2,7 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use api::{AlphaType, ClipMode, ExternalImageType, ImageRendering};
+use api::{AlphaType, ClipMode, ExternalImageType, ImageRendering, MaskCompositeOperator};
 use api::{YuvColorSpace, YuvFormat, ColorDepth, ColorRange, PremultipliedColorF, RasterSpace};
 use api::units::*;
 use crate::clip::{ClipDataStore, ClipNodeFlags, ClipNodeRange, ClipItemKind, ClipStore};
@@ -20,6 +20,8 @@ use crate::prim_store::{DeferredResolve, EdgeAaSegmentMask, PrimitiveInstanceKin
 use crate::prim_store::{VisibleGradientTile, PrimitiveInstance, PrimitiveOpacity, SegmentInstanceIndex};
 use crate::prim_store::{BrushSegment, ClipMaskKind, ClipTaskIndex, VECS_PER_SEGMENT, SpaceMapper};
 use crate::prim_store::image::ImageSource;
+use crate::prim_store::text_run::TextClipSource;
+use crate::prim_store::vector_path::PathClipSource;
 use crate::render_backend::DataStores;
 use crate::render_target::RenderTargetContext;
 use crate::render_task_graph::{RenderTaskId, RenderTaskGraph};
@@ -58,6 +60,7 @@ pub enum BrushBatchKind {
     },
     YuvImage(ImageBufferKind, YuvFormat, ColorDepth, YuvColorSpace, ColorRange),
     RadialGradient,
+    ConicGradient,
     LinearGradient,
 }
 
@@ -1073,6 +1076,65 @@ impl BatchBuilder {
                     prim_vis_mask,
                 );
             }
+            PrimitiveInstanceKind::VectorPath { data_handle, ref cache_handle, .. } => {
+                // The GPU cache data is stored in the template and reused across
+                // frames and display lists.
+                let common_data = &ctx.data_stores.vector_path[data_handle].common;
+                let prim_cache_address = gpu_cache.get_address(&common_data.gpu_cache_handle);
+
+                // Paths that are too small to rasterize don't have a render task.
+                let cache_handle = match cache_handle {
+                    Some(cache_handle) => cache_handle,
+                    None => return,
+                };
+
+                let rt_cache_entry = ctx
+                    .resource_cache
+                    .get_cached_render_task(cache_handle);
+                let cache_item = ctx
+                    .resource_cache
+                    .get_texture_cache_item(&rt_cache_entry.handle);
+                let textures = BatchTextures::color(cache_item.texture_id);
+
+                let prim_header = PrimitiveHeader {
+                    local_rect: prim_rect,
+                    local_clip_rect: prim_info.combined_local_clip_rect,
+                    specific_prim_address: prim_cache_address,
+                    transform_id,
+                };
+
+                let prim_header_index = prim_headers.push(
+                    &prim_header,
+                    z_id,
+                    [
+                        ShaderColorMode::Image as i32 | ((AlphaType::PremultipliedAlpha as i32) << 16),
+                        RasterizationSpace::Local as i32,
+                        get_shader_opacity(1.0),
+                        0,
+                    ],
+                );
+
+                // The edges of a path are anti-aliased, so it always needs blending.
+                let batch_key = BatchKey {
+                    blend_mode: BlendMode::PremultipliedAlpha,
+                    kind: BatchKind::Brush(BrushBatchKind::Image(get_buffer_kind(cache_item.texture_id))),
+                    textures,
+                };
+
+                self.add_brush_instance_to_batches(
+                    batch_key,
+                    batch_features,
+                    bounding_rect,
+                    z_id,
+                    INVALID_SEGMENT_INDEX,
+                    EdgeAaSegmentMask::all(),
+                    clip_task_address.unwrap(),
+                    BrushFlags::PERSPECTIVE_INTERPOLATION,
+                    prim_header_index,
+                    cache_item.uv_rect_handle.as_int(gpu_cache),
+                    prim_vis_mask,
+                );
+            }
             PrimitiveInstanceKind::Picture { pic_index, segment_instance_index, .. } => {
                 let picture = &ctx.prim_store.pictures[pic_index.0];
                 let non_segmented_blend_mode = BlendMode::PremultipliedAlpha;
@@ -1224,13 +1286,12 @@ impl BatchBuilder {
                                                 z_id,
                                             });
                                         }
-                                        TileSurface::Texture { handle, .. } => {
-                                            let cache_item = ctx.resource_cache.texture_cache.get(handle);
+                                        TileSurface::Texture { descriptor, .. } => {
+                                            let surface = descriptor.resolve(ctx.resource_cache);
 
                                             let composite_tile = CompositeTile {
                                                 surface: CompositeTileSurface::Texture {
-                                                    texture_id: cache_item.texture_id,
-                                                    texture_layer: cache_item.texture_layer,
+                                                    surface,
                                                 },
                                                 rect: device_rect,
                                                 dirty_rect,
@@ -1382,6 +1443,66 @@ impl BatchBuilder {
                                             prim_vis_mask,
                                         );
                                     }
+                                    Filter::ColorLut3D { lut, size } => {
+                                        let cache_item = resolve_image(
+                                            ImageRequest {
+                                                key: *lut,
+                                                rendering: ImageRendering::Auto,
+                                                tile: None,
+                                            },
+                                            ctx.resource_cache,
+                                            gpu_cache,
+                                            deferred_resolves,
+                                        );
+
+                                        // Without a table the picture is drawn as is, which
+                                        // matches `Filter::Identity`.
+                                        let (filter_mode, user_data, lut_texture) = match cache_item.texture_id {
+                                            TextureSource::Invalid => (1, 0x10000i32, TextureSource::Invalid),
+                                            texture_id => (15, cache_item.uv_rect_handle.as_int(gpu_cache), texture_id),
+                                        };
+
+                                        let (uv_rect_address, textures) = render_tasks.resolve_surface(
+                                            surface_task.expect("bug: surface must be allocated by now"),
+                                            gpu_cache,
+                                        );
+
+                                        // The table is sampled from the second color slot.
+                                        let textures = BatchTextures {
+                                            colors: [
+                                                textures.colors[0],
+                                                lut_texture,
+                                                TextureSource::Invalid,
+                                            ],
+                                        };
+
+                                        let key = BatchKey::new(
+                                            BatchKind::Brush(BrushBatchKind::Blend),
+                                            BlendMode::PremultipliedAlpha,
+                                            textures,
+                                        );
+
+                                        let prim_header_index = prim_headers.push(&prim_header, z_id, [
+                                            uv_rect_address.as_int(),
+                                            filter_mode,
+                                            user_data,
+                                            *size as i32,
+                                        ]);
+
+                                        self.add_brush_instance_to_batches(
+                                            key,
+                                            batch_features,
+                                            bounding_rect,
+                                            z_id,
+                                            INVALID_SEGMENT_INDEX,
+                                            EdgeAaSegmentMask::empty(),
+                                            clip_task_address.unwrap(),
+                                            brush_flags,
+                                            prim_header_index,
+                                            0,
+                                            prim_vis_mask,
+                                        );
+                                    }
                                     _ => {
                                         let filter_mode = match filter {
                                             Filter::Identity => 1, // matches `Contrast(1)`
@@ -1400,6 +1521,7 @@ impl BatchBuilder {
                                             Filter::LinearToSrgb => 12,
                                             Filter::ComponentTransfer => unreachable!(),
                                             Filter::Flood(..) => 14,
+                                            Filter::ColorLut3D { .. } => unreachable!(),
                                         };
 
                                         let user_data = match filter {
@@ -1429,6 +1551,7 @@ impl BatchBuilder {
                                             Filter::Flood(_) => {
                                                 picture.extra_gpu_data_handles[0].as_int(gpu_cache)
                                             }
+                                            Filter::ColorLut3D { .. } => unreachable!(),
                                         };
 
                                         let (uv_rect_address, textures) = render_tasks.resolve_surface(
@@ -2329,6 +2452,87 @@ impl BatchBuilder {
                     );
                 }
             }
+            PrimitiveInstanceKind::ConicGradient { data_handle, ref visible_tiles_range, .. } => {
+                let prim_data = &ctx.data_stores.conic_grad[data_handle];
+                let specified_blend_mode = BlendMode::PremultipliedAlpha;
+
+                let mut prim_header = PrimitiveHeader {
+                    local_rect: prim_rect,
+                    local_clip_rect: prim_info.combined_local_clip_rect,
+                    specific_prim_address: GpuCacheAddress::INVALID,
+                    transform_id,
+                };
+
+                if visible_tiles_range.is_empty() {
+                    let non_segmented_blend_mode = if !prim_data.opacity.is_opaque ||
+                        prim_info.clip_task_index != ClipTaskIndex::INVALID ||
+                        transform_kind == TransformedRectKind::Complex
+                    {
+                        specified_blend_mode
+                    } else {
+                        BlendMode::None
+                    };
+
+                    let batch_params = BrushBatchParameters::shared(
+                        BrushBatchKind::ConicGradient,
+                        BatchTextures::no_texture(),
+                        [
+                            prim_data.stops_handle.as_int(gpu_cache),
+                            0,
+                            0,
+                            0,
+                        ],
+                        0,
+                    );
+
+                    prim_header.specific_prim_address = gpu_cache.get_address(&prim_data.gpu_cache_handle);
+
+                    let prim_header_index = prim_headers.push(
+                        &prim_header,
+                        z_id,
+                        batch_params.prim_user_data,
+                    );
+
+                    let segments = if prim_data.brush_segments.is_empty() {
+                        None
+                    } else {
+                        Some(prim_data.brush_segments.as_slice())
+                    };
+
+                    self.add_segmented_prim_to_batch(
+                        segments,
+                        prim_data.opacity,
+                        &batch_params,
+                        specified_blend_mode,
+                        non_segmented_blend_mode,
+                        batch_features,
+                        prim_header_index,
+                        bounding_rect,
+                        transform_kind,
+                        render_tasks,
+                        z_id,
+                        prim_info.clip_task_index,
+                        prim_vis_mask,
+                        ctx,
+                    );
+                } else {
+                    let visible_tiles = &ctx.scratch.gradient_tiles[*visible_tiles_range];
+
+                    self.add_gradient_tiles(
+                        visible_tiles,
+                        &prim_data.stops_handle,
+                        BrushBatchKind::ConicGradient,
+                        specified_blend_mode,
+                        bounding_rect,
+                        clip_task_address.unwrap(),
+                        gpu_cache,
+                        &prim_header,
+                        prim_headers,
+                        z_id,
+                        prim_vis_mask,
+                    );
+                }
+            }
             PrimitiveInstanceKind::Backdrop { data_handle } => {
                 let prim_data = &ctx.data_stores.backdrop[data_handle];
                 let backdrop_pic_index = prim_data.kind.pic_index;
@@ -2702,11 +2906,13 @@ impl PrimitiveInstance {
             PrimitiveInstanceKind::Picture { .. } |
             PrimitiveInstanceKind::TextRun { .. } |
             PrimitiveInstanceKind::LineDecoration { .. } |
+            PrimitiveInstanceKind::VectorPath { .. } |
             PrimitiveInstanceKind::NormalBorder { .. } |
             PrimitiveInstanceKind::ImageBorder { .. } |
             PrimitiveInstanceKind::Rectangle { .. } |
             PrimitiveInstanceKind::LinearGradient { .. } |
             PrimitiveInstanceKind::RadialGradient { .. } |
+            PrimitiveInstanceKind::ConicGradient { .. } |
             PrimitiveInstanceKind::PushClipChain |
             PrimitiveInstanceKind::PopClipChain |
             PrimitiveInstanceKind::Clear { .. } |
@@ -2786,6 +2992,17 @@ pub fn resolve_image(
     }
 }
 
+/// A run of consecutive image masks sharing a texture and a composite
+/// operator.
+#[derive(Debug)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct CompositedImageMasks {
+    pub texture: TextureSource,
+    pub composite_op: MaskCompositeOperator,
+    pub instances: Vec<ClipMaskInstance>,
+}
+
 /// A list of clip instances to be drawn into a target.
 #[derive(Debug)]
 #[cfg_attr(feature = "capture", derive(Serialize))]
@@ -2796,6 +3013,10 @@ pub struct ClipBatchList {
     pub fast_rectangles: Vec<ClipMaskInstance>,
     /// Image draws apply the image masking.
     pub images: FastHashMap<TextureSource, Vec<ClipMaskInstance>>,
+    /// Image masks of clip tasks using mask composite operators. These
+    /// depend on the order they are drawn in, so they are kept in clip
+    /// chain order and drawn before any other clip of the list.
+    pub composited_images: Vec<CompositedImageMasks>,
     pub box_shadows: FastHashMap<TextureSource, Vec<ClipMaskInstance>>,
 }
 
@@ -2805,15 +3026,37 @@ impl ClipBatchList {
             slow_rectangles: Vec::new(),
             fast_rectangles: Vec::new(),
             images: FastHashMap::default(),
+            composited_images: Vec::new(),
             box_shadows: FastHashMap::default(),
         }
     }
 
+    fn push_composited_image(
+        &mut self,
+        texture: TextureSource,
+        composite_op: MaskCompositeOperator,
+        instance: ClipMaskInstance,
+    ) {
+        if let Some(run) = self.composited_images.last_mut() {
+            if run.texture == texture && run.composite_op == composite_op {
+                run.instances.push(instance);
+                return;
+            }
+        }
+
+        self.composited_images.push(CompositedImageMasks {
+            texture,
+            composite_op,
+            instances: vec![instance],
+        });
+    }
+
     #[cfg(not(feature = "gl"))]
     pub fn is_empty(&self) -> bool {
         self.slow_rectangles.is_empty()
             && self.fast_rectangles.is_empty()
             && self.images.is_empty()
+            && self.composited_images.is_empty()
             && self.box_shadows.is_empty()
     }
 }
@@ -2997,7 +3240,30 @@ impl ClipBatcher {
     ) {
         let mut is_first_clip = true;
 
-        for i in 0 .. clip_node_range.count {
+        // Image masks combined with anything but an intersection depend on the
+        // order they are drawn in, and on the masks they are combined with not
+        // having been intersected with the other clips yet. When a clip task
+        // has any of them, all its image masks are drawn first, in clip chain
+        // order, followed by the other clips.
+        let image_mask_composite_op = |i| {
+            let clip_instance = clip_store.get_instance_from_range(&clip_node_range, i);
+            match clip_data_store[clip_instance.handle].item.kind {
+                ClipItemKind::Image { composite_op, .. } => Some(composite_op),
+                _ => None,
+            }
+        };
+        let has_composited_masks = (0 .. clip_node_range.count).any(|i| {
+            match image_mask_composite_op(i) {
+                Some(MaskCompositeOperator::Intersect) | None => false,
+                Some(..) => true,
+            }
+        });
+        let mut clip_order: SmallVec<[u32; 8]> = (0 .. clip_node_range.count).collect();
+        if has_composited_masks {
+            clip_order.sort_by_key(|&i| image_mask_composite_op(i).is_none());
+        }
+
+        for i in clip_order {
             let clip_instance = clip_store.get_instance_from_range(&clip_node_range, i);
             let clip_node = &clip_data_store[clip_instance.handle];
 
@@ -3030,7 +3296,7 @@ impl ClipBatcher {
             };
 
             let added_clip = match clip_node.item.kind {
-                ClipItemKind::Image { image, rect, .. } => {
+                ClipItemKind::Image { image, rect, composite_op, .. } => {
                     let request = ImageRequest {
                         key: image,
                         rendering: ImageRendering::Auto,
@@ -3040,6 +3306,14 @@ impl ClipBatcher {
                     let clip_data_address =
                         gpu_cache.get_address(&clip_node.gpu_cache_handle);
 
+                    // There is nothing to combine the first mask with, its
+                    // operator is ignored.
+                    let composite_op = if is_first_clip {
+                        MaskCompositeOperator::Intersect
+                    } else {
+                        composite_op
+                    };
+
                     let mut add_image = |request: ImageRequest, local_tile_rect: LayoutRect| {
                         let cache_item = match resource_cache.get_cached_image(request) {
                             Ok(item) => item,
@@ -3050,17 +3324,28 @@ impl ClipBatcher {
                             }
                         };
 
-                        self.get_batch_list(is_first_clip)
-                            .images
-                            .entry(cache_item.texture_id)
-                            .or_insert_with(Vec::new)
-                            .push(ClipMaskInstance {
-                                clip_data_address,
-                                resource_address: gpu_cache.get_address(&cache_item.uv_rect_handle),
-                                tile_rect: local_tile_rect,
-                                local_pos: rect.origin,
-                                ..instance
-                            });
+                        let instance = ClipMaskInstance {
+                            clip_data_address,
+                            resource_address: gpu_cache.get_address(&cache_item.uv_rect_handle),
+                            tile_rect: local_tile_rect,
+                            local_pos: rect.origin,
+                            ..instance
+                        };
+                        let batch_list = self.get_batch_list(is_first_clip);
+
+                        if has_composited_masks {
+                            batch_list.push_composited_image(
+                                cache_item.texture_id,
+                                composite_op,
+                                instance,
+                            );
+                        } else {
+                            batch_list
+                                .images
+                                .entry(cache_item.texture_id)
+                                .or_insert_with(Vec::new)
+                                .push(instance);
+                        }
                     };
 
                     match clip_instance.visible_tiles {
@@ -3104,6 +3389,35 @@ impl ClipBatcher {
 
                     true
                 }
+                ClipItemKind::Path { source: PathClipSource { ref cache_handle, .. }, .. } |
+                ClipItemKind::Text { source: TextClipSource { ref cache_handle, .. }, .. } => {
+                    // The path or glyph coverage is stored in the texture cache,
+                    // and applied the same way as a box-shadow mask.
+                    match *cache_handle {
+                        Some(ref rt_handle) => {
+                            let gpu_address =
+                                gpu_cache.get_address(&clip_node.gpu_cache_handle);
+                            let rt_cache_entry = resource_cache
+                                .get_cached_render_task(rt_handle);
+                            let cache_item = resource_cache
+                                .get_texture_cache_item(&rt_cache_entry.handle);
+                            debug_assert_ne!(cache_item.texture_id, TextureSource::Invalid);
+
+                            self.get_batch_list(is_first_clip)
+                                .box_shadows
+                                .entry(cache_item.texture_id)
+                                .or_insert_with(Vec::new)
+                                .push(ClipMaskInstance {
+                                    clip_data_address: gpu_address,
+                                    resource_address: gpu_cache.get_address(&cache_item.uv_rect_handle),
+                                    ..instance
+                                });
+
+                            true
+                        }
+                        None => false,
+                    }
+                }
                 ClipItemKind::Rectangle { rect, mode: ClipMode::ClipOut } => {
                     let gpu_address =
                         gpu_cache.get_address(&clip_node.gpu_cache_handle);
@@ -93,18 +93,22 @@
 //!
 
 use api::{BorderRadius, ClipIntern, ClipMode, ComplexClipRegion, ImageMask};
-use api::{BoxShadowClipMode, ImageKey, ImageRendering};
+use api::{BoxShadowClipMode, FillRule, ImageKey, ImageRendering, MaskCompositeOperator};
+use api::{GlyphInstance, MaskMode, PathClip};
 use api::units::*;
 use crate::border::{ensure_no_corner_overlap, BorderRadiusAu};
 use crate::box_shadow::{BLUR_SAMPLE_SCALE, BoxShadowClipSource, BoxShadowCacheKey};
 use crate::clip_scroll_tree::{ROOT_SPATIAL_NODE_INDEX, CoordinateSystemId, ClipScrollTree, SpatialNodeIndex};
 use crate::ellipse::Ellipse;
 use crate::gpu_cache::{GpuCache, GpuCacheHandle, ToGpuBlocks};
+use crate::glyph_rasterizer::FontInstance;
 use crate::gpu_types::{BoxShadowStretchMode};
 use crate::image::{self, Repetition};
 use crate::intern;
 use crate::prim_store::{ClipData, ImageMaskData, SpaceMapper, VisibleMaskImageTile};
 use crate::prim_store::{PointKey, SizeKey, RectangleKey};
+use crate::prim_store::text_run::TextClipSource;
+use crate::prim_store::vector_path::{PathClipSource, PathSegment};
 use crate::render_task_cache::to_cache_size;
 use crate::resource_cache::{ImageRequest, ResourceCache};
 use std::{cmp, ops, u32};
@@ -167,11 +171,13 @@ impl From<ClipItemKey> for ClipNode {
                     mode,
                 }
             }
-            ClipItemKeyKind::ImageMask(rect, image, repeat) => {
+            ClipItemKeyKind::ImageMask(rect, image, repeat, mode, composite_op) => {
                 ClipItemKind::Image {
                     image,
                     rect: rect.into(),
                     repeat,
+                    mode,
+                    composite_op,
                 }
             }
             ClipItemKeyKind::BoxShadow(shadow_rect_fract_offset, shadow_rect_size, shadow_radius, prim_shadow_rect, blur_radius, clip_mode) => {
@@ -184,6 +190,20 @@ impl From<ClipItemKey> for ClipNode {
                     clip_mode,
                 )
             }
+            ClipItemKeyKind::Path(rect, segments, fill_rule, mode) => {
+                let rect: LayoutRect = rect.into();
+                ClipItemKind::Path {
+                    rect,
+                    mode,
+                    source: PathClipSource::new(segments, fill_rule, rect.size),
+                }
+            }
+            ClipItemKeyKind::Text(rect, font, glyphs) => {
+                ClipItemKind::Text {
+                    rect: rect.into(),
+                    source: TextClipSource::new(font, glyphs),
+                }
+            }
         };
 
         ClipNode {
@@ -368,7 +388,7 @@ impl ClipNodeInfo {
 
         let mut visible_tiles = None;
 
-        if let ClipItemKind::Image { rect, image, repeat } = node.item.kind {
+        if let ClipItemKind::Image { rect, image, repeat, .. } = node.item.kind {
             let request = ImageRequest {
                 key: image,
                 rendering: ImageRendering::Auto,
@@ -442,10 +462,11 @@ impl ClipNode {
         device_pixel_scale: DevicePixelScale,
     ) {
         match self.item.kind {
-            ClipItemKind::Image { rect, .. } => {
+            ClipItemKind::Image { rect, mode, .. } => {
                 if let Some(request) = gpu_cache.request(&mut self.gpu_cache_handle) {
                     let data = ImageMaskData {
                         local_mask_size: rect.size,
+                        mode,
                     };
                     data.write_gpu_blocks(request);
                 }
@@ -511,6 +532,47 @@ impl ClipNode {
                     data.write(&mut request);
                 }
             }
+            ClipItemKind::Path { rect, mode, ref mut source } => {
+                // The rasterized path is applied with the box-shadow clip
+                // shader, stretched over the clip rect without any tiling.
+                if let Some(mut request) = gpu_cache.request(&mut self.gpu_cache_handle) {
+                    request.push([
+                        rect.size.width,
+                        rect.size.height,
+                        mode as i32 as f32,
+                        0.0,
+                    ]);
+                    request.push([
+                        BoxShadowStretchMode::Simple as i32 as f32,
+                        BoxShadowStretchMode::Simple as i32 as f32,
+                        0.0,
+                        0.0,
+                    ]);
+                    request.push(rect);
+                }
+
+                if let Some(mut request) = gpu_cache.request(&mut source.paint_handle) {
+                    source.write_paint_gpu_blocks(&mut request, rect.size);
+                }
+            }
+            ClipItemKind::Text { rect, .. } => {
+                // The rasterized glyphs are applied in the same way as a path.
+                if let Some(mut request) = gpu_cache.request(&mut self.gpu_cache_handle) {
+                    request.push([
+                        rect.size.width,
+                        rect.size.height,
+                        ClipMode::Clip as i32 as f32,
+                        0.0,
+                    ]);
+                    request.push([
+                        BoxShadowStretchMode::Simple as i32 as f32,
+                        BoxShadowStretchMode::Simple as i32 as f32,
+                        0.0,
+                        0.0,
+                    ]);
+                    request.push(rect);
+                }
+            }
         }
     }
 }
@@ -524,6 +586,11 @@ pub struct ClipStore {
 
     active_clip_node_info: Vec<ClipNodeInfo>,
     active_local_clip_rect: Option<LayoutRect>,
+    /// True if the active clips contain an image mask using a composite
+    /// operator other than intersect. The result of such a mask can extend
+    /// past the rects of the image masks it is combined with, so none of the
+    /// image masks can be used to reduce or reject the primitive region.
+    active_has_composited_masks: bool,
 }
 
 // A clip chain instance is what gets built for a given clip
@@ -682,6 +749,7 @@ impl ClipStore {
 
             active_clip_node_info: Vec::new(),
             active_local_clip_rect: None,
+            active_has_composited_masks: false,
         }
     }
 
@@ -722,6 +790,12 @@ impl ClipStore {
         self.active_clip_node_info.clear();
         self.active_local_clip_rect = None;
 
+        let clip_chain_nodes = &self.clip_chain_nodes;
+        self.active_has_composited_masks = clip_chains.iter().any(|clip_chain_id| {
+            let clip_chain_node = &clip_chain_nodes[clip_chain_id.0 as usize];
+            clip_data_store[clip_chain_node.handle].item.kind.is_composited_mask()
+        });
+
         let mut local_clip_rect = local_prim_clip_rect;
 
         for clip_chain_id in clip_chains {
@@ -732,6 +806,7 @@ impl ClipStore {
                 spatial_node_index,
                 &mut local_clip_rect,
                 &mut self.active_clip_node_info,
+                self.active_has_composited_masks,
                 clip_data_store,
                 clip_scroll_tree,
             ) {
@@ -756,6 +831,7 @@ impl ClipStore {
 
         self.active_clip_node_info.clear();
         self.active_local_clip_rect = Some(prim_clip_chain.local_clip_rect);
+        self.active_has_composited_masks = false;
 
         let clip_instances = &self
             .clip_node_instances[prim_clip_chain.clips_range.to_range()];
@@ -766,6 +842,7 @@ impl ClipStore {
                 clip_node.item.spatial_node_index,
                 clip_scroll_tree,
             );
+            self.active_has_composited_masks |= clip_node.item.kind.is_composited_mask();
             self.active_clip_node_info.push(ClipNodeInfo {
                 handle: clip_instance.handle,
                 conversion,
@@ -833,6 +910,14 @@ impl ClipStore {
                 }
             };
 
+            // A composited mask may cover the parts of the primitive outside
+            // of this mask, so it always needs to be drawn.
+            let clip_result = if self.active_has_composited_masks && node.item.kind.is_image_mask() {
+                ClipResult::Partial
+            } else {
+                clip_result
+            };
+
             if is_chased {
                 println!("\t\tclip {:?}", node.item);
                 println!("\t\tflags {:?}, resulted in {:?}", node_info.conversion.to_flags(), clip_result);
@@ -849,6 +934,15 @@ impl ClipStore {
                 ClipResult::Partial => {
                     // Needs a mask -> add to clip node indices
 
+                    // A text clip without any glyph coverage, such as an empty
+                    // or whitespace-only glyph run, has an empty mask, so it
+                    // clips out the whole primitive.
+                    if let ClipItemKind::Text { rect, ref mut source } = node.item.kind {
+                        if source.update(rect.size, device_pixel_scale, resource_cache).is_none() {
+                            return None;
+                        }
+                    }
+
                     // TODO(gw): Ensure this only runs once on each node per frame?
                     node.update(
                         gpu_cache,
@@ -874,7 +968,9 @@ impl ClipStore {
                             ClipItemKind::Rectangle { mode: ClipMode::ClipOut, .. } |
                             ClipItemKind::RoundedRectangle { .. } |
                             ClipItemKind::Image { .. } |
-                            ClipItemKind::BoxShadow { .. } => {
+                            ClipItemKind::BoxShadow { .. } |
+                            ClipItemKind::Path { .. } |
+                            ClipItemKind::Text { .. } => {
                                 true
                             }
 
@@ -934,6 +1030,10 @@ pub struct ClipRegion<I> {
     pub main: LayoutRect,
     pub image_mask: Option<ImageMask>,
     pub complex_clips: I,
+    /// An optional path clip, flattened relative to the origin of `main`.
+    pub path_clip: Option<(PathClip, Vec<PathSegment>)>,
+    /// An optional text clip, with glyphs relative to the origin of `main`.
+    pub text_clip: Option<(FontInstance, Vec<GlyphInstance>)>,
 }
 
 impl<J> ClipRegion<ComplexTranslateIter<J>> {
@@ -941,6 +1041,8 @@ impl<J> ClipRegion<ComplexTranslateIter<J>> {
         rect: LayoutRect,
         complex_clips: J,
         mut image_mask: Option<ImageMask>,
+        path_clip: Option<(PathClip, Vec<PathSegment>)>,
+        text_clip: Option<(FontInstance, Vec<GlyphInstance>)>,
         reference_frame_relative_offset: &LayoutVector2D,
     ) -> Self
     where
@@ -957,6 +1059,8 @@ impl<J> ClipRegion<ComplexTranslateIter<J>> {
                 source: complex_clips,
                 offset: *reference_frame_relative_offset,
             },
+            path_clip,
+            text_clip,
         }
     }
 }
@@ -970,6 +1074,8 @@ impl ClipRegion<Option<ComplexClipRegion>> {
             main: local_clip.translate(*reference_frame_relative_offset),
             image_mask: None,
             complex_clips: None,
+            path_clip: None,
+            text_clip: None,
         }
     }
 }
@@ -987,8 +1093,10 @@ impl ClipRegion<Option<ComplexClipRegion>> {
 pub enum ClipItemKeyKind {
     Rectangle(RectangleKey, ClipMode),
     RoundedRectangle(RectangleKey, BorderRadiusAu, ClipMode),
-    ImageMask(RectangleKey, ImageKey, bool),
+    ImageMask(RectangleKey, ImageKey, bool, MaskMode, MaskCompositeOperator),
     BoxShadow(PointKey, SizeKey, BorderRadiusAu, RectangleKey, Au, BoxShadowClipMode),
+    Path(RectangleKey, Vec<PathSegment>, FillRule, ClipMode),
+    Text(RectangleKey, FontInstance, Vec<GlyphInstance>),
 }
 
 impl ClipItemKeyKind {
@@ -1014,6 +1122,8 @@ impl ClipItemKeyKind {
             mask_rect.into(),
             image_mask.image,
             image_mask.repeat,
+            image_mask.mode,
+            image_mask.composite_op,
         )
     }
 
@@ -1041,6 +1151,28 @@ impl ClipItemKeyKind {
         )
     }
 
+    pub fn path(
+        rect: LayoutRect,
+        segments: Vec<PathSegment>,
+        fill_rule: FillRule,
+        mode: ClipMode,
+    ) -> Self {
+        ClipItemKeyKind::Path(
+            rect.into(),
+            segments,
+            fill_rule,
+            mode,
+        )
+    }
+
+    pub fn text(
+        rect: LayoutRect,
+        font: FontInstance,
+        glyphs: Vec<GlyphInstance>,
+    ) -> Self {
+        ClipItemKeyKind::Text(rect.into(), font, glyphs)
+    }
+
     pub fn node_kind(&self) -> ClipNodeKind {
         match *self {
             ClipItemKeyKind::Rectangle(_, ClipMode::Clip) => ClipNodeKind::Rectangle,
@@ -1048,7 +1180,9 @@ impl ClipItemKeyKind {
             ClipItemKeyKind::Rectangle(_, ClipMode::ClipOut) |
             ClipItemKeyKind::RoundedRectangle(..) |
             ClipItemKeyKind::ImageMask(..) |
-            ClipItemKeyKind::BoxShadow(..) => ClipNodeKind::Complex,
+            ClipItemKeyKind::BoxShadow(..) |
+            ClipItemKeyKind::Path(..) |
+            ClipItemKeyKind::Text(..) => ClipNodeKind::Complex,
         }
     }
 }
@@ -1086,10 +1220,21 @@ pub enum ClipItemKind {
         image: ImageKey,
         rect: LayoutRect,
         repeat: bool,
+        mode: MaskMode,
+        composite_op: MaskCompositeOperator,
     },
     BoxShadow {
         source: BoxShadowClipSource,
     },
+    Path {
+        rect: LayoutRect,
+        mode: ClipMode,
+        source: PathClipSource,
+    },
+    Text {
+        rect: LayoutRect,
+        source: TextClipSource,
+    },
 }
 
 #[derive(Debug, MallocSizeOf)]
@@ -1256,13 +1401,33 @@ impl ClipItemKind {
         ClipItemKind::BoxShadow { source }
     }
 
+    fn is_image_mask(&self) -> bool {
+        match *self {
+            ClipItemKind::Image { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Returns true if this is an image mask that is combined with the
+    /// preceding clips using an operator other than intersect.
+    pub fn is_composited_mask(&self) -> bool {
+        match *self {
+            ClipItemKind::Image { composite_op, .. } => {
+                composite_op != MaskCompositeOperator::Intersect
+            }
+            _ => false,
+        }
+    }
+
     /// Returns true if this clip mask can run through the fast path
     /// for the given clip item type.
     fn supports_fast_path_rendering(&self) -> bool {
         match *self {
             ClipItemKind::Rectangle { .. } |
             ClipItemKind::Image { .. } |
-            ClipItemKind::BoxShadow { .. } => {
+            ClipItemKind::BoxShadow { .. } |
+            ClipItemKind::Path { .. } |
+            ClipItemKind::Text { .. } => {
                 false
             }
             ClipItemKind::RoundedRectangle { ref radius, .. } => {
@@ -1291,6 +1456,9 @@ impl ClipItemKind {
                 }
             }
             ClipItemKind::BoxShadow { .. } => None,
+            ClipItemKind::Path { rect, mode: ClipMode::Clip, .. } => Some(rect),
+            ClipItemKind::Path { mode: ClipMode::ClipOut, .. } => None,
+            ClipItemKind::Text { rect, .. } => Some(rect),
         }
     }
 
@@ -1316,6 +1484,12 @@ impl ClipItemKind {
             ClipItemKind::Image { rect, repeat: false, .. } => {
                 (rect, None, ClipMode::Clip)
             }
+            ClipItemKind::Path { rect, mode, .. } => {
+                (rect, None, mode)
+            }
+            ClipItemKind::Text { rect, .. } => {
+                (rect, None, ClipMode::Clip)
+            }
             ClipItemKind::Image { repeat: true, .. } |
             ClipItemKind::BoxShadow { .. } => {
                 return ClipResult::Partial;
@@ -1450,6 +1624,21 @@ impl ClipItemKind {
             ClipItemKind::BoxShadow { .. } => {
                 ClipResult::Partial
             }
+            ClipItemKind::Path { rect, mode, .. } => {
+                // Nothing is known about which parts of the rect the path
+                // covers, so only primitives outside of it can be resolved.
+                match (rect.intersection(prim_rect), mode) {
+                    (Some(..), _) => ClipResult::Partial,
+                    (None, ClipMode::Clip) => ClipResult::Reject,
+                    (None, ClipMode::ClipOut) => ClipResult::Accept,
+                }
+            }
+            ClipItemKind::Text { rect, .. } => {
+                match rect.intersection(prim_rect) {
+                    Some(..) => ClipResult::Partial,
+                    None => ClipResult::Reject,
+                }
+            }
         }
     }
 }
@@ -1538,6 +1727,7 @@ fn add_clip_node_to_current_chain(
     spatial_node_index: SpatialNodeIndex,
     local_clip_rect: &mut LayoutRect,
     clip_node_info: &mut Vec<ClipNodeInfo>,
+    has_composited_masks: bool,
     clip_data_store: &ClipDataStore,
     clip_scroll_tree: &ClipScrollTree,
 ) -> bool {
@@ -1553,7 +1743,12 @@ fn add_clip_node_to_current_chain(
 
     // If we can convert spaces, try to reduce the size of the region
     // requested, and cache the conversion information for the next step.
-    if let Some(clip_rect) = clip_node.item.kind.get_local_clip_rect() {
+    let node_clip_rect = if has_composited_masks && clip_node.item.kind.is_image_mask() {
+        None
+    } else {
+        clip_node.item.kind.get_local_clip_rect()
+    };
+    if let Some(clip_rect) = node_clip_rect {
         match conversion {
             ClipSpaceConversion::Local => {
                 *local_clip_rect = match local_clip_rect.intersection(&clip_rect) {
@@ -3,7 +3,7 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use api::{ExternalScrollId, PropertyBinding, ReferenceFrameKind, TransformStyle};
-use api::{PipelineId, ScrollClamping, ScrollNodeState, ScrollLocation, ScrollSensitivity};
+use api::{OverscrollBehavior, PipelineId, ScrollClamping, ScrollNodeState, ScrollLocation, ScrollSensitivity};
 use api::units::*;
 use euclid::Transform3D;
 use crate::gpu_types::TransformPalette;
@@ -367,6 +367,7 @@ impl ClipScrollTree {
                     result.push(ScrollNodeState {
                         id,
                         scroll_offset: info.offset - info.external_scroll_offset,
+                        is_animating: info.animation.is_some(),
                     })
                 }
             }
@@ -410,32 +411,83 @@ impl ClipScrollTree {
         false
     }
 
-    fn find_nearest_scrolling_ancestor(
+    /// Returns the first scroll frame sensitive to input events found walking
+    /// up the tree from `index`, if any.
+    fn find_input_scroll_frame(
         &self,
         index: Option<SpatialNodeIndex>
-    ) -> SpatialNodeIndex {
-        let index = match index {
-            Some(index) => index,
-            None => return self.topmost_scroll_node_index(),
-        };
+    ) -> Option<SpatialNodeIndex> {
+        let index = index?;
 
         let node = &self.spatial_nodes[index.0 as usize];
         match node.node_type {
-            SpatialNodeType::ScrollFrame(state) if state.sensitive_to_input_events() => index,
-            _ => self.find_nearest_scrolling_ancestor(node.parent)
+            SpatialNodeType::ScrollFrame(state) if state.sensitive_to_input_events() => Some(index),
+            _ => self.find_input_scroll_frame(node.parent)
         }
     }
 
+    fn find_nearest_scrolling_ancestor(
+        &self,
+        index: Option<SpatialNodeIndex>
+    ) -> SpatialNodeIndex {
+        self.find_input_scroll_frame(index)
+            .unwrap_or_else(|| self.topmost_scroll_node_index())
+    }
+
+    /// Scrolls the scroll frame closest to `node_index`. Deltas that scroll
+    /// frame can't absorb chain up to the enclosing scroll frames, unless an
+    /// overscroll behavior stops them. Other scroll locations only apply to
+    /// the closest scroll frame.
     pub fn scroll_nearest_scrolling_ancestor(
         &mut self,
         scroll_location: ScrollLocation,
         node_index: Option<SpatialNodeIndex>,
+        now: u64,
     ) -> bool {
         if self.spatial_nodes.is_empty() {
             return false;
         }
         let node_index = self.find_nearest_scrolling_ancestor(node_index);
-        self.spatial_nodes[node_index.0 as usize].scroll(scroll_location)
+
+        let mut delta = match scroll_location {
+            ScrollLocation::Delta(delta) => delta,
+            _ => return self.spatial_nodes[node_index.0 as usize].scroll(scroll_location, now),
+        };
+
+        let mut scrolled = false;
+        let mut current = Some(node_index);
+        while let Some(index) = current {
+            let node = &mut self.spatial_nodes[index.0 as usize];
+            let (changed, remaining) = node.scroll_by_delta(delta);
+            scrolled |= changed;
+            if remaining == LayoutVector2D::zero() {
+                break;
+            }
+            delta = remaining;
+            let parent = node.parent;
+            current = self.find_input_scroll_frame(parent);
+        }
+
+        scrolled
+    }
+
+    /// Advances all smooth scroll and fling animations to `now`. Returns true
+    /// if any scroll node was animating.
+    pub fn tick_scroll_animations(&mut self, now: u64) -> bool {
+        let mut any_animating = false;
+        for node in &mut self.spatial_nodes {
+            if let SpatialNodeType::ScrollFrame(ref mut info) = node.node_type {
+                any_animating |= info.tick_animation(now);
+            }
+        }
+        any_animating
+    }
+
+    pub fn has_scroll_animations(&self) -> bool {
+        self.spatial_nodes.iter().any(|node| match node.node_type {
+            SpatialNodeType::ScrollFrame(ref info) => info.animation.is_some(),
+            _ => false,
+        })
     }
 
     pub fn update_tree(
@@ -521,6 +573,7 @@ impl ClipScrollTree {
         frame_rect: &LayoutRect,
         content_size: &LayoutSize,
         scroll_sensitivity: ScrollSensitivity,
+        overscroll_behavior: OverscrollBehavior,
         frame_kind: ScrollFrameKind,
         external_scroll_offset: LayoutVector2D,
     ) -> SpatialNodeIndex {
@@ -531,6 +584,7 @@ impl ClipScrollTree {
             frame_rect,
             content_size,
             scroll_sensitivity,
+            overscroll_behavior,
             frame_kind,
             external_scroll_offset,
         );
@@ -895,3 +949,64 @@ fn test_cst_translation_rotate() {
 
     test_pt(100.0, 0.0, &cst, child1, root, 0.0, -100.0);
 }
+
+#[test]
+fn test_cst_scroll_chaining() {
+    // Deltas the inner scroll frame can't absorb move the outer one,
+    // unless the inner scroll frame contains its overscroll.
+
+    fn build(overscroll_behavior: OverscrollBehavior) -> (ClipScrollTree, SpatialNodeIndex, SpatialNodeIndex) {
+        let mut cst = ClipScrollTree::new();
+        let root = add_reference_frame(
+            &mut cst,
+            None,
+            LayoutTransform::identity(),
+            LayoutVector2D::zero(),
+        );
+
+        let outer = cst.add_scroll_frame(
+            root,
+            None,
+            PipelineId::dummy(),
+            &LayoutRect::new(LayoutPoint::zero(), LayoutSize::new(100.0, 100.0)),
+            &LayoutSize::new(100.0, 300.0),
+            ScrollSensitivity::ScriptAndInputEvents,
+            OverscrollBehavior::Auto,
+            ScrollFrameKind::Explicit,
+            LayoutVector2D::zero(),
+        );
+
+        let inner = cst.add_scroll_frame(
+            outer,
+            None,
+            PipelineId::dummy(),
+            &LayoutRect::new(LayoutPoint::zero(), LayoutSize::new(100.0, 100.0)),
+            &LayoutSize::new(100.0, 200.0),
+            ScrollSensitivity::ScriptAndInputEvents,
+            overscroll_behavior,
+            ScrollFrameKind::Explicit,
+            LayoutVector2D::zero(),
+        );
+
+        (cst, outer, inner)
+    }
+
+    let delta = ScrollLocation::Delta(LayoutVector2D::new(0.0, -150.0));
+
+    let (mut cst, outer, inner) = build(OverscrollBehavior::Auto);
+    assert!(cst.scroll_nearest_scrolling_ancestor(delta, Some(inner), 0));
+    assert_eq!(cst.spatial_nodes[inner.0 as usize].scroll_offset(), LayoutVector2D::new(0.0, -100.0));
+    assert_eq!(cst.spatial_nodes[outer.0 as usize].scroll_offset(), LayoutVector2D::new(0.0, -50.0));
+
+    // Once the inner scroll frame is at its end, it passes everything on.
+    assert!(cst.scroll_nearest_scrolling_ancestor(delta, Some(inner), 0));
+    assert_eq!(cst.spatial_nodes[outer.0 as usize].scroll_offset(), LayoutVector2D::new(0.0, -200.0));
+
+    for &behavior in &[OverscrollBehavior::Contain, OverscrollBehavior::None] {
+        let (mut cst, outer, inner) = build(behavior);
+        assert!(cst.scroll_nearest_scrolling_ancestor(delta, Some(inner), 0));
+        assert!(!cst.scroll_nearest_scrolling_ancestor(delta, Some(inner), 0));
+        assert_eq!(cst.spatial_nodes[inner.0 as usize].scroll_offset(), LayoutVector2D::new(0.0, -100.0));
+        assert_eq!(cst.spatial_nodes[outer.0 as usize].scroll_offset(), LayoutVector2D::zero());
+    }
+}
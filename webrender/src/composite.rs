@@ -3,7 +3,11 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use api::ColorF;
-use api::units::DeviceRect;
+use api::units::{DeviceIntRect, DeviceIntSize, DeviceRect};
+#[cfg(feature = "gl")]
+use api::units::{DeviceIntPoint, DevicePixel};
+#[cfg(feature = "gl")]
+use euclid::Transform2D;
 use crate::gpu_types::{ZBufferId, ZBufferIdGenerator};
 use crate::internal_types::TextureSource;
 
@@ -12,13 +16,56 @@ use crate::internal_types::TextureSource;
  and/or OS compositor integration.
  */
 
+/// Selects how picture cache tiles are composited.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub enum CompositorKind {
+    /// WebRender draws the tiles into the framebuffer itself.
+    Draw,
+    /// The tiles are drawn into native surfaces, which are handed to a
+    /// `Compositor` implementation.
+    Native,
+}
+
+/// The surface a picture cache tile gets drawn into, once resolved for
+/// the current frame.
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub enum ResolvedSurfaceTexture {
+    /// A slice of a picture cache texture owned by WebRender.
+    TextureCache {
+        /// The texture ID to draw to.
+        texture: TextureSource,
+        /// Slice index in the texture array to draw to.
+        layer: i32,
+    },
+    /// A surface owned by the compositor.
+    NativeSurface {
+        /// The id the surface was created with.
+        id: NativeSurfaceId,
+        /// Size in device pixels of the surface.
+        size: DeviceIntSize,
+    },
+}
+
+impl ResolvedSurfaceTexture {
+    /// The texture array slice of the surface. Native surfaces only have one.
+    pub fn layer(&self) -> i32 {
+        match *self {
+            ResolvedSurfaceTexture::TextureCache { layer, .. } => layer,
+            ResolvedSurfaceTexture::NativeSurface { .. } => 0,
+        }
+    }
+}
+
 /// Describes the source surface information for a tile to be composited
 #[cfg_attr(feature = "capture", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
 pub enum CompositeTileSurface {
     Texture {
-        texture_id: TextureSource,
-        texture_layer: i32,
+        surface: ResolvedSurfaceTexture,
     },
     Color {
         color: ColorF,
@@ -52,17 +99,35 @@ pub struct CompositeState {
     // it gives us the ability to partial present for any non-scroll
     // case as a simple win (e.g. video, animation etc).
     pub dirty_rects_are_valid: bool,
+    /// How the tiles of this frame are meant to be composited.
+    pub compositor_kind: CompositorKind,
 }
 
 impl CompositeState {
-    pub fn new() -> Self {
+    pub fn new(compositor_kind: CompositorKind) -> Self {
         CompositeState {
             opaque_tiles: Vec::new(),
             alpha_tiles: Vec::new(),
             clear_tiles: Vec::new(),
             z_generator: ZBufferIdGenerator::new(0),
             dirty_rects_are_valid: true,
+            compositor_kind,
+        }
+    }
+
+    /// The region of the frame that changed, as the union of the dirty
+    /// rects of the tiles. `None` if the dirty rects can't be relied on.
+    pub fn damage_rect(&self) -> Option<DeviceIntRect> {
+        if !self.dirty_rects_are_valid {
+            return None;
+        }
+
+        let mut damage_rect = DeviceRect::zero();
+        for tile in self.opaque_tiles.iter().chain(self.alpha_tiles.iter()).chain(self.clear_tiles.iter()) {
+            damage_rect = damage_rect.union(&tile.dirty_rect);
         }
+
+        Some(damage_rect.round_out().to_i32())
     }
 
     #[cfg(not(feature = "gl"))]
@@ -72,3 +137,105 @@ impl CompositeState {
         && self.clear_tiles.is_empty()
     }
 }
+
+/// An arbitrary identifier for a native (OS compositor) surface.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct NativeSurfaceId(pub u64);
+
+/// Where WebRender draws into a native surface, as returned by
+/// `Compositor::bind`.
+#[cfg(feature = "gl")]
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct NativeSurfaceInfo {
+    /// An offset into the framebuffer at which the surface starts. Some
+    /// compositors allocate surfaces in larger atlases.
+    pub origin: DeviceIntPoint,
+    /// The GL framebuffer to draw into. It's expected to have a depth
+    /// attachment of at least the size of the surface.
+    pub fbo_id: u32,
+}
+
+/// Maps the pixels of a native surface into device space when it's
+/// composited.
+#[cfg(feature = "gl")]
+pub type CompositorSurfaceTransform = Transform2D<f32, DevicePixel, DevicePixel>;
+
+/// The native surface operations that the frame builder requests, applied
+/// by the renderer along with the texture cache updates.
+#[derive(Debug)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub enum NativeSurfaceOperation {
+    CreateSurface {
+        id: NativeSurfaceId,
+        size: DeviceIntSize,
+        is_opaque: bool,
+    },
+    DestroySurface {
+        id: NativeSurfaceId,
+    },
+}
+
+/// Defines an interface to a native (OS level) compositor. If supplied by
+/// the embedder through `RendererOptions::compositor`, picture cache tiles
+/// are drawn directly into surfaces owned by the compositor, which is then
+/// responsible for composing them into the final image. Surfaces are GL
+/// framebuffers, so this is only available with the GL device, and it
+/// requires picture caching.
+#[cfg(feature = "gl")]
+pub trait Compositor {
+    /// Create a new surface of the given size. Opaque surfaces are fully
+    /// covered by WebRender every time they are drawn.
+    fn create_surface(
+        &mut self,
+        id: NativeSurfaceId,
+        size: DeviceIntSize,
+        is_opaque: bool,
+    );
+
+    /// Destroy the surface with the given id. WebRender doesn't reference
+    /// it anymore after this call.
+    fn destroy_surface(
+        &mut self,
+        id: NativeSurfaceId,
+    );
+
+    /// Bind a surface for drawing. WebRender only updates the pixels
+    /// within `dirty_rect`, the rest of the surface has to keep the
+    /// contents it had when it was last unbound.
+    fn bind(
+        &mut self,
+        id: NativeSurfaceId,
+        dirty_rect: DeviceIntRect,
+    ) -> NativeSurfaceInfo;
+
+    /// Unbind the surface. This is called before any other surface is
+    /// bound, and before compositing begins.
+    fn unbind(&mut self);
+
+    /// Begin a frame of `device_size` device pixels. The surfaces added
+    /// from here on make up the visual tree of the frame.
+    fn begin_frame(&mut self, device_size: DeviceIntSize);
+
+    /// Add a surface to the visual tree of the frame, on top of the
+    /// surfaces added before it. The transform positions the surface in
+    /// device space, and the clip rect is in device space as well.
+    fn add_surface(
+        &mut self,
+        id: NativeSurfaceId,
+        transform: CompositorSurfaceTransform,
+        clip_rect: DeviceIntRect,
+    );
+
+    /// Finish the frame, and present the visual tree.
+    fn end_frame(&mut self);
+
+    /// Release the resources owned by the compositor, including any
+    /// surfaces that still exist. Called by `Renderer::deinit`, while the
+    /// rendering context is still current.
+    fn deinit(&mut self);
+}
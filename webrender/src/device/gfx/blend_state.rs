@@ -42,6 +42,40 @@ pub(super) const MIN: BlendState = BlendState {
     },
 };
 
+// Composite operators of clip masks, see `MaskCompositeOperator`.
+pub(super) const MASK_ADD: BlendState = BlendState {
+    color: BlendOp::Add {
+        src: Factor::One,
+        dst: Factor::OneMinusSrcColor,
+    },
+    alpha: BlendOp::Add {
+        src: Factor::One,
+        dst: Factor::OneMinusSrcAlpha,
+    },
+};
+
+pub(super) const MASK_SUBTRACT: BlendState = BlendState {
+    color: BlendOp::Add {
+        src: Factor::OneMinusDstColor,
+        dst: Factor::Zero,
+    },
+    alpha: BlendOp::Add {
+        src: Factor::OneMinusDstAlpha,
+        dst: Factor::Zero,
+    },
+};
+
+pub(super) const MASK_EXCLUDE: BlendState = BlendState {
+    color: BlendOp::Add {
+        src: Factor::OneMinusDstColor,
+        dst: Factor::OneMinusSrcColor,
+    },
+    alpha: BlendOp::Add {
+        src: Factor::OneMinusDstAlpha,
+        dst: Factor::OneMinusSrcAlpha,
+    },
+};
+
 pub(super) const SUBPIXEL_PASS0: BlendState = BlendState {
     color: BlendOp::Add {
         src: Factor::Zero,
@@ -0,0 +1,249 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use api::units::{DeviceIntRect, DeviceIntSize};
+use arrayvec::ArrayVec;
+use hal::command::CommandBuffer;
+use hal::device::Device;
+use hal::pso::{DescriptorSetLayoutBinding, DescriptorType as DT, ShaderStageFlags as SSF};
+use crate::gpu_types::BlurDirection;
+use rendy_descriptor::{DescriptorAllocator, DescriptorRanges, DescriptorSet};
+use super::MAX_FRAME_COUNT;
+use super::super::super::shader_source;
+
+/// The largest support the shared memory of `cs_blur_compute` can hold.
+/// Has to match MAX_SUPPORT in cs_blur_compute.glsl.
+pub const MAX_COMPUTE_BLUR_SUPPORT: i32 = 32;
+/// Has to match WORK_GROUP_SIZE in cs_blur_compute.glsl.
+const WORK_GROUP_SIZE: i32 = 128;
+/// Number of 32 bit values in the push constant block of the shader.
+const PUSH_CONSTANT_COUNT: usize = 15;
+/// Number of descriptor sets allocated at once when we run out of them.
+const DESCRIPTOR_SET_BATCH: u32 = 16;
+
+const BLUR_COMPUTE_SET: &'static [DescriptorSetLayoutBinding] = &[
+    // Source
+    DescriptorSetLayoutBinding {
+        binding: 0,
+        ty: DT::CombinedImageSampler,
+        count: 1,
+        stage_flags: SSF::COMPUTE,
+        immutable_samplers: false,
+    },
+    // Destination
+    DescriptorSetLayoutBinding {
+        binding: 1,
+        ty: DT::StorageImage,
+        count: 1,
+        stage_flags: SSF::COMPUTE,
+        immutable_samplers: false,
+    },
+];
+
+/// A single blur pass to be run by the compute pipeline.
+#[derive(Debug, Copy, Clone)]
+pub struct ComputeBlurJob {
+    pub src_rect: DeviceIntRect,
+    pub src_layer: i32,
+    pub blur_region: DeviceIntSize,
+    pub dest_rect: DeviceIntRect,
+    pub dest_layer: i32,
+    pub direction: BlurDirection,
+    pub std_deviation: f32,
+}
+
+impl ComputeBlurJob {
+    /// The number of texels sampled on each side, matching `cs_blur`.
+    pub fn support(&self) -> i32 {
+        (1.5 * self.std_deviation).ceil() as i32 * 2
+    }
+
+    /// Whether the kernel of this job fits in the shared memory of the shader.
+    pub fn is_supported(&self) -> bool {
+        self.support() <= MAX_COMPUTE_BLUR_SUPPORT
+    }
+
+    fn push_constants(&self) -> [u32; PUSH_CONSTANT_COUNT] {
+        [
+            self.src_rect.origin.x as u32,
+            self.src_rect.origin.y as u32,
+            self.src_rect.size.width as u32,
+            self.src_rect.size.height as u32,
+            self.dest_rect.origin.x as u32,
+            self.dest_rect.origin.y as u32,
+            self.dest_rect.size.width as u32,
+            self.dest_rect.size.height as u32,
+            self.blur_region.width as u32,
+            self.blur_region.height as u32,
+            self.src_layer as u32,
+            self.dest_layer as u32,
+            self.direction as u32,
+            self.support() as u32,
+            self.std_deviation.to_bits(),
+        ]
+    }
+
+    fn work_group_count(&self) -> hal::WorkGroupCount {
+        let (along, across) = match self.direction {
+            BlurDirection::Horizontal => (self.dest_rect.size.width, self.dest_rect.size.height),
+            BlurDirection::Vertical => (self.dest_rect.size.height, self.dest_rect.size.width),
+        };
+        [
+            ((along + WORK_GROUP_SIZE - 1) / WORK_GROUP_SIZE) as u32,
+            across as u32,
+            1,
+        ]
+    }
+}
+
+/// Pipeline running separable gaussian blurs as compute dispatches.
+pub(super) struct BlurComputePipeline<B: hal::Backend> {
+    set_layout: B::DescriptorSetLayout,
+    ranges: DescriptorRanges,
+    pipeline_layout: B::PipelineLayout,
+    pipeline: B::ComputePipeline,
+    shader_module: B::ShaderModule,
+    free_sets: Vec<DescriptorSet<B>>,
+    /// Descriptor sets referenced by the command buffer of each frame, they
+    /// can only be reused once the frame has finished on the GPU.
+    used_sets: ArrayVec<[Vec<DescriptorSet<B>>; MAX_FRAME_COUNT]>,
+}
+
+impl<B: hal::Backend> BlurComputePipeline<B> {
+    pub(super) fn new(
+        device: &B::Device,
+        pipeline_cache: Option<&B::PipelineCache>,
+        frame_count: usize,
+    ) -> Self {
+        let shader_module = unsafe {
+            device.create_shader_module(
+                shader_source::SPIRV_BINARIES
+                    .get("cs_blur_compute.comp.spv")
+                    .expect("create_shader_module failed"),
+            )
+        }
+        .expect("Failed to create cs module for: cs_blur_compute.comp.spv!");
+
+        let set_layout = unsafe {
+            device.create_descriptor_set_layout(
+                BLUR_COMPUTE_SET,
+                std::iter::empty::<&B::Sampler>(),
+            )
+        }
+        .expect("create_descriptor_set_layout failed");
+
+        let pipeline_layout = unsafe {
+            device.create_pipeline_layout(
+                Some(&set_layout),
+                &[(SSF::COMPUTE, 0..(PUSH_CONSTANT_COUNT * 4) as u32)],
+            )
+        }
+        .expect("create_pipeline_layout failed");
+
+        let pipeline = unsafe {
+            device.create_compute_pipeline(
+                &hal::pso::ComputePipelineDesc::new(
+                    hal::pso::EntryPoint {
+                        entry: "main",
+                        module: &shader_module,
+                        specialization: hal::pso::Specialization::default(),
+                    },
+                    &pipeline_layout,
+                ),
+                pipeline_cache,
+            )
+        }
+        .expect("Compute pipeline creation failed");
+
+        BlurComputePipeline {
+            set_layout,
+            ranges: DescriptorRanges::from_bindings(BLUR_COMPUTE_SET),
+            pipeline_layout,
+            pipeline,
+            shader_module,
+            free_sets: Vec::new(),
+            used_sets: (0 .. frame_count).map(|_| Vec::new()).collect(),
+        }
+    }
+
+    /// Records the blur jobs, reading from `source` and writing into `destination`.
+    /// The destination has to be in the `General` layout.
+    pub(super) unsafe fn dispatch(
+        &mut self,
+        device: &B::Device,
+        desc_allocator: &mut DescriptorAllocator<B>,
+        command_buffer: &mut B::CommandBuffer,
+        frame_index: usize,
+        source: &B::ImageView,
+        destination: &B::ImageView,
+        sampler: &B::Sampler,
+        jobs: &[ComputeBlurJob],
+    ) {
+        if self.free_sets.is_empty() {
+            desc_allocator
+                .allocate(
+                    device,
+                    &self.set_layout,
+                    self.ranges,
+                    DESCRIPTOR_SET_BATCH,
+                    &mut self.free_sets,
+                )
+                .expect("Allocate descriptor sets failed");
+        }
+        let set = self.free_sets.pop().unwrap();
+
+        device.write_descriptor_sets(vec![
+            hal::pso::DescriptorSetWrite {
+                set: set.raw(),
+                binding: 0,
+                array_offset: 0,
+                descriptors: Some(hal::pso::Descriptor::CombinedImageSampler(
+                    source,
+                    hal::image::Layout::ShaderReadOnlyOptimal,
+                    sampler,
+                )),
+            },
+            hal::pso::DescriptorSetWrite {
+                set: set.raw(),
+                binding: 1,
+                array_offset: 0,
+                descriptors: Some(hal::pso::Descriptor::Image(
+                    destination,
+                    hal::image::Layout::General,
+                )),
+            },
+        ]);
+
+        command_buffer.bind_compute_pipeline(&self.pipeline);
+        command_buffer.bind_compute_descriptor_sets(
+            &self.pipeline_layout,
+            0,
+            Some(set.raw()),
+            &[],
+        );
+        for job in jobs {
+            command_buffer.push_compute_constants(&self.pipeline_layout, 0, &job.push_constants());
+            command_buffer.dispatch(job.work_group_count());
+        }
+
+        self.used_sets[frame_index].push(set);
+    }
+
+    /// Makes the descriptor sets of a finished frame available again.
+    pub(super) fn reset(&mut self, frame_index: usize) {
+        self.free_sets.extend(self.used_sets[frame_index].drain(..));
+    }
+
+    pub(super) unsafe fn deinit(self, device: &B::Device, desc_allocator: &mut DescriptorAllocator<B>) {
+        desc_allocator.free(
+            self.free_sets
+                .into_iter()
+                .chain(self.used_sets.into_iter().flatten()),
+        );
+        device.destroy_compute_pipeline(self.pipeline);
+        device.destroy_pipeline_layout(self.pipeline_layout);
+        device.destroy_descriptor_set_layout(self.set_layout);
+        device.destroy_shader_module(self.shader_module);
+    }
+}
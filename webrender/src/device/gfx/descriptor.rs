@@ -121,13 +121,13 @@ impl From<ShaderKind> for DescriptorGroup {
             | ShaderKind::DebugColor
             | ShaderKind::DebugFont
             | ShaderKind::Service
-            | ShaderKind::VectorStencil
-            | ShaderKind::VectorCover => DescriptorGroup::Default,
+            | ShaderKind::VectorStencil => DescriptorGroup::Default,
             ShaderKind::ClipCache => DescriptorGroup::Clip,
             ShaderKind::Brush
             | ShaderKind::Cache(VertexArrayKind::Blur)
             | ShaderKind::Cache(VertexArrayKind::Scale)
             | ShaderKind::Cache(VertexArrayKind::SvgFilter)
+            | ShaderKind::VectorCover
             | ShaderKind::Primitive
             | ShaderKind::Text => DescriptorGroup::Primitive,
             _ => unimplemented!("No descriptor group for kind {:?}", kind),
@@ -19,7 +19,6 @@ use rendy_memory::{Block, DynamicConfig, Heaps, HeapsConfig, LinearConfig, Memor
 use rendy_descriptor::{DescriptorAllocator, DescriptorRanges, DescriptorSet};
 use ron::de::from_str;
 use smallvec::SmallVec;
-use std::borrow::Borrow;
 use std::cell::Cell;
 use std::convert::Into;
 use std::collections::hash_map::Entry;
@@ -35,6 +34,7 @@ use std::sync::{Arc, Mutex};
 use super::blend_state::*;
 use super::buffer::*;
 use super::command::*;
+use super::compute::{BlurComputePipeline, ComputeBlurJob};
 use super::descriptor::*;
 use super::image::*;
 use super::program::{Program, RenderPassDepthState};
@@ -44,7 +44,7 @@ use super::{LESS_EQUAL_TEST, LESS_EQUAL_WRITE, MAX_FRAME_COUNT};
 use super::vertex_types;
 
 use super::super::{BoundPBO, Capabilities};
-use super::super::{ShaderKind, ExternalTexture, GpuFrameId, TextureSlot, TextureFilter};
+use super::super::{ShaderKind, ExternalTexture, GpuFrameId, TextureSampler, TextureSlot, TextureFilter};
 use super::super::{VertexDescriptor, UploadMethod, Texel, TextureFlags, TextureFormatPair};
 use super::super::{
     Texture, DrawTarget, ReadTarget, FBOId, RBOId, PBO, VertexUsageHint, ShaderError,
@@ -57,7 +57,7 @@ use hal;
 use hal::adapter::PhysicalDevice;
 use hal::pso::{BlendState, DepthTest};
 use hal::device::Device as _;
-use hal::window::{Surface, SwapchainConfig, PresentationSurface};
+use hal::window::{Surface, Swapchain as _, SwapchainConfig, SwapImageIndex};
 use hal::pso::PipelineStage;
 use hal::queue::CommandQueue;
 use hal::command::{
@@ -76,6 +76,9 @@ pub const DEBUG_READ_FBO: FBOId = FBOId(2);
 const HEADLESS_FRAME_COUNT: usize = 1;
 const SURFACE_FORMAT: hal::format::Format = hal::format::Format::Bgra8Unorm;
 const DEPTH_FORMAT: hal::format::Format = hal::format::Format::D32Sfloat;
+/// Device features needed by the compute blur pipeline, render targets are
+/// written through storage images without a format qualifier.
+const COMPUTE_BLUR_FEATURES: hal::Features = hal::Features::SHADER_STORAGE_IMAGE_WRITE_WITHOUT_FORMAT;
 const DITHER_BINDING: usize = 8;
 
 #[derive(PartialEq)]
@@ -170,14 +173,15 @@ struct Fence<B: hal::Backend> {
 
 #[derive(Debug)]
 struct Frame<B: hal::Backend> {
-    swapchain_image: <B::Surface as PresentationSurface<B>>::SwapchainImage,
+    /// The index of the swapchain image acquired for the frame.
+    image_index: SwapImageIndex,
     framebuffers: FastHashMap<(hal::image::Layout, hal::image::Layout, bool), B::Framebuffer>,
 }
 
 impl<B: hal::Backend> Frame<B> {
-    fn new(swapchain_image: <B::Surface as PresentationSurface<B>>::SwapchainImage) -> Self {
+    fn new(image_index: SwapImageIndex) -> Self {
         Frame {
-            swapchain_image,
+            image_index,
             framebuffers: FastHashMap::default(),
         }
     }
@@ -185,6 +189,7 @@ impl<B: hal::Backend> Frame<B> {
     fn get_or_create_fbo(
         &mut self,
         device: &B::Device,
+        image_view: &B::ImageView,
         old_layout: hal::image::Layout,
         new_layout: hal::image::Layout,
         clear: bool,
@@ -202,7 +207,7 @@ impl<B: hal::Backend> Frame<B> {
             let framebuffer = unsafe {
                 device.create_framebuffer(
                     &render_passe,
-                    std::iter::once(self.swapchain_image.borrow()).chain(std::iter::once(depth)),
+                    std::iter::once(image_view).chain(std::iter::once(depth)),
                     image_extent,
                 )
             }
@@ -221,6 +226,69 @@ impl<B: hal::Backend> Frame<B> {
     }
 }
 
+/// The swapchain of the window surface. The presentation engine decides
+/// which image is acquired next, so the state of each image is tracked by
+/// the index `acquire_image` returns for it.
+struct WindowSwapchain<B: hal::Backend> {
+    swapchain: B::Swapchain,
+    image_views: Vec<B::ImageView>,
+    image_layouts: Vec<hal::image::Layout>,
+    /// The region of each image that is out of date compared to the last
+    /// presented frame, `None` meaning the whole image.
+    image_damage: Vec<Option<DeviceIntRect>>,
+}
+
+impl<B: hal::Backend> WindowSwapchain<B> {
+    fn new(device: &B::Device, surface: &mut B::Surface, config: SwapchainConfig) -> Self {
+        let format = config.format;
+        let (swapchain, images) = unsafe { device.create_swapchain(surface, config, None) }
+            .expect("create_swapchain failed");
+        let image_views: Vec<_> = images
+            .iter()
+            .map(|image| {
+                unsafe {
+                    device.create_image_view(
+                        image,
+                        hal::image::ViewKind::D2,
+                        format,
+                        hal::format::Swizzle::NO,
+                        hal::image::SubresourceRange {
+                            aspects: hal::format::Aspects::COLOR,
+                            levels: 0 .. 1,
+                            layers: 0 .. 1,
+                        },
+                    )
+                }
+                .expect("create_image_view failed")
+            })
+            .collect();
+
+        WindowSwapchain {
+            swapchain,
+            image_layouts: vec![hal::image::Layout::Undefined; image_views.len()],
+            image_damage: vec![None; image_views.len()],
+            image_views,
+        }
+    }
+
+    fn deinit(self, device: &B::Device) {
+        unsafe {
+            for view in self.image_views {
+                device.destroy_image_view(view);
+            }
+            device.destroy_swapchain(self.swapchain);
+        }
+    }
+}
+
+/// Returns the union of two damaged regions, `None` meaning everything.
+fn union_damage(a: Option<DeviceIntRect>, b: Option<DeviceIntRect>) -> Option<DeviceIntRect> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.union(&b)),
+        _ => None,
+    }
+}
+
 struct ClearValues {
     color: ClearValue,
     depth: Option<ClearValue>,
@@ -242,7 +310,13 @@ pub struct Device<B: hal::Backend> {
     staging_buffer_pool: ArrayVec<[BufferPool<B>; MAX_FRAME_COUNT]>,
     frame: Option<Frame<B>>,
     frame_depth: DepthBuffer<B>,
-    swapchain_image_layouts: ArrayVec<[hal::image::Layout; MAX_FRAME_COUNT]>,
+    swapchain: Option<WindowSwapchain<B>>,
+    /// Signaled once the image acquired from the swapchain is ready.
+    acquire_fence: B::Fence,
+    /// The region of the main framebuffer that changes in the current frame,
+    /// `None` meaning all of it. Only valid if `frame_damage_is_set`.
+    frame_damage: Option<DeviceIntRect>,
+    frame_damage_is_set: bool,
     readback_texture: Option<Texture>,
     render_passes: HalRenderPasses<B>,
     pub frame_count: usize,
@@ -353,6 +427,8 @@ pub struct Device<B: hal::Backend> {
     shader_is_ready: bool,
     rebind_descriptors: bool,
     render_target_memory: MemoryAllocator<B>,
+    /// Only present if the selected queue family supports compute work.
+    blur_compute: Option<BlurComputePipeline<B>>,
 }
 
 impl<B: hal::Backend> Device<B> {
@@ -424,7 +500,7 @@ impl<B: hal::Backend> Device<B> {
         let limits = adapter.physical_device.limits();
         let max_texture_size = limits.max_image_2d_size as i32;
 
-        let (device, queue_group_family, queue_group_queues) = {
+        let (device, queue_group_family, queue_group_queues, supports_compute_blur) = {
             use hal::queue::QueueFamily;
 
             let family = adapter
@@ -439,6 +515,25 @@ impl<B: hal::Backend> Device<B> {
                 })
                 .unwrap();
 
+            // Blurs can only run as compute dispatches if the queue accepts compute
+            // work and both render target formats can be used as storage images.
+            let mut supports_compute_blur = family.queue_type().supports_compute()
+                && features.contains(COMPUTE_BLUR_FEATURES)
+                && [hal::format::Format::Bgra8Unorm, hal::format::Format::R8Unorm]
+                    .iter()
+                    .all(|format| {
+                        adapter
+                            .physical_device
+                            .format_properties(Some(*format))
+                            .optimal_tiling
+                            .contains(hal::format::ImageFeature::STORAGE)
+                    });
+            let requested_features = if supports_compute_blur {
+                hal::Features::DUAL_SRC_BLENDING | COMPUTE_BLUR_FEATURES
+            } else {
+                hal::Features::DUAL_SRC_BLENDING
+            };
+
             let priorities = vec![1.0];
             let (id, families) = (family.id(), [(family, priorities.as_slice())]);
             let hal::adapter::Gpu {
@@ -447,21 +542,22 @@ impl<B: hal::Backend> Device<B> {
             } = unsafe {
                 adapter
                     .physical_device
-                    .open(&families, hal::Features::DUAL_SRC_BLENDING)
+                    .open(&families, requested_features)
                     .unwrap_or_else(|_| {
+                        supports_compute_blur = false;
                         adapter
                             .physical_device
                             .open(&families, hal::Features::empty())
                             .unwrap()
                     })
             };
-            (device, id, queue_groups.remove(id.0 as _).queues)
+            (device, id, queue_groups.remove(id.0 as _).queues, supports_compute_blur)
         };
 
         let render_passes =
             HalRenderPasses::create_render_passes(&device, SURFACE_FORMAT, DEPTH_FORMAT);
 
-        let (frame_depth, surface_format, dimensions, frame_count) =
+        let (frame_depth, surface_format, dimensions, frame_count, swapchain) =
             Self::init_drawables(&device, &mut heaps, &adapter, surface.as_mut(), dimensions);
 
         let viewport = hal::pso::Viewport {
@@ -501,7 +597,6 @@ impl<B: hal::Backend> Device<B> {
         let mut command_pools: ArrayVec<[CommandPool<B>; MAX_FRAME_COUNT]> = ArrayVec::new();
         let mut staging_buffer_pool = ArrayVec::new();
         let mut instance_buffers = ArrayVec::new();
-        let mut swapchain_image_layouts = ArrayVec::new();
         for _ in 0..frame_count {
             let fence = device.create_fence(false).expect("create_fence failed");
             frame_fence.push(Fence {
@@ -538,9 +633,10 @@ impl<B: hal::Backend> Device<B> {
                 (limits.optimal_buffer_copy_pitch_alignment - 1) as usize,
                 instance_buffer_size,
             ));
-            swapchain_image_layouts.push(hal::image::Layout::Undefined);
         }
 
+        let acquire_fence = device.create_fence(false).expect("create_fence failed");
+
         let mut command_buffer = command_pools[0].remove_cmd_buffer();
         // Start recording for the 1st frame
         unsafe { Self::begin_cmd_buffer(&mut command_buffer) };
@@ -695,9 +791,16 @@ impl<B: hal::Backend> Device<B> {
             None
         };
 
+        let blur_compute = if supports_compute_blur {
+            Some(BlurComputePipeline::new(&device, pipeline_cache.as_ref(), frame_count))
+        } else {
+            None
+        };
+
         let render_target_memory = MemoryAllocator::new(
             &device,
             &mut heaps,
+            Self::render_target_usage(blur_compute.is_some()),
         );
 
         let mut device = Device {
@@ -718,7 +821,10 @@ impl<B: hal::Backend> Device<B> {
             readback_texture: None,
             frame: None,
             frame_depth,
-            swapchain_image_layouts,
+            swapchain,
+            acquire_fence,
+            frame_damage: None,
+            frame_damage_is_set: false,
             frame_count,
             scissor_rect: viewport.rect,
             viewport,
@@ -818,6 +924,7 @@ impl<B: hal::Backend> Device<B> {
             shader_is_ready: false,
             rebind_descriptors: true,
             render_target_memory,
+            blur_compute,
         };
 
         if readback_supported || device.headless_mode() {
@@ -841,6 +948,25 @@ impl<B: hal::Backend> Device<B> {
         false
     }
 
+    /// The usage of render target images, which are also written as storage
+    /// images when blurs run through the compute pipeline.
+    fn render_target_usage(supports_compute_blur: bool) -> hal::image::Usage {
+        let usage = hal::image::Usage::TRANSFER_SRC
+            | hal::image::Usage::TRANSFER_DST
+            | hal::image::Usage::SAMPLED
+            | hal::image::Usage::COLOR_ATTACHMENT;
+        if supports_compute_blur {
+            usage | hal::image::Usage::STORAGE
+        } else {
+            usage
+        }
+    }
+
+    /// Whether blurs can be run with `dispatch_compute_blurs` instead of `cs_blur`.
+    pub fn supports_compute_blur(&self) -> bool {
+        self.blur_compute.is_some()
+    }
+
     pub fn enable_pixel_local_storage(&mut self, _enable: bool) {
         warn!("Pixel local storage not supported");
     }
@@ -1009,7 +1135,18 @@ impl<B: hal::Backend> Device<B> {
 
             self.uniform_buffer_handler.reset(self.next_id);
 
-            let (frame_depth, surface_format, dimensions, _frame_count) = Self::init_drawables(
+            // The framebuffers of the old swapchain images may still be in use.
+            // PresentationSurface also waits for the device to be idle before
+            // the swapchain is replaced.
+            self.device.wait_idle().expect("wait_idle failed");
+            if let Some(old_frame) = self.frame.take() {
+                old_frame.deinit(self.device.as_ref());
+            }
+            if let Some(old_swapchain) = self.swapchain.take() {
+                old_swapchain.deinit(self.device.as_ref());
+            }
+
+            let (frame_depth, surface_format, dimensions, _frame_count, swapchain) = Self::init_drawables(
                 self.device.as_ref(),
                 heaps,
                 &self.adapter,
@@ -1017,19 +1154,13 @@ impl<B: hal::Backend> Device<B> {
                 window_size.unwrap_or(self.dimensions),
             );
 
-            if let Some(old_frame) = self.frame.take() {
-                old_frame.deinit(self.device.as_ref());
-            }
+            self.swapchain = swapchain;
             let old_depth = mem::replace(&mut self.frame_depth, frame_depth);
             old_depth.deinit(self.device.as_ref(), heaps);
             self.dimensions = dimensions;
             self.surface_format = surface_format;
         }
 
-        for layout in self.swapchain_image_layouts.iter_mut() {
-            *layout = hal::image::Layout::Undefined;
-        }
-
         let old_readback_texture = self.readback_texture.take();
         if let Some(texture) = old_readback_texture {
             self.free_texture_unconditionaly(texture);
@@ -1057,8 +1188,8 @@ impl<B: hal::Backend> Device<B> {
         adapter: &hal::adapter::Adapter<B>,
         mut surface: Option<&mut B::Surface>,
         dimensions: (i32, i32),
-    ) -> (DepthBuffer<B>, ImageFormat, (i32, i32), usize) {
-        let (surface_format, extent, frame_count) = match surface.as_mut() {
+    ) -> (DepthBuffer<B>, ImageFormat, (i32, i32), usize, Option<WindowSwapchain<B>>) {
+        let (surface_format, extent, frame_count, swapchain) = match surface.as_mut() {
             Some(ref mut surface) => {
                 let caps = surface.capabilities(&adapter.physical_device);
                 let formats = surface.supported_formats(&adapter.physical_device);
@@ -1089,19 +1220,15 @@ impl<B: hal::Backend> Device<B> {
                 swap_config.image_count = 3;
 
                 let frame_cunt = swap_config.image_count as usize;
-                unsafe {
-                    surface
-                        .configure_swapchain(&device, swap_config)
-                        .expect("Can't configure swapchain");
-                };
-                (surface_format, window_extent, frame_cunt)
+                let swapchain = WindowSwapchain::new(device, surface, swap_config);
+                (surface_format, window_extent, frame_cunt, Some(swapchain))
             }
             None => {
                 let extent = hal::window::Extent2D {
                     width: dimensions.0 as u32,
                     height: dimensions.1 as u32,
                 };
-                (ImageFormat::BGRA8, extent, HEADLESS_FRAME_COUNT)
+                (ImageFormat::BGRA8, extent, HEADLESS_FRAME_COUNT, None)
             }
         };
         info!("Frame count {}", frame_count);
@@ -1113,6 +1240,7 @@ impl<B: hal::Backend> Device<B> {
             surface_format,
             (extent.width as i32, extent.height as i32),
             frame_count,
+            swapchain,
         )
     }
 
@@ -1179,6 +1307,9 @@ impl<B: hal::Backend> Device<B> {
         }
         self.bound_projection = Default::default();
         self.uniform_buffer_handler.reset(self.next_id);
+        if let Some(ref mut blur_compute) = self.blur_compute {
+            blur_compute.reset(self.next_id);
+        }
         self.staging_buffer_pool[self.next_id].reset();
         self.instance_buffers[self.next_id].reset(&mut self.free_instance_buffers);
         self.delete_retained_textures();
@@ -1990,6 +2121,10 @@ impl<B: hal::Backend> Device<B> {
                 usage_base | hal::image::Usage::COLOR_ATTACHMENT,
             ),
         };
+        let usage = match render_target {
+            Some(_) => usage | Self::render_target_usage(self.blur_compute.is_some()),
+            None => usage,
+        };
 
         let (memory, id) = match render_target {
             Some(RenderTargetInfo { has_depth: _, persistent, used_in_multiple_passes}) if !persistent => (Some(&mut self.render_target_memory), used_in_multiple_passes),
@@ -3222,8 +3357,13 @@ impl<B: hal::Backend> Device<B> {
                 hal::image::Layout::ColorAttachmentOptimal
             };
 
+            let image_index = self.frame.as_ref().unwrap().image_index as usize;
+            let swapchain = self.swapchain.as_mut().unwrap();
+            let old_layout = swapchain.image_layouts[image_index];
+            swapchain.image_layouts[image_index] = new_layout;
+
             let render_pass = self.render_passes.main_target_pass(
-                self.swapchain_image_layouts[self.next_id],
+                old_layout,
                 new_layout,
                 color_clear.is_some(),
             );
@@ -3236,14 +3376,14 @@ impl<B: hal::Backend> Device<B> {
 
             let frame_buffer = self.frame.as_mut().unwrap().get_or_create_fbo(
                 self.device.as_ref(),
-                self.swapchain_image_layouts[self.next_id],
+                &swapchain.image_views[image_index],
+                old_layout,
                 new_layout,
                 color_clear.is_some(),
                 &self.frame_depth.core.view,
                 rect,
                 &render_pass,
             );
-            self.swapchain_image_layouts[self.next_id] = new_layout;
             (render_pass, frame_buffer, rect)
         } else {
             let format = self.fbos[&self.bound_draw_fbo].format;
@@ -3472,6 +3612,92 @@ impl<B: hal::Backend> Device<B> {
         };
     }
 
+    /// Runs the blur jobs through the compute pipeline, reading from the bound
+    /// previous pass texture and writing into the bound draw target. Has to be
+    /// called outside of a render pass, after the pending clear of the target.
+    pub fn dispatch_compute_blurs(&mut self, jobs: &[ComputeBlurJob], color_target: bool) {
+        assert!(!self.inside_render_pass);
+        assert_ne!(self.bound_draw_fbo, DEFAULT_DRAW_FBO);
+        let blur_compute = self
+            .blur_compute
+            .as_mut()
+            .expect("Compute blurs are not supported by the device");
+
+        let source_sampler = if color_target {
+            TextureSampler::PrevPassColor
+        } else {
+            TextureSampler::PrevPassAlpha
+        };
+        let source_slot: TextureSlot = source_sampler.into();
+        let source_id = self.bound_textures[source_slot.0];
+        let source = &self.images[&source_id].core;
+        let destination = &self.images[&self.fbos[&self.bound_draw_fbo].texture_id].core;
+
+        unsafe {
+            if let Some((barrier, pipeline_stages)) = source.transit(
+                (
+                    hal::image::Access::SHADER_READ,
+                    hal::image::Layout::ShaderReadOnlyOptimal,
+                ),
+                source.subresource_range.clone(),
+            ) {
+                self.command_buffer.pipeline_barrier(
+                    pipeline_stages,
+                    hal::memory::Dependencies::empty(),
+                    &[barrier],
+                );
+            }
+            // The transitions above only wait for the fragment stages, so make
+            // the source visible for the compute stage too.
+            self.command_buffer.pipeline_barrier(
+                PipelineStage::FRAGMENT_SHADER..PipelineStage::COMPUTE_SHADER,
+                hal::memory::Dependencies::empty(),
+                &[hal::memory::Barrier::AllImages(
+                    hal::image::Access::SHADER_READ..hal::image::Access::SHADER_READ,
+                )],
+            );
+            if let Some((barrier, pipeline_stages)) = destination.transit(
+                (
+                    hal::image::Access::SHADER_WRITE,
+                    hal::image::Layout::General,
+                ),
+                destination.subresource_range.clone(),
+            ) {
+                self.command_buffer.pipeline_barrier(
+                    pipeline_stages,
+                    hal::memory::Dependencies::empty(),
+                    &[barrier],
+                );
+            }
+
+            blur_compute.dispatch(
+                self.device.as_ref(),
+                &mut self.desc_allocator,
+                &mut self.command_buffer,
+                self.next_id,
+                &source.view,
+                &destination.view,
+                &self.sampler_nearest,
+                jobs,
+            );
+
+            // Bring the target back into a renderable state for the render pass.
+            if let Some((barrier, pipeline_stages)) = destination.transit(
+                (
+                    hal::image::Access::COLOR_ATTACHMENT_WRITE,
+                    hal::image::Layout::ColorAttachmentOptimal,
+                ),
+                destination.subresource_range.clone(),
+            ) {
+                self.command_buffer.pipeline_barrier(
+                    pipeline_stages,
+                    hal::memory::Dependencies::empty(),
+                    &[barrier],
+                );
+            }
+        }
+    }
+
     pub fn enable_depth(&mut self) {
         assert!(
             self.depth_available,
@@ -3558,6 +3784,18 @@ impl<B: hal::Backend> Device<B> {
         self.set_blend_state(Some(BlendState::MULTIPLY));
     }
 
+    pub fn set_blend_mode_mask_add(&mut self) {
+        self.set_blend_state(Some(MASK_ADD));
+    }
+
+    pub fn set_blend_mode_mask_subtract(&mut self) {
+        self.set_blend_state(Some(MASK_SUBTRACT));
+    }
+
+    pub fn set_blend_mode_mask_exclude(&mut self) {
+        self.set_blend_state(Some(MASK_EXCLUDE));
+    }
+
     pub fn set_blend_mode_max(&mut self) {
         self.set_blend_state(Some(MAX));
     }
@@ -3566,6 +3804,10 @@ impl<B: hal::Backend> Device<B> {
         self.set_blend_state(Some(MIN));
     }
 
+    pub fn set_blend_mode_additive(&mut self) {
+        self.set_blend_state(Some(BlendState::ADD));
+    }
+
     pub fn set_blend_mode_subpixel_pass0(&mut self) {
         self.set_blend_state(Some(SUBPIXEL_PASS0));
     }
@@ -3630,32 +3872,101 @@ impl<B: hal::Backend> Device<B> {
                 );
             }
         }
+        if self.frame.is_some() {
+            return;
+        }
         unsafe {
-            if let Some(ref mut surface) = self.surface.as_mut() {
-                if self.frame.is_some() {
-                    return;
+            let acquired = match self.swapchain {
+                Some(ref mut swapchain) => {
+                    swapchain.swapchain.acquire_image(!0, None, Some(&self.acquire_fence))
                 }
-                match surface.acquire_image(!0) {
-                    Ok((swapchain_image, suboptimal)) => {
-                        if suboptimal.is_some() {
-                            warn!("The swapchain no longer matches the surface, but we still can use it.");
-                        }
-                        self.frame = Some(Frame::new(swapchain_image));
-                    }
-                    Err(acquire_error) => {
-                        error!("Acquire error {:?}, recrating swapchian.", acquire_error);
-                        self.recreate_swapchain(None);
+                None => return,
+            };
+            match acquired {
+                Ok((image_index, suboptimal)) => {
+                    // Block until the image is free, as PresentationSurface
+                    // does on its own. Waiting on a semaphore in the frame's
+                    // submission instead would need the semaphore to be
+                    // unsignaled again when the frame is dropped on resize.
+                    self.device
+                        .wait_for_fence(&self.acquire_fence, !0)
+                        .expect("wait_for_fence failed");
+                    self.device
+                        .reset_fence(&self.acquire_fence)
+                        .expect("reset_fence failed");
+                    if suboptimal.is_some() {
+                        warn!("The swapchain no longer matches the surface, but we still can use it.");
                     }
+                    self.frame = Some(Frame::new(image_index));
+                }
+                Err(acquire_error) => {
+                    error!("Acquire error {:?}, recrating swapchian.", acquire_error);
+                    self.recreate_swapchain(None);
                 }
             }
         }
     }
 
+    /// Whether the contents of the swapchain images are kept between frames,
+    /// so that only the damaged region of a frame has to be redrawn. The
+    /// images are still presented in full, as gfx-hal doesn't support
+    /// present regions.
+    pub fn preserves_swapchain_contents(&self) -> bool {
+        self.swapchain.is_some()
+    }
+
+    /// Record the region of the main framebuffer that changes this frame,
+    /// `None` meaning all of it. The damage of several calls within a frame
+    /// is accumulated. Frames without any recorded damage are assumed to
+    /// change the whole framebuffer.
+    pub fn add_frame_damage(&mut self, damage: Option<DeviceIntRect>) {
+        self.frame_damage = if self.frame_damage_is_set {
+            union_damage(self.frame_damage, damage)
+        } else {
+            damage
+        };
+        self.frame_damage_is_set = true;
+    }
+
+    /// Returns the region of the acquired swapchain image that has to be
+    /// redrawn for a frame that changes `frame_damage`, taking the frames
+    /// presented since the image was last used into account. `None` means
+    /// the whole image has to be redrawn.
+    pub fn buffer_damage(&self, frame_damage: DeviceIntRect) -> Option<DeviceIntRect> {
+        match (self.swapchain.as_ref(), self.frame.as_ref()) {
+            (Some(swapchain), Some(frame)) => union_damage(
+                swapchain.image_damage[frame.image_index as usize],
+                Some(frame_damage),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Update the damage of the swapchain images once the image at
+    /// `image_index` is presented, and reset the damage of the frame.
+    fn update_swapchain_image_damage(&mut self, image_index: usize) {
+        let frame_damage = if self.frame_damage_is_set {
+            self.frame_damage
+        } else {
+            None
+        };
+        let swapchain = self.swapchain.as_mut().unwrap();
+        for (index, damage) in swapchain.image_damage.iter_mut().enumerate() {
+            *damage = if index == image_index && self.frame_damage_is_set {
+                Some(DeviceIntRect::zero())
+            } else {
+                union_damage(*damage, frame_damage)
+            };
+        }
+        self.frame_damage = None;
+        self.frame_damage_is_set = false;
+    }
+
     pub fn submit_to_gpu(&mut self) {
         unsafe {
             self.command_buffer.finish();
-            match self.surface.as_mut() {
-                Some(surface) => {
+            match self.swapchain {
+                Some(_) => {
                     let submission = hal::queue::Submission {
                         command_buffers: &[&self.command_buffer],
                         wait_semaphores: None,
@@ -3667,11 +3978,16 @@ impl<B: hal::Backend> Device<B> {
 
                     let frame = self.frame.take().unwrap();
 
+                    // The presentation engine isn't told about the damage, as
+                    // gfx-hal doesn't expose incremental present. Only the
+                    // drawing into the swapchain image is limited to it.
+                    self.update_swapchain_image_damage(frame.image_index as usize);
+
                     // present frame
-                    match self.queue_group_queues[0].present_surface(
-                        surface,
-                        frame.swapchain_image,
-                        Some(&self.render_finished_semaphores[self.next_id]),
+                    let swapchain = self.swapchain.as_ref().unwrap();
+                    match self.queue_group_queues[0].present(
+                        std::iter::once((&swapchain.swapchain, frame.image_index)),
+                        std::iter::once(&self.render_finished_semaphores[self.next_id]),
                     ) {
                         Ok(suboptimal) => {
                             if suboptimal.is_some() {
@@ -3829,8 +4145,8 @@ impl<B: hal::Backend> Device<B> {
                 frame.deinit(self.device.as_ref())
             }
             self.frame_depth.deinit(self.device.as_ref(), &mut heaps);
-            if let Some(mut surface) = self.surface {
-                surface.unconfigure_swapchain(&self.device);
+            if let Some(swapchain) = self.swapchain {
+                swapchain.deinit(self.device.as_ref());
             }
             for (_, image) in self.images {
                 image.deinit(self.device.as_ref(), &mut heaps);
@@ -3859,6 +4175,9 @@ impl<B: hal::Backend> Device<B> {
             self.per_pass_descriptors.free(&mut self.desc_allocator);
 
             self.uniform_buffer_handler.deinit(self.device.as_ref(), &mut heaps, &mut self.desc_allocator);
+            if let Some(blur_compute) = self.blur_compute {
+                blur_compute.deinit(self.device.as_ref(), &mut self.desc_allocator);
+            }
             self.desc_allocator.dispose(self.device.as_ref());
             for (_, program) in self.programs {
                 program.deinit(self.device.as_ref(), &mut heaps)
@@ -3877,6 +4196,7 @@ impl<B: hal::Backend> Device<B> {
             for fence in self.frame_fence {
                 self.device.destroy_fence(fence.inner);
             }
+            self.device.destroy_fence(self.acquire_fence);
             for semaphore in self.render_finished_semaphores {
                 self.device.destroy_semaphore(semaphore);
             }
@@ -50,11 +50,8 @@ impl<B: hal::Backend> MemoryBlock<B> {
         device: &B::Device,
         heaps: &mut Heaps<B>,
         size: u64,
+        usage: hal::image::Usage,
     ) -> Self {
-        let usage = hal::image::Usage::TRANSFER_SRC
-            | hal::image::Usage::TRANSFER_DST
-            | hal::image::Usage::SAMPLED
-            | hal::image::Usage::COLOR_ATTACHMENT;
 
         //Dummy image to get image requirements
         let image = unsafe {
@@ -139,16 +136,20 @@ impl<B: hal::Backend> MemoryBlock<B> {
 pub(super) struct MemoryAllocator<B: hal::Backend>  {
     per_pass_block: MemoryBlock<B>,
     per_frame_blocks: Vec<MemoryBlock<B>>,
+    /// The usage of the render target images bound to the memory blocks.
+    usage: hal::image::Usage,
 }
 
 impl<B: hal::Backend> MemoryAllocator<B> {
     pub(super) fn new(
         device: &B::Device,
         heaps: &mut Heaps<B>,
+        usage: hal::image::Usage,
     ) -> Self {
         MemoryAllocator {
-            per_pass_block: MemoryBlock::new(device, heaps, PER_PASS_RENDER_TARGET_MEMORY_SIZE),
-            per_frame_blocks: vec![MemoryBlock::new(device, heaps, PER_FRAME_RENDER_TARGET_MEMORY_SIZE)],
+            per_pass_block: MemoryBlock::new(device, heaps, PER_PASS_RENDER_TARGET_MEMORY_SIZE, usage),
+            per_frame_blocks: vec![MemoryBlock::new(device, heaps, PER_FRAME_RENDER_TARGET_MEMORY_SIZE, usage)],
+            usage,
         }
     }
 
@@ -170,7 +171,7 @@ impl<B: hal::Backend> MemoryAllocator<B> {
                     TextureScope::Frame,
                 )
             }) {
-                let mut memory_block = MemoryBlock::new(device, heaps, PER_FRAME_RENDER_TARGET_MEMORY_SIZE.max(size));
+                let mut memory_block = MemoryBlock::new(device, heaps, PER_FRAME_RENDER_TARGET_MEMORY_SIZE.max(size), self.usage);
                 memory_block.bind_image(
                     device,
                     image,
@@ -335,6 +336,8 @@ impl<B: hal::Backend> ImageCore<B> {
             Layout::DepthStencilAttachmentOptimal => {
                 PipelineStage::EARLY_FRAGMENT_TESTS | PipelineStage::LATE_FRAGMENT_TESTS
             }
+            // Only used for storage images written by compute shaders
+            Layout::General => PipelineStage::COMPUTE_SHADER,
             state => unimplemented!("State not covered {:?}", state),
         }
     }
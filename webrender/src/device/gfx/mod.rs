@@ -5,6 +5,7 @@
 mod blend_state;
 mod buffer;
 mod command;
+mod compute;
 mod descriptor;
 mod device;
 mod image;
@@ -14,6 +15,7 @@ pub(crate) mod vertex_types;
 
 pub use self::device::*;
 pub use self::buffer::{BufferMemorySlice, GpuCacheBuffer, PersistentlyMappedBuffer};
+pub use self::compute::ComputeBlurJob;
 
 use hal;
 use crate::internal_types::FastHashMap;
@@ -194,6 +196,42 @@ impl PrimitiveType for crate::render_target::GradientJob {
     }
 }
 
+impl PrimitiveType for crate::gpu_types::VectorStencilInstance {
+    type Primitive = vertex_types::VectorStencilInstance;
+    fn to_primitive_type(&self) -> vertex_types::VectorStencilInstance {
+        vertex_types::VectorStencilInstance {
+            aFromPosition: [self.from_position.x, self.from_position.y],
+            aCtrlPosition: [self.ctrl_position.x, self.ctrl_position.y],
+            aToPosition: [self.to_position.x, self.to_position.y],
+            aTaskRect: [
+                self.task_rect.origin.x,
+                self.task_rect.origin.y,
+                self.task_rect.size.width,
+                self.task_rect.size.height,
+            ],
+        }
+    }
+}
+
+impl PrimitiveType for crate::gpu_types::VectorCoverInstance {
+    type Primitive = vertex_types::VectorCoverInstance;
+    fn to_primitive_type(&self) -> vertex_types::VectorCoverInstance {
+        vertex_types::VectorCoverInstance {
+            aData: [0, 0, 0, 0],
+            aTaskRect: [
+                self.task_rect.origin.x,
+                self.task_rect.origin.y,
+                self.task_rect.size.width,
+                self.task_rect.size.height,
+            ],
+            aStencilTaskAddress: self.stencil_task_address,
+            aFillRule: self.fill_rule,
+            aPaintAddress: self.paint_address,
+            aStopsAddress: self.stops_address,
+        }
+    }
+}
+
 impl PrimitiveType for crate::gpu_types::CompositeInstance {
     type Primitive = vertex_types::CompositeInstance;
     fn to_primitive_type(&self) -> vertex_types::CompositeInstance {
@@ -214,6 +214,9 @@ impl<B: hal::Backend> Program<B> {
                         RPDS::Disabled,
                         None,
                     ),
+                    // Glyphs of text clips.
+                    (surface_format, Some(MAX), RPDS::Enabled, None),
+                    (surface_format, Some(MAX), RPDS::Disabled, None),
                 ],
                 ShaderKind::Cache(VertexArrayKind::Blur) => {
                     if features.contains(&"ALPHA_TARGET") {
@@ -230,6 +233,13 @@ impl<B: hal::Backend> Program<B> {
                         ]
                     }
                 }
+                ShaderKind::VectorStencil => vec![
+                    (surface_format, Some(BlendState::ADD), RPDS::Enabled, None),
+                    (surface_format, Some(BlendState::ADD), RPDS::Disabled, None),
+                ],
+                ShaderKind::VectorCover => {
+                    vec![(surface_format, None, RPDS::Disabled, None)]
+                }
                 ShaderKind::Cache(VertexArrayKind::Border)
                 | ShaderKind::Cache(VertexArrayKind::LineDecoration) => vec![(
                     surface_format,
@@ -251,6 +261,12 @@ impl<B: hal::Backend> Program<B> {
                         RPDS::Disabled,
                         None,
                     ),
+                    (ImageFormat::R8, Some(MASK_ADD), RPDS::Enabled, None),
+                    (ImageFormat::R8, Some(MASK_ADD), RPDS::Disabled, None),
+                    (ImageFormat::R8, Some(MASK_SUBTRACT), RPDS::Enabled, None),
+                    (ImageFormat::R8, Some(MASK_SUBTRACT), RPDS::Disabled, None),
+                    (ImageFormat::R8, Some(MASK_EXCLUDE), RPDS::Enabled, None),
+                    (ImageFormat::R8, Some(MASK_EXCLUDE), RPDS::Disabled, None),
                 ],
                 ShaderKind::Text => {
                     if features.contains(&"DUAL_SOURCE_BLENDING") {
@@ -22,6 +22,8 @@ pub(super) struct HalRenderPasses<B: hal::Backend> {
 
     // main target passes
     pub(super) cao_to_present: B::RenderPass,
+    pub(super) present_to_cao: B::RenderPass,
+    pub(super) present_to_present: B::RenderPass,
     pub(super) undef_to_cao: B::RenderPass,
     pub(super) undef_to_present: B::RenderPass,
 }
@@ -63,6 +65,8 @@ impl<B: hal::Backend> HalRenderPasses<B> {
                 &self.bgra8_depth
             }
             (Layout::ColorAttachmentOptimal, Layout::Present, false) => &self.cao_to_present,
+            (Layout::Present, Layout::ColorAttachmentOptimal, false) => &self.present_to_cao,
+            (Layout::Present, Layout::Present, false) => &self.present_to_present,
             (Layout::Present, Layout::ColorAttachmentOptimal, true) => &self.undef_to_cao,
             (Layout::Present, Layout::Present, true) => &self.undef_to_present,
             (Layout::Undefined, Layout::ColorAttachmentOptimal, true) => &self.undef_to_cao,
@@ -84,6 +88,8 @@ impl<B: hal::Backend> HalRenderPasses<B> {
             device.destroy_render_pass(self.rgbaf32_depth);
 
             device.destroy_render_pass(self.cao_to_present);
+            device.destroy_render_pass(self.present_to_cao);
+            device.destroy_render_pass(self.present_to_present);
             device.destroy_render_pass(self.undef_to_cao);
             device.destroy_render_pass(self.undef_to_present);
         }
@@ -182,6 +188,30 @@ impl<B: hal::Backend> HalRenderPasses<B> {
             layouts: Layout::ColorAttachmentOptimal..Layout::Present,
         };
 
+        // Used for partial redraws, which keep the contents of the last frame
+        // presented with the swapchain image.
+        let attachment_present_to_cao = hal::pass::Attachment {
+            format: Some(surface_format),
+            samples: 1,
+            ops: hal::pass::AttachmentOps::new(
+                hal::pass::AttachmentLoadOp::Load,
+                hal::pass::AttachmentStoreOp::Store,
+            ),
+            stencil_ops: hal::pass::AttachmentOps::DONT_CARE,
+            layouts: Layout::Present..Layout::ColorAttachmentOptimal,
+        };
+
+        let attachment_present_to_present = hal::pass::Attachment {
+            format: Some(surface_format),
+            samples: 1,
+            ops: hal::pass::AttachmentOps::new(
+                hal::pass::AttachmentLoadOp::Load,
+                hal::pass::AttachmentStoreOp::Store,
+            ),
+            stencil_ops: hal::pass::AttachmentOps::DONT_CARE,
+            layouts: Layout::Present..Layout::Present,
+        };
+
         let attachment_undef_to_cao = hal::pass::Attachment {
             format: Some(surface_format),
             samples: 1,
@@ -319,7 +349,23 @@ impl<B: hal::Backend> HalRenderPasses<B> {
             // main target passes
             cao_to_present: unsafe {
                 device.create_render_pass(
-                    &[attachment_cao_to_present, attachment_depth],
+                    &[attachment_cao_to_present, attachment_depth.clone()],
+                    &[subpass_depth_bgra8.clone()],
+                    &[],
+                )
+            }
+            .expect("create_render_pass failed"),
+            present_to_cao: unsafe {
+                device.create_render_pass(
+                    &[attachment_present_to_cao, attachment_depth.clone()],
+                    &[subpass_depth_bgra8.clone()],
+                    &[],
+                )
+            }
+            .expect("create_render_pass failed"),
+            present_to_present: unsafe {
+                device.create_render_pass(
+                    &[attachment_present_to_present, attachment_depth],
                     &[subpass_depth_bgra8.clone()],
                     &[],
                 )
@@ -128,6 +128,26 @@ pub struct PrimitiveInstanceData {
     pub aData: [i32; 4],
 }
 
+#[derive(Debug, Clone, Copy)]
+#[allow(non_snake_case)]
+pub struct VectorStencilInstance {
+    pub aFromPosition: [f32; 2],
+    pub aCtrlPosition: [f32; 2],
+    pub aToPosition: [f32; 2],
+    pub aTaskRect: [f32; 4],
+}
+
+#[derive(Debug, Clone, Copy)]
+#[allow(non_snake_case)]
+pub struct VectorCoverInstance {
+    pub aData: [i32; 4],
+    pub aTaskRect: [f32; 4],
+    pub aStencilTaskAddress: i32,
+    pub aFillRule: i32,
+    pub aPaintAddress: i32,
+    pub aStopsAddress: i32,
+}
+
 #[derive(Debug, Clone, Copy)]
 #[allow(non_snake_case)]
 pub struct Vertex {
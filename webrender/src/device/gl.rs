@@ -432,6 +432,8 @@ impl PrimitiveType for crate::gpu_types::ScalingInstance { }
 impl PrimitiveType for crate::gpu_types::SvgFilterInstance { }
 impl PrimitiveType for crate::gpu_types::ResolveInstanceData { }
 impl PrimitiveType for crate::gpu_types::CompositeInstance { }
+impl PrimitiveType for crate::gpu_types::VectorStencilInstance { }
+impl PrimitiveType for crate::gpu_types::VectorCoverInstance { }
 impl PrimitiveType for crate::render_target::LineDecorationJob { }
 impl PrimitiveType for crate::render_target::GradientJob { }
 
@@ -1178,6 +1180,13 @@ impl<B: hal::Backend> Device<B> {
                 (fbo_id, rect, with_depth)
             },
             DrawTarget::External { fbo, size } => (fbo, size.into(), false),
+            DrawTarget::NativeSurface { external_fbo_id, offset, dimensions } => {
+                let rect = FramebufferIntRect::new(
+                    FramebufferIntPoint::from_untyped(offset.to_untyped()),
+                    FramebufferIntSize::from_untyped(dimensions.to_untyped()),
+                );
+                (FBOId(external_fbo_id), rect, true)
+            }
         };
 
         self.depth_available = depth_available;
@@ -2713,6 +2722,27 @@ impl<B: hal::Backend> Device<B> {
             (gl::ZERO, gl::SRC_ALPHA),
         );
     }
+    /// Union of a clip mask with the one in the target.
+    pub fn set_blend_mode_mask_add(&mut self) {
+        self.set_blend_factors(
+            (gl::ONE, gl::ONE_MINUS_SRC_COLOR),
+            (gl::ONE, gl::ONE_MINUS_SRC_ALPHA),
+        );
+    }
+    /// The parts of a clip mask outside of the one in the target.
+    pub fn set_blend_mode_mask_subtract(&mut self) {
+        self.set_blend_factors(
+            (gl::ONE_MINUS_DST_COLOR, gl::ZERO),
+            (gl::ONE_MINUS_DST_ALPHA, gl::ZERO),
+        );
+    }
+    /// The non-overlapping parts of a clip mask and the one in the target.
+    pub fn set_blend_mode_mask_exclude(&mut self) {
+        self.set_blend_factors(
+            (gl::ONE_MINUS_DST_COLOR, gl::ONE_MINUS_SRC_COLOR),
+            (gl::ONE_MINUS_DST_ALPHA, gl::ONE_MINUS_SRC_ALPHA),
+        );
+    }
     pub fn set_blend_mode_subpixel_pass0(&mut self) {
         self.set_blend_factors(
             (gl::ZERO, gl::ONE_MINUS_SRC_COLOR),
@@ -2782,6 +2812,12 @@ impl<B: hal::Backend> Device<B> {
             self.shader_is_ready = false;
         }
     }
+    pub fn set_blend_mode_additive(&mut self) {
+        self.set_blend_factors(
+            (gl::ONE, gl::ONE),
+            (gl::ONE, gl::ONE),
+        );
+    }
     pub fn set_blend_mode_advanced(&mut self, mode: MixBlendMode) {
         self.gl.blend_equation(match mode {
             MixBlendMode::Normal => {
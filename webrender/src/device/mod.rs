@@ -606,9 +606,7 @@ pub enum ShaderKind {
     ClipCache,
     Brush,
     Text,
-    #[allow(dead_code)]
     VectorStencil,
-    #[allow(dead_code)]
     VectorCover,
     Resolve,
     Composite,
@@ -625,9 +623,7 @@ pub enum VertexArrayKind {
     Primitive,
     Blur,
     Clip,
-    #[cfg_attr(not(feature = "gl"), allow(dead_code))]
     VectorStencil,
-    #[cfg_attr(not(feature = "gl"), allow(dead_code))]
     VectorCover,
     Border,
     Scale,
@@ -686,6 +682,16 @@ pub enum DrawTarget {
         fbo: FBOId,
         size: FramebufferIntSize,
     },
+    /// An OS compositor surface, bound by the native compositor.
+    #[cfg(feature = "gl")]
+    NativeSurface {
+        /// Offset of the surface within the bound framebuffer.
+        offset: DeviceIntPoint,
+        /// FBO owned by the compositor.
+        external_fbo_id: IdType,
+        /// Size of the surface in pixels.
+        dimensions: DeviceIntSize,
+    },
     /// Used for reading back for gfx-backends
     #[cfg(not(feature = "gl"))]
     ReadBack {
@@ -767,6 +773,8 @@ impl DrawTarget {
             DrawTarget::Default { total_size, .. } => DeviceIntSize::from_untyped(total_size.to_untyped()),
             DrawTarget::Texture { dimensions, .. } => dimensions,
             DrawTarget::External { size, .. } => DeviceIntSize::from_untyped(size.to_untyped()),
+            #[cfg(feature = "gl")]
+            DrawTarget::NativeSurface { dimensions, .. } => dimensions,
             #[cfg(not(feature = "gl"))]
             DrawTarget::ReadBack { total_size, .. } => DeviceIntSize::from_untyped(total_size.to_untyped()),
         }
@@ -783,6 +791,10 @@ impl DrawTarget {
                 fb_rect.origin.x += rect.origin.x;
             }
             DrawTarget::Texture { .. } | DrawTarget::External { .. } => (),
+            #[cfg(feature = "gl")]
+            DrawTarget::NativeSurface { offset, .. } => {
+                fb_rect.origin += FramebufferIntPoint::from_untyped(offset.to_untyped()).to_vector();
+            }
             #[cfg(not(feature = "gl"))]
             DrawTarget::ReadBack { ref rect, .. } => {
                 fb_rect.origin.x += rect.origin.x;
@@ -811,6 +823,10 @@ impl DrawTarget {
                 DrawTarget::Texture { .. } | DrawTarget::External { .. } => {
                     FramebufferIntRect::from_untyped(&scissor_rect.to_untyped())
                 }
+                #[cfg(feature = "gl")]
+                DrawTarget::NativeSurface { offset, .. } => {
+                    FramebufferIntRect::from_untyped(&scissor_rect.translate(offset.to_vector()).to_untyped())
+                }
                 #[cfg(not(feature = "gl"))]
                 DrawTarget::ReadBack { ref rect, .. } => {
                     self.to_framebuffer_rect(scissor_rect.translate(-content_origin.to_vector()))
@@ -863,6 +879,9 @@ impl From<DrawTarget> for ReadTarget {
                 ReadTarget::Texture { fbo_id },
             DrawTarget::External { fbo, .. } =>
                 ReadTarget::External { fbo },
+            #[cfg(feature = "gl")]
+            DrawTarget::NativeSurface { external_fbo_id, .. } =>
+                ReadTarget::External { fbo: FBOId(external_fbo_id) },
             #[cfg(not(feature = "gl"))]
             _ => unimplemented!(),
         }
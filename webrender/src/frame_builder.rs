@@ -8,7 +8,7 @@ use api::units::*;
 use crate::batch::{BatchBuilder, AlphaBatchBuilder, AlphaBatchContainer};
 use crate::clip::{ClipStore, ClipChainStack};
 use crate::clip_scroll_tree::{ClipScrollTree, ROOT_SPATIAL_NODE_INDEX, SpatialNodeIndex};
-use crate::composite::CompositeState;
+use crate::composite::{CompositeState, CompositorKind};
 use crate::debug_render::DebugItem;
 use crate::gpu_cache::{GpuCache, GpuCacheHandle};
 use crate::gpu_types::{PrimitiveHeaders, TransformPalette, UvRectKind, ZBufferIdGenerator};
@@ -16,8 +16,9 @@ use crate::gpu_types::TransformData;
 use crate::internal_types::{FastHashMap, PlaneSplitter, SavedTargetIndex};
 use crate::picture::{PictureUpdateState, SurfaceInfo, ROOT_SURFACE_INDEX, SurfaceIndex, RecordedDirtyRegion};
 use crate::picture::{RetainedTiles, TileCacheInstance, DirtyRegion, SurfaceRenderTasks, SubpixelMode};
+use crate::picture::SnapshotDescriptor;
 use crate::prim_store::{SpaceMapper, PictureIndex, PrimitiveDebugId, PrimitiveScratchBuffer};
-use crate::prim_store::{DeferredResolve, PrimitiveVisibilityMask};
+use crate::prim_store::{DeferredResolve, PrimitiveVisibilityMask, PrimitiveStore};
 use crate::profiler::{FrameProfileCounters, GpuCacheProfileCounters, TextureCacheProfileCounters};
 use crate::render_backend::{DataStores, FrameStamp, FrameId};
 use crate::render_target::{RenderTarget, PictureCacheTarget, TextureCacheRenderTarget};
@@ -27,10 +28,11 @@ use crate::render_task_graph::{RenderPassKind, RenderPass};
 use crate::render_task::{RenderTask, RenderTaskLocation, RenderTaskKind};
 use crate::resource_cache::{ResourceCache};
 use crate::scene::{BuiltScene, ScenePipeline, SceneProperties};
+use crate::scene_building::DisplayListLimits;
 use crate::segment::SegmentBuilder;
 use std::{f32, mem};
 use std::sync::Arc;
-use crate::util::MaxRect;
+use crate::util::{MaxRect, raster_rect_to_device_pixels};
 
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -64,6 +66,8 @@ pub struct FrameBuilderConfig {
     pub advanced_blend_is_coherent: bool,
     pub batch_lookback_count: usize,
     pub background_color: Option<ColorF>,
+    pub display_list_limits: DisplayListLimits,
+    pub compositor_kind: CompositorKind,
 }
 
 /// A set of common / global resources that are retained between
@@ -305,7 +309,7 @@ impl FrameBuilder {
         // set up render tasks, determine scaling of surfaces, and detect
         // which surfaces have valid cached surfaces that don't need to
         // be rendered this frame.
-        PictureUpdateState::update_all(
+        let snapshot_pictures = PictureUpdateState::update_all(
             surfaces,
             scene.root_pic_index,
             &mut scene.prim_store.pictures,
@@ -315,6 +319,15 @@ impl FrameBuilder {
             data_stores,
         );
 
+        // Clips shared by all the primitives of a picture cache are applied when
+        // compositing its tiles, so they aren't applied to snapshots either.
+        let shared_clips: Vec<_> = scene.prim_store.pictures
+            .iter()
+            .filter_map(|pic| pic.tile_cache.as_ref())
+            .flat_map(|tile_cache| tile_cache.shared_clips.iter().cloned())
+            .collect();
+        let mut snapshots_to_render = Vec::new();
+
         {
             profile_marker!("UpdateVisibility");
 
@@ -341,6 +354,32 @@ impl FrameBuilder {
                 composite_state,
             };
 
+            // The contents of snapshot pictures are visited as a whole first,
+            // whether they are visible on screen or not, so that the images
+            // showing them are invalidated before the rest of the frame is.
+            visibility_state.clip_chain_stack.push_surface(&shared_clips);
+            for pic_index in snapshot_pictures {
+                scene.prim_store.update_visibility(
+                    pic_index,
+                    ROOT_SURFACE_INDEX,
+                    &WorldRect::max_rect(),
+                    &visibility_context,
+                    &mut visibility_state,
+                );
+
+                let descriptor = SnapshotDescriptor::new(
+                    pic_index,
+                    &scene.prim_store,
+                    &visibility_context,
+                    &visibility_state,
+                );
+                let image_key = scene.prim_store.pictures[pic_index.0].snapshot.unwrap();
+                if visibility_state.resource_cache.update_snapshot(image_key, descriptor) {
+                    snapshots_to_render.push(pic_index);
+                }
+            }
+            visibility_state.clip_chain_stack.pop_surface();
+
             scene.prim_store.update_visibility(
                 scene.root_pic_index,
                 ROOT_SURFACE_INDEX,
@@ -350,6 +389,10 @@ impl FrameBuilder {
             );
         }
 
+        // Retained tiles of picture caches that aren't part of the new
+        // scene are dropped now.
+        retained_tiles.destroy(resource_cache);
+
         let mut frame_state = FrameBuildingState {
             render_tasks,
             profile_counters,
@@ -381,6 +424,22 @@ impl FrameBuilder {
         );
         frame_state.push_dirty_region(default_dirty_region);
 
+        {
+            profile_marker!("PrepareSnapshots");
+
+            for pic_index in snapshots_to_render {
+                prepare_snapshot(
+                    pic_index,
+                    &mut scene.prim_store,
+                    root_render_task_id,
+                    &frame_context,
+                    &mut frame_state,
+                    data_stores,
+                    scratch,
+                );
+            }
+        }
+
         let (pic_context, mut pic_state, mut prim_list) = scene
             .prim_store
             .pictures[scene.root_pic_index.0]
@@ -479,7 +538,7 @@ impl FrameBuilder {
 
         let output_size = scene.output_rect.size.to_i32();
         let screen_world_rect = (scene.output_rect.to_f32() / global_device_pixel_scale).round_out();
-        let mut composite_state = CompositeState::new();
+        let mut composite_state = CompositeState::new(scene.config.compositor_kind);
 
         let main_render_task_id = self.build_layer_screen_rects_and_cull_layers(
             scene,
@@ -592,6 +651,96 @@ impl FrameBuilder {
     }
 }
 
+/// Prepare a picture that renders into a snapshot image as a whole, rather
+/// than only the part of it that is visible on screen, and copy its surface
+/// into the snapshot ahead of the main render task.
+fn prepare_snapshot(
+    pic_index: PictureIndex,
+    prim_store: &mut PrimitiveStore,
+    root_render_task_id: RenderTaskId,
+    frame_context: &FrameBuildingContext,
+    frame_state: &mut FrameBuildingState,
+    data_stores: &mut DataStores,
+    scratch: &mut PrimitiveScratchBuffer,
+) {
+    let pic = &mut prim_store.pictures[pic_index.0];
+    let image_key = pic.snapshot.unwrap();
+    let surface_index = pic.raster_config.as_ref().unwrap().surface_index;
+
+    // The picture task is as large as the snapshot, so both have to fit
+    // in a texture.
+    let surface = &frame_state.surfaces[surface_index.0];
+    let map_pic_to_raster = SpaceMapper::new_with_target(
+        surface.raster_spatial_node_index,
+        pic.spatial_node_index,
+        RasterRect::max_rect(),
+        frame_context.clip_scroll_tree,
+    );
+    let pic_rect = PictureRect::from_untyped(&pic.precise_local_rect.to_untyped());
+    let device_size = match map_pic_to_raster.map(&pic_rect) {
+        Some(raster_rect) => raster_rect_to_device_pixels(raster_rect, surface.device_pixel_scale).size,
+        None => return,
+    };
+    let max_texture_size = frame_state.resource_cache.max_texture_size() as f32;
+    if device_size.width > max_texture_size || device_size.height > max_texture_size {
+        warn!("Dropping snapshot, snapshot:(w:{},h:{}) is too big for hardware!",
+              device_size.width, device_size.height);
+        return;
+    }
+
+    let mut dirty_region = DirtyRegion::new();
+    dirty_region.push(WorldRect::max_rect(), PrimitiveVisibilityMask::all());
+    frame_state.push_dirty_region(dirty_region);
+
+    let context = pic.take_context(
+        pic_index,
+        WorldRect::max_rect(),
+        ROOT_SPATIAL_NODE_INDEX,
+        ROOT_SPATIAL_NODE_INDEX,
+        ROOT_SURFACE_INDEX,
+        SubpixelMode::Deny,
+        frame_state,
+        frame_context,
+        scratch,
+    );
+
+    if let Some((pic_context, mut pic_state, mut prim_list)) = context {
+        prim_store.prepare_primitives(
+            &mut prim_list,
+            &pic_context,
+            &mut pic_state,
+            frame_context,
+            frame_state,
+            data_stores,
+            scratch,
+        );
+
+        prim_store.pictures[pic_index.0].restore_context(
+            prim_list,
+            pic_context,
+            pic_state,
+            frame_state,
+        );
+
+        let source_task_id = frame_state.surfaces[surface_index.0].render_tasks.unwrap().root;
+        let size = frame_state.render_tasks[source_task_id].location.size();
+
+        let snapshot_task_id = frame_state.resource_cache.render_snapshot(
+            image_key,
+            size,
+            source_task_id,
+            frame_state.gpu_cache,
+            frame_state.render_tasks,
+        );
+
+        if let Some(snapshot_task_id) = snapshot_task_id {
+            frame_state.render_tasks.add_dependency(root_render_task_id, snapshot_task_id);
+        }
+    }
+
+    frame_state.pop_dirty_region();
+}
+
 /// Processes this pass to prepare it for rendering.
 ///
 /// Among other things, this allocates output regions for each of our tasks
@@ -731,7 +880,7 @@ pub fn build_render_pass(
                             .or_insert_with(||
                                 TextureCacheRenderTarget::new(target_kind)
                             );
-                        texture.add_task(task_id, render_tasks);
+                        texture.add_task(task_id, render_tasks, gpu_cache);
                     }
                     None => {
                         match target_kind {
@@ -842,7 +991,7 @@ pub fn build_render_pass(
                     let (target_rect, _) = task.get_target_rect();
 
                     match task.location {
-                        RenderTaskLocation::PictureCache { texture, layer, .. } => {
+                        RenderTaskLocation::PictureCache { surface, .. } => {
                             // TODO(gw): The interface here is a bit untidy since it's
                             //           designed to support batch merging, which isn't
                             //           relevant for picture cache targets. We
@@ -862,8 +1011,7 @@ pub fn build_render_pass(
                             debug_assert!(batch_containers.is_empty());
 
                             let target = PictureCacheTarget {
-                                texture,
-                                layer: layer as usize,
+                                surface,
                                 clear_color,
                                 alpha_batch_container,
                             };
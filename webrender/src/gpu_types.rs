@@ -90,7 +90,7 @@ pub enum BlurDirection {
     Vertical,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone)]
 #[repr(C)]
 #[cfg_attr(feature = "capture", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
@@ -124,6 +124,33 @@ pub struct SvgFilterInstance {
     pub extra_data_address: GpuCacheAddress,
 }
 
+/// A quadratic segment of a path outline, accumulated into a vector
+/// stencil task. Positions are relative to the task origin.
+#[derive(Debug)]
+#[repr(C)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct VectorStencilInstance {
+    pub from_position: DevicePoint,
+    pub ctrl_position: DevicePoint,
+    pub to_position: DevicePoint,
+    pub task_rect: DeviceRect,
+}
+
+/// Resolves a vector stencil task into the coverage of a path, and
+/// fills it with the path paint.
+#[derive(Debug)]
+#[repr(C)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct VectorCoverInstance {
+    pub task_rect: DeviceRect,
+    pub stencil_task_address: i32,
+    pub fill_rule: i32,
+    pub paint_address: i32,
+    pub stops_address: i32,
+}
+
 #[derive(Copy, Clone, Debug, Hash, MallocSizeOf, PartialEq, Eq)]
 #[repr(C)]
 #[cfg_attr(feature = "capture", derive(Serialize))]
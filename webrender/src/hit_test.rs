@@ -2,13 +2,15 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use api::{BorderRadius, ClipMode, HitTestFlags, HitTestItem, HitTestResult, ItemTag, PrimitiveFlags};
+use api::{BorderRadius, ClipMode, FillRule, HitTestFlags, HitTestItem, HitTestResult, ItemTag};
+use api::{MaskCompositeOperator, PrimitiveFlags};
 use api::PipelineId;
 use api::units::*;
 use crate::clip::{ClipChainId, ClipDataStore, ClipNode, ClipItemKind, ClipStore};
 use crate::clip::{rounded_rectangle_contains_point};
 use crate::clip_scroll_tree::{SpatialNodeIndex, ClipScrollTree};
 use crate::internal_types::{FastHashMap, LayoutPrimitiveInfo};
+use crate::prim_store::vector_path::{path_contains_point, PathSegment};
 use std::{ops, u32};
 use std::sync::Arc;
 use crate::util::LayoutToWorldFastTransform;
@@ -47,10 +49,24 @@ impl HitTestClipNode {
             ClipItemKind::RoundedRectangle { rect, radius, mode } => {
                 HitTestRegion::RoundedRectangle(rect, radius, mode)
             }
-            ClipItemKind::Image { rect, .. } => {
+            ClipItemKind::Image { rect, composite_op: MaskCompositeOperator::Intersect, .. } => {
                 HitTestRegion::Rectangle(rect, ClipMode::Clip)
             }
+            // The area of a composited mask depends on the masks it is combined with.
+            ClipItemKind::Image { .. } => HitTestRegion::Invalid,
             ClipItemKind::BoxShadow { .. } => HitTestRegion::Invalid,
+            ClipItemKind::Path { rect, mode, ref source } => {
+                HitTestRegion::Path(
+                    rect,
+                    source.cache_key.segments.clone(),
+                    source.cache_key.fill_rule,
+                    mode,
+                )
+            }
+            // Only the bounds of the glyphs are known at this point.
+            ClipItemKind::Text { rect, .. } => {
+                HitTestRegion::Rectangle(rect, ClipMode::Clip)
+            }
         };
 
         HitTestClipNode {
@@ -188,6 +204,7 @@ enum HitTestRegion {
     Invalid,
     Rectangle(LayoutRect, ClipMode),
     RoundedRectangle(LayoutRect, BorderRadius, ClipMode),
+    Path(LayoutRect, Vec<PathSegment>, FillRule, ClipMode),
 }
 
 impl HitTestRegion {
@@ -201,6 +218,14 @@ impl HitTestRegion {
                 rounded_rectangle_contains_point(point, &rect, &radii),
             HitTestRegion::RoundedRectangle(rect, radii, ClipMode::ClipOut) =>
                 !rounded_rectangle_contains_point(point, &rect, &radii),
+            HitTestRegion::Path(ref rect, ref segments, fill_rule, mode) => {
+                let inside = rect.contains(*point) &&
+                    path_contains_point(segments, fill_rule, *point - rect.origin.to_vector());
+                match mode {
+                    ClipMode::Clip => inside,
+                    ClipMode::ClipOut => !inside,
+                }
+            }
             HitTestRegion::Invalid => true,
         }
     }
@@ -6,6 +6,7 @@ use api::{ColorF, DebugCommand, DocumentId, ExternalImageData, ExternalImageId,
 use api::{ImageFormat, ItemTag, NotificationRequest, Shadow, FilterOp, MAX_BLUR_RADIUS};
 use api::units::*;
 use api;
+use crate::composite::NativeSurfaceOperation;
 use crate::device::TextureFilter;
 use crate::renderer::PipelineInfo;
 #[cfg(not(feature="gl"))]
@@ -69,7 +70,7 @@ const OPACITY_EPSILON: f32 = 0.001;
 #[cfg_attr(feature = "replay", derive(Deserialize))]
 pub enum Filter {
     Identity,
-    Blur(f32),
+    Blur(f32, f32),
     Brightness(f32),
     Contrast(f32),
     Grayscale(f32),
@@ -84,6 +85,7 @@ pub enum Filter {
     LinearToSrgb,
     ComponentTransfer,
     Flood(ColorF),
+    ColorLut3D { lut: api::ImageKey, size: u32 },
 }
 
 impl Filter {
@@ -91,8 +93,9 @@ impl Filter {
     /// are sensible.
     pub fn sanitize(&mut self) {
         match self {
-            Filter::Blur(ref mut radius) => {
-                *radius = radius.min(MAX_BLUR_RADIUS);
+            Filter::Blur(ref mut width, ref mut height) => {
+                *width = width.min(MAX_BLUR_RADIUS);
+                *height = height.min(MAX_BLUR_RADIUS);
             }
             Filter::DropShadows(ref mut stack) => {
                 for shadow in stack {
@@ -118,7 +121,8 @@ impl Filter {
             Filter::ColorMatrix(..) |
             Filter::SrgbToLinear |
             Filter::LinearToSrgb |
-            Filter::ComponentTransfer  => true,
+            Filter::ComponentTransfer |
+            Filter::ColorLut3D { .. } => true,
             Filter::Opacity(_, amount) => {
                 amount > OPACITY_EPSILON
             },
@@ -131,7 +135,7 @@ impl Filter {
     pub fn is_noop(&self) -> bool {
         match *self {
             Filter::Identity => false, // this is intentional
-            Filter::Blur(length) => length == 0.0,
+            Filter::Blur(width, height) => width == 0.0 && height == 0.0,
             Filter::Brightness(amount) => amount == 1.0,
             Filter::Contrast(amount) => amount == 1.0,
             Filter::Grayscale(amount) => amount == 0.0,
@@ -162,6 +166,8 @@ impl Filter {
             Filter::LinearToSrgb |
             Filter::ComponentTransfer |
             Filter::Flood(..) => false,
+            // A table with fewer than two entries per axis can't be interpolated.
+            Filter::ColorLut3D { size, .. } => size < 2,
         }
     }
 }
@@ -170,7 +176,7 @@ impl From<FilterOp> for Filter {
     fn from(op: FilterOp) -> Self {
         match op {
             FilterOp::Identity => Filter::Identity,
-            FilterOp::Blur(r) => Filter::Blur(r),
+            FilterOp::Blur(w, h) => Filter::Blur(w, h),
             FilterOp::Brightness(b) => Filter::Brightness(b),
             FilterOp::Contrast(c) => Filter::Contrast(c),
             FilterOp::Grayscale(g) => Filter::Grayscale(g),
@@ -185,6 +191,7 @@ impl From<FilterOp> for Filter {
             FilterOp::ComponentTransfer => Filter::ComponentTransfer,
             FilterOp::DropShadow(shadow) => Filter::DropShadows(smallvec![shadow]),
             FilterOp::Flood(color) => Filter::Flood(color),
+            FilterOp::ColorLut3D { lut, size } => Filter::ColorLut3D { lut, size },
         }
     }
 }
@@ -370,6 +377,9 @@ pub struct TextureUpdateList {
     pub allocations: Vec<TextureCacheAllocation>,
     /// Commands to update the contents of the textures. Processed second.
     pub updates: Vec<TextureCacheUpdate>,
+    /// Commands to create and destroy native compositor surfaces. Processed
+    /// along with the allocations.
+    pub native_surface_updates: Vec<NativeSurfaceOperation>,
 }
 
 impl TextureUpdateList {
@@ -379,6 +389,7 @@ impl TextureUpdateList {
             clears_shared_cache: false,
             allocations: Vec::new(),
             updates: Vec::new(),
+            native_surface_updates: Vec::new(),
         }
     }
 
@@ -513,6 +524,9 @@ impl TextureUpdateList {
 pub struct RenderedDocument {
     pub frame: Frame,
     pub is_new_scene: bool,
+    /// True if scroll animations were still running when the frame was built,
+    /// in which case the renderer requests the next frame once this one is drawn.
+    pub has_scroll_animations: bool,
 }
 
 pub enum DebugOutput {
@@ -122,6 +122,8 @@ mod segment;
 mod shade;
 mod spatial_node;
 mod storage;
+#[cfg(feature = "gl")]
+mod sw_compositor;
 mod texture_allocator;
 mod texture_cache;
 mod util;
@@ -203,6 +205,9 @@ extern crate webrender_build;
 
 #[doc(hidden)]
 pub use crate::device::{build_shader_strings, UploadMethod, VertexUsageHint};
+pub use crate::composite::NativeSurfaceId;
+#[cfg(feature = "gl")]
+pub use crate::composite::{Compositor, CompositorSurfaceTransform, NativeSurfaceInfo};
 pub use crate::device::{ProgramBinary, ProgramCache, ProgramCacheObserver, FormatDesc, ShaderPrecacheFlags};
 pub use crate::device::{Device, DeviceInit};
 pub use crate::frame_builder::ChasePrimitive;
@@ -214,12 +219,15 @@ pub use crate::renderer::{
     PipelineInfo, Renderer, RendererOptions, RenderResults, RendererStats, SceneBuilderHooks,
     ThreadListener, MAX_VERTEX_TEXTURE_WIDTH, PresentConfig,
 };
+pub use crate::scene_building::DisplayListLimits;
 pub use crate::screen_capture::{AsyncScreenshotHandle, RecordedFrameHandle};
 pub use crate::shade::{Shaders, WrShaders};
 pub use api as webrender_api;
 pub use webrender_build::shader::ProgramSourceDigest;
 #[cfg(feature = "gl")]
 pub use crate::device::get_gl_target;
+#[cfg(feature = "gl")]
+pub use crate::sw_compositor::SwCompositor;
 pub use rendy_memory::{DynamicConfig, HeapsConfig, LinearConfig};
 #[cfg(not(feature = "gl"))]
 pub use device::BackendApiType;
@@ -2,15 +2,16 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use api::{MixBlendMode, PipelineId, PremultipliedColorF, FilterPrimitiveKind};
+use api::{MixBlendMode, PipelineId, PremultipliedColorF, FilterPrimitiveKind, MorphologyOperator};
 use api::{PropertyBinding, PropertyBindingId, FilterPrimitive, FontRenderMode};
-use api::{DebugFlags, RasterSpace, ImageKey, ColorF, PrimitiveFlags};
+use api::{DebugFlags, RasterSpace, ImageKey, ImageRendering, ColorF, ColorU, PrimitiveFlags};
 use api::units::*;
 use crate::box_shadow::{BLUR_SAMPLE_SCALE};
 use crate::clip::{ClipStore, ClipChainInstance, ClipDataHandle, ClipChainId};
 use crate::clip_scroll_tree::{ROOT_SPATIAL_NODE_INDEX,
     ClipScrollTree, CoordinateSpaceMapping, SpatialNodeIndex, VisibleFace, CoordinateSystemId
 };
+use crate::composite::{CompositorKind, NativeSurfaceId, ResolvedSurfaceTexture};
 use crate::debug_colors;
 use euclid::{vec3, Point2D, Scale, Size2D, Vector2D, Rect};
 use euclid::approxeq::ApproxEq;
@@ -26,12 +27,14 @@ use crate::prim_store::{SpaceMapper, PrimitiveVisibilityMask, PointKey, Primitiv
 use crate::prim_store::{SpaceSnapper, PictureIndex, PrimitiveInstance, PrimitiveInstanceKind};
 use crate::prim_store::{get_raster_rects, PrimitiveScratchBuffer, RectangleKey};
 use crate::prim_store::{OpacityBindingStorage, ImageInstanceStorage, OpacityBindingIndex};
+use crate::prim_store::{ColorBindingStorage, ColorBindingIndex, PrimitiveStore};
+use crate::prim_store::PrimitiveVisibilityIndex;
 use crate::print_tree::PrintTreePrinter;
 use crate::render_backend::DataStores;
 use crate::render_task_graph::RenderTaskId;
 use crate::render_target::RenderTargetKind;
 use crate::render_task::{RenderTask, RenderTaskLocation, BlurTaskCache, ClearMode};
-use crate::resource_cache::ResourceCache;
+use crate::resource_cache::{ImageRequest, ResourceCache};
 use crate::scene::SceneProperties;
 use smallvec::SmallVec;
 use std::{mem, u8, marker};
@@ -139,6 +142,8 @@ pub struct PictureCacheState {
     spatial_nodes: FastHashMap<SpatialNodeIndex, SpatialNodeDependency>,
     /// State of opacity bindings from previous frame
     opacity_bindings: FastHashMap<PropertyBindingId, OpacityBindingInfo>,
+    /// State of color bindings from previous frame
+    color_bindings: FastHashMap<PropertyBindingId, ColorBindingInfo>,
     /// The current transform of the picture cache root spatial node
     root_transform: TransformKey,
     /// The current tile size in device pixels
@@ -168,6 +173,16 @@ impl RetainedTiles {
             self.caches = other.caches;
         }
     }
+
+    /// Drop the tiles that weren't picked up by a picture cache of the
+    /// new scene, releasing their native surfaces.
+    pub fn destroy(self, resource_cache: &mut ResourceCache) {
+        for (_, cache) in self.caches {
+            for (_, tile) in cache.tiles {
+                tile.destroy(resource_cache);
+            }
+        }
+    }
 }
 
 /// Unit for tile coordinates.
@@ -242,6 +257,31 @@ impl From<PropertyBinding<f32>> for OpacityBinding {
     }
 }
 
+/// Information about the state of a color binding.
+#[derive(Debug)]
+pub struct ColorBindingInfo {
+    /// The current value retrieved from dynamic scene properties.
+    value: ColorU,
+    /// True if it was changed (or is new) since the last frame build.
+    changed: bool,
+}
+
+/// Information stored in a tile descriptor for a color binding.
+#[derive(Debug, PartialEq, Clone)]
+pub enum ColorBinding {
+    Value(ColorU),
+    Binding(PropertyBindingId),
+}
+
+impl From<PropertyBinding<ColorU>> for ColorBinding {
+    fn from(binding: PropertyBinding<ColorU>) -> ColorBinding {
+        match binding {
+            PropertyBinding::Binding(key, _) => ColorBinding::Binding(key.id),
+            PropertyBinding::Value(value) => ColorBinding::Value(value),
+        }
+    }
+}
+
 /// Information about the state of a spatial node value
 #[derive(Debug)]
 pub struct SpatialNodeDependency {
@@ -284,8 +324,14 @@ struct TilePostUpdateContext<'a> {
     /// Information about opacity bindings from the picture cache.
     opacity_bindings: &'a FastHashMap<PropertyBindingId, OpacityBindingInfo>,
 
+    /// Information about color bindings from the picture cache.
+    color_bindings: &'a FastHashMap<PropertyBindingId, ColorBindingInfo>,
+
     /// Current size in device pixels of tiles for this cache
     current_tile_size: DeviceIntSize,
+
+    /// How the tiles are going to be composited.
+    compositor_kind: CompositorKind,
 }
 
 // Mutable state passed to picture cache tiles during post_update
@@ -317,6 +363,9 @@ struct PrimitiveDependencyInfo {
     /// Opacity bindings this primitive depends on.
     opacity_bindings: SmallVec<[OpacityBinding; 4]>,
 
+    /// Color binding this primitive depends on.
+    color_binding: Option<ColorBinding>,
+
     /// Clips that this primitive depends on.
     clips: SmallVec<[ItemUid; 8]>,
 
@@ -337,6 +386,7 @@ impl PrimitiveDependencyInfo {
             is_cacheable,
             image_keys: SmallVec::new(),
             opacity_bindings: SmallVec::new(),
+            color_binding: None,
             clip_by_tile: false,
             prim_clip_rect: PictureRect::zero(),
             clips: SmallVec::new(),
@@ -349,12 +399,55 @@ impl PrimitiveDependencyInfo {
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct TileId(usize);
 
+/// Describes the storage a picture cache tile gets drawn into.
+#[derive(Debug)]
+pub enum SurfaceTextureDescriptor {
+    /// When WebRender composites the tiles itself, they are drawn into
+    /// entries of the texture cache.
+    TextureCache {
+        handle: TextureCacheHandle,
+    },
+    /// When using a native compositor, each tile is drawn into a native
+    /// surface. The id is only allocated once the tile is first drawn.
+    NativeSurface {
+        id: Option<NativeSurfaceId>,
+        size: DeviceIntSize,
+        /// The opacity the surface was created with.
+        is_opaque: bool,
+    },
+}
+
+impl SurfaceTextureDescriptor {
+    /// Resolve the descriptor into the surface to draw into this frame.
+    pub fn resolve(
+        &self,
+        resource_cache: &ResourceCache,
+    ) -> ResolvedSurfaceTexture {
+        match *self {
+            SurfaceTextureDescriptor::TextureCache { ref handle } => {
+                let cache_item = resource_cache.texture_cache.get(handle);
+
+                ResolvedSurfaceTexture::TextureCache {
+                    texture: cache_item.texture_id,
+                    layer: cache_item.texture_layer,
+                }
+            }
+            SurfaceTextureDescriptor::NativeSurface { id, size, .. } => {
+                ResolvedSurfaceTexture::NativeSurface {
+                    id: id.expect("bug: native surface not allocated"),
+                    size,
+                }
+            }
+        }
+    }
+}
+
 /// The backing surface for this tile.
 #[derive(Debug)]
 pub enum TileSurface {
     Texture {
-        /// Handle to the texture cache entry which gets drawn to.
-        handle: TextureCacheHandle,
+        /// Describes the storage which gets drawn to.
+        descriptor: SurfaceTextureDescriptor,
         /// Bitfield specifying the dirty region(s) that are relevant to this tile.
         visibility_mask: PrimitiveVisibilityMask,
     },
@@ -449,6 +542,7 @@ impl Tile {
             state.resource_cache,
             ctx.spatial_nodes,
             ctx.opacity_bindings,
+            ctx.color_bindings,
         );
 
         self.root.update_dirty_rects(
@@ -535,6 +629,11 @@ impl Tile {
         // Include any opacity bindings this primitive depends on.
         self.current_descriptor.opacity_bindings.extend_from_slice(&info.opacity_bindings);
 
+        // Include any color bindings this primitive depends on.
+        if let Some(ref color_binding) = info.color_binding {
+            self.current_descriptor.color_bindings.push(color_binding.clone());
+        }
+
         // Include any clip nodes that this primitive depends on.
         self.current_descriptor.clips.extend_from_slice(&info.clips);
 
@@ -593,6 +692,7 @@ impl Tile {
             clip_dep_count: info.clips.len() as u8,
             image_dep_count: info.image_keys.len() as u8,
             opacity_binding_dep_count: info.opacity_bindings.len() as u8,
+            color_binding_dep_count: info.color_binding.is_some() as u8,
         });
 
         // Add this primitive to the dirty rect quadtree.
@@ -639,7 +739,10 @@ impl Tile {
 
         // See if this tile is a simple color, in which case we can just draw
         // it as a rect, and avoid allocating a texture surface and drawing it.
-        let is_simple_prim = self.current_descriptor.prims.len() == 1 && self.is_opaque;
+        // Native compositors are only handed surfaces, so every tile gets one.
+        let is_simple_prim = self.current_descriptor.prims.len() == 1 &&
+            self.is_opaque &&
+            ctx.compositor_kind == CompositorKind::Draw;
 
         // Set up the backing surface for this tile.
         let surface = if is_simple_prim {
@@ -666,8 +769,23 @@ impl Tile {
                     old_surface
                 }
                 Some(TileSurface::Color { .. }) | Some(TileSurface::Clear) | None => {
+                    let descriptor = match ctx.compositor_kind {
+                        CompositorKind::Draw => {
+                            SurfaceTextureDescriptor::TextureCache {
+                                handle: TextureCacheHandle::invalid(),
+                            }
+                        }
+                        CompositorKind::Native => {
+                            SurfaceTextureDescriptor::NativeSurface {
+                                id: None,
+                                size: ctx.current_tile_size,
+                                is_opaque: self.is_opaque,
+                            }
+                        }
+                    };
+
                     TileSurface::Texture {
-                        handle: TextureCacheHandle::invalid(),
+                        descriptor,
                         visibility_mask: PrimitiveVisibilityMask::empty(),
                     }
                 }
@@ -679,6 +797,17 @@ impl Tile {
 
         true
     }
+
+    /// Release the native surface backing this tile, if any. Tiles that
+    /// are drawn into the texture cache don't need this, their entries
+    /// get evicted once they stop being requested.
+    fn destroy(self, resource_cache: &mut ResourceCache) {
+        if let Some(TileSurface::Texture { descriptor, .. }) = self.surface {
+            if let SurfaceTextureDescriptor::NativeSurface { id: Some(id), .. } = descriptor {
+                resource_cache.destroy_compositor_surface(id);
+            }
+        }
+    }
 }
 
 /// Defines a key that uniquely identifies a primitive instance.
@@ -696,6 +825,7 @@ pub struct PrimitiveDescriptor {
     transform_dep_count: u8,
     image_dep_count: u8,
     opacity_binding_dep_count: u8,
+    color_binding_dep_count: u8,
     clip_dep_count: u8,
 }
 
@@ -829,6 +959,9 @@ pub struct TileDescriptor {
     // TODO(gw): Ugh, get rid of all opacity binding support!
     opacity_bindings: Vec<OpacityBinding>,
 
+    /// The set of color bindings that this tile depends on.
+    color_bindings: Vec<ColorBinding>,
+
     /// List of the effects of transforms that we care about
     /// tracking for this tile.
     transforms: Vec<SpatialNodeIndex>,
@@ -840,6 +973,7 @@ impl TileDescriptor {
             prims: Vec::new(),
             clips: Vec::new(),
             opacity_bindings: Vec::new(),
+            color_bindings: Vec::new(),
             image_keys: Vec::new(),
             transforms: Vec::new(),
         }
@@ -851,6 +985,7 @@ impl TileDescriptor {
         self.prims.clear();
         self.clips.clear();
         self.opacity_bindings.clear();
+        self.color_bindings.clear();
         self.image_keys.clear();
         self.transforms.clear();
     }
@@ -1024,6 +1159,9 @@ pub struct TileCacheInstance {
     /// List of opacity bindings, with some extra information
     /// about whether they changed since last frame.
     opacity_bindings: FastHashMap<PropertyBindingId, OpacityBindingInfo>,
+    /// List of color bindings, with some extra information
+    /// about whether they changed since last frame.
+    color_bindings: FastHashMap<PropertyBindingId, ColorBindingInfo>,
     /// List of spatial nodes, with some extra information
     /// about whether they changed since last frame.
     spatial_nodes: FastHashMap<SpatialNodeIndex, SpatialNodeDependency>,
@@ -1091,6 +1229,7 @@ impl TileCacheInstance {
                 PictureRect::zero(),
             ),
             opacity_bindings: FastHashMap::default(),
+            color_bindings: FastHashMap::default(),
             spatial_nodes: FastHashMap::default(),
             used_spatial_nodes: FastHashSet::default(),
             dirty_region: DirtyRegion::new(),
@@ -1225,6 +1364,7 @@ impl TileCacheInstance {
             self.root_transform = prev_state.root_transform;
             self.spatial_nodes = prev_state.spatial_nodes;
             self.opacity_bindings = prev_state.opacity_bindings;
+            self.color_bindings = prev_state.color_bindings;
             self.current_tile_size = prev_state.current_tile_size;
         }
 
@@ -1243,7 +1383,9 @@ impl TileCacheInstance {
         //           threshold above. If we ever see this happening we can improve
         //           the theshold logic above.
         if desired_tile_size != self.current_tile_size {
-            self.tiles.clear();
+            for (_, tile) in self.tiles.drain() {
+                tile.destroy(frame_state.resource_cache);
+            }
             self.current_tile_size = desired_tile_size;
         }
 
@@ -1304,6 +1446,22 @@ impl TileCacheInstance {
             });
         }
 
+        // Do the same for color bindings.
+        let current_properties = frame_context.scene_properties.color_properties();
+        let old_properties = mem::replace(&mut self.color_bindings, FastHashMap::default());
+
+        for (id, value) in current_properties {
+            let value: ColorU = (*value).into();
+            let changed = match old_properties.get(id) {
+                Some(old_property) => old_property.value != value,
+                None => true,
+            };
+            self.color_bindings.insert(*id, ColorBindingInfo {
+                value,
+                changed,
+            });
+        }
+
         let world_tile_size = WorldSize::new(
             self.current_tile_size.width as f32 / frame_context.global_device_pixel_scale.0,
             self.current_tile_size.height as f32 / frame_context.global_device_pixel_scale.0,
@@ -1400,6 +1558,11 @@ impl TileCacheInstance {
             }
         }
 
+        // Any tiles that are no longer part of the picture are dropped.
+        for (_, tile) in old_tiles.drain() {
+            tile.destroy(frame_state.resource_cache);
+        }
+
         world_culling_rect
     }
 
@@ -1416,6 +1579,7 @@ impl TileCacheInstance {
         pictures: &[PicturePrimitive],
         resource_cache: &ResourceCache,
         opacity_binding_store: &OpacityBindingStorage,
+        color_binding_store: &ColorBindingStorage,
         image_instances: &ImageInstanceStorage,
         surface_index: SurfaceIndex,
     ) -> bool {
@@ -1500,15 +1664,22 @@ impl TileCacheInstance {
                 if let Some(PictureCompositeMode::Filter(Filter::Opacity(binding, _))) = pic.requested_composite_mode {
                     prim_info.opacity_bindings.push(binding.into());
                 }
+
+                // The children of snapshot pictures aren't visited here, their
+                // changes are tracked by the snapshot image instead.
+                if let Some(key) = pic.snapshot_key() {
+                    prim_info.image_keys.push(key);
+                }
             }
-            PrimitiveInstanceKind::Rectangle { data_handle, opacity_binding_index, .. } => {
-                if opacity_binding_index == OpacityBindingIndex::INVALID {
+            PrimitiveInstanceKind::Rectangle { data_handle, opacity_binding_index, color_binding_index, .. } => {
+                if opacity_binding_index == OpacityBindingIndex::INVALID &&
+                   color_binding_index == ColorBindingIndex::INVALID {
                     // Check a number of conditions to see if we can consider this
                     // primitive as an opaque rect. Several of these are conservative
                     // checks and could be relaxed in future. However, these checks
                     // are quick and capture the common cases of background rects.
                     // Specifically, we currently require:
-                    //  - No opacity binding (to avoid resolving the opacity here).
+                    //  - No opacity or color binding (to avoid resolving them here).
                     //  - Color.a >= 1.0 (the primitive is opaque).
                     //  - Same coord system as picture cache (ensures rects are axis-aligned).
                     //  - No clip masks exist.
@@ -1516,7 +1687,7 @@ impl TileCacheInstance {
                     let on_picture_surface = surface_index == self.surface_index;
 
                     let color = match data_stores.prim[data_handle].kind {
-                        PrimitiveTemplateKind::Rectangle { color, .. } => color,
+                        PrimitiveTemplateKind::Rectangle { color: PropertyBinding::Value(color), .. } => color,
                         _ => unreachable!(),
                     };
 
@@ -1544,9 +1715,15 @@ impl TileCacheInstance {
                         }
                     };
                 } else {
-                    let opacity_binding = &opacity_binding_store[opacity_binding_index];
-                    for binding in &opacity_binding.bindings {
-                        prim_info.opacity_bindings.push(OpacityBinding::from(*binding));
+                    if opacity_binding_index != OpacityBindingIndex::INVALID {
+                        let opacity_binding = &opacity_binding_store[opacity_binding_index];
+                        for binding in &opacity_binding.bindings {
+                            prim_info.opacity_bindings.push(OpacityBinding::from(*binding));
+                        }
+                    }
+
+                    if color_binding_index != ColorBindingIndex::INVALID {
+                        prim_info.color_binding = Some(color_binding_store[color_binding_index].into());
                     }
                 }
 
@@ -1613,9 +1790,11 @@ impl TileCacheInstance {
                 }
             }
             PrimitiveInstanceKind::LineDecoration { .. } |
+            PrimitiveInstanceKind::VectorPath { .. } |
             PrimitiveInstanceKind::NormalBorder { .. } |
             PrimitiveInstanceKind::LinearGradient { .. } |
             PrimitiveInstanceKind::RadialGradient { .. } |
+            PrimitiveInstanceKind::ConicGradient { .. } |
             PrimitiveInstanceKind::Backdrop { .. } => {
                 // These don't contribute dependencies
             }
@@ -1711,7 +1890,9 @@ impl TileCacheInstance {
             backdrop: self.backdrop,
             spatial_nodes: &self.spatial_nodes,
             opacity_bindings: &self.opacity_bindings,
+            color_bindings: &self.color_bindings,
             current_tile_size: self.current_tile_size,
+            compositor_kind: frame_context.config.compositor_kind,
         };
 
         let mut state = TilePostUpdateState {
@@ -1743,9 +1924,12 @@ pub struct PictureUpdateState<'a> {
     surface_stack: Vec<SurfaceIndex>,
     picture_stack: Vec<PictureInfo>,
     are_raster_roots_assigned: bool,
+    snapshot_pictures: Vec<PictureIndex>,
 }
 
 impl<'a> PictureUpdateState<'a> {
+    /// Returns the pictures that render into a snapshot image this frame,
+    /// with any nested snapshot pictures ahead of the ones containing them.
     pub fn update_all(
         surfaces: &'a mut Vec<SurfaceInfo>,
         pic_index: PictureIndex,
@@ -1754,7 +1938,7 @@ impl<'a> PictureUpdateState<'a> {
         gpu_cache: &mut GpuCache,
         clip_store: &ClipStore,
         data_stores: &mut DataStores,
-    ) {
+    ) -> Vec<PictureIndex> {
         profile_marker!("UpdatePictures");
 
         let mut state = PictureUpdateState {
@@ -1762,6 +1946,7 @@ impl<'a> PictureUpdateState<'a> {
             surface_stack: vec![SurfaceIndex(0)],
             picture_stack: Vec::new(),
             are_raster_roots_assigned: true,
+            snapshot_pictures: Vec::new(),
         };
 
         state.update(
@@ -1780,6 +1965,8 @@ impl<'a> PictureUpdateState<'a> {
                 ROOT_SPATIAL_NODE_INDEX,
             );
         }
+
+        state.snapshot_pictures
     }
 
     /// Return the current surface
@@ -1865,6 +2052,10 @@ impl<'a> PictureUpdateState<'a> {
                 frame_context,
                 data_stores,
             );
+
+            if picture_primitives[pic_index.0].snapshot_key().is_some() {
+                self.snapshot_pictures.push(pic_index);
+            }
         }
     }
 
@@ -2016,6 +2207,8 @@ bitflags! {
         const PRESERVE3D = 4;
         /// A backdrop that is reused which requires a surface.
         const BACKDROP = 8;
+        /// The contents are also rendered into a snapshot image.
+        const SNAPSHOT = 16;
     }
 }
 
@@ -2046,7 +2239,7 @@ impl PictureCompositeMode {
         let mut result_rect = picture_rect;
         match self {
             PictureCompositeMode::Filter(filter) => match filter {
-                Filter::Blur(_) => {
+                Filter::Blur(..) => {
                     result_rect = picture_rect.inflate(inflation_factor, inflation_factor);
                 },
                 Filter::DropShadows(shadows) => {
@@ -2065,8 +2258,10 @@ impl PictureCompositeMode {
                     let output_rect = match primitive.kind {
                         FilterPrimitiveKind::Blur(ref primitive) => {
                             let input = primitive.input.to_index(cur_index).map(|index| output_rects[index]).unwrap_or(picture_rect);
-                            let inflation_factor = primitive.radius.round() * BLUR_SAMPLE_SCALE;
-                            input.inflate(inflation_factor, inflation_factor)
+                            input.inflate(
+                                primitive.width.round() * BLUR_SAMPLE_SCALE,
+                                primitive.height.round() * BLUR_SAMPLE_SCALE,
+                            )
                         }
                         FilterPrimitiveKind::DropShadow(ref primitive) => {
                             let inflation_factor = primitive.shadow.blur_radius.round() * BLUR_SAMPLE_SCALE;
@@ -2094,8 +2289,44 @@ impl PictureCompositeMode {
                             let input_rect = primitive.input.to_index(cur_index).map(|index| output_rects[index]).unwrap_or(picture_rect);
                             input_rect.translate(primitive.offset * Scale::new(1.0))
                         },
+                        FilterPrimitiveKind::Morphology(ref primitive) => {
+                            let input_rect = primitive.input.to_index(cur_index).map(|index| output_rects[index]).unwrap_or(picture_rect);
+                            match primitive.operator {
+                                MorphologyOperator::Dilate => {
+                                    input_rect.inflate(primitive.radius_x.max(0.0), primitive.radius_y.max(0.0))
+                                }
+                                MorphologyOperator::Erode => input_rect,
+                            }
+                        }
+                        FilterPrimitiveKind::DisplacementMap(ref primitive) => {
+                            // Pixels of the first input move by up to half the scale.
+                            let input_rect = primitive.input1.to_index(cur_index).map(|index| output_rects[index]).unwrap_or(picture_rect);
+                            let inflation_factor = primitive.scale.abs() / 2.0;
+                            input_rect.inflate(inflation_factor, inflation_factor)
+                        }
+                        FilterPrimitiveKind::ConvolveMatrix(ref primitive) => {
+                            // The kernel can spread content by up to its size, minus the target pixel.
+                            let input_rect = primitive.input.to_index(cur_index).map(|index| output_rects[index]).unwrap_or(picture_rect);
+                            input_rect.inflate(
+                                primitive.order[0].saturating_sub(1) as f32,
+                                primitive.order[1].saturating_sub(1) as f32,
+                            )
+                        }
+
+                        FilterPrimitiveKind::Image(ref primitive) => primitive.rect * Scale::new(1.0),
+                        FilterPrimitiveKind::Merge(ref primitive) => {
+                            primitive.inputs().iter().fold(PictureRect::zero(), |rect, input| {
+                                rect.union(&input.to_index(cur_index).map(|index| output_rects[index]).unwrap_or(picture_rect))
+                            })
+                        }
 
-                        FilterPrimitiveKind::Flood(..) => picture_rect,
+                        // Tiles repeat across the whole region, and lighting also
+                        // lights the transparent parts of its input.
+                        FilterPrimitiveKind::Tile(..) |
+                        FilterPrimitiveKind::DiffuseLighting(..) |
+                        FilterPrimitiveKind::SpecularLighting(..) |
+                        FilterPrimitiveKind::Flood(..) |
+                        FilterPrimitiveKind::Turbulence(..) => picture_rect,
                     };
                     output_rects.push(output_rect);
                     result_rect = result_rect.union(&output_rect);
@@ -2415,6 +2646,10 @@ pub struct PicturePrimitive {
     // pages to a texture), this is the pipeline this
     // picture is the root of.
     pub frame_output_pipeline_id: Option<PipelineId>,
+    /// If set, the surface of this picture is rendered into this
+    /// snapshot image whenever its contents change, even when the
+    /// picture itself is not visible.
+    pub snapshot: Option<ImageKey>,
     // Optional cache handles for storing extra data
     // in the GPU cache, depending on the type of
     // picture.
@@ -2518,6 +2753,11 @@ impl PicturePrimitive {
         }
     }
 
+    /// The snapshot image this picture renders into this frame, if any.
+    pub fn snapshot_key(&self) -> Option<ImageKey> {
+        self.raster_config.as_ref().and(self.snapshot)
+    }
+
     pub fn is_visible(&self) -> bool {
         match self.requested_composite_mode {
             Some(PictureCompositeMode::Filter(ref filter)) => {
@@ -2543,6 +2783,7 @@ impl PicturePrimitive {
                         tiles: tile_cache.tiles,
                         spatial_nodes: tile_cache.spatial_nodes,
                         opacity_bindings: tile_cache.opacity_bindings,
+                        color_bindings: tile_cache.color_bindings,
                         fract_offset: tile_cache.fract_offset,
                         root_transform: tile_cache.root_transform,
                         current_tile_size: tile_cache.current_tile_size,
@@ -2576,6 +2817,7 @@ impl PicturePrimitive {
             raster_config: None,
             context_3d,
             frame_output_pipeline_id,
+            snapshot: None,
             extra_gpu_data_handles: SmallVec::new(),
             apply_local_clip_rect,
             is_backface_visible: flags.contains(PrimitiveFlags::IS_BACKFACE_VISIBLE),
@@ -2708,12 +2950,11 @@ impl PicturePrimitive {
                 let transform = map_pic_to_raster.get_transform();
 
                 let dep_info = match raster_config.composite_mode {
-                    PictureCompositeMode::Filter(Filter::Blur(blur_radius)) => {
-                        let blur_std_deviation = blur_radius * device_pixel_scale.0;
+                    PictureCompositeMode::Filter(Filter::Blur(width, height)) => {
                         let scale_factors = scale_factors(&transform);
                         let blur_std_deviation = DeviceSize::new(
-                            blur_std_deviation * scale_factors.0,
-                            blur_std_deviation * scale_factors.1
+                            width * device_pixel_scale.0 * scale_factors.0,
+                            height * device_pixel_scale.0 * scale_factors.1,
                         );
                         let mut device_rect = if self.options.inflate_if_required {
                             let inflation_factor = frame_state.surfaces[raster_config.surface_index.0].inflation_factor;
@@ -2951,6 +3192,7 @@ impl PicturePrimitive {
                     }
                     PictureCompositeMode::TileCache { .. } => {
                         let tile_cache = self.tile_cache.as_mut().unwrap();
+                        let cache_is_opaque = tile_cache.is_opaque();
                         let mut first = true;
 
                         for key in &tile_cache.tiles_to_draw {
@@ -2991,23 +3233,44 @@ impl PicturePrimitive {
                                 }
                             }
 
-                            if let TileSurface::Texture { ref handle, .. } = surface {
-                                // Invalidate if the backing texture was evicted.
-                                if frame_state.resource_cache.texture_cache.is_allocated(handle) {
-                                    // Request the backing texture so it won't get evicted this frame.
-                                    // We specifically want to mark the tile texture as used, even
-                                    // if it's detected not visible below and skipped. This is because
-                                    // we maintain the set of tiles we care about based on visibility
-                                    // during pre_update. If a tile still exists after that, we are
-                                    // assuming that it's either visible or we want to retain it for
-                                    // a while in case it gets scrolled back onto screen soon.
-                                    // TODO(gw): Consider switching to manual eviction policy?
-                                    frame_state.resource_cache.texture_cache.request(handle, frame_state.gpu_cache);
-                                } else {
-                                    // If the texture was evicted on a previous frame, we need to assume
-                                    // that the entire tile rect is dirty.
-                                    tile.is_valid = false;
-                                    tile.dirty_rect = tile.rect;
+                            if let TileSurface::Texture { ref mut descriptor, .. } = surface {
+                                match *descriptor {
+                                    SurfaceTextureDescriptor::TextureCache { ref handle } => {
+                                        // Invalidate if the backing texture was evicted.
+                                        if frame_state.resource_cache.texture_cache.is_allocated(handle) {
+                                            // Request the backing texture so it won't get evicted this frame.
+                                            // We specifically want to mark the tile texture as used, even
+                                            // if it's detected not visible below and skipped. This is because
+                                            // we maintain the set of tiles we care about based on visibility
+                                            // during pre_update. If a tile still exists after that, we are
+                                            // assuming that it's either visible or we want to retain it for
+                                            // a while in case it gets scrolled back onto screen soon.
+                                            // TODO(gw): Consider switching to manual eviction policy?
+                                            frame_state.resource_cache.texture_cache.request(handle, frame_state.gpu_cache);
+                                        } else {
+                                            // If the texture was evicted on a previous frame, we need to assume
+                                            // that the entire tile rect is dirty.
+                                            tile.is_valid = false;
+                                            tile.dirty_rect = tile.rect;
+                                        }
+                                    }
+                                    SurfaceTextureDescriptor::NativeSurface { ref mut id, ref mut is_opaque, .. } => {
+                                        // Native surfaces are never evicted, but the compositor is told
+                                        // about their opacity when they are created. If that changed,
+                                        // replace the surface with a new one.
+                                        let tile_is_opaque = tile.is_opaque || cache_is_opaque;
+                                        if *is_opaque != tile_is_opaque {
+                                            if let Some(old_id) = id.take() {
+                                                frame_state.resource_cache.destroy_compositor_surface(old_id);
+                                            }
+                                            *is_opaque = tile_is_opaque;
+                                        }
+
+                                        if id.is_none() {
+                                            tile.is_valid = false;
+                                            tile.dirty_rect = tile.rect;
+                                        }
+                                    }
                                 }
                             }
 
@@ -3019,13 +3282,25 @@ impl PicturePrimitive {
                             }
 
                             // Ensure that this texture is allocated.
-                            if let TileSurface::Texture { ref mut handle, ref mut visibility_mask } = surface {
-                                if !frame_state.resource_cache.texture_cache.is_allocated(handle) {
-                                    frame_state.resource_cache.texture_cache.update_picture_cache(
-                                        tile_cache.current_tile_size,
-                                        handle,
-                                        frame_state.gpu_cache,
-                                    );
+                            if let TileSurface::Texture { ref mut descriptor, ref mut visibility_mask } = surface {
+                                match *descriptor {
+                                    SurfaceTextureDescriptor::TextureCache { ref mut handle } => {
+                                        if !frame_state.resource_cache.texture_cache.is_allocated(handle) {
+                                            frame_state.resource_cache.texture_cache.update_picture_cache(
+                                                tile_cache.current_tile_size,
+                                                handle,
+                                                frame_state.gpu_cache,
+                                            );
+                                        }
+                                    }
+                                    SurfaceTextureDescriptor::NativeSurface { ref mut id, size, is_opaque } => {
+                                        if id.is_none() {
+                                            *id = Some(frame_state.resource_cache.create_compositor_surface(
+                                                size,
+                                                is_opaque,
+                                            ));
+                                        }
+                                    }
                                 }
 
                                 *visibility_mask = PrimitiveVisibilityMask::empty();
@@ -3068,12 +3343,11 @@ impl PicturePrimitive {
                                 // CPUs). Round the rect here before casting to integer device pixels
                                 // to ensure the scissor rect is correct.
                                 let scissor_rect = (scissor_rect * device_pixel_scale).round();
-                                let cache_item = frame_state.resource_cache.texture_cache.get(handle);
+                                let surface = descriptor.resolve(frame_state.resource_cache);
 
                                 let task = RenderTask::new_picture(
                                     RenderTaskLocation::PictureCache {
-                                        texture: cache_item.texture_id,
-                                        layer: cache_item.texture_layer,
+                                        surface,
                                         size: tile_cache.current_tile_size,
                                     },
                                     tile_cache.current_tile_size.to_f32(),
@@ -3169,6 +3443,13 @@ impl PicturePrimitive {
 
                         let picture_task_id = frame_state.render_tasks.add(picture_task);
 
+                        // The local position of the clipped content, which noise
+                        // generated by the filter is anchored to.
+                        let content_origin = pic_rect.origin + PictureVector2D::new(
+                            clipped.origin.x as f32 - unclipped.origin.x,
+                            clipped.origin.y as f32 - unclipped.origin.y,
+                        ) / device_pixel_scale.0;
+
                         let filter_task_id = RenderTask::new_svg_filter(
                             primitives,
                             filter_datas,
@@ -3177,6 +3458,10 @@ impl PicturePrimitive {
                             uv_rect_kind,
                             picture_task_id,
                             device_pixel_scale,
+                            pic_rect,
+                            content_origin,
+                            frame_state.resource_cache,
+                            frame_state.gpu_cache,
                         );
 
                         Some((filter_task_id, picture_task_id))
@@ -3471,13 +3756,13 @@ impl PicturePrimitive {
 
             // This inflation factor is to be applied to all primitives within the surface.
             let inflation_factor = match composite_mode {
-                PictureCompositeMode::Filter(Filter::Blur(blur_radius)) => {
+                PictureCompositeMode::Filter(Filter::Blur(width, height)) => {
                     // Only inflate if the caller hasn't already inflated
                     // the bounding rects for this filter.
                     if self.options.inflate_if_required {
                         // The amount of extra space needed for primitives inside
                         // this picture to ensure the visibility check is correct.
-                        BLUR_SAMPLE_SCALE * blur_radius
+                        BLUR_SAMPLE_SCALE * width.max(height)
                     } else {
                         0.0
                     }
@@ -3486,7 +3771,7 @@ impl PicturePrimitive {
                     let mut max = 0.0;
                     for primitive in primitives {
                         if let FilterPrimitiveKind::Blur(ref blur) = primitive.kind {
-                            max = f32::max(max, blur.radius * BLUR_SAMPLE_SCALE);
+                            max = f32::max(max, blur.width.max(blur.height) * BLUR_SAMPLE_SCALE);
                         }
                     }
                     max
@@ -3794,6 +4079,16 @@ impl PicturePrimitive {
                             request.push(color.to_array());
                         }
                     }
+                    Filter::ColorLut3D { lut, .. } => {
+                        frame_state.resource_cache.request_image(
+                            ImageRequest {
+                                key: lut,
+                                rendering: ImageRendering::Auto,
+                                tile: None,
+                            },
+                            frame_state.gpu_cache,
+                        );
+                    }
                     _ => {}
                 }
             }
@@ -3945,6 +4240,197 @@ fn get_transform_key(
     transform.into()
 }
 
+/// A primitive drawn into a snapshot, along with the state it is rasterized
+/// with, relative to the snapshot picture.
+#[derive(Debug, PartialEq)]
+struct SnapshotPrimitive {
+    uid: ItemUid,
+    clip_rect: RectangleKey,
+    clips: Vec<ItemUid>,
+    transforms: Vec<TransformKey>,
+    opacities: Vec<f32>,
+    color: Option<ColorU>,
+}
+
+/// Describes the contents of a picture that renders into a snapshot image,
+/// so that the snapshot is only rendered again when they change.
+#[derive(Debug, PartialEq)]
+pub struct SnapshotDescriptor {
+    raster_transform: TransformKey,
+    device_pixel_scale: f32,
+    prims: Vec<SnapshotPrimitive>,
+}
+
+impl SnapshotDescriptor {
+    /// Describe the contents of a snapshot picture, after its primitives went
+    /// through the visibility pass. Returns None if the contents can't be
+    /// compared between frames, e.g. because they contain external images or
+    /// images that changed.
+    pub fn new(
+        pic_index: PictureIndex,
+        prim_store: &PrimitiveStore,
+        frame_context: &FrameVisibilityContext,
+        frame_state: &FrameVisibilityState,
+    ) -> Option<Self> {
+        let pic = &prim_store.pictures[pic_index.0];
+        let surface_index = pic.raster_config.as_ref()?.surface_index;
+        let surface = &frame_context.surfaces[surface_index.0];
+
+        let mut descriptor = SnapshotDescriptor {
+            raster_transform: get_transform_key(
+                pic.spatial_node_index,
+                surface.raster_spatial_node_index,
+                frame_context.clip_scroll_tree,
+            ),
+            device_pixel_scale: surface.device_pixel_scale.0,
+            prims: Vec::new(),
+        };
+
+        if !descriptor.add_picture(
+            pic_index,
+            pic.spatial_node_index,
+            prim_store,
+            frame_context,
+            frame_state,
+        ) {
+            return None;
+        }
+
+        Some(descriptor)
+    }
+
+    /// Add the visible primitives of a picture, returning false if any of
+    /// them can't be compared between frames. Nested snapshot pictures are
+    /// described by their own snapshot image.
+    fn add_picture(
+        &mut self,
+        pic_index: PictureIndex,
+        spatial_node_index: SpatialNodeIndex,
+        prim_store: &PrimitiveStore,
+        frame_context: &FrameVisibilityContext,
+        frame_state: &FrameVisibilityState,
+    ) -> bool {
+        let clip_scroll_tree = frame_context.clip_scroll_tree;
+        let data_stores = &*frame_state.data_stores;
+        let resource_cache = &*frame_state.resource_cache;
+
+        for cluster in &prim_store.pictures[pic_index.0].prim_list.clusters {
+            if !cluster.flags.contains(ClusterFlags::IS_VISIBLE) {
+                continue;
+            }
+
+            for prim_instance in &cluster.prim_instances {
+                if prim_instance.visibility_info == PrimitiveVisibilityIndex::INVALID {
+                    continue;
+                }
+
+                if !prim_instance.is_cacheable(data_stores, resource_cache) {
+                    return false;
+                }
+
+                let prim_info = &frame_state.scratch.prim_info[prim_instance.visibility_info.0 as usize];
+                let mut prim = SnapshotPrimitive {
+                    uid: prim_instance.uid(),
+                    clip_rect: prim_info.clip_chain.pic_clip_rect.into(),
+                    clips: Vec::new(),
+                    transforms: vec![get_transform_key(
+                        cluster.spatial_node_index,
+                        spatial_node_index,
+                        clip_scroll_tree,
+                    )],
+                    opacities: Vec::new(),
+                    color: None,
+                };
+
+                let clip_instances = &frame_state
+                    .clip_store
+                    .clip_node_instances[prim_info.clip_chain.clips_range.to_range()];
+                for clip_instance in clip_instances {
+                    let clip_node = &data_stores.clip[clip_instance.handle];
+                    prim.clips.push(clip_instance.handle.uid());
+                    prim.transforms.push(get_transform_key(
+                        clip_node.item.spatial_node_index,
+                        spatial_node_index,
+                        clip_scroll_tree,
+                    ));
+                }
+
+                let mut opacity_binding_index = OpacityBindingIndex::INVALID;
+                let mut image_keys: SmallVec<[ImageKey; 3]> = SmallVec::new();
+
+                match prim_instance.kind {
+                    PrimitiveInstanceKind::Picture { pic_index: child_pic_index, .. } => {
+                        let child_pic = &prim_store.pictures[child_pic_index.0];
+                        if let Some(PictureCompositeMode::Filter(Filter::Opacity(_, opacity))) = child_pic.requested_composite_mode {
+                            prim.opacities.push(opacity);
+                        }
+
+                        match child_pic.snapshot_key() {
+                            Some(key) => image_keys.push(key),
+                            None => {
+                                if !self.add_picture(
+                                    child_pic_index,
+                                    spatial_node_index,
+                                    prim_store,
+                                    frame_context,
+                                    frame_state,
+                                ) {
+                                    return false;
+                                }
+                            }
+                        }
+                    }
+                    PrimitiveInstanceKind::Rectangle { data_handle, opacity_binding_index: index, .. } => {
+                        opacity_binding_index = index;
+                        if let PrimitiveTemplateKind::Rectangle { ref color, .. } = data_stores.prim[data_handle].kind {
+                            prim.color = Some(frame_context.scene_properties.resolve_color(color).into());
+                        }
+                    }
+                    PrimitiveInstanceKind::Image { data_handle, image_instance_index, .. } => {
+                        opacity_binding_index = prim_store.images[image_instance_index].opacity_binding_index;
+                        image_keys.push(data_stores.image[data_handle].kind.key);
+                    }
+                    PrimitiveInstanceKind::YuvImage { data_handle, .. } => {
+                        image_keys.extend_from_slice(&data_stores.yuv_image[data_handle].kind.yuv_key);
+                    }
+                    PrimitiveInstanceKind::ImageBorder { data_handle, .. } => {
+                        image_keys.push(data_stores.image_border[data_handle].kind.request.key);
+                    }
+                    PrimitiveInstanceKind::Backdrop { .. } => {
+                        // The backdrop is drawn from another picture, which
+                        // isn't described here.
+                        return false;
+                    }
+                    PrimitiveInstanceKind::TextRun { .. } |
+                    PrimitiveInstanceKind::LineDecoration { .. } |
+                    PrimitiveInstanceKind::VectorPath { .. } |
+                    PrimitiveInstanceKind::NormalBorder { .. } |
+                    PrimitiveInstanceKind::LinearGradient { .. } |
+                    PrimitiveInstanceKind::RadialGradient { .. } |
+                    PrimitiveInstanceKind::ConicGradient { .. } |
+                    PrimitiveInstanceKind::Clear { .. } |
+                    PrimitiveInstanceKind::PushClipChain |
+                    PrimitiveInstanceKind::PopClipChain => {}
+                }
+
+                if opacity_binding_index != OpacityBindingIndex::INVALID {
+                    for binding in &prim_store.opacity_bindings[opacity_binding_index].bindings {
+                        prim.opacities.push(frame_context.scene_properties.resolve_float(binding));
+                    }
+                }
+
+                if image_keys.iter().any(|key| resource_cache.is_image_dirty(*key)) {
+                    return false;
+                }
+
+                self.prims.push(prim);
+            }
+        }
+
+        true
+    }
+}
+
 /// A key for storing primitive comparison results during tile dependency tests.
 #[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
 struct PrimitiveComparisonKey {
@@ -3958,9 +4444,11 @@ struct PrimitiveComparer<'a> {
     transform_comparer: CompareHelper<'a, SpatialNodeIndex>,
     image_comparer: CompareHelper<'a, ImageKey>,
     opacity_comparer: CompareHelper<'a, OpacityBinding>,
+    color_comparer: CompareHelper<'a, ColorBinding>,
     resource_cache: &'a ResourceCache,
     spatial_nodes: &'a FastHashMap<SpatialNodeIndex, SpatialNodeDependency>,
     opacity_bindings: &'a FastHashMap<PropertyBindingId, OpacityBindingInfo>,
+    color_bindings: &'a FastHashMap<PropertyBindingId, ColorBindingInfo>,
 }
 
 impl<'a> PrimitiveComparer<'a> {
@@ -3970,6 +4458,7 @@ impl<'a> PrimitiveComparer<'a> {
         resource_cache: &'a ResourceCache,
         spatial_nodes: &'a FastHashMap<SpatialNodeIndex, SpatialNodeDependency>,
         opacity_bindings: &'a FastHashMap<PropertyBindingId, OpacityBindingInfo>,
+        color_bindings: &'a FastHashMap<PropertyBindingId, ColorBindingInfo>,
     ) -> Self {
         let clip_comparer = CompareHelper::new(
             &prev.clips,
@@ -3991,14 +4480,21 @@ impl<'a> PrimitiveComparer<'a> {
             &curr.opacity_bindings,
         );
 
+        let color_comparer = CompareHelper::new(
+            &prev.color_bindings,
+            &curr.color_bindings,
+        );
+
         PrimitiveComparer {
             clip_comparer,
             transform_comparer,
             image_comparer,
             opacity_comparer,
+            color_comparer,
             resource_cache,
             spatial_nodes,
             opacity_bindings,
+            color_bindings,
         }
     }
 
@@ -4007,6 +4503,7 @@ impl<'a> PrimitiveComparer<'a> {
         self.transform_comparer.reset();
         self.image_comparer.reset();
         self.opacity_comparer.reset();
+        self.color_comparer.reset();
     }
 
     fn advance_prev(&mut self, prim: &PrimitiveDescriptor) {
@@ -4014,6 +4511,7 @@ impl<'a> PrimitiveComparer<'a> {
         self.transform_comparer.advance_prev(prim.transform_dep_count);
         self.image_comparer.advance_prev(prim.image_dep_count);
         self.opacity_comparer.advance_prev(prim.opacity_binding_dep_count);
+        self.color_comparer.advance_prev(prim.color_binding_dep_count);
     }
 
     fn advance_curr(&mut self, prim: &PrimitiveDescriptor) {
@@ -4021,6 +4519,7 @@ impl<'a> PrimitiveComparer<'a> {
         self.transform_comparer.advance_curr(prim.transform_dep_count);
         self.image_comparer.advance_curr(prim.image_dep_count);
         self.opacity_comparer.advance_curr(prim.opacity_binding_dep_count);
+        self.color_comparer.advance_curr(prim.color_binding_dep_count);
     }
 
     /// Check if two primitive descriptors are the same.
@@ -4032,6 +4531,7 @@ impl<'a> PrimitiveComparer<'a> {
         let resource_cache = self.resource_cache;
         let spatial_nodes = self.spatial_nodes;
         let opacity_bindings = self.opacity_bindings;
+        let color_bindings = self.color_bindings;
 
         // Check equality of the PrimitiveDescriptor
         if prev != curr {
@@ -4090,6 +4590,25 @@ impl<'a> PrimitiveComparer<'a> {
             return false;
         }
 
+        // Check if any of the color bindings this prim has are different.
+        if !self.color_comparer.is_same(
+            prev.color_binding_dep_count,
+            curr.color_binding_dep_count,
+            |curr| {
+                if let ColorBinding::Binding(id) = curr {
+                    if color_bindings
+                        .get(id)
+                        .map_or(true, |info| info.changed) {
+                        return true;
+                    }
+                }
+
+                false
+            }
+        ) {
+            return false;
+        }
+
         true
     }
 }
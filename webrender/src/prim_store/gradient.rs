@@ -3,7 +3,7 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use api::{
-    ColorF, ColorU, ExtendMode, GradientStop,
+    ColorF, ColorU, ExtendMode, GradientColorSpace, GradientStop,
     PremultipliedColorF, LineOrientation, PrimitiveFlags,
 };
 use api::units::{LayoutPoint, LayoutSize, LayoutVector2D};
@@ -19,11 +19,16 @@ use crate::prim_store::{PrimKeyCommonData, PrimTemplateCommonData, PrimitiveStor
 use crate::prim_store::{NinePatchDescriptor, PointKey, SizeKey, InternablePrimitive};
 use crate::render_task_cache::RenderTaskCacheEntryHandle;
 use std::{hash, ops::{Deref, DerefMut}, mem};
+use std::f32::consts::PI;
 use crate::util::pack_as_float;
 
 /// The maximum number of stops a gradient may have to use the fast path.
 pub const GRADIENT_FP_STOPS: usize = 4;
 
+/// The number of pieces the range between two stops is split into, when it
+/// can't be interpolated linearly in premultiplied sRGB.
+const GRADIENT_INTERPOLATION_STEPS: usize = 16;
+
 /// A hashable gradient stop that can be used in primitive keys.
 #[cfg_attr(feature = "capture", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
@@ -31,6 +36,7 @@ pub const GRADIENT_FP_STOPS: usize = 4;
 pub struct GradientStopKey {
     pub offset: f32,
     pub color: ColorU,
+    pub midpoint: Option<f32>,
 }
 
 impl GradientStopKey {
@@ -38,6 +44,7 @@ impl GradientStopKey {
         GradientStopKey {
             offset: 0.0,
             color: ColorU::new(0, 0, 0, 0),
+            midpoint: None,
         }
     }
 }
@@ -47,6 +54,17 @@ impl Into<GradientStopKey> for GradientStop {
         GradientStopKey {
             offset: self.offset,
             color: self.color.into(),
+            midpoint: self.midpoint,
+        }
+    }
+}
+
+impl Into<GradientStop> for GradientStopKey {
+    fn into(self) -> GradientStop {
+        GradientStop {
+            offset: self.offset,
+            color: self.color.into(),
+            midpoint: self.midpoint,
         }
     }
 }
@@ -57,7 +75,203 @@ impl hash::Hash for GradientStopKey {
     fn hash<H: hash::Hasher>(&self, state: &mut H) {
         self.offset.to_bits().hash(state);
         self.color.hash(state);
+        self.midpoint.map(f32::to_bits).hash(state);
+    }
+}
+
+/// Whether the stops can be written to the gradient data table as they are,
+/// with colors linearly interpolated in premultiplied sRGB.
+fn needs_stop_expansion(stops: &[GradientStopKey], color_space: GradientColorSpace) -> bool {
+    color_space != GradientColorSpace::Srgb ||
+        stops.iter().any(|stop| stop.midpoint.map_or(false, |midpoint| midpoint != 0.5))
+}
+
+/// Convert the stops of a gradient into an equivalent list of stops that can
+/// be linearly interpolated in premultiplied sRGB, by splitting the range
+/// between each pair of stops into smaller pieces. The gradient data table is
+/// interpolated linearly, so this is the only place the color space and the
+/// color hints of a gradient are taken into account.
+pub fn expand_gradient_stops(
+    stops: &[GradientStopKey],
+    color_space: GradientColorSpace,
+) -> Vec<GradientStop> {
+    if !needs_stop_expansion(stops, color_space) {
+        return stops.iter().map(|stop| (*stop).into()).collect();
+    }
+
+    let mut expanded = Vec::with_capacity(stops.len() * GRADIENT_INTERPOLATION_STEPS);
+    let mut prev: Option<GradientStop> = None;
+
+    for stop in stops {
+        let stop: GradientStop = (*stop).into();
+
+        if let Some(prev) = prev {
+            // Hard stops don't need any interpolation.
+            if stop.offset > prev.offset {
+                for step in 1 .. GRADIENT_INTERPOLATION_STEPS {
+                    let t = step as f32 / GRADIENT_INTERPOLATION_STEPS as f32;
+                    let weight = apply_color_hint(t, stop.midpoint.unwrap_or(0.5));
+                    expanded.push(GradientStop {
+                        offset: prev.offset + (stop.offset - prev.offset) * t,
+                        color: interpolate_color(&prev.color, &stop.color, weight, color_space),
+                        midpoint: None,
+                    });
+                }
+            }
+        }
+
+        expanded.push(GradientStop { midpoint: None, ..stop });
+        prev = Some(stop);
+    }
+
+    expanded
+}
+
+/// Map a position between two stops to the weight of the second stop, such that
+/// the colors are mixed half and half at the midpoint.
+/// See https://drafts.csswg.org/css-images-4/#color-stop-syntax
+fn apply_color_hint(t: f32, midpoint: f32) -> f32 {
+    if midpoint <= 0.0 {
+        1.0
+    } else if midpoint >= 1.0 {
+        0.0
+    } else {
+        t.powf(0.5f32.ln() / midpoint.ln())
+    }
+}
+
+/// Interpolate between two colors in the given color space, with premultiplied
+/// alpha, as described in https://drafts.csswg.org/css-color-4/#interpolation
+fn interpolate_color(
+    from: &ColorF,
+    to: &ColorF,
+    t: f32,
+    color_space: GradientColorSpace,
+) -> ColorF {
+    let alpha = from.a + (to.a - from.a) * t;
+    if alpha <= 0.0 {
+        return ColorF::TRANSPARENT;
+    }
+
+    let mut start = srgb_to_color_space(from, color_space);
+    let mut end = srgb_to_color_space(to, color_space);
+
+    // The hue of a color without chroma is powerless and takes the hue of the
+    // other color.
+    let has_hue = color_space == GradientColorSpace::Oklch;
+    if has_hue {
+        if start[1] < 1e-4 {
+            start[2] = end[2];
+        } else if end[1] < 1e-4 {
+            end[2] = start[2];
+        }
+    }
+
+    let mut result = [0.0; 3];
+    for (i, value) in result.iter_mut().enumerate() {
+        if has_hue && i == 2 {
+            // Interpolate hues along the shorter arc, without premultiplication.
+            let mut delta = end[2] - start[2];
+            if delta > PI {
+                delta -= 2.0 * PI;
+            } else if delta < -PI {
+                delta += 2.0 * PI;
+            }
+            *value = start[2] + delta * t;
+        } else {
+            let start = start[i] * from.a;
+            let end = end[i] * to.a;
+            *value = (start + (end - start) * t) / alpha;
+        }
+    }
+
+    color_space_to_srgb(result, alpha, color_space)
+}
+
+fn srgb_to_color_space(color: &ColorF, color_space: GradientColorSpace) -> [f32; 3] {
+    let srgb = [color.r, color.g, color.b];
+    match color_space {
+        GradientColorSpace::Srgb => srgb,
+        GradientColorSpace::LinearSrgb => linear_srgb(srgb),
+        GradientColorSpace::Oklab => oklab(linear_srgb(srgb)),
+        GradientColorSpace::Oklch => {
+            let [l, a, b] = oklab(linear_srgb(srgb));
+            [l, a.hypot(b), b.atan2(a)]
+        }
+    }
+}
+
+fn color_space_to_srgb(color: [f32; 3], alpha: f32, color_space: GradientColorSpace) -> ColorF {
+    let [r, g, b] = match color_space {
+        GradientColorSpace::Srgb => color,
+        GradientColorSpace::LinearSrgb => gamma_srgb(color),
+        GradientColorSpace::Oklab => gamma_srgb(oklab_to_linear_srgb(color)),
+        GradientColorSpace::Oklch => {
+            let [l, c, h] = color;
+            gamma_srgb(oklab_to_linear_srgb([l, c * h.cos(), c * h.sin()]))
+        }
+    };
+
+    // Colors outside of the sRGB gamut are clipped.
+    ColorF::new(
+        r.max(0.0).min(1.0),
+        g.max(0.0).min(1.0),
+        b.max(0.0).min(1.0),
+        alpha,
+    )
+}
+
+fn linear_srgb(color: [f32; 3]) -> [f32; 3] {
+    let mut result = color;
+    for value in &mut result {
+        *value = if *value <= 0.04045 {
+            *value / 12.92
+        } else {
+            ((*value + 0.055) / 1.055).powf(2.4)
+        };
+    }
+    result
+}
+
+fn gamma_srgb(color: [f32; 3]) -> [f32; 3] {
+    let mut result = color;
+    for value in &mut result {
+        *value = if *value <= 0.0031308 {
+            *value * 12.92
+        } else {
+            1.055 * value.powf(1.0 / 2.4) - 0.055
+        };
     }
+    result
+}
+
+// See https://bottosson.github.io/posts/oklab/
+fn oklab([r, g, b]: [f32; 3]) -> [f32; 3] {
+    let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+    let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+    let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+
+    [
+        0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+        1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+        0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+    ]
+}
+
+fn oklab_to_linear_srgb([l, a, b]: [f32; 3]) -> [f32; 3] {
+    let l_ = l + 0.3963377774 * a + 0.2158037573 * b;
+    let m_ = l - 0.1055613458 * a - 0.0638541728 * b;
+    let s_ = l - 0.0894841775 * a - 1.2914855480 * b;
+
+    let l = l_ * l_ * l_;
+    let m = m_ * m_ * m_;
+    let s = s_ * s_ * s_;
+
+    [
+        4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+        -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+        -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+    ]
 }
 
 /// Identifying key for a line decoration.
@@ -67,6 +281,7 @@ impl hash::Hash for GradientStopKey {
 pub struct LinearGradientKey {
     pub common: PrimKeyCommonData,
     pub extend_mode: ExtendMode,
+    pub color_space: GradientColorSpace,
     pub start_point: PointKey,
     pub end_point: PointKey,
     pub stretch_size: SizeKey,
@@ -88,6 +303,7 @@ impl LinearGradientKey {
                 prim_size: prim_size.into(),
             },
             extend_mode: linear_grad.extend_mode,
+            color_space: linear_grad.color_space,
             start_point: linear_grad.start_point,
             end_point: linear_grad.end_point,
             stretch_size: linear_grad.stretch_size,
@@ -164,13 +380,13 @@ impl From<LinearGradientKey> for LinearGradientTemplate {
             // Fast path supports a limited number of stops
             item.stops.len() <= GRADIENT_FP_STOPS &&
             // Fast path not supported on segmented (border-image) gradients.
-            item.nine_patch.is_none();
+            item.nine_patch.is_none() &&
+            // Fast path only interpolates linearly between the stops.
+            !needs_stop_expansion(&item.stops, item.color_space);
 
-        // Convert the stops to more convenient representation
-        // for the current gradient builder.
         let mut prev_color = None;
 
-        let stops: Vec<GradientStop> = item.stops.iter().map(|stop| {
+        for stop in &item.stops {
             let color: ColorF = stop.color.into();
             min_alpha = min_alpha.min(color.a);
 
@@ -185,12 +401,11 @@ impl From<LinearGradientKey> for LinearGradientTemplate {
             }
 
             prev_color = Some(color);
+        }
 
-            GradientStop {
-                offset: stop.offset,
-                color,
-            }
-        }).collect();
+        // Convert the stops to more convenient representation
+        // for the current gradient builder.
+        let stops = expand_gradient_stops(&item.stops, item.color_space);
 
         let mut brush_segments = Vec::new();
 
@@ -287,6 +502,7 @@ pub type LinearGradientDataHandle = InternHandle<LinearGradient>;
 #[cfg_attr(feature = "replay", derive(Deserialize))]
 pub struct LinearGradient {
     pub extend_mode: ExtendMode,
+    pub color_space: GradientColorSpace,
     pub start_point: PointKey,
     pub end_point: PointKey,
     pub stretch_size: SizeKey,
@@ -374,6 +590,7 @@ impl hash::Hash for RadialGradientParams {
 pub struct RadialGradientKey {
     pub common: PrimKeyCommonData,
     pub extend_mode: ExtendMode,
+    pub color_space: GradientColorSpace,
     pub center: PointKey,
     pub params: RadialGradientParams,
     pub stretch_size: SizeKey,
@@ -394,6 +611,7 @@ impl RadialGradientKey {
                 prim_size: prim_size.into(),
             },
             extend_mode: radial_grad.extend_mode,
+            color_space: radial_grad.color_space,
             center: radial_grad.center,
             params: radial_grad.params,
             stretch_size: radial_grad.stretch_size,
@@ -443,12 +661,7 @@ impl From<RadialGradientKey> for RadialGradientTemplate {
             brush_segments = nine_patch.create_segments(common.prim_size);
         }
 
-        let stops = item.stops.iter().map(|stop| {
-            GradientStop {
-                offset: stop.offset,
-                color: stop.color.into(),
-            }
-        }).collect();
+        let stops = expand_gradient_stops(&item.stops, item.color_space);
 
         RadialGradientTemplate {
             common,
@@ -518,6 +731,7 @@ pub type RadialGradientDataHandle = InternHandle<RadialGradient>;
 #[cfg_attr(feature = "replay", derive(Deserialize))]
 pub struct RadialGradient {
     pub extend_mode: ExtendMode,
+    pub color_space: GradientColorSpace,
     pub center: PointKey,
     pub params: RadialGradientParams,
     pub stretch_size: SizeKey,
@@ -565,6 +779,220 @@ impl IsVisible for RadialGradient {
 
 ////////////////////////////////////////////////////////////////////////////////
 
+/// Hashable conic gradient parameters, for use during prim interning.
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+#[derive(Debug, Clone, MallocSizeOf, PartialEq)]
+pub struct ConicGradientParams {
+    pub angle: f32, // in radians
+    pub start_offset: f32,
+    pub end_offset: f32,
+}
+
+impl Eq for ConicGradientParams {}
+
+impl hash::Hash for ConicGradientParams {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.angle.to_bits().hash(state);
+        self.start_offset.to_bits().hash(state);
+        self.end_offset.to_bits().hash(state);
+    }
+}
+
+/// Identifying key for a conic gradient.
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+#[derive(Debug, Clone, Eq, PartialEq, Hash, MallocSizeOf)]
+pub struct ConicGradientKey {
+    pub common: PrimKeyCommonData,
+    pub extend_mode: ExtendMode,
+    pub center: PointKey,
+    pub params: ConicGradientParams,
+    pub stretch_size: SizeKey,
+    pub stops: Vec<GradientStopKey>,
+    pub tile_spacing: SizeKey,
+    pub nine_patch: Option<Box<NinePatchDescriptor>>,
+}
+
+impl ConicGradientKey {
+    pub fn new(
+        flags: PrimitiveFlags,
+        prim_size: LayoutSize,
+        conic_grad: ConicGradient,
+    ) -> Self {
+        ConicGradientKey {
+            common: PrimKeyCommonData {
+                flags,
+                prim_size: prim_size.into(),
+            },
+            extend_mode: conic_grad.extend_mode,
+            center: conic_grad.center,
+            params: conic_grad.params,
+            stretch_size: conic_grad.stretch_size,
+            stops: conic_grad.stops,
+            tile_spacing: conic_grad.tile_spacing,
+            nine_patch: conic_grad.nine_patch,
+        }
+    }
+}
+
+impl InternDebug for ConicGradientKey {}
+
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+#[derive(MallocSizeOf)]
+pub struct ConicGradientTemplate {
+    pub common: PrimTemplateCommonData,
+    pub extend_mode: ExtendMode,
+    pub center: LayoutPoint,
+    pub params: ConicGradientParams,
+    pub stretch_size: LayoutSize,
+    pub tile_spacing: LayoutSize,
+    pub brush_segments: Vec<BrushSegment>,
+    pub stops: Vec<GradientStop>,
+    pub stops_handle: GpuCacheHandle,
+}
+
+impl Deref for ConicGradientTemplate {
+    type Target = PrimTemplateCommonData;
+    fn deref(&self) -> &Self::Target {
+        &self.common
+    }
+}
+
+impl DerefMut for ConicGradientTemplate {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.common
+    }
+}
+
+impl From<ConicGradientKey> for ConicGradientTemplate {
+    fn from(item: ConicGradientKey) -> Self {
+        let common = PrimTemplateCommonData::with_key_common(item.common);
+        let mut brush_segments = Vec::new();
+
+        if let Some(ref nine_patch) = item.nine_patch {
+            brush_segments = nine_patch.create_segments(common.prim_size);
+        }
+
+        // Conic gradients are always interpolated in sRGB, but may have color hints.
+        let stops = expand_gradient_stops(&item.stops, GradientColorSpace::Srgb);
+
+        ConicGradientTemplate {
+            common,
+            center: item.center.into(),
+            extend_mode: item.extend_mode,
+            params: item.params,
+            stretch_size: item.stretch_size.into(),
+            tile_spacing: item.tile_spacing.into(),
+            brush_segments,
+            stops,
+            stops_handle: GpuCacheHandle::new(),
+        }
+    }
+}
+
+impl ConicGradientTemplate {
+    /// Update the GPU cache for a given primitive template. This may be called multiple
+    /// times per frame, by each primitive reference that refers to this interned
+    /// template. The initial request call to the GPU cache ensures that work is only
+    /// done if the cache entry is invalid (due to first use or eviction).
+    pub fn update(
+        &mut self,
+        frame_state: &mut FrameBuildingState,
+    ) {
+        if let Some(mut request) =
+            frame_state.gpu_cache.request(&mut self.common.gpu_cache_handle) {
+            // write_prim_gpu_blocks
+            request.push([
+                self.center.x,
+                self.center.y,
+                self.params.start_offset,
+                self.params.end_offset,
+            ]);
+            request.push([
+                self.params.angle,
+                pack_as_float(self.extend_mode as u32),
+                self.stretch_size.width,
+                self.stretch_size.height,
+            ]);
+
+            // write_segment_gpu_blocks
+            for segment in &self.brush_segments {
+                // has to match VECS_PER_SEGMENT
+                request.write_segment(
+                    segment.local_rect,
+                    segment.extra_data,
+                );
+            }
+        }
+
+        if let Some(mut request) = frame_state.gpu_cache.request(&mut self.stops_handle) {
+            GradientGpuBlockBuilder::build(
+                false,
+                &mut request,
+                &self.stops,
+            );
+        }
+
+        self.opacity = PrimitiveOpacity::translucent();
+    }
+}
+
+pub type ConicGradientDataHandle = InternHandle<ConicGradient>;
+
+#[derive(Debug, MallocSizeOf)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct ConicGradient {
+    pub extend_mode: ExtendMode,
+    pub center: PointKey,
+    pub params: ConicGradientParams,
+    pub stretch_size: SizeKey,
+    pub stops: Vec<GradientStopKey>,
+    pub tile_spacing: SizeKey,
+    pub nine_patch: Option<Box<NinePatchDescriptor>>,
+}
+
+impl Internable for ConicGradient {
+    type Key = ConicGradientKey;
+    type StoreData = ConicGradientTemplate;
+    type InternData = PrimitiveSceneData;
+}
+
+impl InternablePrimitive for ConicGradient {
+    fn into_key(
+        self,
+        info: &LayoutPrimitiveInfo,
+    ) -> ConicGradientKey {
+        ConicGradientKey::new(
+            info.flags,
+            info.rect.size,
+            self,
+        )
+    }
+
+    fn make_instance_kind(
+        _key: ConicGradientKey,
+        data_handle: ConicGradientDataHandle,
+        _prim_store: &mut PrimitiveStore,
+        _reference_frame_relative_offset: LayoutVector2D,
+    ) -> PrimitiveInstanceKind {
+        PrimitiveInstanceKind::ConicGradient {
+            data_handle,
+            visible_tiles_range: GradientTileRange::empty(),
+        }
+    }
+}
+
+impl IsVisible for ConicGradient {
+    fn is_visible(&self) -> bool {
+        true
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
 // The gradient entry index for the first color stop
 pub const GRADIENT_DATA_FIRST_STOP: usize = 0;
 // The gradient entry index for the last color stop
@@ -589,7 +1017,7 @@ pub struct GradientDataEntry {
 }
 
 // TODO(gw): Tidy this up to be a free function / module?
-struct GradientGpuBlockBuilder {}
+pub struct GradientGpuBlockBuilder {}
 
 impl GradientGpuBlockBuilder {
     /// Generate a color ramp filling the indices in [start_idx, end_idx) and interpolating
@@ -632,7 +1060,7 @@ impl GradientGpuBlockBuilder {
     }
 
     // Build the gradient data from the supplied stops, reversing them if necessary.
-    fn build(
+    pub fn build(
         reverse_stops: bool,
         request: &mut GpuDataRequest,
         src_stops: &[GradientStop],
@@ -790,4 +1218,134 @@ fn test_struct_sizes() {
     assert_eq!(mem::size_of::<RadialGradient>(), 72, "RadialGradient size changed");
     assert_eq!(mem::size_of::<RadialGradientTemplate>(), 120, "RadialGradientTemplate size changed");
     assert_eq!(mem::size_of::<RadialGradientKey>(), 88, "RadialGradientKey size changed");
+
+    assert_eq!(mem::size_of::<ConicGradient>(), 72, "ConicGradient size changed");
+    assert_eq!(mem::size_of::<ConicGradientTemplate>(), 120, "ConicGradientTemplate size changed");
+    assert_eq!(mem::size_of::<ConicGradientKey>(), 88, "ConicGradientKey size changed");
+}
+
+#[cfg(test)]
+fn assert_colors_close(a: &ColorF, b: &ColorF) {
+    let close = |a: f32, b: f32| (a - b).abs() < 1e-3;
+    assert!(
+        close(a.r, b.r) && close(a.g, b.g) && close(a.b, b.b) && close(a.a, b.a),
+        "{:?} != {:?}", a, b,
+    );
+}
+
+#[cfg(test)]
+fn stop_key(offset: f32, color: ColorF, midpoint: Option<f32>) -> GradientStopKey {
+    GradientStopKey { offset, color: color.into(), midpoint }
+}
+
+#[test]
+fn test_color_hint() {
+    // A hint in the middle is the same as no hint at all.
+    for step in 0 ..= 10 {
+        let t = step as f32 / 10.0;
+        assert!((apply_color_hint(t, 0.5) - t).abs() < 1e-6);
+    }
+
+    // The colors are mixed half and half at the hint.
+    assert!((apply_color_hint(0.2, 0.2) - 0.5).abs() < 1e-6);
+    assert!((apply_color_hint(0.9, 0.9) - 0.5).abs() < 1e-6);
+
+    // Hints at either end turn into hard stops.
+    assert_eq!(apply_color_hint(0.5, 0.0), 1.0);
+    assert_eq!(apply_color_hint(0.5, 1.0), 0.0);
+}
+
+#[test]
+fn test_srgb_stops_not_expanded() {
+    let stops = [
+        stop_key(0.0, ColorF::new(1.0, 0.0, 0.0, 1.0), None),
+        stop_key(0.5, ColorF::new(0.0, 1.0, 0.0, 1.0), Some(0.5)),
+        stop_key(1.0, ColorF::new(0.0, 0.0, 1.0, 0.5), None),
+    ];
+    let expanded = expand_gradient_stops(&stops, GradientColorSpace::Srgb);
+
+    assert_eq!(expanded.len(), stops.len());
+    for (stop, key) in expanded.iter().zip(stops.iter()) {
+        assert_eq!(stop.offset, key.offset);
+        assert_eq!(ColorU::from(stop.color), key.color);
+    }
+}
+
+#[test]
+fn test_expand_color_hint() {
+    let from = ColorF::new(0.0, 0.0, 0.0, 1.0);
+    let to = ColorF::new(1.0, 1.0, 1.0, 1.0);
+    let stops = [
+        stop_key(0.0, from, None),
+        stop_key(1.0, to, Some(0.25)),
+    ];
+    let expanded = expand_gradient_stops(&stops, GradientColorSpace::Srgb);
+
+    assert_eq!(expanded.len(), GRADIENT_INTERPOLATION_STEPS + 1);
+    assert!(expanded.iter().all(|stop| stop.midpoint.is_none()));
+    assert_eq!(expanded[0].offset, 0.0);
+    assert_eq!(expanded[GRADIENT_INTERPOLATION_STEPS].offset, 1.0);
+
+    let hint = &expanded[GRADIENT_INTERPOLATION_STEPS / 4];
+    assert_eq!(hint.offset, 0.25);
+    assert_colors_close(&hint.color, &ColorF::new(0.5, 0.5, 0.5, 1.0));
+
+    // Hard stops are left alone.
+    let stops = [
+        stop_key(0.0, from, None),
+        stop_key(0.5, from, None),
+        stop_key(0.5, to, Some(0.25)),
+    ];
+    let expanded = expand_gradient_stops(&stops, GradientColorSpace::Srgb);
+    assert_eq!(expanded.len(), GRADIENT_INTERPOLATION_STEPS + 2);
+}
+
+#[test]
+fn test_color_space_round_trip() {
+    let colors = [
+        ColorF::new(0.0, 0.0, 0.0, 1.0),
+        ColorF::new(1.0, 1.0, 1.0, 1.0),
+        ColorF::new(1.0, 0.0, 0.0, 1.0),
+        ColorF::new(0.2, 0.6, 0.9, 0.5),
+        ColorF::new(0.9, 0.8, 0.1, 0.25),
+    ];
+    let color_spaces = [
+        GradientColorSpace::LinearSrgb,
+        GradientColorSpace::Oklab,
+        GradientColorSpace::Oklch,
+    ];
+
+    for color in &colors {
+        for &color_space in &color_spaces {
+            let converted = srgb_to_color_space(color, color_space);
+            let result = color_space_to_srgb(converted, color.a, color_space);
+            assert_colors_close(&result, color);
+        }
+    }
+
+    // White has a lightness of 1 and no chroma.
+    let [l, a, b] = srgb_to_color_space(&ColorF::WHITE, GradientColorSpace::Oklab);
+    assert!((l - 1.0).abs() < 1e-3);
+    assert!(a.abs() < 1e-3 && b.abs() < 1e-3);
+}
+
+#[test]
+fn test_oklch_hue_wraps() {
+    // Two colors either side of a hue of 0 degrees.
+    let from_hue = 350f32.to_radians();
+    let to_hue = 10f32.to_radians();
+    let from = color_space_to_srgb([0.7, 0.1, from_hue], 1.0, GradientColorSpace::Oklch);
+    let to = color_space_to_srgb([0.7, 0.1, to_hue], 1.0, GradientColorSpace::Oklch);
+
+    // Halfway between them is a hue of 0, not 180 degrees.
+    let mid = interpolate_color(&from, &to, 0.5, GradientColorSpace::Oklch);
+    let [l, c, h] = srgb_to_color_space(&mid, GradientColorSpace::Oklch);
+    assert!((l - 0.7).abs() < 1e-2);
+    assert!((c - 0.1).abs() < 1e-2);
+    assert!(h.abs() < 1e-2, "hue {} isn't close to 0", h.to_degrees());
+
+    // The same holds going the other way around.
+    let mid = interpolate_color(&to, &from, 0.5, GradientColorSpace::Oklch);
+    let [_, _, h] = srgb_to_color_space(&mid, GradientColorSpace::Oklch);
+    assert!(h.abs() < 1e-2, "hue {} isn't close to 0", h.to_degrees());
 }
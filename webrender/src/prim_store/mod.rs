@@ -2,8 +2,8 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use api::{BorderRadius, ClipMode, ColorF};
-use api::{ImageRendering, RepeatMode, PrimitiveFlags};
+use api::{BorderRadius, ClipMode, ColorF, ColorU};
+use api::{ImageRendering, MaskMode, RepeatMode, PrimitiveFlags};
 use api::{PremultipliedColorF, PropertyBinding, Shadow, GradientStop};
 use api::{BoxShadowClipMode, LineStyle, LineOrientation, BorderStyle};
 use api::{PrimitiveKeyKind};
@@ -33,10 +33,12 @@ use crate::prim_store::backdrop::BackdropDataHandle;
 use crate::prim_store::borders::{ImageBorderDataHandle, NormalBorderDataHandle};
 use crate::prim_store::gradient::{GRADIENT_FP_STOPS, GradientCacheKey, GradientStopKey};
 use crate::prim_store::gradient::{LinearGradientPrimitive, LinearGradientDataHandle, RadialGradientDataHandle};
+use crate::prim_store::gradient::ConicGradientDataHandle;
 use crate::prim_store::image::{ImageDataHandle, ImageInstance, VisibleImageTile, YuvImageDataHandle};
 use crate::prim_store::line_dec::LineDecorationDataHandle;
 use crate::prim_store::picture::PictureDataHandle;
 use crate::prim_store::text_run::{TextRunDataHandle, TextRunPrimitive};
+use crate::prim_store::vector_path::{MAX_VECTOR_PATH_TASK_SIZE, VectorPathDataHandle, VectorPathPaint};
 #[cfg(debug_assertions)]
 use crate::render_backend::{FrameId};
 use crate::render_backend::DataStores;
@@ -64,6 +66,7 @@ pub mod image;
 pub mod line_dec;
 pub mod picture;
 pub mod text_run;
+pub mod vector_path;
 pub mod interned;
 
 /// Counter for unique primitive IDs for debug tracing.
@@ -709,7 +712,7 @@ impl intern::InternDebug for PrimitiveKey {}
 #[derive(MallocSizeOf)]
 pub enum PrimitiveTemplateKind {
     Rectangle {
-        color: ColorF,
+        color: PropertyBinding<ColorF>,
     },
     Clear,
 }
@@ -801,7 +804,8 @@ impl PrimitiveTemplateKind {
     /// Write any GPU blocks for the primitive template to the given request object.
     fn write_prim_gpu_blocks(
         &self,
-        request: &mut GpuDataRequest
+        request: &mut GpuDataRequest,
+        scene_properties: &SceneProperties,
     ) {
         match *self {
             PrimitiveTemplateKind::Clear => {
@@ -809,7 +813,7 @@ impl PrimitiveTemplateKind {
                 request.push(PremultipliedColorF::BLACK);
             }
             PrimitiveTemplateKind::Rectangle { ref color, .. } => {
-                request.push(color.premultiplied());
+                request.push(scene_properties.resolve_color(color).premultiplied());
             }
         }
     }
@@ -823,9 +827,10 @@ impl PrimitiveTemplate {
     pub fn update(
         &mut self,
         frame_state: &mut FrameBuildingState,
+        scene_properties: &SceneProperties,
     ) {
         if let Some(mut request) = frame_state.gpu_cache.request(&mut self.common.gpu_cache_handle) {
-            self.kind.write_prim_gpu_blocks(&mut request);
+            self.kind.write_prim_gpu_blocks(&mut request, scene_properties);
         }
 
         self.opacity = match self.kind {
@@ -833,7 +838,7 @@ impl PrimitiveTemplate {
                 PrimitiveOpacity::translucent()
             }
             PrimitiveTemplateKind::Rectangle { ref color, .. } => {
-                PrimitiveOpacity::from_alpha(color.a)
+                PrimitiveOpacity::from_alpha(scene_properties.resolve_color(color).a)
             }
         };
     }
@@ -862,7 +867,7 @@ impl InternablePrimitive for PrimitiveKeyKind {
     fn make_instance_kind(
         key: PrimitiveKey,
         data_handle: PrimitiveDataHandle,
-        _: &mut PrimitiveStore,
+        prim_store: &mut PrimitiveStore,
         _reference_frame_relative_offset: LayoutVector2D,
     ) -> PrimitiveInstanceKind {
         match key.kind {
@@ -871,11 +876,18 @@ impl InternablePrimitive for PrimitiveKeyKind {
                     data_handle
                 }
             }
-            PrimitiveKeyKind::Rectangle { .. } => {
+            PrimitiveKeyKind::Rectangle { color, .. } => {
+                let color_binding_index = match color {
+                    PropertyBinding::Binding(..) => {
+                        prim_store.color_bindings.push(color)
+                    }
+                    PropertyBinding::Value(..) => ColorBindingIndex::INVALID,
+                };
                 PrimitiveInstanceKind::Rectangle {
                     data_handle,
                     opacity_binding_index: OpacityBindingIndex::INVALID,
                     segment_instance_index: SegmentInstanceIndex::INVALID,
+                    color_binding_index,
                 }
             }
         }
@@ -1131,6 +1143,8 @@ impl ClipCorner {
 pub struct ImageMaskData {
     /// The local size of the whole masked area.
     pub local_mask_size: LayoutSize,
+    /// Which channel of the image the coverage is read from.
+    pub mode: MaskMode,
 }
 
 impl ToGpuBlocks for ImageMaskData {
@@ -1138,7 +1152,7 @@ impl ToGpuBlocks for ImageMaskData {
         request.push([
             self.local_mask_size.width,
             self.local_mask_size.height,
-            0.0,
+            self.mode as i32 as f32,
             0.0,
         ]);
     }
@@ -1318,7 +1332,12 @@ impl IsVisible for PrimitiveKeyKind {
                 true
             }
             PrimitiveKeyKind::Rectangle { ref color, .. } => {
-                color.a > 0
+                match *color {
+                    PropertyBinding::Value(value) => value.a > 0,
+                    // An animated color may become visible without
+                    // rebuilding the scene.
+                    PropertyBinding::Binding(..) => true,
+                }
             }
         }
     }
@@ -1335,7 +1354,7 @@ impl CreateShadow for PrimitiveKeyKind {
         match *self {
             PrimitiveKeyKind::Rectangle { .. } => {
                 PrimitiveKeyKind::Rectangle {
-                    color: shadow.color.into(),
+                    color: PropertyBinding::Value(shadow.color.into()),
                 }
             }
             PrimitiveKeyKind::Clear => {
@@ -1382,6 +1401,13 @@ pub enum PrimitiveInstanceKind {
         //           but also the opacity, clip_task_id etc below.
         cache_handle: Option<RenderTaskCacheEntryHandle>,
     },
+    /// A filled vector path. cache_handle refers to the cached
+    /// render task the path is rasterized into.
+    VectorPath {
+        /// Handle to the common interned data for this primitive.
+        data_handle: VectorPathDataHandle,
+        cache_handle: Option<RenderTaskCacheEntryHandle>,
+    },
     NormalBorder {
         /// Handle to the common interned data for this primitive.
         data_handle: NormalBorderDataHandle,
@@ -1396,6 +1422,7 @@ pub enum PrimitiveInstanceKind {
         data_handle: PrimitiveDataHandle,
         opacity_binding_index: OpacityBindingIndex,
         segment_instance_index: SegmentInstanceIndex,
+        color_binding_index: ColorBindingIndex,
     },
     YuvImage {
         /// Handle to the common interned data for this primitive.
@@ -1417,6 +1444,11 @@ pub enum PrimitiveInstanceKind {
         data_handle: RadialGradientDataHandle,
         visible_tiles_range: GradientTileRange,
     },
+    ConicGradient {
+        /// Handle to the common interned data for this primitive.
+        data_handle: ConicGradientDataHandle,
+        visible_tiles_range: GradientTileRange,
+    },
     /// Clear out a rect, used for special effects.
     Clear {
         /// Handle to the common interned data for this primitive.
@@ -1602,6 +1634,9 @@ impl PrimitiveInstance {
             PrimitiveInstanceKind::LineDecoration { data_handle, .. } => {
                 data_handle.uid()
             }
+            PrimitiveInstanceKind::VectorPath { data_handle, .. } => {
+                data_handle.uid()
+            }
             PrimitiveInstanceKind::LinearGradient { data_handle, .. } => {
                 data_handle.uid()
             }
@@ -1614,6 +1649,9 @@ impl PrimitiveInstance {
             PrimitiveInstanceKind::RadialGradient { data_handle, .. } => {
                 data_handle.uid()
             }
+            PrimitiveInstanceKind::ConicGradient { data_handle, .. } => {
+                data_handle.uid()
+            }
             PrimitiveInstanceKind::TextRun { data_handle, .. } => {
                 data_handle.uid()
             }
@@ -1643,6 +1681,8 @@ pub type TextRunIndex = storage::Index<TextRunPrimitive>;
 pub type TextRunStorage = storage::Storage<TextRunPrimitive>;
 pub type OpacityBindingIndex = storage::Index<OpacityBinding>;
 pub type OpacityBindingStorage = storage::Storage<OpacityBinding>;
+pub type ColorBindingIndex = storage::Index<PropertyBinding<ColorU>>;
+pub type ColorBindingStorage = storage::Storage<PropertyBinding<ColorU>>;
 pub type BorderHandleStorage = storage::Storage<RenderTaskCacheEntryHandle>;
 pub type SegmentStorage = storage::Storage<BrushSegment>;
 pub type SegmentsRange = storage::Range<BrushSegment>;
@@ -1780,6 +1820,7 @@ pub struct PrimitiveStoreStats {
     picture_count: usize,
     text_run_count: usize,
     opacity_binding_count: usize,
+    color_binding_count: usize,
     image_count: usize,
     linear_gradient_count: usize,
 }
@@ -1790,6 +1831,7 @@ impl PrimitiveStoreStats {
             picture_count: 0,
             text_run_count: 0,
             opacity_binding_count: 0,
+            color_binding_count: 0,
             image_count: 0,
             linear_gradient_count: 0,
         }
@@ -1809,6 +1851,8 @@ pub struct PrimitiveStore {
 
     /// List of animated opacity bindings for a primitive.
     pub opacity_bindings: OpacityBindingStorage,
+    /// List of animated color bindings for a primitive.
+    pub color_bindings: ColorBindingStorage,
 }
 
 impl PrimitiveStore {
@@ -1818,6 +1862,7 @@ impl PrimitiveStore {
             text_runs: TextRunStorage::new(stats.text_run_count),
             images: ImageInstanceStorage::new(stats.image_count),
             opacity_bindings: OpacityBindingStorage::new(stats.opacity_binding_count),
+            color_bindings: ColorBindingStorage::new(stats.color_binding_count),
             linear_gradients: LinearGradientStorage::new(stats.linear_gradient_count),
         }
     }
@@ -1828,6 +1873,7 @@ impl PrimitiveStore {
             text_run_count: self.text_runs.len(),
             image_count: self.images.len(),
             opacity_binding_count: self.opacity_bindings.len(),
+            color_binding_count: self.color_bindings.len(),
             linear_gradient_count: self.linear_gradients.len(),
         }
     }
@@ -1965,20 +2011,28 @@ impl PrimitiveStore {
                             continue;
                         }
 
-                        frame_state.clip_chain_stack.push_clip(
-                            prim_instance.clip_chain_id,
-                            frame_state.clip_store,
-                        );
+                        // The contents of snapshot pictures are visited before the
+                        // rest of the frame, regardless of what is visible.
+                        let pic_surface_rect = if self.pictures[pic_index.0].snapshot_key().is_some() {
+                            None
+                        } else {
+                            frame_state.clip_chain_stack.push_clip(
+                                prim_instance.clip_chain_id,
+                                frame_state.clip_store,
+                            );
 
-                        let pic_surface_rect = self.update_visibility(
-                            pic_index,
-                            surface_index,
-                            &world_culling_rect,
-                            frame_context,
-                            frame_state,
-                        );
+                            let pic_surface_rect = self.update_visibility(
+                                pic_index,
+                                surface_index,
+                                &world_culling_rect,
+                                frame_context,
+                                frame_state,
+                            );
 
-                        frame_state.clip_chain_stack.pop_clip();
+                            frame_state.clip_chain_stack.pop_clip();
+
+                            pic_surface_rect
+                        };
 
                         // The local rect of pictures is calculated dynamically based on
                         // the content of children, which may move due to the spatial
@@ -2117,6 +2171,7 @@ impl PrimitiveStore {
                             &self.pictures,
                             frame_state.resource_cache,
                             &self.opacity_bindings,
+                            &self.color_bindings,
                             &self.images,
                             surface_index,
                         ) {
@@ -2211,6 +2266,7 @@ impl PrimitiveStore {
                             PrimitiveInstanceKind::Picture { .. } => ColorF::TRANSPARENT,
                             PrimitiveInstanceKind::TextRun { .. } => debug_colors::RED,
                             PrimitiveInstanceKind::LineDecoration { .. } => debug_colors::PURPLE,
+                            PrimitiveInstanceKind::VectorPath { .. } => debug_colors::GREEN,
                             PrimitiveInstanceKind::NormalBorder { .. } |
                             PrimitiveInstanceKind::ImageBorder { .. } => debug_colors::ORANGE,
                             PrimitiveInstanceKind::Rectangle { .. } => ColorF { r: 0.8, g: 0.8, b: 0.8, a: 0.5 },
@@ -2218,6 +2274,7 @@ impl PrimitiveStore {
                             PrimitiveInstanceKind::Image { .. } => debug_colors::BLUE,
                             PrimitiveInstanceKind::LinearGradient { .. } => debug_colors::PINK,
                             PrimitiveInstanceKind::RadialGradient { .. } => debug_colors::PINK,
+                            PrimitiveInstanceKind::ConicGradient { .. } => debug_colors::PINK,
                             PrimitiveInstanceKind::Clear { .. } => debug_colors::CYAN,
                             PrimitiveInstanceKind::Backdrop { .. } => debug_colors::MEDIUMAQUAMARINE,
                         };
@@ -2523,9 +2580,11 @@ impl PrimitiveStore {
             PrimitiveInstanceKind::YuvImage { .. } |
             PrimitiveInstanceKind::LinearGradient { .. } |
             PrimitiveInstanceKind::RadialGradient { .. } |
+            PrimitiveInstanceKind::ConicGradient { .. } |
             PrimitiveInstanceKind::PushClipChain |
             PrimitiveInstanceKind::PopClipChain |
             PrimitiveInstanceKind::LineDecoration { .. } |
+            PrimitiveInstanceKind::VectorPath { .. } |
             PrimitiveInstanceKind::Backdrop { .. } => {
                 // These prims don't support opacity collapse
             }
@@ -2623,7 +2682,30 @@ impl PrimitiveStore {
         // For example, scrolling may affect the location of an item in
         // local space, which may force us to render this item on a larger
         // picture target, if being composited.
-        let pic_info = {
+        //
+        // Snapshot pictures that were prepared as a whole ahead of the rest of
+        // the frame only need their surface to be attached to the parent one.
+        let snapshot_render_tasks = match prim_instance.kind {
+            PrimitiveInstanceKind::Picture { pic_index, .. } => {
+                let pic = &self.pictures[pic_index.0];
+                match pic.raster_config {
+                    Some(ref raster_config) if pic.snapshot.is_some() => {
+                        frame_state.surfaces[raster_config.surface_index.0].render_tasks
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+
+        let pic_info = if let Some(render_tasks) = snapshot_render_tasks {
+            frame_state.render_tasks.add_dependency(
+                frame_state.surfaces[pic_context.surface_index.0].render_tasks.unwrap().port,
+                render_tasks.root,
+            );
+
+            None
+        } else {
             match prim_instance.kind {
                 PrimitiveInstanceKind::Picture { pic_index ,.. } => {
                     let pic = &mut self.pictures[pic_index.0];
@@ -2658,12 +2740,14 @@ impl PrimitiveStore {
                 PrimitiveInstanceKind::TextRun { .. } |
                 PrimitiveInstanceKind::Rectangle { .. } |
                 PrimitiveInstanceKind::LineDecoration { .. } |
+                PrimitiveInstanceKind::VectorPath { .. } |
                 PrimitiveInstanceKind::NormalBorder { .. } |
                 PrimitiveInstanceKind::ImageBorder { .. } |
                 PrimitiveInstanceKind::YuvImage { .. } |
                 PrimitiveInstanceKind::Image { .. } |
                 PrimitiveInstanceKind::LinearGradient { .. } |
                 PrimitiveInstanceKind::RadialGradient { .. } |
+                PrimitiveInstanceKind::ConicGradient { .. } |
                 PrimitiveInstanceKind::PushClipChain |
                 PrimitiveInstanceKind::PopClipChain |
                 PrimitiveInstanceKind::Clear { .. } |
@@ -2870,6 +2954,67 @@ impl PrimitiveStore {
                     ));
                 }
             }
+            PrimitiveInstanceKind::VectorPath { data_handle, ref mut cache_handle, .. } => {
+                let prim_data = &mut data_stores.vector_path[*data_handle];
+                let common_data = &mut prim_data.common;
+                let path_data = &mut prim_data.kind;
+
+                // Update the template this instance references, which may refresh the GPU
+                // cache with any shared template data.
+                path_data.update(common_data, frame_state);
+
+                if is_chased {
+                    println!("\tvector path segments={}", path_data.cache_key.segments.len());
+                }
+
+                // Paths are rasterized in local space at the device pixel scale of the
+                // surface, and clamped to a maximum size to bound the stencil target.
+                let local_size = common_data.prim_size;
+                let scale_factor = Scale::new(1.0) * device_pixel_scale;
+                let task_size = (local_size * scale_factor)
+                    .ceil()
+                    .to_i32()
+                    .min(DeviceIntSize::new(MAX_VECTOR_PATH_TASK_SIZE, MAX_VECTOR_PATH_TASK_SIZE));
+
+                if task_size.width <= 0 || task_size.height <= 0 {
+                    *cache_handle = None;
+                } else {
+                    let cache_key = &path_data.cache_key;
+                    let paint_handle = path_data.paint_handle;
+                    let stops_handle = match cache_key.paint {
+                        VectorPathPaint::Color(..) => None,
+                        VectorPathPaint::LinearGradient { .. } => Some(path_data.stops_handle),
+                    };
+
+                    *cache_handle = Some(frame_state.resource_cache.request_render_task(
+                        RenderTaskCacheKey {
+                            size: task_size,
+                            kind: RenderTaskCacheKeyKind::VectorPath(cache_key.clone()),
+                        },
+                        frame_state.gpu_cache,
+                        frame_state.render_tasks,
+                        None,
+                        false,
+                        |render_tasks| {
+                            let stencil_task = RenderTask::new_vector_stencil(
+                                task_size,
+                                cache_key.segments.clone(),
+                                local_size,
+                            );
+                            let stencil_task_id = render_tasks.add(stencil_task);
+
+                            let cover_task = RenderTask::new_vector_cover(
+                                task_size,
+                                stencil_task_id,
+                                cache_key.fill_rule,
+                                paint_handle,
+                                stops_handle,
+                            );
+                            render_tasks.add(cover_task)
+                        }
+                    ));
+                }
+            }
             PrimitiveInstanceKind::TextRun { run_index, data_handle, .. } => {
                 let prim_data = &mut data_stores.text_run[*data_handle];
                 let run = &mut self.text_runs[*run_index];
@@ -2915,7 +3060,7 @@ impl PrimitiveStore {
 
                 // Update the template this instane references, which may refresh the GPU
                 // cache with any shared template data.
-                prim_data.update(frame_state);
+                prim_data.update(frame_state, frame_context.scene_properties);
             }
             PrimitiveInstanceKind::NormalBorder { data_handle, ref mut cache_handles, .. } => {
                 let prim_data = &mut data_stores.normal_border[*data_handle];
@@ -3012,13 +3157,23 @@ impl PrimitiveStore {
                 // cache with any shared template data.
                 prim_data.kind.update(&mut prim_data.common, frame_state);
             }
-            PrimitiveInstanceKind::Rectangle { data_handle, segment_instance_index, opacity_binding_index, .. } => {
+            PrimitiveInstanceKind::Rectangle { data_handle, segment_instance_index, opacity_binding_index, color_binding_index, .. } => {
                 let prim_data = &mut data_stores.prim[*data_handle];
                 prim_data.common.may_need_repetition = false;
 
+                // The resolved color is written to the GPU cache, so it needs
+                // to be refreshed every frame while the color is animated.
+                if *color_binding_index != ColorBindingIndex::INVALID {
+                    frame_state.gpu_cache.invalidate(&prim_data.common.gpu_cache_handle);
+                    if *segment_instance_index != SegmentInstanceIndex::UNUSED {
+                        let segment_instance = &scratch.segment_instances[*segment_instance_index];
+                        frame_state.gpu_cache.invalidate(&segment_instance.gpu_cache_handle);
+                    }
+                }
+
                 // Update the template this instane references, which may refresh the GPU
                 // cache with any shared template data.
-                prim_data.update(frame_state);
+                prim_data.update(frame_state, frame_context.scene_properties);
 
                 update_opacity_binding(
                     &mut self.opacity_bindings,
@@ -3034,6 +3189,7 @@ impl PrimitiveStore {
                     |request| {
                         prim_data.kind.write_prim_gpu_blocks(
                             request,
+                            frame_context.scene_properties,
                         );
                     }
                 );
@@ -3074,6 +3230,14 @@ impl PrimitiveStore {
                 // be done after the culling.
                 frame_state.resource_cache.set_image_active(image_data.key);
 
+                // Snapshots rendered this frame have to be ready before the
+                // surface showing them is drawn.
+                if let Some(task_id) = frame_state.resource_cache.get_snapshot_task(image_data.key) {
+                    if let Some(render_tasks) = frame_state.surfaces[pic_context.surface_index.0].render_tasks {
+                        frame_state.render_tasks.add_dependency(render_tasks.port, task_id);
+                    }
+                }
+
                 if image_data.stretch_size.width >= common_data.prim_size.width &&
                     image_data.stretch_size.height >= common_data.prim_size.height {
 
@@ -3143,9 +3307,11 @@ impl PrimitiveStore {
                     // for the slow path.
                     if prim_data.reverse_stops {
                         for (src, dest) in prim_data.stops.iter().rev().zip(stops.iter_mut()) {
+                            // Gradients with color hints don't use the fast path.
                             let stop = GradientStop {
                                 offset: 1.0 - src.offset,
                                 color: src.color,
+                                midpoint: None,
                             };
                             *dest = stop.into();
                         }
@@ -3303,6 +3469,70 @@ impl PrimitiveStore {
                 // TODO(gw): Consider whether it's worth doing segment building
                 //           for gradient primitives.
             }
+            PrimitiveInstanceKind::ConicGradient { data_handle, ref mut visible_tiles_range, .. } => {
+                let prim_data = &mut data_stores.conic_grad[*data_handle];
+
+                if prim_data.stretch_size.width >= prim_data.common.prim_size.width &&
+                    prim_data.stretch_size.height >= prim_data.common.prim_size.height {
+
+                    // We are performing the decomposition on the CPU here, no need to
+                    // have it in the shader.
+                    prim_data.common.may_need_repetition = false;
+                }
+
+                // Update the template this instane references, which may refresh the GPU
+                // cache with any shared template data.
+                prim_data.update(frame_state);
+
+                if prim_data.tile_spacing != LayoutSize::zero() {
+                    let prim_info = &scratch.prim_info[prim_instance.visibility_info.0 as usize];
+                    let prim_rect = LayoutRect::new(
+                        prim_instance.prim_origin,
+                        prim_data.common.prim_size,
+                    );
+
+                    let map_local_to_world = SpaceMapper::new_with_target(
+                        ROOT_SPATIAL_NODE_INDEX,
+                        prim_spatial_node_index,
+                        frame_context.global_screen_world_rect,
+                        frame_context.clip_scroll_tree,
+                    );
+
+                    prim_data.common.may_need_repetition = false;
+
+                    *visible_tiles_range = decompose_repeated_primitive(
+                        &prim_info.combined_local_clip_rect,
+                        &prim_rect,
+                        prim_info.clipped_world_rect,
+                        &prim_data.stretch_size,
+                        &prim_data.tile_spacing,
+                        frame_state,
+                        &mut scratch.gradient_tiles,
+                        &map_local_to_world,
+                        &mut |_, mut request| {
+                            request.push([
+                                prim_data.center.x,
+                                prim_data.center.y,
+                                prim_data.params.start_offset,
+                                prim_data.params.end_offset,
+                            ]);
+                            request.push([
+                                prim_data.params.angle,
+                                pack_as_float(prim_data.extend_mode as u32),
+                                prim_data.stretch_size.width,
+                                prim_data.stretch_size.height,
+                            ]);
+                        },
+                    );
+
+                    if visible_tiles_range.is_empty() {
+                        prim_instance.visibility_info = PrimitiveVisibilityIndex::INVALID;
+                    }
+                }
+
+                // TODO(gw): Consider whether it's worth doing segment building
+                //           for gradient primitives.
+            }
             PrimitiveInstanceKind::Picture { pic_index, segment_instance_index, data_handle, .. } => {
                 let pic = &mut self.pictures[pic_index.0];
                 let prim_info = &scratch.prim_info[prim_instance.visibility_info.0 as usize];
@@ -3573,8 +3803,10 @@ impl<'a> GpuDataRequest<'a> {
 
                     continue;
                 }
-                ClipItemKind::Image { .. } => {
-                    // If we encounter an image mask, bail out from segment building.
+                ClipItemKind::Image { .. } |
+                ClipItemKind::Path { .. } |
+                ClipItemKind::Text { .. } => {
+                    // If we encounter an image mask, a path or text, bail out from segment building.
                     // It's not possible to know which parts of the primitive are affected
                     // by the mask (without inspecting the pixels). We could do something
                     // better here in the future if it ever shows up as a performance issue
@@ -3694,9 +3926,11 @@ impl PrimitiveInstance {
             PrimitiveInstanceKind::Clear { .. } |
             PrimitiveInstanceKind::LinearGradient { .. } |
             PrimitiveInstanceKind::RadialGradient { .. } |
+            PrimitiveInstanceKind::ConicGradient { .. } |
             PrimitiveInstanceKind::PushClipChain |
             PrimitiveInstanceKind::PopClipChain |
             PrimitiveInstanceKind::LineDecoration { .. } |
+            PrimitiveInstanceKind::VectorPath { .. } |
             PrimitiveInstanceKind::Backdrop { .. } => {
                 // These primitives don't support / need segments.
                 return;
@@ -3765,6 +3999,7 @@ impl PrimitiveInstance {
             PrimitiveInstanceKind::PushClipChain |
             PrimitiveInstanceKind::PopClipChain |
             PrimitiveInstanceKind::LineDecoration { .. } |
+            PrimitiveInstanceKind::VectorPath { .. } |
             PrimitiveInstanceKind::Backdrop { .. } => {
                 return false;
             }
@@ -3839,6 +4074,17 @@ impl PrimitiveInstance {
                     return false;
                 }
 
+                prim_data.brush_segments.as_slice()
+            }
+            PrimitiveInstanceKind::ConicGradient { data_handle, .. } => {
+                let prim_data = &data_stores.conic_grad[data_handle];
+
+                // TODO: This is quite messy - once we remove legacy primitives we
+                //       can change this to be a tuple match on (instance, template)
+                if prim_data.brush_segments.is_empty() {
+                    return false;
+                }
+
                 prim_data.brush_segments.as_slice()
             }
         };
@@ -4196,8 +4442,8 @@ fn test_struct_sizes() {
     //     be done with care, and after checking if talos performance regresses badly.
     assert_eq!(mem::size_of::<PrimitiveInstance>(), 88, "PrimitiveInstance size changed");
     assert_eq!(mem::size_of::<PrimitiveInstanceKind>(), 40, "PrimitiveInstanceKind size changed");
-    assert_eq!(mem::size_of::<PrimitiveTemplate>(), 40, "PrimitiveTemplate size changed");
-    assert_eq!(mem::size_of::<PrimitiveTemplateKind>(), 20, "PrimitiveTemplateKind size changed");
-    assert_eq!(mem::size_of::<PrimitiveKey>(), 20, "PrimitiveKey size changed");
-    assert_eq!(mem::size_of::<PrimitiveKeyKind>(), 5, "PrimitiveKeyKind size changed");
+    assert_eq!(mem::size_of::<PrimitiveTemplate>(), 48, "PrimitiveTemplate size changed");
+    assert_eq!(mem::size_of::<PrimitiveTemplateKind>(), 28, "PrimitiveTemplateKind size changed");
+    assert_eq!(mem::size_of::<PrimitiveKey>(), 28, "PrimitiveKey size changed");
+    assert_eq!(mem::size_of::<PrimitiveKeyKind>(), 16, "PrimitiveKeyKind size changed");
 }
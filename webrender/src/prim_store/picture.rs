@@ -5,18 +5,21 @@
 use api::{
     ColorU, MixBlendMode, FilterPrimitiveInput, FilterPrimitiveKind, ColorSpace,
     PropertyBinding, PropertyBindingId, CompositeOperator, PrimitiveFlags,
+    MorphologyOperator, ConvolveEdgeMode, ConvolveMatrixPrimitive,
+    TurbulencePrimitive, TurbulenceType, ColorChannel, LightSource, ImageKey,
 };
-use api::units::{Au, LayoutSize, LayoutVector2D};
+use api::units::{Au, LayoutPoint3D, LayoutSize, LayoutVector2D};
 use crate::scene_building::IsVisible;
 use crate::filterdata::SFilterData;
 use crate::intern::ItemUid;
 use crate::intern::{Internable, InternDebug, Handle as InternHandle};
 use crate::internal_types::{LayoutPrimitiveInfo, Filter};
 use crate::picture::PictureCompositeMode;
+use std::hash;
 use crate::prim_store::{
     PrimKey, PrimKeyCommonData, PrimTemplate, PrimTemplateCommonData,
     PrimitiveInstanceKind, PrimitiveSceneData, PrimitiveStore, VectorKey,
-    InternablePrimitive,
+    InternablePrimitive, RectangleKey,
 };
 
 #[cfg_attr(feature = "capture", derive(Serialize))]
@@ -54,6 +57,119 @@ impl From<CompositeOperator> for CompositeOperatorKey {
     }
 }
 
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+#[derive(Debug, Clone, MallocSizeOf, PartialEq, Hash, Eq)]
+pub struct ConvolveMatrixKey {
+    order: [u32; 2],
+    kernel: [Au; 25],
+    divisor: Au,
+    bias: Au,
+    target: [u32; 2],
+    edge_mode: ConvolveEdgeMode,
+    preserve_alpha: bool,
+}
+
+impl From<ConvolveMatrixPrimitive> for ConvolveMatrixKey {
+    fn from(info: ConvolveMatrixPrimitive) -> Self {
+        let mut kernel = [Au(0); 25];
+        for (value, result) in info.kernel.iter().zip(kernel.iter_mut()) {
+            *result = Au::from_f32_px(*value);
+        }
+
+        ConvolveMatrixKey {
+            order: info.order,
+            kernel,
+            divisor: Au::from_f32_px(info.divisor),
+            bias: Au::from_f32_px(info.bias),
+            target: info.target,
+            edge_mode: info.edge_mode,
+            preserve_alpha: info.preserve_alpha,
+        }
+    }
+}
+
+/// A hashable turbulence primitive. The base frequencies are typically much
+/// smaller than a pixel, so they are kept as floats rather than quantized.
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+#[derive(Debug, Clone, MallocSizeOf, PartialEq)]
+pub struct TurbulenceKey {
+    base_frequency: (f32, f32),
+    num_octaves: u32,
+    seed: i32,
+    stitch_tiles: bool,
+    noise_type: TurbulenceType,
+}
+
+impl Eq for TurbulenceKey {}
+
+impl hash::Hash for TurbulenceKey {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.base_frequency.0.to_bits().hash(state);
+        self.base_frequency.1.to_bits().hash(state);
+        self.num_octaves.hash(state);
+        self.seed.hash(state);
+        self.stitch_tiles.hash(state);
+        self.noise_type.hash(state);
+    }
+}
+
+impl From<TurbulencePrimitive> for TurbulenceKey {
+    fn from(info: TurbulencePrimitive) -> Self {
+        TurbulenceKey {
+            base_frequency: (info.base_frequency_x, info.base_frequency_y),
+            num_octaves: info.num_octaves,
+            seed: info.seed,
+            stitch_tiles: info.stitch_tiles,
+            noise_type: info.noise_type,
+        }
+    }
+}
+
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+#[derive(Debug, Clone, MallocSizeOf, PartialEq, Hash, Eq)]
+pub enum LightSourceKey {
+    Distant {
+        azimuth: Au,
+        elevation: Au,
+    },
+    Point {
+        position: (Au, Au, Au),
+    },
+    Spot {
+        position: (Au, Au, Au),
+        points_at: (Au, Au, Au),
+        specular_exponent: Au,
+        limiting_cone_angle: Au,
+    },
+}
+
+impl From<LightSource> for LightSourceKey {
+    fn from(light: LightSource) -> Self {
+        fn point_key(point: LayoutPoint3D) -> (Au, Au, Au) {
+            (Au::from_f32_px(point.x), Au::from_f32_px(point.y), Au::from_f32_px(point.z))
+        }
+
+        match light {
+            LightSource::Distant { azimuth, elevation } => LightSourceKey::Distant {
+                azimuth: Au::from_f32_px(azimuth),
+                elevation: Au::from_f32_px(elevation),
+            },
+            LightSource::Point { position } => LightSourceKey::Point {
+                position: point_key(position),
+            },
+            LightSource::Spot { position, points_at, specular_exponent, limiting_cone_angle } => LightSourceKey::Spot {
+                position: point_key(position),
+                points_at: point_key(points_at),
+                specular_exponent: Au::from_f32_px(specular_exponent),
+                limiting_cone_angle: Au::from_f32_px(limiting_cone_angle),
+            },
+        }
+    }
+}
+
 #[cfg_attr(feature = "capture", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
 #[derive(Debug, Clone, MallocSizeOf, PartialEq, Hash, Eq)]
@@ -61,13 +177,22 @@ pub enum FilterPrimitiveKey {
     Identity(ColorSpace, FilterPrimitiveInput),
     Flood(ColorSpace, ColorU),
     Blend(ColorSpace, MixBlendMode, FilterPrimitiveInput, FilterPrimitiveInput),
-    Blur(ColorSpace, Au, FilterPrimitiveInput),
+    Blur(ColorSpace, Au, Au, FilterPrimitiveInput),
     Opacity(ColorSpace, Au, FilterPrimitiveInput),
     ColorMatrix(ColorSpace, [Au; 20], FilterPrimitiveInput),
     DropShadow(ColorSpace, (VectorKey, Au, ColorU), FilterPrimitiveInput),
     ComponentTransfer(ColorSpace, FilterPrimitiveInput, Vec<SFilterData>),
     Offset(ColorSpace, FilterPrimitiveInput, VectorKey),
     Composite(ColorSpace, FilterPrimitiveInput, FilterPrimitiveInput, CompositeOperatorKey),
+    Morphology(ColorSpace, FilterPrimitiveInput, (Au, Au), MorphologyOperator),
+    ConvolveMatrix(ColorSpace, FilterPrimitiveInput, ConvolveMatrixKey),
+    Turbulence(ColorSpace, TurbulenceKey),
+    DisplacementMap(ColorSpace, FilterPrimitiveInput, FilterPrimitiveInput, Au, (ColorChannel, ColorChannel)),
+    DiffuseLighting(ColorSpace, FilterPrimitiveInput, (Au, Au), ColorU, LightSourceKey),
+    SpecularLighting(ColorSpace, FilterPrimitiveInput, (Au, Au, Au), ColorU, LightSourceKey),
+    Image(ColorSpace, ImageKey, RectangleKey),
+    Tile(ColorSpace, FilterPrimitiveInput, RectangleKey),
+    Merge(ColorSpace, Vec<FilterPrimitiveInput>),
 }
 
 /// Represents a hashable description of how a picture primitive
@@ -80,7 +205,7 @@ pub enum PictureCompositeKey {
     Identity,
 
     // FilterOp
-    Blur(Au),
+    Blur(Au, Au),
     Brightness(Au),
     Contrast(Au),
     Grayscale(Au),
@@ -96,6 +221,7 @@ pub enum PictureCompositeKey {
     LinearToSrgb,
     ComponentTransfer(ItemUid),
     Flood(ColorU),
+    ColorLut3D(ImageKey, u32),
     SvgFilter(Vec<FilterPrimitiveKey>),
 
     // MixBlendMode
@@ -141,7 +267,8 @@ impl From<Option<PictureCompositeMode>> for PictureCompositeKey {
             }
             Some(PictureCompositeMode::Filter(op)) => {
                 match op {
-                    Filter::Blur(value) => PictureCompositeKey::Blur(Au::from_f32_px(value)),
+                    Filter::Blur(width, height) =>
+                        PictureCompositeKey::Blur(Au::from_f32_px(width), Au::from_f32_px(height)),
                     Filter::Brightness(value) => PictureCompositeKey::Brightness(Au::from_f32_px(value)),
                     Filter::Contrast(value) => PictureCompositeKey::Contrast(Au::from_f32_px(value)),
                     Filter::Grayscale(value) => PictureCompositeKey::Grayscale(Au::from_f32_px(value)),
@@ -178,6 +305,7 @@ impl From<Option<PictureCompositeMode>> for PictureCompositeKey {
                     }
                     Filter::ComponentTransfer => unreachable!(),
                     Filter::Flood(color) => PictureCompositeKey::Flood(color.into()),
+                    Filter::ColorLut3D { lut, size } => PictureCompositeKey::ColorLut3D(lut, size),
                 }
             }
             Some(PictureCompositeMode::ComponentTransferFilter(handle)) => {
@@ -189,7 +317,13 @@ impl From<Option<PictureCompositeMode>> for PictureCompositeKey {
                         FilterPrimitiveKind::Identity(identity) => FilterPrimitiveKey::Identity(primitive.color_space, identity.input),
                         FilterPrimitiveKind::Blend(blend) => FilterPrimitiveKey::Blend(primitive.color_space, blend.mode, blend.input1, blend.input2),
                         FilterPrimitiveKind::Flood(flood) => FilterPrimitiveKey::Flood(primitive.color_space, flood.color.into()),
-                        FilterPrimitiveKind::Blur(blur) => FilterPrimitiveKey::Blur(primitive.color_space, Au::from_f32_px(blur.radius), blur.input),
+                        FilterPrimitiveKind::Blur(blur) =>
+                            FilterPrimitiveKey::Blur(
+                                primitive.color_space,
+                                Au::from_f32_px(blur.width),
+                                Au::from_f32_px(blur.height),
+                                blur.input,
+                            ),
                         FilterPrimitiveKind::Opacity(opacity) =>
                             FilterPrimitiveKey::Opacity(primitive.color_space, Au::from_f32_px(opacity.opacity), opacity.input),
                         FilterPrimitiveKind::ColorMatrix(color_matrix) => {
@@ -216,6 +350,51 @@ impl From<Option<PictureCompositeMode>> for PictureCompositeKey {
                             FilterPrimitiveKey::Offset(primitive.color_space, info.input, info.offset.into()),
                         FilterPrimitiveKind::Composite(info) =>
                             FilterPrimitiveKey::Composite(primitive.color_space, info.input1, info.input2, info.operator.into()),
+                        FilterPrimitiveKind::Morphology(info) =>
+                            FilterPrimitiveKey::Morphology(
+                                primitive.color_space,
+                                info.input,
+                                (Au::from_f32_px(info.radius_x), Au::from_f32_px(info.radius_y)),
+                                info.operator,
+                            ),
+                        FilterPrimitiveKind::ConvolveMatrix(info) =>
+                            FilterPrimitiveKey::ConvolveMatrix(primitive.color_space, info.input, info.into()),
+                        FilterPrimitiveKind::Turbulence(info) =>
+                            FilterPrimitiveKey::Turbulence(primitive.color_space, info.into()),
+                        FilterPrimitiveKind::DisplacementMap(info) =>
+                            FilterPrimitiveKey::DisplacementMap(
+                                primitive.color_space,
+                                info.input1,
+                                info.input2,
+                                Au::from_f32_px(info.scale),
+                                (info.x_channel_selector, info.y_channel_selector),
+                            ),
+                        FilterPrimitiveKind::DiffuseLighting(info) =>
+                            FilterPrimitiveKey::DiffuseLighting(
+                                primitive.color_space,
+                                info.input,
+                                (Au::from_f32_px(info.surface_scale), Au::from_f32_px(info.diffuse_constant)),
+                                info.lighting_color.into(),
+                                info.light.into(),
+                            ),
+                        FilterPrimitiveKind::SpecularLighting(info) =>
+                            FilterPrimitiveKey::SpecularLighting(
+                                primitive.color_space,
+                                info.input,
+                                (
+                                    Au::from_f32_px(info.surface_scale),
+                                    Au::from_f32_px(info.specular_constant),
+                                    Au::from_f32_px(info.specular_exponent),
+                                ),
+                                info.lighting_color.into(),
+                                info.light.into(),
+                            ),
+                        FilterPrimitiveKind::Image(info) =>
+                            FilterPrimitiveKey::Image(primitive.color_space, info.image_key, info.rect.into()),
+                        FilterPrimitiveKind::Tile(info) =>
+                            FilterPrimitiveKey::Tile(primitive.color_space, info.input, info.source_rect.into()),
+                        FilterPrimitiveKind::Merge(info) =>
+                            FilterPrimitiveKey::Merge(primitive.color_space, info.inputs().to_vec()),
                     }
                 }).collect())
             }
@@ -3,16 +3,18 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use api::{ColorF, GlyphInstance, RasterSpace, Shadow};
-use api::units::{DevicePixelScale, LayoutToWorldTransform, LayoutVector2D};
+use api::units::{DeviceIntSize, DevicePixelScale, DevicePoint, DeviceRect, DeviceSize};
+use api::units::{LayoutSize, LayoutToDeviceScale, LayoutToWorldTransform, LayoutVector2D};
 use crate::scene_building::{CreateShadow, IsVisible};
 use crate::frame_builder::FrameBuildingState;
-use crate::glyph_rasterizer::{FontInstance, FontTransform, GlyphKey, FONT_SIZE_LIMIT};
+use crate::glyph_rasterizer::{FontInstance, FontTransform, GlyphKey, SubpixelDirection, FONT_SIZE_LIMIT};
 use crate::gpu_cache::GpuCache;
 use crate::intern;
 use crate::internal_types::LayoutPrimitiveInfo;
 use crate::picture::{SubpixelMode, SurfaceInfo};
 use crate::prim_store::{PrimitiveOpacity, PrimitiveSceneData,  PrimitiveScratchBuffer};
 use crate::prim_store::{PrimitiveStore, PrimKeyCommonData, PrimTemplateCommonData};
+use crate::render_task_cache::RenderTaskCacheEntryHandle;
 use crate::render_task_graph::RenderTaskGraph;
 use crate::renderer::{MAX_VERTEX_TEXTURE_WIDTH};
 use crate::resource_cache::{ResourceCache};
@@ -337,6 +339,111 @@ impl TextRunPrimitive {
     }
 }
 
+/// Maximum width and height of the render task the glyphs of a text
+/// clip are rasterized into.
+pub const MAX_TEXT_CLIP_TASK_SIZE: i32 = 2048;
+
+/// The key of the render task cache entry holding the coverage of a text clip.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct TextClipCacheKey {
+    pub font: FontInstance,
+    pub glyphs: Vec<GlyphInstance>,
+}
+
+/// The glyphs of a text clip, rasterized through the glyph cache into a
+/// cached render task, which is then applied like a path clip.
+#[derive(Debug, MallocSizeOf)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct TextClipSource {
+    /// The specified font, in white, with glyphs relative to the clip rect.
+    pub font: FontInstance,
+    pub glyphs: Vec<GlyphInstance>,
+    /// The font the glyphs are rasterized with, at the scale of the task.
+    pub used_font: FontInstance,
+    /// The rasterized glyphs, and where they are placed in the task.
+    pub glyph_keys: Vec<GlyphKey>,
+    pub glyph_rects: Vec<DeviceRect>,
+    pub cache_handle: Option<RenderTaskCacheEntryHandle>,
+}
+
+impl TextClipSource {
+    pub fn new(
+        font: FontInstance,
+        glyphs: Vec<GlyphInstance>,
+    ) -> Self {
+        TextClipSource {
+            used_font: font.clone(),
+            font,
+            glyphs,
+            glyph_keys: Vec::new(),
+            glyph_rects: Vec::new(),
+            cache_handle: None,
+        }
+    }
+
+    /// Picks the scale the glyphs are rasterized at for a clip rect of the
+    /// given size, and places the glyphs if that scale changed. Returns the
+    /// size of the render task, or None if nothing needs to be rasterized.
+    pub fn update(
+        &mut self,
+        rect_size: LayoutSize,
+        device_pixel_scale: DevicePixelScale,
+        resource_cache: &mut ResourceCache,
+    ) -> Option<DeviceIntSize> {
+        // Scale the whole clip down uniformly if it doesn't fit in the task,
+        // so the glyphs keep their proportions.
+        let max_size = MAX_TEXT_CLIP_TASK_SIZE as f32;
+        let scale = device_pixel_scale.0
+            .min(max_size / rect_size.width)
+            .min(max_size / rect_size.height);
+        let task_size = (rect_size * LayoutToDeviceScale::new(scale)).ceil().to_i32();
+
+        if task_size.width <= 0 || task_size.height <= 0 || self.glyphs.is_empty() {
+            return None;
+        }
+
+        let device_font_size = self.font.size.scale_by(scale);
+        if self.used_font.size == device_font_size && !self.glyph_keys.is_empty() {
+            return Some(task_size);
+        }
+
+        self.used_font = FontInstance {
+            size: device_font_size,
+            ..self.font.clone()
+        };
+        self.glyph_keys.clear();
+        self.glyph_rects.clear();
+
+        // Glyphs are snapped to device pixels, so the dimensions of the
+        // rasterized glyphs are known up front.
+        for glyph in &self.glyphs {
+            let dimensions = match resource_cache.get_scaled_glyph_dimensions(&self.used_font, glyph.index) {
+                Some(dimensions) => dimensions,
+                None => continue,
+            };
+            let origin = (glyph.point * scale).round();
+
+            self.glyph_keys.push(GlyphKey::new(glyph.index, DevicePoint::zero(), SubpixelDirection::None));
+            self.glyph_rects.push(DeviceRect::new(
+                DevicePoint::new(
+                    origin.x + dimensions.left as f32,
+                    origin.y - dimensions.top as f32,
+                ),
+                DeviceSize::new(dimensions.width as f32, dimensions.height as f32),
+            ));
+        }
+
+        if self.glyph_keys.is_empty() {
+            return None;
+        }
+
+        Some(task_size)
+    }
+}
+
 /// These are linux only because FontInstancePlatformOptions varies in size by platform.
 #[test]
 #[cfg(target_os = "linux")]
@@ -0,0 +1,596 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use api::{
+    ColorF, ColorU, ExtendMode, FillRule, GradientColorSpace, GradientStop,
+    PathCommand, PremultipliedColorF,
+};
+use api::units::{LayoutPoint, LayoutSize, LayoutVector2D};
+use crate::render_task_cache::RenderTaskCacheEntryHandle;
+use crate::scene_building::IsVisible;
+use crate::frame_builder::FrameBuildingState;
+use crate::gpu_cache::{GpuCacheHandle, GpuDataRequest};
+use crate::intern;
+use crate::internal_types::LayoutPrimitiveInfo;
+use crate::prim_store::{
+    PrimKey, PrimKeyCommonData, PrimTemplate, PrimTemplateCommonData,
+    InternablePrimitive, PrimitiveSceneData, PrimitiveStore, PointKey, SizeKey,
+};
+use crate::prim_store::PrimitiveInstanceKind;
+use crate::prim_store::gradient::{GradientGpuBlockBuilder, GradientStopKey, expand_gradient_stops};
+use crate::util::pack_as_float;
+
+/// The number of quadratic segments a cubic segment is approximated with.
+const QUADS_PER_CUBIC: usize = 4;
+
+/// Maximum width and height of the render task a vector path is
+/// rasterized into.
+pub const MAX_VECTOR_PATH_TASK_SIZE: i32 = 2048;
+
+// Has to match PAINT_KIND_* in pf_vector_cover.
+const PAINT_KIND_COLOR: u32 = 0;
+const PAINT_KIND_LINEAR_GRADIENT: u32 = 1;
+
+/// A quadratic segment of a flattened path outline, relative to the
+/// primitive origin. Segments are monotonic in x, which the stencil
+/// shader relies on to find the single point where a vertical ray
+/// crosses the curve.
+#[derive(Clone, Debug, Hash, MallocSizeOf, PartialEq, Eq)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct PathSegment {
+    pub from: PointKey,
+    pub ctrl: PointKey,
+    pub to: PointKey,
+}
+
+/// Converts path commands into monotonic quadratic segments, relative to
+/// `origin`. Subpaths that are left open are closed, as required when
+/// filling them. Vertical segments are dropped since they don't cover
+/// any area.
+pub fn flatten_path<I>(commands: I, origin: LayoutPoint) -> Vec<PathSegment>
+where
+    I: IntoIterator<Item = PathCommand>,
+{
+    let mut flattener = PathFlattener {
+        segments: Vec::new(),
+        subpath_start: LayoutPoint::zero(),
+        current: LayoutPoint::zero(),
+    };
+    let offset = origin.to_vector();
+
+    for command in commands {
+        match command {
+            PathCommand::MoveTo(to) => {
+                flattener.close();
+                flattener.subpath_start = to - offset;
+                flattener.current = to - offset;
+            }
+            PathCommand::LineTo(to) => {
+                flattener.line_to(to - offset);
+            }
+            PathCommand::QuadTo(ctrl, to) => {
+                flattener.quad_to(ctrl - offset, to - offset);
+            }
+            PathCommand::CubicTo(ctrl1, ctrl2, to) => {
+                flattener.cubic_to(ctrl1 - offset, ctrl2 - offset, to - offset);
+            }
+            PathCommand::Close => {
+                flattener.close();
+            }
+        }
+    }
+    flattener.close();
+
+    flattener.segments
+}
+
+struct PathFlattener {
+    segments: Vec<PathSegment>,
+    subpath_start: LayoutPoint,
+    current: LayoutPoint,
+}
+
+impl PathFlattener {
+    fn close(&mut self) {
+        if self.current != self.subpath_start {
+            let start = self.subpath_start;
+            self.line_to(start);
+        }
+    }
+
+    fn line_to(&mut self, to: LayoutPoint) {
+        let ctrl = self.current.lerp(to, 0.5);
+        self.quad_to(ctrl, to);
+    }
+
+    fn quad_to(&mut self, ctrl: LayoutPoint, to: LayoutPoint) {
+        let from = self.current;
+
+        // Split the curve at its horizontal extremum, if it has one.
+        let denom = from.x - 2.0 * ctrl.x + to.x;
+        let t = if denom != 0.0 { (from.x - ctrl.x) / denom } else { -1.0 };
+        if t > 0.0 && t < 1.0 {
+            let ctrl0 = from.lerp(ctrl, t);
+            let ctrl1 = ctrl.lerp(to, t);
+            let mid = ctrl0.lerp(ctrl1, t);
+            self.push_segment(from, ctrl0, mid);
+            self.push_segment(mid, ctrl1, to);
+        } else {
+            self.push_segment(from, ctrl, to);
+        }
+
+        self.current = to;
+    }
+
+    fn cubic_to(&mut self, ctrl1: LayoutPoint, ctrl2: LayoutPoint, to: LayoutPoint) {
+        let points = [
+            self.current.to_vector(),
+            ctrl1.to_vector(),
+            ctrl2.to_vector(),
+            to.to_vector(),
+        ];
+
+        for i in 0 .. QUADS_PER_CUBIC {
+            let t0 = i as f32 / QUADS_PER_CUBIC as f32;
+            let t1 = (i + 1) as f32 / QUADS_PER_CUBIC as f32;
+            let step = (t1 - t0) / 3.0;
+
+            // Work out the control points of the piece of the cubic between
+            // t0 and t1, and approximate it with a quadratic curve whose
+            // control point is the average of the ones extrapolated from
+            // each end.
+            let p0 = cubic_point(&points, t0);
+            let p3 = cubic_point(&points, t1);
+            let p1 = p0 + cubic_derivative(&points, t0) * step;
+            let p2 = p3 - cubic_derivative(&points, t1) * step;
+            let ctrl = ((p1 + p2) * 3.0 - p0 - p3) * 0.25;
+
+            let end = if i + 1 == QUADS_PER_CUBIC { to } else { p3.to_point() };
+            self.quad_to(ctrl.to_point(), end);
+        }
+    }
+
+    fn push_segment(&mut self, from: LayoutPoint, ctrl: LayoutPoint, to: LayoutPoint) {
+        if from.x == to.x {
+            return;
+        }
+
+        self.segments.push(PathSegment {
+            from: from.into(),
+            ctrl: ctrl.into(),
+            to: to.into(),
+        });
+    }
+}
+
+fn cubic_point(p: &[LayoutVector2D; 4], t: f32) -> LayoutVector2D {
+    let mt = 1.0 - t;
+    p[0] * (mt * mt * mt) +
+        p[1] * (3.0 * mt * mt * t) +
+        p[2] * (3.0 * mt * t * t) +
+        p[3] * (t * t * t)
+}
+
+fn cubic_derivative(p: &[LayoutVector2D; 4], t: f32) -> LayoutVector2D {
+    let mt = 1.0 - t;
+    (p[1] - p[0]) * (3.0 * mt * mt) +
+        (p[2] - p[1]) * (6.0 * mt * t) +
+        (p[3] - p[2]) * (3.0 * t * t)
+}
+
+/// How the inside of a vector path is filled.
+#[derive(Clone, Debug, Hash, MallocSizeOf, PartialEq, Eq)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub enum VectorPathPaint {
+    Color(ColorU),
+    LinearGradient {
+        start_point: PointKey,
+        end_point: PointKey,
+        extend_mode: ExtendMode,
+        stops: Vec<GradientStopKey>,
+    },
+}
+
+#[derive(Clone, Debug, Hash, MallocSizeOf, PartialEq, Eq)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct VectorPathCacheKey {
+    pub segments: Vec<PathSegment>,
+    pub fill_rule: FillRule,
+    pub paint: VectorPathPaint,
+    pub size: SizeKey,
+}
+
+/// A vector path primitive, interned by its cache key.
+#[derive(Clone, Debug, Hash, MallocSizeOf, PartialEq, Eq)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct VectorPath {
+    pub cache_key: VectorPathCacheKey,
+}
+
+pub type VectorPathKey = PrimKey<VectorPath>;
+
+impl VectorPathKey {
+    pub fn new(
+        info: &LayoutPrimitiveInfo,
+        path: VectorPath,
+    ) -> Self {
+        VectorPathKey {
+            common: PrimKeyCommonData::with_info(
+                info,
+            ),
+            kind: path,
+        }
+    }
+}
+
+impl intern::InternDebug for VectorPathKey {}
+
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+#[derive(MallocSizeOf)]
+pub struct VectorPathData {
+    pub cache_key: VectorPathCacheKey,
+    pub stops: Vec<GradientStop>,
+    /// The paint parameters read by the cover shader.
+    pub paint_handle: GpuCacheHandle,
+    /// The gradient ramp, if the path is filled with a gradient.
+    pub stops_handle: GpuCacheHandle,
+}
+
+impl VectorPathData {
+    /// Update the GPU cache for a given primitive template. This may be called multiple
+    /// times per frame, by each primitive reference that refers to this interned
+    /// template. The initial request call to the GPU cache ensures that work is only
+    /// done if the cache entry is invalid (due to first use or eviction).
+    pub fn update(
+        &mut self,
+        common: &mut PrimTemplateCommonData,
+        frame_state: &mut FrameBuildingState,
+    ) {
+        if let Some(ref mut request) = frame_state.gpu_cache.request(&mut common.gpu_cache_handle) {
+            self.write_prim_gpu_blocks(request, common.prim_size);
+        }
+
+        if let Some(ref mut request) = frame_state.gpu_cache.request(&mut self.paint_handle) {
+            write_paint_gpu_blocks(&self.cache_key.paint, request, common.prim_size);
+        }
+
+        if let VectorPathPaint::LinearGradient { .. } = self.cache_key.paint {
+            if let Some(mut request) = frame_state.gpu_cache.request(&mut self.stops_handle) {
+                GradientGpuBlockBuilder::build(
+                    false,
+                    &mut request,
+                    &self.stops,
+                );
+            }
+        }
+    }
+
+    fn write_prim_gpu_blocks(
+        &self,
+        request: &mut GpuDataRequest,
+        prim_size: LayoutSize,
+    ) {
+        // The paint is already applied by the cover task, so the
+        // cached image is drawn as is.
+        request.push(PremultipliedColorF::WHITE);
+        request.push(PremultipliedColorF::WHITE);
+        request.push([
+            prim_size.width,
+            prim_size.height,
+            0.0,
+            0.0,
+        ]);
+    }
+}
+
+/// Writes the paint parameters read by the cover shader.
+fn write_paint_gpu_blocks(
+    paint: &VectorPathPaint,
+    request: &mut GpuDataRequest,
+    size: LayoutSize,
+) {
+    match *paint {
+        VectorPathPaint::Color(color) => {
+            request.push(ColorF::from(color).premultiplied());
+            request.push([
+                size.width,
+                size.height,
+                0.0,
+                pack_as_float(PAINT_KIND_COLOR),
+            ]);
+        }
+        VectorPathPaint::LinearGradient { ref start_point, ref end_point, extend_mode, .. } => {
+            request.push([
+                start_point.x,
+                start_point.y,
+                end_point.x,
+                end_point.y,
+            ]);
+            request.push([
+                size.width,
+                size.height,
+                pack_as_float(extend_mode as u32),
+                pack_as_float(PAINT_KIND_LINEAR_GRADIENT),
+            ]);
+        }
+    }
+}
+
+pub type VectorPathTemplate = PrimTemplate<VectorPathData>;
+
+impl From<VectorPathKey> for VectorPathTemplate {
+    fn from(path: VectorPathKey) -> Self {
+        let common = PrimTemplateCommonData::with_key_common(path.common);
+
+        let stops = match path.kind.cache_key.paint {
+            VectorPathPaint::Color(..) => Vec::new(),
+            VectorPathPaint::LinearGradient { ref stops, .. } => {
+                expand_gradient_stops(stops, GradientColorSpace::Srgb)
+            }
+        };
+
+        VectorPathTemplate {
+            common,
+            kind: VectorPathData {
+                cache_key: path.kind.cache_key,
+                stops,
+                paint_handle: GpuCacheHandle::new(),
+                stops_handle: GpuCacheHandle::new(),
+            }
+        }
+    }
+}
+
+pub type VectorPathDataHandle = intern::Handle<VectorPath>;
+
+impl intern::Internable for VectorPath {
+    type Key = VectorPathKey;
+    type StoreData = VectorPathTemplate;
+    type InternData = PrimitiveSceneData;
+}
+
+impl InternablePrimitive for VectorPath {
+    fn into_key(
+        self,
+        info: &LayoutPrimitiveInfo,
+    ) -> VectorPathKey {
+        VectorPathKey::new(
+            info,
+            self,
+        )
+    }
+
+    fn make_instance_kind(
+        _key: VectorPathKey,
+        data_handle: VectorPathDataHandle,
+        _: &mut PrimitiveStore,
+        _reference_frame_relative_offset: LayoutVector2D,
+    ) -> PrimitiveInstanceKind {
+        PrimitiveInstanceKind::VectorPath {
+            data_handle,
+            cache_handle: None,
+        }
+    }
+}
+
+impl IsVisible for VectorPath {
+    fn is_visible(&self) -> bool {
+        if self.cache_key.segments.is_empty() {
+            return false;
+        }
+
+        match self.cache_key.paint {
+            VectorPathPaint::Color(color) => color.a > 0,
+            VectorPathPaint::LinearGradient { .. } => true,
+        }
+    }
+}
+
+/// A path clip. It is rasterized into the texture cache by the same stencil
+/// and cover tasks as vector path primitives, painted in white so that the
+/// cached image holds the coverage of the path.
+#[derive(Debug, MallocSizeOf)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct PathClipSource {
+    pub cache_key: VectorPathCacheKey,
+    pub paint_handle: GpuCacheHandle,
+    pub cache_handle: Option<RenderTaskCacheEntryHandle>,
+}
+
+impl PathClipSource {
+    pub fn new(
+        segments: Vec<PathSegment>,
+        fill_rule: FillRule,
+        size: LayoutSize,
+    ) -> Self {
+        PathClipSource {
+            cache_key: VectorPathCacheKey {
+                segments,
+                fill_rule,
+                paint: VectorPathPaint::Color(ColorU::new(255, 255, 255, 255)),
+                size: size.into(),
+            },
+            paint_handle: GpuCacheHandle::new(),
+            cache_handle: None,
+        }
+    }
+
+    pub fn write_paint_gpu_blocks(
+        &self,
+        request: &mut GpuDataRequest,
+        size: LayoutSize,
+    ) {
+        write_paint_gpu_blocks(&self.cache_key.paint, request, size);
+    }
+}
+
+/// Returns true if `point`, relative to the origin the segments were
+/// flattened against, is inside the path according to `fill_rule`.
+pub fn path_contains_point(
+    segments: &[PathSegment],
+    fill_rule: FillRule,
+    point: LayoutPoint,
+) -> bool {
+    // Count the signed crossings of a vertical ray going up from the point.
+    // Each segment is monotonic in x, so it crosses the ray at most once.
+    let mut winding = 0;
+    for segment in segments {
+        let (from, ctrl, to) = (&segment.from, &segment.ctrl, &segment.to);
+        if point.x < from.x.min(to.x) || point.x >= from.x.max(to.x) {
+            continue;
+        }
+
+        let a = from.x - 2.0 * ctrl.x + to.x;
+        let b = 2.0 * (ctrl.x - from.x);
+        let c = from.x - point.x;
+        let t = if a.abs() < 1e-6 {
+            -c / b
+        } else {
+            let discriminant = (b * b - 4.0 * a * c).max(0.0).sqrt();
+            let t0 = (-b + discriminant) / (2.0 * a);
+            if t0 >= 0.0 && t0 <= 1.0 {
+                t0
+            } else {
+                (-b - discriminant) / (2.0 * a)
+            }
+        };
+
+        let mt = 1.0 - t;
+        let y = mt * mt * from.y + 2.0 * mt * t * ctrl.y + t * t * to.y;
+        if y < point.y {
+            winding += if from.x < to.x { 1 } else { -1 };
+        }
+    }
+
+    match fill_rule {
+        FillRule::NonZero => winding != 0,
+        FillRule::EvenOdd => winding % 2 != 0,
+    }
+}
+
+#[cfg(test)]
+fn segments_contain(segments: &[PathSegment], fill_rule: FillRule, x: f32, y: f32) -> bool {
+    path_contains_point(segments, fill_rule, LayoutPoint::new(x, y))
+}
+
+#[cfg(test)]
+fn polygon(points: &[(f32, f32)]) -> Vec<PathCommand> {
+    points.iter().enumerate().map(|(i, &(x, y))| {
+        let point = LayoutPoint::new(x, y);
+        if i == 0 { PathCommand::MoveTo(point) } else { PathCommand::LineTo(point) }
+    }).collect()
+}
+
+#[test]
+fn test_flatten_rect() {
+    let commands = polygon(&[(10.0, 10.0), (30.0, 10.0), (30.0, 20.0), (10.0, 20.0)]);
+    let segments = flatten_path(commands, LayoutPoint::new(10.0, 10.0));
+
+    // The vertical edges, including the implicit closing one, are dropped.
+    assert_eq!(segments.len(), 2);
+    assert_eq!(segments[0].from, PointKey { x: 0.0, y: 0.0 });
+    assert_eq!(segments[0].to, PointKey { x: 20.0, y: 0.0 });
+    assert_eq!(segments[1].from, PointKey { x: 20.0, y: 10.0 });
+    assert_eq!(segments[1].to, PointKey { x: 0.0, y: 10.0 });
+
+    for &fill_rule in &[FillRule::NonZero, FillRule::EvenOdd] {
+        assert!(segments_contain(&segments, fill_rule, 10.0, 5.0));
+        assert!(segments_contain(&segments, fill_rule, 0.0, 0.5));
+        assert!(!segments_contain(&segments, fill_rule, 10.0, 15.0));
+        assert!(!segments_contain(&segments, fill_rule, 25.0, 5.0));
+        assert!(!segments_contain(&segments, fill_rule, -5.0, 5.0));
+    }
+}
+
+#[test]
+fn test_flatten_concave() {
+    // An L shape, with the notch in the bottom right.
+    let commands = polygon(&[
+        (0.0, 0.0), (10.0, 0.0), (10.0, 4.0), (4.0, 4.0), (4.0, 10.0), (0.0, 10.0),
+    ]);
+    let segments = flatten_path(commands, LayoutPoint::zero());
+    assert_eq!(segments.len(), 3);
+
+    for &fill_rule in &[FillRule::NonZero, FillRule::EvenOdd] {
+        assert!(segments_contain(&segments, fill_rule, 2.0, 8.0));
+        assert!(segments_contain(&segments, fill_rule, 8.0, 2.0));
+        assert!(!segments_contain(&segments, fill_rule, 8.0, 8.0));
+    }
+}
+
+#[test]
+fn test_flatten_self_intersecting() {
+    // A five pointed star drawn in a single stroke winds twice around its
+    // center, so the center is only filled with the non-zero rule.
+    let points: Vec<(f32, f32)> = (0 .. 5).map(|i| {
+        let angle = (i as f32 * 144.0 - 90.0).to_radians();
+        (50.0 + 40.0 * angle.cos(), 50.0 + 40.0 * angle.sin())
+    }).collect();
+    let segments = flatten_path(polygon(&points), LayoutPoint::zero());
+
+    assert!(segments_contain(&segments, FillRule::NonZero, 50.0, 50.0));
+    assert!(!segments_contain(&segments, FillRule::EvenOdd, 50.0, 50.0));
+
+    // The tip of a branch is covered once and filled with both rules.
+    assert!(segments_contain(&segments, FillRule::NonZero, 50.0, 15.0));
+    assert!(segments_contain(&segments, FillRule::EvenOdd, 50.0, 15.0));
+
+    assert!(!segments_contain(&segments, FillRule::NonZero, 5.0, 5.0));
+    assert!(!segments_contain(&segments, FillRule::EvenOdd, 5.0, 5.0));
+}
+
+#[test]
+fn test_flatten_quad() {
+    // A curve that turns back on itself in x is split at its extremum.
+    let commands = vec![
+        PathCommand::MoveTo(LayoutPoint::new(0.0, 0.0)),
+        PathCommand::QuadTo(LayoutPoint::new(20.0, 10.0), LayoutPoint::new(0.0, 20.0)),
+        PathCommand::Close,
+    ];
+    let segments = flatten_path(commands, LayoutPoint::zero());
+
+    assert_eq!(segments.len(), 2);
+    assert_eq!(segments[0].to, PointKey { x: 10.0, y: 10.0 });
+    assert_eq!(segments[1].from, PointKey { x: 10.0, y: 10.0 });
+    for segment in &segments {
+        assert!(segment.ctrl.x >= segment.from.x.min(segment.to.x));
+        assert!(segment.ctrl.x <= segment.from.x.max(segment.to.x));
+    }
+
+    assert!(segments_contain(&segments, FillRule::NonZero, 5.0, 10.0));
+    assert!(segments_contain(&segments, FillRule::NonZero, 9.0, 10.0));
+    assert!(!segments_contain(&segments, FillRule::NonZero, 11.0, 10.0));
+    assert!(!segments_contain(&segments, FillRule::NonZero, 5.0, 1.0));
+}
+
+#[test]
+fn test_flatten_cubic() {
+    let commands = vec![
+        PathCommand::MoveTo(LayoutPoint::new(0.0, 0.0)),
+        PathCommand::CubicTo(
+            LayoutPoint::new(0.0, 40.0),
+            LayoutPoint::new(40.0, 40.0),
+            LayoutPoint::new(40.0, 0.0),
+        ),
+    ];
+    let segments = flatten_path(commands, LayoutPoint::zero());
+
+    // The cubic is monotonic in x, so each of its pieces is kept, along
+    // with the closing line back to the start.
+    assert_eq!(segments.len(), QUADS_PER_CUBIC + 1);
+    assert_eq!(segments[QUADS_PER_CUBIC - 1].to, PointKey { x: 40.0, y: 0.0 });
+    assert_eq!(segments[QUADS_PER_CUBIC].to, PointKey { x: 0.0, y: 0.0 });
+
+    // The cubic peaks at y = 30 in the middle.
+    assert!(segments_contain(&segments, FillRule::NonZero, 20.0, 29.0));
+    assert!(!segments_contain(&segments, FillRule::NonZero, 20.0, 31.0));
+    assert!(segments_contain(&segments, FillRule::EvenOdd, 2.0, 1.0));
+    assert!(!segments_contain(&segments, FillRule::EvenOdd, 20.0, -1.0));
+}
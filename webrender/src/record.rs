@@ -98,6 +98,7 @@ fn should_record_transaction_msg(msgs: &TransactionMsg) -> bool {
     for msg in &msgs.scene_ops {
         match *msg {
             SceneMsg::SetDisplayList { .. } |
+            SceneMsg::UpdateDisplayList { .. } |
             SceneMsg::SetRootPipeline { .. } => return true,
             _ => {}
         }
@@ -11,7 +11,7 @@
 use api::{ApiMsg, BuiltDisplayList, ClearCache, DebugCommand, DebugFlags};
 #[cfg(feature = "debugger")]
 use api::{BuiltDisplayListIter, DisplayItem};
-use api::{DocumentId, DocumentLayer, ExternalScrollId, FrameMsg, HitTestFlags, HitTestResult};
+use api::{DocumentId, DocumentLayer, Epoch, ExternalScrollId, FrameMsg, HitTestFlags, HitTestResult};
 use api::{IdNamespace, MemoryReport, PipelineId, RenderNotifier, SceneMsg, ScrollClamping};
 use api::{ScrollLocation, TransactionMsg, ResourceUpdate, BlobImageKey};
 use api::{NotificationRequest, Checkpoint};
@@ -301,6 +301,10 @@ impl DataStores {
                 let prim_data = &self.line_decoration[data_handle];
                 &prim_data.common
             }
+            PrimitiveInstanceKind::VectorPath { data_handle, .. } => {
+                let prim_data = &self.vector_path[data_handle];
+                &prim_data.common
+            }
             PrimitiveInstanceKind::LinearGradient { data_handle, .. } => {
                 let prim_data = &self.linear_grad[data_handle];
                 &prim_data.common
@@ -317,6 +321,10 @@ impl DataStores {
                 let prim_data = &self.radial_grad[data_handle];
                 &prim_data.common
             }
+            PrimitiveInstanceKind::ConicGradient { data_handle, .. } => {
+                let prim_data = &self.conic_grad[data_handle];
+                &prim_data.common
+            }
             PrimitiveInstanceKind::TextRun { data_handle, .. }  => {
                 let prim_data = &self.text_run[data_handle];
                 &prim_data.common
@@ -455,7 +463,7 @@ impl Document {
                 };
 
                 if self.hit_tester.is_some() {
-                    if self.scroll_nearest_scrolling_ancestor(delta, node_index) {
+                    if self.scroll_nearest_scrolling_ancestor(delta, node_index, precise_time_ns()) {
                         self.hit_tester_is_valid = false;
                         self.frame_is_valid = false;
                     }
@@ -580,6 +588,7 @@ impl Document {
         RenderedDocument {
             frame,
             is_new_scene,
+            has_scroll_animations: self.scene.clip_scroll_tree.has_scroll_animations(),
         }
     }
 
@@ -616,8 +625,13 @@ impl Document {
         &mut self,
         scroll_location: ScrollLocation,
         scroll_node_index: Option<SpatialNodeIndex>,
+        now: u64,
     ) -> bool {
-        self.scene.clip_scroll_tree.scroll_nearest_scrolling_ancestor(scroll_location, scroll_node_index)
+        self.scene.clip_scroll_tree.scroll_nearest_scrolling_ancestor(
+            scroll_location,
+            scroll_node_index,
+            now,
+        )
     }
 
     /// Returns true if the node actually changed position or false otherwise.
@@ -878,27 +892,25 @@ impl<B: hal::Backend> RenderBackend<B> {
             } => {
                 profile_scope!("SetDisplayList");
 
-                let data = if let Some(idx) = self.payload_buffer.iter().position(|data|
-                    data.epoch == epoch && data.pipeline_id == pipeline_id
-                ) {
-                    self.payload_buffer.swap_remove(idx)
-                } else {
-                    loop {
-                        let data = self.payload_rx.recv().unwrap();
-                        if data.epoch == epoch && data.pipeline_id == pipeline_id {
-                            break data;
-                        } else {
-                            self.payload_buffer.push(data);
-                        }
-                    }
-                };
+                let data = take_payload(
+                    &mut self.payload_buffer,
+                    &self.payload_rx,
+                    epoch,
+                    pipeline_id,
+                );
 
                 if let Some(ref mut r) = self.recorder {
                     r.write_payload(frame_counter, &data.to_data());
                 }
 
                 let built_display_list =
-                    BuiltDisplayList::from_data(data.display_list_data, list_descriptor);
+                    match BuiltDisplayList::from_data(data.display_list_data, list_descriptor) {
+                        Ok(built_display_list) => built_display_list,
+                        Err(err) => {
+                            error!("Dropping display list for {:?}: {:?}", pipeline_id, err);
+                            return;
+                        }
+                    };
 
                 if !preserve_frame_state {
                     doc.discard_frame_state_for_pipeline(pipeline_id);
@@ -932,6 +944,40 @@ impl<B: hal::Backend> RenderBackend<B> {
                     display_list_len,
                 );
             }
+            SceneMsg::UpdateDisplayList {
+                epoch,
+                pipeline_id,
+                list_descriptor,
+                ops,
+            } => {
+                profile_scope!("UpdateDisplayList");
+
+                let data = take_payload(
+                    &mut self.payload_buffer,
+                    &self.payload_rx,
+                    epoch,
+                    pipeline_id,
+                );
+
+                if let Some(ref mut r) = self.recorder {
+                    r.write_payload(frame_counter, &data.to_data());
+                }
+
+                let patch = match BuiltDisplayList::from_data(data.display_list_data, list_descriptor) {
+                    Ok(patch) => patch,
+                    Err(err) => {
+                        error!("Dropping display list patch for {:?}: {:?}", pipeline_id, err);
+                        return;
+                    }
+                };
+
+                txn.display_list_patches.push(DisplayListPatch {
+                    patch,
+                    ops,
+                    pipeline_id,
+                    epoch,
+                });
+            }
             SceneMsg::SetRootPipeline(pipeline_id) => {
                 profile_scope!("SetRootPipeline");
                 txn.set_root_pipeline = Some(pipeline_id);
@@ -1369,6 +1415,7 @@ impl<B: hal::Backend> RenderBackend<B> {
                 let mut txn = Box::new(Transaction {
                     document_id,
                     display_list_updates: Vec::new(),
+                    display_list_patches: Vec::new(),
                     removed_pipelines: Vec::new(),
                     epoch_updates: Vec::new(),
                     request_scene_build: None,
@@ -1551,6 +1598,14 @@ impl<B: hal::Backend> RenderBackend<B> {
             render_frame = false;
         }
 
+        // Smooth scrolls and flings are sampled once per generated frame, and
+        // keep invalidating it for as long as they run.
+        if render_frame && doc.scene.clip_scroll_tree.tick_scroll_animations(precise_time_ns()) {
+            doc.frame_is_valid = false;
+            doc.hit_tester_is_valid = false;
+            scroll = true;
+        }
+
         if doc.frame_is_valid {
             // Invalidate WR frame if ResourceUpdate::UpdateImage exists except
             // when image of ExternalImageType::TextureHandle is not used.
@@ -1854,6 +1909,29 @@ impl<B: hal::Backend> RenderBackend<B> {
     }
 }
 
+/// Take the payload sent along with the display list of a scene message,
+/// buffering the payloads of other messages that are received meanwhile.
+fn take_payload(
+    payload_buffer: &mut Vec<Payload>,
+    payload_rx: &Receiver<Payload>,
+    epoch: Epoch,
+    pipeline_id: PipelineId,
+) -> Payload {
+    if let Some(idx) = payload_buffer.iter().position(|data|
+        data.epoch == epoch && data.pipeline_id == pipeline_id
+    ) {
+        return payload_buffer.swap_remove(idx);
+    }
+
+    loop {
+        let data = payload_rx.recv().unwrap();
+        if data.epoch == epoch && data.pipeline_id == pipeline_id {
+            return data;
+        }
+        payload_buffer.push(data);
+    }
+}
+
 fn get_blob_image_updates(updates: &[ResourceUpdate]) -> Vec<BlobImageKey> {
     let mut requests = Vec::new();
     for update in updates {
@@ -2127,7 +2205,7 @@ impl<B: hal::Backend> RenderBackend<B> {
 
                     let msg_publish = ResultMsg::PublishDocument(
                         id,
-                        RenderedDocument { frame, is_new_scene: true },
+                        RenderedDocument { frame, is_new_scene: true, has_scroll_animations: false },
                         self.resource_cache.pending_updates(),
                         profile_counters.clone(),
                     );
@@ -9,15 +9,16 @@ use crate::batch::{AlphaBatchBuilder, AlphaBatchContainer, BatchTextures, resolv
 use crate::batch::{ClipBatcher, BatchBuilder};
 use crate::clip_scroll_tree::{ClipScrollTree, ROOT_SPATIAL_NODE_INDEX};
 use crate::clip::ClipStore;
-use crate::composite::CompositeState;
+use crate::composite::{CompositeState, ResolvedSurfaceTexture};
 use crate::device::Texture;
 use crate::frame_builder::{FrameGlobalResources};
 use crate::gpu_cache::{GpuCache, GpuCacheAddress};
 use crate::gpu_types::{BorderInstance, SvgFilterInstance, BlurDirection, BlurInstance, PrimitiveHeaders, ScalingInstance};
-use crate::gpu_types::{TransformPalette, ZBufferIdGenerator};
+use crate::gpu_types::{TransformPalette, ZBufferIdGenerator, VectorCoverInstance, VectorStencilInstance};
 use crate::internal_types::{FastHashMap, TextureSource, LayerIndex, Swizzle, SavedTargetIndex};
 use crate::picture::SurfaceInfo;
 use crate::prim_store::{PrimitiveStore, DeferredResolve, PrimitiveScratchBuffer, PrimitiveVisibilityMask};
+use crate::prim_store::PointKey;
 use crate::prim_store::gradient::GRADIENT_FP_STOPS;
 use crate::render_backend::DataStores;
 use crate::render_task::{RenderTaskKind, RenderTaskAddress, ClearMode, BlitSource};
@@ -293,9 +294,13 @@ pub struct ColorRenderTarget {
     // List of blur operations to apply for this render target.
     pub vertical_blurs: Vec<BlurInstance>,
     pub horizontal_blurs: Vec<BlurInstance>,
+    pub dual_filter_blurs: Vec<BlurInstance>,
     pub readbacks: Vec<DeviceIntRect>,
     pub scalings: FastHashMap<TextureSource, Vec<ScalingInstance>>,
     pub svg_filters: Vec<(BatchTextures, Vec<SvgFilterInstance>)>,
+    pub vector_stencils: Vec<VectorStencilInstance>,
+    // Glyphs copied out of the glyph cache for text clips, per glyph texture.
+    pub text_masks: FastHashMap<TextureSource, Vec<ScalingInstance>>,
     pub blits: Vec<BlitJob>,
     // List of frame buffer outputs for this render target.
     pub outputs: Vec<FrameOutput>,
@@ -312,8 +317,11 @@ impl ColorRenderTarget {
     pub fn empty_without_batches(&self) -> bool {
         self.vertical_blurs.is_empty()
         && self.horizontal_blurs.is_empty()
+        && self.dual_filter_blurs.is_empty()
         && self.scalings.is_empty()
         && self.svg_filters.is_empty()
+        && self.vector_stencils.is_empty()
+        && self.text_masks.is_empty()
     }
 }
 
@@ -326,9 +334,12 @@ impl RenderTarget for ColorRenderTarget {
             alpha_batch_containers: Vec::new(),
             vertical_blurs: Vec::new(),
             horizontal_blurs: Vec::new(),
+            dual_filter_blurs: Vec::new(),
             readbacks: Vec::new(),
             scalings: FastHashMap::default(),
             svg_filters: Vec::new(),
+            vector_stencils: Vec::new(),
+            text_masks: FastHashMap::default(),
             blits: Vec::new(),
             outputs: Vec::new(),
             alpha_tasks: Vec::new(),
@@ -468,6 +479,15 @@ impl RenderTarget for ColorRenderTarget {
                     render_tasks.get_task_address(task.children[0]),
                 );
             }
+            RenderTaskKind::DualFilterBlur(..) => {
+                // The direction is ignored by cs_dual_filter.
+                add_blur_instances(
+                    &mut self.dual_filter_blurs,
+                    BlurDirection::Horizontal,
+                    render_tasks.get_task_address(task_id),
+                    render_tasks.get_task_address(task.children[0]),
+                );
+            }
             RenderTaskKind::Picture(ref task_info) => {
                 let pic = &ctx.prim_store.pictures[task_info.pic_index.0];
                 self.alpha_tasks.push(task_id);
@@ -492,11 +512,51 @@ impl RenderTarget for ColorRenderTarget {
                     task_info.extra_gpu_cache_handle.map(|handle| gpu_cache.get_address(&handle)),
                 )
             }
+            RenderTaskKind::VectorStencil(ref task_info) => {
+                let (target_rect, _) = task.get_target_rect();
+                let task_rect = target_rect.to_f32();
+                let scale_x = task_rect.size.width / task_info.local_size.width;
+                let scale_y = task_rect.size.height / task_info.local_size.height;
+
+                for segment in &task_info.segments {
+                    let to_device = |point: &PointKey| {
+                        let point = LayoutPoint::from(point.clone());
+                        DevicePoint::new(point.x * scale_x, point.y * scale_y)
+                    };
+
+                    self.vector_stencils.push(VectorStencilInstance {
+                        from_position: to_device(&segment.from),
+                        ctrl_position: to_device(&segment.ctrl),
+                        to_position: to_device(&segment.to),
+                        task_rect,
+                    });
+                }
+            }
+            RenderTaskKind::TextMask(ref task_info) => {
+                let task_origin = task.get_target_rect().0.origin.to_f32().to_vector();
+                let text_masks = &mut self.text_masks;
+
+                ctx.resource_cache.fetch_glyph_cache_items(
+                    task_info.font.clone(),
+                    &task_info.glyph_keys,
+                    |index, cache_item| {
+                        text_masks
+                            .entry(cache_item.texture_id)
+                            .or_insert_with(Vec::new)
+                            .push(ScalingInstance {
+                                target_rect: task_info.glyph_rects[index].translate(task_origin),
+                                source_rect: cache_item.uv_rect,
+                                source_layer: cache_item.texture_layer,
+                            });
+                    },
+                );
+            }
             RenderTaskKind::ClipRegion(..) |
             RenderTaskKind::Border(..) |
             RenderTaskKind::CacheMask(..) |
             RenderTaskKind::Gradient(..) |
-            RenderTaskKind::LineDecoration(..) => {
+            RenderTaskKind::LineDecoration(..) |
+            RenderTaskKind::VectorCover(..) => {
                 panic!("Should not be added to color target!");
             }
             RenderTaskKind::Readback(device_rect) => {
@@ -589,6 +649,7 @@ pub struct AlphaRenderTarget {
     // List of blur operations to apply for this render target.
     pub vertical_blurs: Vec<BlurInstance>,
     pub horizontal_blurs: Vec<BlurInstance>,
+    pub dual_filter_blurs: Vec<BlurInstance>,
     pub scalings: FastHashMap<TextureSource, Vec<ScalingInstance>>,
     pub zero_clears: Vec<RenderTaskId>,
     pub one_clears: Vec<RenderTaskId>,
@@ -604,6 +665,7 @@ impl AlphaRenderTarget {
         self.clip_batcher.is_empty()
             && self.vertical_blurs.is_empty()
             && self.horizontal_blurs.is_empty()
+            && self.dual_filter_blurs.is_empty()
             && self.scalings.is_empty()
     }
 }
@@ -617,6 +679,7 @@ impl RenderTarget for AlphaRenderTarget {
             clip_batcher: ClipBatcher::new(gpu_supports_fast_clears),
             vertical_blurs: Vec::new(),
             horizontal_blurs: Vec::new(),
+            dual_filter_blurs: Vec::new(),
             scalings: FastHashMap::default(),
             zero_clears: Vec::new(),
             one_clears: Vec::new(),
@@ -656,6 +719,9 @@ impl RenderTarget for AlphaRenderTarget {
             RenderTaskKind::Blit(..) |
             RenderTaskKind::Border(..) |
             RenderTaskKind::LineDecoration(..) |
+            RenderTaskKind::VectorStencil(..) |
+            RenderTaskKind::VectorCover(..) |
+            RenderTaskKind::TextMask(..) |
             RenderTaskKind::Gradient(..) |
             RenderTaskKind::SvgFilter(..) => {
                 panic!("BUG: should not be added to alpha target!");
@@ -676,6 +742,15 @@ impl RenderTarget for AlphaRenderTarget {
                     render_tasks.get_task_address(task.children[0]),
                 );
             }
+            RenderTaskKind::DualFilterBlur(..) => {
+                // The direction is ignored by cs_dual_filter.
+                add_blur_instances(
+                    &mut self.dual_filter_blurs,
+                    BlurDirection::Horizontal,
+                    render_tasks.get_task_address(task_id),
+                    render_tasks.get_task_address(task.children[0]),
+                );
+            }
             RenderTaskKind::CacheMask(ref task_info) => {
                 self.clip_batcher.add(
                     task_info.clip_node_range,
@@ -739,8 +814,7 @@ impl RenderTarget for AlphaRenderTarget {
 #[cfg_attr(feature = "capture", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
 pub struct PictureCacheTarget {
-    pub texture: TextureSource,
-    pub layer: usize,
+    pub surface: ResolvedSurfaceTexture,
     pub alpha_batch_container: AlphaBatchContainer,
     pub clear_color: Option<ColorF>,
 }
@@ -750,12 +824,14 @@ pub struct PictureCacheTarget {
 pub struct TextureCacheRenderTarget {
     pub target_kind: RenderTargetKind,
     pub horizontal_blurs: Vec<BlurInstance>,
+    pub dual_filter_blurs: Vec<BlurInstance>,
     pub blits: Vec<BlitJob>,
     pub border_segments_complex: Vec<BorderInstance>,
     pub border_segments_solid: Vec<BorderInstance>,
     pub clears: Vec<DeviceIntRect>,
     pub line_decorations: Vec<LineDecorationJob>,
     pub gradients: Vec<GradientJob>,
+    pub vector_covers: Vec<VectorCoverInstance>,
 }
 
 impl TextureCacheRenderTarget {
@@ -763,22 +839,26 @@ impl TextureCacheRenderTarget {
         TextureCacheRenderTarget {
             target_kind,
             horizontal_blurs: vec![],
+            dual_filter_blurs: vec![],
             blits: vec![],
             border_segments_complex: vec![],
             border_segments_solid: vec![],
             clears: vec![],
             line_decorations: vec![],
             gradients: vec![],
+            vector_covers: vec![],
         }
     }
 
     #[cfg(not(feature = "gl"))]
     pub fn is_empty(&self) -> bool {
         self.horizontal_blurs.is_empty()
+        && self.dual_filter_blurs.is_empty()
         && self.border_segments_complex.is_empty()
         && self.border_segments_solid.is_empty()
         && self.line_decorations.is_empty()
         && self.gradients.is_empty()
+        && self.vector_covers.is_empty()
         && self.clears.is_empty()
         && self.blits.is_empty()
     }
@@ -787,6 +867,7 @@ impl TextureCacheRenderTarget {
         &mut self,
         task_id: RenderTaskId,
         render_tasks: &mut RenderTaskGraph,
+        gpu_cache: &GpuCache,
     ) {
         let task_address = render_tasks.get_task_address(task_id);
         let src_task_address = render_tasks[task_id].children.get(0).map(|src_task_id| {
@@ -816,6 +897,15 @@ impl TextureCacheRenderTarget {
                     src_task_address.unwrap(),
                 );
             }
+            RenderTaskKind::DualFilterBlur(..) => {
+                // The direction is ignored by cs_dual_filter.
+                add_blur_instances(
+                    &mut self.dual_filter_blurs,
+                    BlurDirection::Horizontal,
+                    task_address,
+                    src_task_address.unwrap(),
+                );
+            }
             RenderTaskKind::Blit(ref task_info) => {
                 match task_info.source {
                     BlitSource::Image { .. } => {
@@ -871,13 +961,24 @@ impl TextureCacheRenderTarget {
                     start_stop: [task_info.start_point, task_info.end_point],
                 });
             }
+            RenderTaskKind::VectorCover(ref task_info) => {
+                self.vector_covers.push(VectorCoverInstance {
+                    task_rect: target_rect.0.to_f32(),
+                    stencil_task_address: src_task_address.unwrap().0 as i32,
+                    fill_rule: task_info.fill_rule as i32,
+                    paint_address: gpu_cache.get_address(&task_info.paint_handle).as_int(),
+                    stops_address: task_info.stops_handle.map_or(0, |handle| gpu_cache.get_address(&handle).as_int()),
+                });
+            }
             RenderTaskKind::VerticalBlur(..) |
             RenderTaskKind::Picture(..) |
             RenderTaskKind::ClipRegion(..) |
             RenderTaskKind::CacheMask(..) |
             RenderTaskKind::Readback(..) |
             RenderTaskKind::Scaling(..) |
-            RenderTaskKind::SvgFilter(..) => {
+            RenderTaskKind::SvgFilter(..) |
+            RenderTaskKind::VectorStencil(..) |
+            RenderTaskKind::TextMask(..) => {
                 panic!("BUG: unexpected task kind for texture cache target");
             }
             #[cfg(test)]
@@ -1003,10 +1104,19 @@ fn add_svg_filter_instances(
         SvgFilterInfo::ComponentTransfer(..) => 8,
         SvgFilterInfo::Identity => 9,
         SvgFilterInfo::Composite(..) => 10,
+        SvgFilterInfo::Morphology(..) => 11,
+        SvgFilterInfo::ConvolveMatrix(..) => 12,
+        SvgFilterInfo::Turbulence(..) => 13,
+        SvgFilterInfo::DisplacementMap(..) => 14,
+        SvgFilterInfo::DiffuseLighting(..) => 15,
+        SvgFilterInfo::SpecularLighting(..) => 16,
+        SvgFilterInfo::Image(..) => 17,
+        SvgFilterInfo::Tile(..) => 18,
     };
 
     let input_count = match filter {
-        SvgFilterInfo::Flood(..) => 0,
+        SvgFilterInfo::Flood(..) |
+        SvgFilterInfo::Turbulence(..) => 0,
 
         SvgFilterInfo::LinearToSrgb |
         SvgFilterInfo::SrgbToLinear |
@@ -1014,12 +1124,19 @@ fn add_svg_filter_instances(
         SvgFilterInfo::ColorMatrix(..) |
         SvgFilterInfo::Offset(..) |
         SvgFilterInfo::ComponentTransfer(..) |
+        SvgFilterInfo::Morphology(..) |
+        SvgFilterInfo::ConvolveMatrix(..) |
+        SvgFilterInfo::DiffuseLighting(..) |
+        SvgFilterInfo::SpecularLighting(..) |
+        SvgFilterInfo::Image(..) |
+        SvgFilterInfo::Tile(..) |
         SvgFilterInfo::Identity => 1,
 
         // Not techincally a 2 input filter, but we have 2 inputs here: original content & blurred content.
         SvgFilterInfo::DropShadow(..) |
         SvgFilterInfo::Blend(..) |
-        SvgFilterInfo::Composite(..) => 2,
+        SvgFilterInfo::Composite(..) |
+        SvgFilterInfo::DisplacementMap(..) => 2,
     };
 
     let generic_int = match filter {
@@ -1031,6 +1148,15 @@ fn add_svg_filter_instances(
               data.a_func.to_int()) as u16),
         SvgFilterInfo::Composite(operator) =>
             operator.as_int() as u16,
+        SvgFilterInfo::Morphology(operator, _) =>
+            operator.as_int() as u16,
+        SvgFilterInfo::Turbulence(info) =>
+            info.primitive.noise_type.as_int() as u16,
+        SvgFilterInfo::DisplacementMap(_, x_channel, y_channel) =>
+            (x_channel.as_int() << 4 | y_channel.as_int()) as u16,
+        SvgFilterInfo::DiffuseLighting(info) |
+        SvgFilterInfo::SpecularLighting(info) =>
+            info.light.as_int() as u16,
         SvgFilterInfo::LinearToSrgb |
         SvgFilterInfo::SrgbToLinear |
         SvgFilterInfo::Flood(..) |
@@ -1038,6 +1164,9 @@ fn add_svg_filter_instances(
         SvgFilterInfo::ColorMatrix(..) |
         SvgFilterInfo::DropShadow(..) |
         SvgFilterInfo::Offset(..) |
+        SvgFilterInfo::ConvolveMatrix(..) |
+        SvgFilterInfo::Image(..) |
+        SvgFilterInfo::Tile(..) |
         SvgFilterInfo::Identity => 0,
     };
 
@@ -3,21 +3,28 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use api::{CompositeOperator, FilterPrimitive, FilterPrimitiveInput, FilterPrimitiveKind};
-use api::{LineStyle, LineOrientation, ClipMode, MixBlendMode, ColorF, ColorSpace};
+use api::{LineStyle, LineOrientation, ClipMode, MixBlendMode, ColorF, ColorSpace, FillRule};
+use api::{ConvolveMatrixPrimitive, MorphologyOperator, MAX_MORPHOLOGY_RADIUS};
+use api::{ColorChannel, LightSource, TurbulencePrimitive, ImageRendering};
 use api::units::*;
+use euclid::Scale;
 use crate::clip::{ClipDataStore, ClipItemKind, ClipStore, ClipNodeRange, ClipNodeFlags};
 use crate::clip_scroll_tree::SpatialNodeIndex;
+use crate::composite::ResolvedSurfaceTexture;
 use crate::filterdata::SFilterData;
 use crate::frame_builder::FrameBuilderConfig;
-use crate::gpu_cache::{GpuCache, GpuCacheAddress, GpuCacheHandle};
+use crate::gpu_cache::{GpuCache, GpuCacheAddress, GpuCacheHandle, GpuDataRequest};
+use crate::glyph_rasterizer::{FontInstance, GlyphKey};
 use crate::gpu_types::{BorderInstance, ImageSource, UvRectKind};
-use crate::internal_types::{CacheTextureId, FastHashMap, LayerIndex, SavedTargetIndex, TextureSource};
+use crate::internal_types::{CacheTextureId, FastHashMap, LayerIndex, SavedTargetIndex};
 use crate::prim_store::{PictureIndex, PrimitiveVisibilityMask};
 use crate::prim_store::image::ImageCacheKey;
 use crate::prim_store::gradient::{GRADIENT_FP_STOPS, GradientStopKey};
+use crate::prim_store::text_run::TextClipCacheKey;
+use crate::prim_store::vector_path::{MAX_VECTOR_PATH_TASK_SIZE, PathSegment};
 #[cfg(feature = "debugger")]
 use crate::print_tree::{PrintTreePrinter};
-use crate::resource_cache::ResourceCache;
+use crate::resource_cache::{ImageRequest, ResourceCache};
 use std::{usize, f32, i32, u32};
 use crate::render_target::{RenderTargetIndex, RenderTargetKind};
 use crate::render_task_graph::{RenderTaskGraph, RenderTaskId};
@@ -27,6 +34,12 @@ const RENDER_TASK_SIZE_SANITY_CHECK: i32 = 16000;
 const FLOATS_PER_RENDER_TASK_INFO: usize = 8;
 pub const MAX_BLUR_STD_DEVIATION: f32 = 4.0;
 pub const MIN_DOWNSCALING_RT_SIZE: i32 = 8;
+/// Blurs with a std deviation of at least this on both axes use a dual filter
+/// pyramid rather than down-scaling followed by separable gaussian passes.
+pub const MIN_DUAL_FILTER_STD_DEVIATION: f32 = 16.0;
+/// The largest factor the sample offsets of the dual filter passes are scaled
+/// by before the sparse taps become visible.
+const MAX_DUAL_FILTER_OFFSET_SCALE: f32 = 2.0;
 
 fn render_task_sanity_check(size: &DeviceIntSize) {
     if size.width > RENDER_TASK_SIZE_SANITY_CHECK ||
@@ -74,10 +87,8 @@ pub enum RenderTaskLocation {
     /// This render task will be drawn to a picture cache texture that is
     /// persisted between both frames and scenes, if the content remains valid.
     PictureCache {
-        /// The texture ID to draw to.
-        texture: TextureSource,
-        /// Slice index in the texture array to draw to.
-        layer: i32,
+        /// The surface to draw to.
+        surface: ResolvedSurfaceTexture,
         /// Size in device pixels of this picture cache tile.
         size: DeviceIntSize,
     },
@@ -107,7 +118,7 @@ impl RenderTaskLocation {
             RenderTaskLocation::Dynamic(None, _) => panic!("Expected position to be set for the task!"),
             RenderTaskLocation::Dynamic(Some((origin, layer)), size) => (DeviceIntRect::new(origin, size), layer.0 as LayerIndex),
             RenderTaskLocation::TextureCache { rect, layer, .. } => (rect, layer),
-            RenderTaskLocation::PictureCache { layer, size, .. } => (size.into(), layer as LayerIndex),
+            RenderTaskLocation::PictureCache { ref surface, size } => (size.into(), surface.layer() as LayerIndex),
         }
     }
 }
@@ -166,6 +177,36 @@ impl BlurTask {
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub enum DualFilterPass {
+    Down = 0,
+    Up = 1,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct DualFilterTask {
+    pub pass: DualFilterPass,
+    /// Scale applied to the sample offsets, in order to approximate std
+    /// deviations between two powers of two.
+    pub offset_scale: DeviceVector2D,
+    pub target_kind: RenderTargetKind,
+    pub uv_rect_handle: GpuCacheHandle,
+    uv_rect_kind: UvRectKind,
+}
+
+impl DualFilterTask {
+    #[cfg(feature = "debugger")]
+    fn print_with<T: PrintTreePrinter>(&self, pt: &mut T) {
+        pt.add_item(format!("pass: {:?}", self.pass));
+        pt.add_item(format!("offset scale: {:?}", self.offset_scale));
+        pt.add_item(format!("target: {:?}", self.target_kind));
+    }
+}
+
 #[derive(Debug)]
 #[cfg_attr(feature = "capture", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
@@ -224,6 +265,37 @@ pub struct LineDecorationTask {
     pub local_size: LayoutSize,
 }
 
+/// Accumulates the winding of a path outline into a color target.
+#[derive(Debug)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct VectorStencilTask {
+    pub segments: Vec<PathSegment>,
+    pub local_size: LayoutSize,
+}
+
+/// Resolves the winding accumulated by its vector stencil child task
+/// into coverage, and applies the paint of the path.
+#[derive(Debug)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct VectorCoverTask {
+    pub fill_rule: FillRule,
+    pub paint_handle: GpuCacheHandle,
+    pub stops_handle: Option<GpuCacheHandle>,
+}
+
+/// Copies the glyphs of a text clip out of the glyph cache, keeping the
+/// highest coverage where glyphs overlap.
+#[derive(Debug)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct TextMaskTask {
+    pub font: FontInstance,
+    pub glyph_keys: Vec<GlyphKey>,
+    pub glyph_rects: Vec<DeviceRect>,
+}
+
 #[derive(Debug)]
 #[cfg_attr(feature = "capture", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
@@ -238,10 +310,208 @@ pub enum SvgFilterInfo {
     Offset(DeviceVector2D),
     ComponentTransfer(SFilterData),
     Composite(CompositeOperator),
+    /// One pass of a morphology filter, along the axis of the non-zero radius.
+    Morphology(MorphologyOperator, DeviceVector2D),
+    ConvolveMatrix(Box<ConvolveMatrixPrimitive>),
+    Turbulence(Box<TurbulenceInfo>),
+    /// The scale in device pixels, and the channels of the second input
+    /// that displace the first along each axis.
+    DisplacementMap(f32, ColorChannel, ColorChannel),
+    DiffuseLighting(Box<LightingInfo>),
+    SpecularLighting(Box<LightingInfo>),
+    /// The rectangle that the image, the only input, is drawn in.
+    Image(Box<FilterRectInfo>),
+    /// The rectangle of the input that is repeated.
+    Tile(Box<FilterRectInfo>),
     // TODO: This is used as a hack to ensure that a blur task's input is always in the blur's previous pass.
     Identity,
 }
 
+/// The number of entries of the lattice that turbulence noise is
+/// interpolated from.
+const TURBULENCE_LATTICE_SIZE: usize = 256;
+
+/// A turbulence primitive, along with where the task it is rendered to
+/// lies in the local space of the filtered picture.
+#[derive(Debug)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct TurbulenceInfo {
+    pub primitive: TurbulencePrimitive,
+    /// The local position of the top left corner of the task.
+    pub origin: PicturePoint,
+    /// The local size of a device pixel.
+    pub pixel_size: f32,
+    /// The region the noise tiles across when stitching.
+    pub tile_rect: PictureRect,
+}
+
+impl TurbulenceInfo {
+    /// Writes the noise parameters, followed by the lattice of the noise
+    /// function. The lattice is generated from the seed with the pseudo
+    /// random number generator given by the feTurbulence specification,
+    /// so that the same seed produces the same noise as other renderers.
+    fn write_gpu_blocks(&self, mut request: GpuDataRequest) {
+        const RAND_M: i64 = 2147483647;
+        const RAND_A: i64 = 16807;
+        const RAND_Q: i64 = 127773;
+        const RAND_R: i64 = 2836;
+
+        fn random(seed: i64) -> i64 {
+            let result = RAND_A * (seed % RAND_Q) - RAND_R * (seed / RAND_Q);
+            if result <= 0 { result + RAND_M } else { result }
+        }
+
+        let mut seed = self.primitive.seed as i64;
+        if seed <= 0 {
+            seed = -(seed % (RAND_M - 1)) + 1;
+        }
+        if seed > RAND_M - 1 {
+            seed = RAND_M - 1;
+        }
+
+        // One gradient per lattice entry for each color channel.
+        let mut gradients = [[[0.0f32; 2]; TURBULENCE_LATTICE_SIZE]; 4];
+        for channel in gradients.iter_mut() {
+            for gradient in channel.iter_mut() {
+                for value in gradient.iter_mut() {
+                    seed = random(seed);
+                    let size = TURBULENCE_LATTICE_SIZE as i64;
+                    *value = ((seed % (size * 2)) - size) as f32 / size as f32;
+                }
+                let length = (gradient[0] * gradient[0] + gradient[1] * gradient[1]).sqrt();
+                if length > 0.0 {
+                    gradient[0] /= length;
+                    gradient[1] /= length;
+                }
+            }
+        }
+
+        let mut lattice = [0usize; TURBULENCE_LATTICE_SIZE];
+        for (i, selector) in lattice.iter_mut().enumerate() {
+            *selector = i;
+        }
+        for i in (1 .. TURBULENCE_LATTICE_SIZE).rev() {
+            seed = random(seed);
+            lattice.swap(i, seed as usize % TURBULENCE_LATTICE_SIZE);
+        }
+
+        // Adjust the base frequencies so that a whole number of lattice
+        // cells fits in the tile, so that its edges are continuous.
+        let stitch_frequency = |frequency: f32, size: f32| {
+            if frequency == 0.0 || size <= 0.0 {
+                return frequency;
+            }
+            let low = (size * frequency).floor() / size;
+            let high = (size * frequency).ceil() / size;
+            if frequency / low < high / frequency { low } else { high }
+        };
+        let tile_size = self.tile_rect.size;
+        let frequency = if self.primitive.stitch_tiles {
+            (
+                stitch_frequency(self.primitive.base_frequency_x, tile_size.width),
+                stitch_frequency(self.primitive.base_frequency_y, tile_size.height),
+            )
+        } else {
+            (self.primitive.base_frequency_x, self.primitive.base_frequency_y)
+        };
+        let stitch_width = (tile_size.width * frequency.0 + 0.5).floor();
+        let stitch_height = (tile_size.height * frequency.1 + 0.5).floor();
+
+        request.push([
+            frequency.0,
+            frequency.1,
+            self.primitive.num_octaves as f32,
+            if self.primitive.stitch_tiles { 1.0 } else { 0.0 },
+        ]);
+        request.push([
+            stitch_width,
+            stitch_height,
+            (self.tile_rect.origin.x * frequency.0).floor() + stitch_width,
+            (self.tile_rect.origin.y * frequency.1).floor() + stitch_height,
+        ]);
+        for selectors in lattice.chunks(4) {
+            request.push([
+                selectors[0] as f32,
+                selectors[1] as f32,
+                selectors[2] as f32,
+                selectors[3] as f32,
+            ]);
+        }
+        // The gradients of all channels for a lattice entry are stored next
+        // to each other, so that the shader fetches them together.
+        for i in 0 .. TURBULENCE_LATTICE_SIZE {
+            request.push([gradients[0][i][0], gradients[0][i][1], gradients[1][i][0], gradients[1][i][1]]);
+            request.push([gradients[2][i][0], gradients[2][i][1], gradients[3][i][0], gradients[3][i][1]]);
+        }
+    }
+}
+
+/// A diffuse or specular lighting primitive, along with where the task it
+/// is rendered to lies in the local space of the filtered picture, which
+/// the light source is positioned in.
+#[derive(Debug)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct LightingInfo {
+    pub surface_scale: f32,
+    /// The diffuse or specular constant.
+    pub constant: f32,
+    /// Only used by specular lighting.
+    pub specular_exponent: f32,
+    pub color: ColorF,
+    pub light: LightSource,
+    /// The local position of the top left corner of the task.
+    pub origin: PicturePoint,
+    /// The local size of a device pixel.
+    pub pixel_size: f32,
+}
+
+impl LightingInfo {
+    fn write_gpu_blocks(&self, mut request: GpuDataRequest) {
+        request.push([self.surface_scale, self.constant, self.specular_exponent, 0.0]);
+        request.push(self.color.to_array());
+        match self.light {
+            LightSource::Distant { azimuth, elevation } => {
+                // The direction towards the light.
+                let (azimuth, elevation) = (azimuth.to_radians(), elevation.to_radians());
+                request.push([
+                    azimuth.cos() * elevation.cos(),
+                    azimuth.sin() * elevation.cos(),
+                    elevation.sin(),
+                    0.0,
+                ]);
+                request.push([0.0; 4]);
+            }
+            LightSource::Point { position } => {
+                request.push([position.x, position.y, position.z, 0.0]);
+                request.push([0.0; 4]);
+            }
+            LightSource::Spot { position, points_at, specular_exponent, limiting_cone_angle } => {
+                let direction = points_at - position;
+                let length = direction.length();
+                let direction = if length > 0.0 { direction / length } else { direction };
+                let cone_angle = limiting_cone_angle.abs().min(90.0).to_radians();
+                request.push([position.x, position.y, position.z, cone_angle.cos()]);
+                request.push([direction.x, direction.y, direction.z, specular_exponent]);
+            }
+        }
+    }
+}
+
+/// A rectangle in the local space of the filtered picture, along with
+/// where the task it is used by lies in that space.
+#[derive(Debug)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct FilterRectInfo {
+    pub rect: PictureRect,
+    /// The local position of the top left corner of the task.
+    pub origin: PicturePoint,
+    /// The local size of a device pixel.
+    pub pixel_size: f32,
+}
+
 #[derive(Debug)]
 #[cfg_attr(feature = "capture", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
@@ -267,11 +537,15 @@ pub enum RenderTaskKind {
     ClipRegion(ClipRegionTask),
     VerticalBlur(BlurTask),
     HorizontalBlur(BlurTask),
+    DualFilterBlur(DualFilterTask),
     Readback(DeviceIntRect),
     Scaling(ScalingTask),
     Blit(BlitTask),
     Border(BorderTask),
     LineDecoration(LineDecorationTask),
+    VectorStencil(VectorStencilTask),
+    VectorCover(VectorCoverTask),
+    TextMask(TextMaskTask),
     Gradient(GradientTask),
     SvgFilter(SvgFilterTask),
     #[cfg(test)]
@@ -286,11 +560,15 @@ impl RenderTaskKind {
             RenderTaskKind::ClipRegion(..) => "ClipRegion",
             RenderTaskKind::VerticalBlur(..) => "VerticalBlur",
             RenderTaskKind::HorizontalBlur(..) => "HorizontalBlur",
+            RenderTaskKind::DualFilterBlur(..) => "DualFilterBlur",
             RenderTaskKind::Readback(..) => "Readback",
             RenderTaskKind::Scaling(..) => "Scaling",
             RenderTaskKind::Blit(..) => "Blit",
             RenderTaskKind::Border(..) => "Border",
             RenderTaskKind::LineDecoration(..) => "LineDecoration",
+            RenderTaskKind::VectorStencil(..) => "VectorStencil",
+            RenderTaskKind::VectorCover(..) => "VectorCover",
+            RenderTaskKind::TextMask(..) => "TextMask",
             RenderTaskKind::Gradient(..) => "Gradient",
             RenderTaskKind::SvgFilter(..) => "SvgFilter",
             #[cfg(test)]
@@ -324,18 +602,30 @@ pub type BlurTaskCache = FastHashMap<BlurTaskKey, RenderTaskId>;
 pub enum BlurTaskKey {
     DownScale(u32),
     Blur { downscale_level: u32, stddev_x: u32, stddev_y: u32 },
+    DualFilter { levels: u32, stddev_x: u32, stddev_y: u32 },
 }
 
 impl BlurTaskKey {
     fn downscale_and_blur(downscale_level: u32, blur_stddev: DeviceSize) -> Self {
+        let (stddev_x, stddev_y) = Self::quantize(blur_stddev);
+        BlurTaskKey::Blur { downscale_level, stddev_x, stddev_y }
+    }
+
+    fn dual_filter(levels: u32, blur_stddev: DeviceSize) -> Self {
+        let (stddev_x, stddev_y) = Self::quantize(blur_stddev);
+        BlurTaskKey::DualFilter { levels, stddev_x, stddev_y }
+    }
+
+    fn quantize(blur_stddev: DeviceSize) -> (u32, u32) {
         // Quantise the std deviations and store it as integers to work around
         // Eq and Hash's f32 allergy.
         // The blur radius is rounded before RenderTask::new_blur so we don't need
         // a lot of precision.
         const QUANTIZATION_FACTOR: f32 = 1024.0;
-        let stddev_x = (blur_stddev.width * QUANTIZATION_FACTOR) as u32;
-        let stddev_y = (blur_stddev.height * QUANTIZATION_FACTOR) as u32;
-        BlurTaskKey::Blur { downscale_level, stddev_x, stddev_y }
+        (
+            (blur_stddev.width * QUANTIZATION_FACTOR) as u32,
+            (blur_stddev.height * QUANTIZATION_FACTOR) as u32,
+        )
     }
 }
 
@@ -507,6 +797,59 @@ impl RenderTask {
         )
     }
 
+    pub fn new_vector_stencil(
+        size: DeviceIntSize,
+        segments: Vec<PathSegment>,
+        local_size: LayoutSize,
+    ) -> Self {
+        RenderTask::with_dynamic_location(
+            size,
+            Vec::new(),
+            RenderTaskKind::VectorStencil(VectorStencilTask {
+                segments,
+                local_size,
+            }),
+            ClearMode::Transparent,
+        )
+    }
+
+    pub fn new_vector_cover(
+        size: DeviceIntSize,
+        stencil_task_id: RenderTaskId,
+        fill_rule: FillRule,
+        paint_handle: GpuCacheHandle,
+        stops_handle: Option<GpuCacheHandle>,
+    ) -> Self {
+        RenderTask::with_dynamic_location(
+            size,
+            vec![stencil_task_id],
+            RenderTaskKind::VectorCover(VectorCoverTask {
+                fill_rule,
+                paint_handle,
+                stops_handle,
+            }),
+            ClearMode::Transparent,
+        )
+    }
+
+    pub fn new_text_mask(
+        size: DeviceIntSize,
+        font: FontInstance,
+        glyph_keys: Vec<GlyphKey>,
+        glyph_rects: Vec<DeviceRect>,
+    ) -> Self {
+        RenderTask::with_dynamic_location(
+            size,
+            Vec::new(),
+            RenderTaskKind::TextMask(TextMaskTask {
+                font,
+                glyph_keys,
+                glyph_rects,
+            }),
+            ClearMode::Transparent,
+        )
+    }
+
     pub fn new_mask(
         outer_rect: DeviceIntRect,
         clip_node_range: ClipNodeRange,
@@ -578,6 +921,105 @@ impl RenderTask {
                         }
                     ));
                 }
+                ClipItemKind::Path { rect, ref mut source, .. } => {
+                    // Rasterize the path coverage at the device pixel scale, in the
+                    // same way vector path primitives are, so it can be cached in
+                    // the texture cache and stretched over the clip rect.
+                    let content_scale = LayoutToWorldScale::new(1.0) * device_pixel_scale;
+                    let task_size = (rect.size * content_scale)
+                        .ceil()
+                        .to_i32()
+                        .min(DeviceIntSize::new(MAX_VECTOR_PATH_TASK_SIZE, MAX_VECTOR_PATH_TASK_SIZE));
+
+                    if task_size.width <= 0 || task_size.height <= 0 {
+                        source.cache_handle = None;
+                        continue;
+                    }
+
+                    let cache_key = &source.cache_key;
+                    let paint_handle = source.paint_handle;
+
+                    source.cache_handle = Some(resource_cache.request_render_task(
+                        RenderTaskCacheKey {
+                            size: task_size,
+                            kind: RenderTaskCacheKeyKind::VectorPath(cache_key.clone()),
+                        },
+                        gpu_cache,
+                        render_tasks,
+                        None,
+                        false,
+                        |render_tasks| {
+                            let stencil_task = RenderTask::new_vector_stencil(
+                                task_size,
+                                cache_key.segments.clone(),
+                                rect.size,
+                            );
+                            let stencil_task_id = render_tasks.add(stencil_task);
+
+                            let cover_task = RenderTask::new_vector_cover(
+                                task_size,
+                                stencil_task_id,
+                                cache_key.fill_rule,
+                                paint_handle,
+                                None,
+                            );
+                            render_tasks.add(cover_task)
+                        }
+                    ));
+                }
+                ClipItemKind::Text { rect, ref mut source } => {
+                    let task_size = match source.update(rect.size, device_pixel_scale, resource_cache) {
+                        Some(task_size) => task_size,
+                        None => {
+                            source.cache_handle = None;
+                            continue;
+                        }
+                    };
+
+                    // The glyphs are needed whenever the cached coverage gets
+                    // (re)built, so keep them alive in the glyph cache.
+                    resource_cache.request_glyphs(
+                        source.used_font.clone(),
+                        &source.glyph_keys,
+                        gpu_cache,
+                        render_tasks,
+                    );
+
+                    let used_font = &source.used_font;
+                    let glyph_keys = &source.glyph_keys;
+                    let glyph_rects = &source.glyph_rects;
+
+                    source.cache_handle = Some(resource_cache.request_render_task(
+                        RenderTaskCacheKey {
+                            size: task_size,
+                            kind: RenderTaskCacheKeyKind::TextClip(TextClipCacheKey {
+                                font: used_font.clone(),
+                                glyphs: source.glyphs.clone(),
+                            }),
+                        },
+                        gpu_cache,
+                        render_tasks,
+                        None,
+                        false,
+                        |render_tasks| {
+                            // The glyphs live in the texture cache as well, so they are
+                            // copied into an intermediate target first, and blitted into
+                            // the texture cache from there.
+                            let mask_task = RenderTask::new_text_mask(
+                                task_size,
+                                used_font.clone(),
+                                glyph_keys.clone(),
+                                glyph_rects.clone(),
+                            );
+                            let mask_task_id = render_tasks.add(mask_task);
+
+                            render_tasks.add(RenderTask::new_blit(
+                                task_size,
+                                BlitSource::RenderTask { task_id: mask_task_id },
+                            ))
+                        }
+                    ));
+                }
                 ClipItemKind::Rectangle { mode: ClipMode::Clip, .. } => {
                     if !clip_instance.flags.contains(ClipNodeFlags::SAME_COORD_SYSTEM) {
                         // This is conservative - it's only the case that we actually need
@@ -644,6 +1086,12 @@ impl RenderTask {
     // source of each down-scale pass to be a multuple of two. If need be, this inflates
     // the source size so that each down-scale pass will sample correctly.
     pub fn adjusted_blur_source_size(original_size: DeviceIntSize, mut std_dev: DeviceSize) -> DeviceIntSize {
+        // The same applies to each level of the dual filter pyramid.
+        if let Some(levels) = Self::dual_filter_levels(original_size, std_dev) {
+            let scale_factor = 1 << levels;
+            return (original_size.to_f32() / scale_factor as f32).ceil().to_i32() * scale_factor;
+        }
+
         let mut adjusted_size = original_size;
         let mut scale_factor = 1.0;
         while std_dev.width > MAX_BLUR_STD_DEVIATION && std_dev.height > MAX_BLUR_STD_DEVIATION {
@@ -686,12 +1134,25 @@ impl RenderTask {
         mut blur_cache: Option<&mut BlurTaskCache>,
         blur_region: DeviceIntSize,
     ) -> RenderTaskId {
-        // Adjust large std deviation value.
-        let mut adjusted_blur_std_deviation = blur_std_deviation;
         let (blur_target_size, uv_rect_kind) = {
             let src_task = &render_tasks[src_task_id];
             (src_task.get_dynamic_size(), src_task.uv_rect_kind())
         };
+
+        if let Some(levels) = Self::dual_filter_levels(blur_target_size, blur_std_deviation) {
+            return RenderTask::new_dual_filter_blur(
+                levels,
+                blur_std_deviation,
+                src_task_id,
+                render_tasks,
+                target_kind,
+                clear_mode,
+                blur_cache,
+            );
+        }
+
+        // Adjust large std deviation value.
+        let mut adjusted_blur_std_deviation = blur_std_deviation;
         let mut adjusted_blur_target_size = blur_target_size;
         let mut downscaling_src_task_id = src_task_id;
         let mut scale_factor = 1.0;
@@ -777,6 +1238,119 @@ impl RenderTask {
         blur_task_id
     }
 
+    /// Returns the number of levels of the dual filter pyramid to blur a task of
+    /// the given size with, or None if the blur should use gaussian passes.
+    fn dual_filter_levels(size: DeviceIntSize, std_dev: DeviceSize) -> Option<u32> {
+        let min_std_dev = std_dev.width.min(std_dev.height);
+        if min_std_dev < MIN_DUAL_FILTER_STD_DEVIATION {
+            return None;
+        }
+
+        // Each level of the pyramid roughly doubles the std deviation of the blur.
+        let mut levels = min_std_dev.log2().floor() as u32;
+        while levels > 0 && (size.width.min(size.height) >> levels) < MIN_DOWNSCALING_RT_SIZE {
+            levels -= 1;
+        }
+
+        if levels == 0 {
+            return None;
+        }
+
+        let max_std_dev = std_dev.width.max(std_dev.height);
+        if Self::dual_filter_offset_scale(max_std_dev, levels) > MAX_DUAL_FILTER_OFFSET_SCALE {
+            return None;
+        }
+
+        Some(levels)
+    }
+
+    /// Returns the factor to scale the sample offsets of each pass of a dual
+    /// filter pyramid with the given number of levels by, so that the whole
+    /// pyramid approximates a gaussian of the given std deviation.
+    fn dual_filter_offset_scale(std_dev: f32, levels: u32) -> f32 {
+        // With an offset scale of s (in texels of the source of a pass), the taps
+        // of a down pass have a variance of s^2 / 2 per axis, and the bilinear
+        // fetch at the center of a 2x2 block adds 1/4. The taps of an up pass
+        // have a variance of s^2 / 3, and the bilinear up-scaling adds 3/16.
+        // Each level is twice as coarse as the previous one, so summed over the
+        // pyramid the variance is about 4^levels / 3 * (11 / 6 * s^2 + 1).
+        // Solve that for s. The std deviation is at least 2^levels, so this
+        // never goes negative.
+        let scale = (1 << (2 * levels)) as f32;
+        ((3.0 * std_dev * std_dev / scale - 1.0) * 6.0 / 11.0).sqrt()
+    }
+
+    // Construct a dual filter blur (Marius Bjørge, "Bandwidth-Efficient Rendering",
+    // SIGGRAPH 2015) for large std deviations, which is much cheaper than gaussian
+    // passes on the source since most of the work happens on small targets.
+    // The render task chain that is constructed looks like:
+    //
+    //    PrimitiveCacheTask: Draw the primitives.
+    //           ^
+    //           |
+    //    DualFilterBlur(Down)(s): Each pass reduces the size of the render target
+    //           ^                 to half, blurring with a 5 tap kernel.
+    //           |
+    //    DualFilterBlur(Up)(s): Each pass doubles the size of the render target
+    //           |               back up to the source size, blurring with an 8 tap kernel.
+    //           |
+    //           +---- This is stored as the input task to the primitive shader.
+    //
+    fn new_dual_filter_blur(
+        levels: u32,
+        blur_std_deviation: DeviceSize,
+        src_task_id: RenderTaskId,
+        render_tasks: &mut RenderTaskGraph,
+        target_kind: RenderTargetKind,
+        clear_mode: ClearMode,
+        mut blur_cache: Option<&mut BlurTaskCache>,
+    ) -> RenderTaskId {
+        let blur_key = BlurTaskKey::dual_filter(levels, blur_std_deviation);
+        if let Some(task_id) = blur_cache.as_ref().and_then(|cache| cache.get(&blur_key)) {
+            return *task_id;
+        }
+
+        let (src_size, uv_rect_kind) = {
+            let src_task = &render_tasks[src_task_id];
+            (src_task.get_dynamic_size(), src_task.uv_rect_kind())
+        };
+
+        let offset_scale = DeviceVector2D::new(
+            Self::dual_filter_offset_scale(blur_std_deviation.width, levels),
+            Self::dual_filter_offset_scale(blur_std_deviation.height, levels),
+        );
+        let level_sizes: Vec<DeviceIntSize> = (0 ..= levels)
+            .map(|level| (src_size.to_f32() / (1 << level) as f32).ceil().to_i32())
+            .collect();
+
+        let passes = (1 ..= levels as usize)
+            .map(|level| (DualFilterPass::Down, level_sizes[level]))
+            .chain((0 .. levels as usize).rev().map(|level| (DualFilterPass::Up, level_sizes[level])));
+
+        let mut task_id = src_task_id;
+        for (pass, size) in passes {
+            let task = RenderTask::with_dynamic_location(
+                size,
+                vec![task_id],
+                RenderTaskKind::DualFilterBlur(DualFilterTask {
+                    pass,
+                    offset_scale,
+                    target_kind,
+                    uv_rect_handle: GpuCacheHandle::new(),
+                    uv_rect_kind,
+                }),
+                clear_mode,
+            );
+            task_id = render_tasks.add(task);
+        }
+
+        if let Some(ref mut cache) = blur_cache {
+            cache.insert(blur_key, task_id);
+        }
+
+        task_id
+    }
+
     pub fn new_border_segment(
         size: DeviceIntSize,
         instances: Vec<BorderInstance>,
@@ -839,6 +1413,10 @@ impl RenderTask {
         uv_rect_kind: UvRectKind,
         original_task_id: RenderTaskId,
         device_pixel_scale: DevicePixelScale,
+        filter_region: PictureRect,
+        content_origin: PicturePoint,
+        resource_cache: &mut ResourceCache,
+        gpu_cache: &mut GpuCache,
     ) -> RenderTaskId {
 
         if filter_primitives.is_empty() {
@@ -940,7 +1518,10 @@ impl RenderTask {
                     render_tasks.add(task)
                 }
                 FilterPrimitiveKind::Blur(ref blur) => {
-                    let blur_std_deviation = blur.radius * device_pixel_scale.0;
+                    let blur_std_deviation = DeviceSize::new(
+                        blur.width * device_pixel_scale.0,
+                        blur.height * device_pixel_scale.0,
+                    );
                     let input_task_id = get_task_input(
                         &blur.input,
                         filter_primitives,
@@ -960,7 +1541,7 @@ impl RenderTask {
                     );
 
                     RenderTask::new_blur(
-                        DeviceSize::new(blur_std_deviation, blur_std_deviation),
+                        blur_std_deviation,
                         render_tasks.add(svg_task),
                         render_tasks,
                         RenderTargetKind::Color,
@@ -1120,6 +1701,289 @@ impl RenderTask {
                     );
                     render_tasks.add(task)
                 }
+                FilterPrimitiveKind::Morphology(ref info) => {
+                    let input_task_id = get_task_input(
+                        &info.input,
+                        filter_primitives,
+                        render_tasks,
+                        cur_index,
+                        &outputs,
+                        original_task_id,
+                        primitive.color_space
+                    );
+
+                    // The structuring element is a rectangle, so the filter is
+                    // applied as a horizontal pass followed by a vertical one.
+                    // A radius that isn't positive disables the pass on its axis.
+                    let radius_x = (info.radius_x * device_pixel_scale.0).round().min(MAX_MORPHOLOGY_RADIUS);
+                    let radius_y = (info.radius_y * device_pixel_scale.0).round().min(MAX_MORPHOLOGY_RADIUS);
+                    let passes = [
+                        DeviceVector2D::new(radius_x, 0.0),
+                        DeviceVector2D::new(0.0, radius_y),
+                    ];
+
+                    let mut task_id = input_task_id;
+                    for radius in passes.iter().filter(|radius| radius.x > 0.0 || radius.y > 0.0) {
+                        let task = RenderTask::new_svg_filter_primitive(
+                            vec![task_id],
+                            content_size,
+                            uv_rect_kind,
+                            SvgFilterInfo::Morphology(info.operator, *radius),
+                        );
+                        task_id = render_tasks.add(task);
+                    }
+                    task_id
+                }
+                FilterPrimitiveKind::ConvolveMatrix(ref info) => {
+                    let input_task_id = get_task_input(
+                        &info.input,
+                        filter_primitives,
+                        render_tasks,
+                        cur_index,
+                        &outputs,
+                        original_task_id,
+                        primitive.color_space
+                    );
+
+                    let task = RenderTask::new_svg_filter_primitive(
+                        vec![input_task_id],
+                        content_size,
+                        uv_rect_kind,
+                        SvgFilterInfo::ConvolveMatrix(Box::new(*info)),
+                    );
+                    render_tasks.add(task)
+                }
+                FilterPrimitiveKind::DisplacementMap(ref info) => {
+                    let input_1_task_id = get_task_input(
+                        &info.input1,
+                        filter_primitives,
+                        render_tasks,
+                        cur_index,
+                        &outputs,
+                        original_task_id,
+                        primitive.color_space
+                    );
+                    let input_2_task_id = get_task_input(
+                        &info.input2,
+                        filter_primitives,
+                        render_tasks,
+                        cur_index,
+                        &outputs,
+                        original_task_id,
+                        primitive.color_space
+                    );
+
+                    let task = RenderTask::new_svg_filter_primitive(
+                        vec![input_1_task_id, input_2_task_id],
+                        content_size,
+                        uv_rect_kind,
+                        SvgFilterInfo::DisplacementMap(
+                            info.scale * device_pixel_scale.0,
+                            info.x_channel_selector,
+                            info.y_channel_selector,
+                        ),
+                    );
+                    render_tasks.add(task)
+                }
+                FilterPrimitiveKind::DiffuseLighting(ref info) => {
+                    let input_task_id = get_task_input(
+                        &info.input,
+                        filter_primitives,
+                        render_tasks,
+                        cur_index,
+                        &outputs,
+                        original_task_id,
+                        primitive.color_space
+                    );
+
+                    let task = RenderTask::new_svg_filter_primitive(
+                        vec![input_task_id],
+                        content_size,
+                        uv_rect_kind,
+                        SvgFilterInfo::DiffuseLighting(Box::new(LightingInfo {
+                            surface_scale: info.surface_scale,
+                            constant: info.diffuse_constant,
+                            specular_exponent: 1.0,
+                            color: info.lighting_color,
+                            light: info.light,
+                            origin: content_origin,
+                            pixel_size: 1.0 / device_pixel_scale.0,
+                        })),
+                    );
+                    render_tasks.add(task)
+                }
+                FilterPrimitiveKind::SpecularLighting(ref info) => {
+                    let input_task_id = get_task_input(
+                        &info.input,
+                        filter_primitives,
+                        render_tasks,
+                        cur_index,
+                        &outputs,
+                        original_task_id,
+                        primitive.color_space
+                    );
+
+                    let task = RenderTask::new_svg_filter_primitive(
+                        vec![input_task_id],
+                        content_size,
+                        uv_rect_kind,
+                        SvgFilterInfo::SpecularLighting(Box::new(LightingInfo {
+                            surface_scale: info.surface_scale,
+                            constant: info.specular_constant,
+                            specular_exponent: info.specular_exponent,
+                            color: info.lighting_color,
+                            light: info.light,
+                            origin: content_origin,
+                            pixel_size: 1.0 / device_pixel_scale.0,
+                        })),
+                    );
+                    render_tasks.add(task)
+                }
+                FilterPrimitiveKind::Image(ref info) => {
+                    let image_size = DeviceIntSize::new(
+                        (info.rect.size.width * device_pixel_scale.0).ceil() as i32,
+                        (info.rect.size.height * device_pixel_scale.0).ceil() as i32,
+                    );
+                    // Tiled images would need a task per tile, which isn't
+                    // supported, so they are left out like missing images.
+                    let is_drawable = !image_size.is_empty_or_negative() &&
+                        resource_cache
+                            .get_image_properties(info.image_key)
+                            .map_or(false, |properties| properties.tiling.is_none());
+
+                    if is_drawable {
+                        let request = ImageRequest {
+                            key: info.image_key,
+                            rendering: ImageRendering::Auto,
+                            tile: None,
+                        };
+                        resource_cache.request_image(request, gpu_cache);
+
+                        // Copy the image out of the texture cache, at the size
+                        // it is drawn at.
+                        let image_task = RenderTask::new_scaling_with_padding(
+                            BlitSource::Image {
+                                key: ImageCacheKey {
+                                    request,
+                                    texel_rect: None,
+                                },
+                            },
+                            render_tasks,
+                            RenderTargetKind::Color,
+                            image_size,
+                            DeviceIntSideOffsets::zero(),
+                        );
+                        let image_task_id = render_tasks.add(image_task);
+
+                        let task = RenderTask::new_svg_filter_primitive(
+                            vec![image_task_id],
+                            content_size,
+                            uv_rect_kind,
+                            SvgFilterInfo::Image(Box::new(FilterRectInfo {
+                                rect: info.rect * Scale::new(1.0),
+                                origin: content_origin,
+                                pixel_size: 1.0 / device_pixel_scale.0,
+                            })),
+                        );
+                        let mut task_id = render_tasks.add(task);
+
+                        // Images are in sRGB.
+                        if primitive.color_space == ColorSpace::LinearRgb {
+                            let task = RenderTask::new_svg_filter_primitive(
+                                vec![task_id],
+                                content_size,
+                                uv_rect_kind,
+                                SvgFilterInfo::SrgbToLinear,
+                            );
+                            task_id = render_tasks.add(task);
+                        }
+                        task_id
+                    } else {
+                        let task = RenderTask::new_svg_filter_primitive(
+                            vec![],
+                            content_size,
+                            uv_rect_kind,
+                            SvgFilterInfo::Flood(ColorF::TRANSPARENT),
+                        );
+                        render_tasks.add(task)
+                    }
+                }
+                FilterPrimitiveKind::Tile(ref info) => {
+                    let input_task_id = get_task_input(
+                        &info.input,
+                        filter_primitives,
+                        render_tasks,
+                        cur_index,
+                        &outputs,
+                        original_task_id,
+                        primitive.color_space
+                    );
+
+                    let task = RenderTask::new_svg_filter_primitive(
+                        vec![input_task_id],
+                        content_size,
+                        uv_rect_kind,
+                        SvgFilterInfo::Tile(Box::new(FilterRectInfo {
+                            rect: info.source_rect * Scale::new(1.0),
+                            origin: content_origin,
+                            pixel_size: 1.0 / device_pixel_scale.0,
+                        })),
+                    );
+                    render_tasks.add(task)
+                }
+                FilterPrimitiveKind::Merge(ref info) => {
+                    // Merging is the same as compositing each input over
+                    // the result of merging the previous ones.
+                    let mut merged_task_id = None;
+                    for input in info.inputs() {
+                        let input_task_id = get_task_input(
+                            input,
+                            filter_primitives,
+                            render_tasks,
+                            cur_index,
+                            &outputs,
+                            original_task_id,
+                            primitive.color_space
+                        );
+
+                        merged_task_id = Some(match merged_task_id {
+                            Some(below_task_id) => {
+                                let task = RenderTask::new_svg_filter_primitive(
+                                    vec![input_task_id, below_task_id],
+                                    content_size,
+                                    uv_rect_kind,
+                                    SvgFilterInfo::Composite(CompositeOperator::Over),
+                                );
+                                render_tasks.add(task)
+                            }
+                            None => input_task_id,
+                        });
+                    }
+
+                    merged_task_id.unwrap_or_else(|| {
+                        let task = RenderTask::new_svg_filter_primitive(
+                            vec![],
+                            content_size,
+                            uv_rect_kind,
+                            SvgFilterInfo::Flood(ColorF::TRANSPARENT),
+                        );
+                        render_tasks.add(task)
+                    })
+                }
+                FilterPrimitiveKind::Turbulence(ref info) => {
+                    let task = RenderTask::new_svg_filter_primitive(
+                        vec![],
+                        content_size,
+                        uv_rect_kind,
+                        SvgFilterInfo::Turbulence(Box::new(TurbulenceInfo {
+                            primitive: *info,
+                            origin: content_origin,
+                            pixel_size: 1.0 / device_pixel_scale.0,
+                            tile_rect: filter_region,
+                        })),
+                    );
+                    render_tasks.add(task)
+                }
             };
             outputs.push(render_task_id);
         }
@@ -1176,6 +2040,10 @@ impl RenderTask {
                 task.uv_rect_kind
             }
 
+            RenderTaskKind::DualFilterBlur(ref task) => {
+                task.uv_rect_kind
+            }
+
             RenderTaskKind::Scaling(ref task) => {
                 task.uv_rect_kind
             }
@@ -1188,6 +2056,9 @@ impl RenderTask {
             RenderTaskKind::Border(..) |
             RenderTaskKind::Gradient(..) |
             RenderTaskKind::LineDecoration(..) |
+            RenderTaskKind::VectorStencil(..) |
+            RenderTaskKind::VectorCover(..) |
+            RenderTaskKind::TextMask(..) |
             RenderTaskKind::Blit(..) => {
                 UvRectKind::Rect
             }
@@ -1242,10 +2113,20 @@ impl RenderTask {
                     task.blur_region.height as f32,
                 ]
             }
+            RenderTaskKind::DualFilterBlur(ref task) => {
+                [
+                    task.pass as u32 as f32,
+                    task.offset_scale.x,
+                    task.offset_scale.y,
+                ]
+            }
             RenderTaskKind::Readback(..) |
             RenderTaskKind::Scaling(..) |
             RenderTaskKind::Border(..) |
             RenderTaskKind::LineDecoration(..) |
+            RenderTaskKind::VectorStencil(..) |
+            RenderTaskKind::VectorCover(..) |
+            RenderTaskKind::TextMask(..) |
             RenderTaskKind::Gradient(..) |
             RenderTaskKind::Blit(..) => {
                 [0.0; 3]
@@ -1256,6 +2137,13 @@ impl RenderTask {
                 match task.info {
                     SvgFilterInfo::Opacity(opacity) => [opacity, 0.0, 0.0],
                     SvgFilterInfo::Offset(offset) => [offset.x, offset.y, 0.0],
+                    SvgFilterInfo::Morphology(_, radius) => [radius.x, radius.y, 0.0],
+                    SvgFilterInfo::Turbulence(ref info) => [info.origin.x, info.origin.y, info.pixel_size],
+                    SvgFilterInfo::DisplacementMap(scale, ..) => [scale, 0.0, 0.0],
+                    SvgFilterInfo::DiffuseLighting(ref info) |
+                    SvgFilterInfo::SpecularLighting(ref info) => [info.origin.x, info.origin.y, info.pixel_size],
+                    SvgFilterInfo::Image(ref info) |
+                    SvgFilterInfo::Tile(ref info) => [info.origin.x, info.origin.y, info.pixel_size],
                     _ => [0.0; 3]
                 }
             }
@@ -1297,6 +2185,9 @@ impl RenderTask {
             RenderTaskKind::HorizontalBlur(ref info) => {
                 gpu_cache.get_address(&info.uv_rect_handle)
             }
+            RenderTaskKind::DualFilterBlur(ref info) => {
+                gpu_cache.get_address(&info.uv_rect_handle)
+            }
             RenderTaskKind::SvgFilter(ref info) => {
                 gpu_cache.get_address(&info.uv_rect_handle)
             }
@@ -1307,7 +2198,10 @@ impl RenderTask {
             RenderTaskKind::Border(..) |
             RenderTaskKind::CacheMask(..) |
             RenderTaskKind::Gradient(..) |
-            RenderTaskKind::LineDecoration(..) => {
+            RenderTaskKind::LineDecoration(..) |
+            RenderTaskKind::VectorStencil(..) |
+            RenderTaskKind::VectorCover(..) |
+            RenderTaskKind::TextMask(..) => {
                 panic!("texture handle not supported for this task kind");
             }
             #[cfg(test)]
@@ -1354,13 +2248,13 @@ impl RenderTask {
             RenderTaskLocation::TextureCache {layer, rect, .. } => {
                 (rect, RenderTargetIndex(layer as usize))
             }
-            RenderTaskLocation::PictureCache { size, layer, .. } => {
+            RenderTaskLocation::PictureCache { ref surface, size } => {
                 (
                     DeviceIntRect::new(
                         DeviceIntPoint::zero(),
                         size,
                     ),
-                    RenderTargetIndex(layer as usize),
+                    RenderTargetIndex(surface.layer() as usize),
                 )
             }
         }
@@ -1369,6 +2263,9 @@ impl RenderTask {
     pub fn target_kind(&self) -> RenderTargetKind {
         match self.kind {
             RenderTaskKind::LineDecoration(..) |
+            RenderTaskKind::VectorStencil(..) |
+            RenderTaskKind::VectorCover(..) |
+            RenderTaskKind::TextMask(..) |
             RenderTaskKind::Readback(..) |
             RenderTaskKind::Border(..) |
             RenderTaskKind::Gradient(..) |
@@ -1388,6 +2285,10 @@ impl RenderTask {
                 task_info.target_kind
             }
 
+            RenderTaskKind::DualFilterBlur(ref task_info) => {
+                task_info.target_kind
+            }
+
             RenderTaskKind::Scaling(ref task_info) => {
                 task_info.target_kind
             }
@@ -1408,6 +2309,9 @@ impl RenderTask {
             RenderTaskKind::VerticalBlur(ref mut info) => {
                 (&mut info.uv_rect_handle, info.uv_rect_kind)
             }
+            RenderTaskKind::DualFilterBlur(ref mut info) => {
+                (&mut info.uv_rect_handle, info.uv_rect_kind)
+            }
             RenderTaskKind::Picture(ref mut info) => {
                 (&mut info.uv_rect_handle, info.uv_rect_kind)
             }
@@ -1421,7 +2325,10 @@ impl RenderTask {
             RenderTaskKind::Border(..) |
             RenderTaskKind::CacheMask(..) |
             RenderTaskKind::Gradient(..) |
-            RenderTaskKind::LineDecoration(..) => {
+            RenderTaskKind::LineDecoration(..) |
+            RenderTaskKind::VectorStencil(..) |
+            RenderTaskKind::VectorCover(..) |
+            RenderTaskKind::TextMask(..) => {
                 return;
             }
             #[cfg(test)]
@@ -1474,6 +2381,53 @@ impl RenderTask {
                         }
                     }
                 }
+                SvgFilterInfo::ConvolveMatrix(ref info) => {
+                    let handle = filter_task.extra_gpu_cache_handle.get_or_insert_with(|| GpuCacheHandle::new());
+                    if let Some(mut request) = gpu_cache.request(handle) {
+                        request.push([
+                            info.order[0] as f32,
+                            info.order[1] as f32,
+                            info.target[0] as f32,
+                            info.target[1] as f32,
+                        ]);
+                        request.push([
+                            info.divisor,
+                            info.bias,
+                            info.edge_mode.as_int() as f32,
+                            if info.preserve_alpha { 1.0 } else { 0.0 },
+                        ]);
+                        for values in info.kernel.chunks(4) {
+                            let mut block = [0.0; 4];
+                            block[.. values.len()].copy_from_slice(values);
+                            request.push(block);
+                        }
+                    }
+                }
+                SvgFilterInfo::Turbulence(ref info) => {
+                    let handle = filter_task.extra_gpu_cache_handle.get_or_insert_with(|| GpuCacheHandle::new());
+                    if let Some(request) = gpu_cache.request(handle) {
+                        info.write_gpu_blocks(request);
+                    }
+                }
+                SvgFilterInfo::DiffuseLighting(ref info) |
+                SvgFilterInfo::SpecularLighting(ref info) => {
+                    let handle = filter_task.extra_gpu_cache_handle.get_or_insert_with(|| GpuCacheHandle::new());
+                    if let Some(request) = gpu_cache.request(handle) {
+                        info.write_gpu_blocks(request);
+                    }
+                }
+                SvgFilterInfo::Image(ref info) |
+                SvgFilterInfo::Tile(ref info) => {
+                    let handle = filter_task.extra_gpu_cache_handle.get_or_insert_with(|| GpuCacheHandle::new());
+                    if let Some(mut request) = gpu_cache.request(handle) {
+                        request.push([
+                            info.rect.origin.x,
+                            info.rect.origin.y,
+                            info.rect.size.width,
+                            info.rect.size.height,
+                        ]);
+                    }
+                }
                 _ => {},
             }
         }
@@ -1492,6 +2446,18 @@ impl RenderTask {
             RenderTaskKind::LineDecoration(..) => {
                 pt.new_level("LineDecoration".to_owned());
             }
+            RenderTaskKind::VectorStencil(ref task) => {
+                pt.new_level("VectorStencil".to_owned());
+                pt.add_item(format!("segments: {}", task.segments.len()));
+            }
+            RenderTaskKind::VectorCover(ref task) => {
+                pt.new_level("VectorCover".to_owned());
+                pt.add_item(format!("fill_rule: {:?}", task.fill_rule));
+            }
+            RenderTaskKind::TextMask(ref task) => {
+                pt.new_level("TextMask".to_owned());
+                pt.add_item(format!("glyphs: {}", task.glyph_keys.len()));
+            }
             RenderTaskKind::ClipRegion(..) => {
                 pt.new_level("ClipRegion".to_owned());
             }
@@ -1503,6 +2469,10 @@ impl RenderTask {
                 pt.new_level("HorizontalBlur".to_owned());
                 task.print_with(pt);
             }
+            RenderTaskKind::DualFilterBlur(ref task) => {
+                pt.new_level("DualFilterBlur".to_owned());
+                task.print_with(pt);
+            }
             RenderTaskKind::Readback(ref rect) => {
                 pt.new_level("Readback".to_owned());
                 pt.add_item(format!("rect: {:?}", rect));
@@ -14,6 +14,8 @@ use crate::internal_types::FastHashMap;
 use crate::prim_store::image::ImageCacheKey;
 use crate::prim_store::gradient::GradientCacheKey;
 use crate::prim_store::line_dec::LineDecorationCacheKey;
+use crate::prim_store::text_run::TextClipCacheKey;
+use crate::prim_store::vector_path::VectorPathCacheKey;
 use crate::resource_cache::CacheItem;
 use std::{mem, usize, f32, i32};
 use crate::texture_cache::{TextureCache, TextureCacheHandle, Eviction};
@@ -29,6 +31,8 @@ pub enum RenderTaskCacheKeyKind {
     Image(ImageCacheKey),
     BorderSegment(BorderSegmentCacheKey),
     LineDecoration(LineDecorationCacheKey),
+    VectorPath(VectorPathCacheKey),
+    TextClip(TextClipCacheKey),
     Gradient(GradientCacheKey),
 }
 
@@ -109,9 +113,14 @@ impl RenderTaskCache {
         });
     }
 
-    fn alloc_render_task(
+    /// Allocates space for a render task in the texture cache, and makes the
+    /// task draw into it. This is also used for texture cache entries that
+    /// are owned outside of this cache, like snapshot images.
+    pub fn alloc_render_task(
         render_task: &mut RenderTask,
-        entry: &mut RenderTaskCacheEntry,
+        handle: &mut TextureCacheHandle,
+        user_data: Option<[f32; 3]>,
+        is_opaque: bool,
         gpu_cache: &mut GpuCache,
         texture_cache: &mut TextureCache,
     ) {
@@ -135,7 +144,7 @@ impl RenderTaskCache {
             size.width,
             size.height,
             image_format,
-            entry.is_opaque,
+            is_opaque,
             false,
         );
 
@@ -150,11 +159,11 @@ impl RenderTaskCache {
         // more expensive than borders, for example). Telemetry could
         // inform our decisions here as well.
         texture_cache.update(
-            &mut entry.handle,
+            handle,
             descriptor,
             TextureFilter::Linear,
             None,
-            entry.user_data.unwrap_or([0.0; 3]),
+            user_data.unwrap_or([0.0; 3]),
             DirtyRect::All,
             gpu_cache,
             None,
@@ -167,7 +176,7 @@ impl RenderTaskCache {
         // task into the appropriate layer and rect of the texture
         // cache on this frame.
         let (texture_id, texture_layer, uv_rect, _, _) =
-            texture_cache.get_cache_location(&entry.handle);
+            texture_cache.get_cache_location(handle);
 
         render_task.location = RenderTaskLocation::TextureCache {
             texture: texture_id,
@@ -214,7 +223,9 @@ impl RenderTaskCache {
 
             RenderTaskCache::alloc_render_task(
                 &mut render_tasks[render_task_id],
-                cache_entry,
+                &mut cache_entry.handle,
+                cache_entry.user_data,
+                cache_entry.is_opaque,
                 gpu_cache,
                 texture_cache,
             );
@@ -254,6 +254,12 @@ impl RenderTaskGraph {
                     continue;
                 }
 
+                if let RenderTaskLocation::TextureCache { .. } = self.tasks[child_task_index].location {
+                    // Tasks rendering into the texture cache (e.g. snapshots) only need
+                    // to run in an earlier pass, their output stays available.
+                    continue;
+                }
+
                 // TODO: Picture tasks don't support having their dependency tasks redirected.
                 // Pictures store their respective render task(s) on their SurfaceInfo.
                 // We cannot blit the picture task here because we would need to update the
@@ -863,3 +869,85 @@ fn culled_tasks() {
     assert_eq!(passes[1].tasks, vec![b2]);
     assert_eq!(passes[2].tasks, vec![main_pic]);
 }
+
+#[test]
+fn dual_filter_blur_task_graph() {
+    // Large blurs are built as a chain of down and up passes, each of which
+    // ends up in its own pass.
+    use crate::render_task::{ClearMode, DualFilterPass};
+    use api::units::DeviceSize;
+
+    let color = RenderTargetKind::Color;
+
+    let counters = RenderTaskGraphCounters::new();
+    let mut tasks = RenderTaskGraph::new(FrameId::first(), &counters);
+
+    let pic = tasks.add(RenderTask::new_test(color, dyn_location(640, 640), Vec::new()));
+    let src = tasks.add(RenderTask::new_blit(size2(640, 640), BlitSource::RenderTask { task_id: pic }));
+
+    let blur = RenderTask::new_blur(
+        DeviceSize::new(40.0, 40.0),
+        src,
+        &mut tasks,
+        color,
+        ClearMode::Transparent,
+        None,
+        size2(640, 640),
+    );
+
+    let main_pic = tasks.add(RenderTask::new_test(
+        color,
+        RenderTaskLocation::Fixed(rect(0, 0, 3200, 1800)),
+        vec![blur],
+    ));
+
+    let passes = tasks.generate_passes(Some(main_pic), size2(3200, 1800), true);
+
+    // Five levels down to 20x20 and back up again.
+    assert_eq!(passes.len(), 13);
+    assert_eq!(passes[0].tasks, vec![pic]);
+    assert_eq!(passes[1].tasks, vec![src]);
+    assert_eq!(passes[12].tasks, vec![main_pic]);
+
+    let expected_levels = [
+        (DualFilterPass::Down, 320),
+        (DualFilterPass::Down, 160),
+        (DualFilterPass::Down, 80),
+        (DualFilterPass::Down, 40),
+        (DualFilterPass::Down, 20),
+        (DualFilterPass::Up, 40),
+        (DualFilterPass::Up, 80),
+        (DualFilterPass::Up, 160),
+        (DualFilterPass::Up, 320),
+        (DualFilterPass::Up, 640),
+    ];
+    for (pass, &(expected_pass, expected_size)) in passes[2 .. 12].iter().zip(expected_levels.iter()) {
+        assert_eq!(pass.tasks.len(), 1);
+        let task = &tasks[pass.tasks[0]];
+        match task.kind {
+            RenderTaskKind::DualFilterBlur(ref info) => {
+                assert_eq!(info.pass, expected_pass);
+                // sqrt((3 * 40^2 / 4^5 - 1) * 6 / 11)
+                assert!((info.offset_scale.x - 1.4182).abs() < 0.001);
+            }
+            _ => { panic!("This should be a dual filter blur task."); }
+        }
+        assert_eq!(task.get_dynamic_size(), size2(expected_size, expected_size));
+    }
+    assert_eq!(passes[11].tasks, vec![blur]);
+
+    // Small blurs keep using the separable gaussian passes.
+    let small_blur = RenderTask::new_blur(
+        DeviceSize::new(4.0, 4.0),
+        src,
+        &mut tasks,
+        color,
+        ClearMode::Transparent,
+        None,
+        size2(640, 640),
+    );
+    match tasks[small_blur].kind {
+        RenderTaskKind::HorizontalBlur(..) => {}
+        _ => { panic!("This should be a horizontal blur task."); }
+    }
+}
@@ -37,9 +37,9 @@
 use api::{ApiMsg, BlobImageHandler, ColorF, ColorU, MixBlendMode};
 use api::{DocumentId, Epoch, ExternalImageId};
 use api::{ExternalImageType, FontRenderMode, FrameMsg, ImageFormat, PipelineId};
-use api::{ImageRendering, Checkpoint, NotificationRequest};
+use api::{ImageRendering, Checkpoint, MaskCompositeOperator, NotificationRequest};
 use api::{DebugCommand, MemoryReport, VoidPtrToSizeFn};
-use api::{RenderApiSender, RenderNotifier, TextureTarget};
+use api::{RenderApiSender, RenderNotifier, TextureTarget, TransactionMsg};
 use api::channel;
 use api::units::*;
 pub use api::DebugFlags;
@@ -47,7 +47,10 @@ use api::channel::{MsgSender, PayloadReceiverHelperMethods};
 use crate::batch::{AlphaBatchContainer, BatchKind, BatchFeatures, BatchTextures, BrushBatchKind, ClipBatchList};
 #[cfg(any(feature = "capture", feature = "replay"))]
 use crate::capture::{CaptureConfig, ExternalCaptureImage, PlainExternalImage};
-use crate::composite::{CompositeState, CompositeTileSurface, CompositeTile};
+use crate::composite::{CompositeState, CompositeTileSurface, CompositeTile, CompositorKind};
+use crate::composite::ResolvedSurfaceTexture;
+#[cfg(feature = "gl")]
+use crate::composite::{Compositor, CompositorSurfaceTransform, NativeSurfaceOperation};
 use crate::debug_colors;
 use crate::debug_render::{DebugItem, DebugRenderer};
 use crate::device::{DepthFunction, Device, GpuFrameId, UploadMethod, Texture, PBO};
@@ -84,6 +87,7 @@ use crate::render_task_graph::RenderTaskGraph;
 use crate::render_task::{RenderTask, RenderTaskData, RenderTaskKind};
 use crate::resource_cache::ResourceCache;
 use crate::scene_builder_thread::{SceneBuilderThread, LowPrioritySceneBuilderThread};
+use crate::scene_building::DisplayListLimits;
 use crate::screen_capture::AsyncScreenshotGrabber;
 use crate::shade::{Shaders, WrShaders};
 use smallvec::SmallVec;
@@ -95,7 +99,9 @@ use crate::render_task_graph::RenderPassKind;
 #[cfg(not(feature = "gl"))]
 use rendy_memory::HeapsConfig;
 #[cfg(not(feature="gl"))]
-use crate::device::{BufferMemorySlice, DrawTargetUsage};
+use crate::device::{BufferMemorySlice, ComputeBlurJob, DrawTargetUsage};
+#[cfg(not(feature = "gl"))]
+use crate::gpu_types::BlurInstance;
 #[cfg(feature = "replay")]
 use crate::device::IdType;
 #[cfg(not(feature = "gl"))]
@@ -162,6 +168,10 @@ const GPU_TAG_BRUSH_RADIAL_GRADIENT: GpuProfileTag = GpuProfileTag {
     label: "B_RadialGradient",
     color: debug_colors::LIGHTPINK,
 };
+const GPU_TAG_BRUSH_CONIC_GRADIENT: GpuProfileTag = GpuProfileTag {
+    label: "B_ConicGradient",
+    color: debug_colors::HOTPINK,
+};
 const GPU_TAG_BRUSH_YUV_IMAGE: GpuProfileTag = GpuProfileTag {
     label: "B_YuvImage",
     color: debug_colors::DARKGREEN,
@@ -198,6 +208,14 @@ const GPU_TAG_CACHE_GRADIENT: GpuProfileTag = GpuProfileTag {
     label: "C_Gradient",
     color: debug_colors::BROWN,
 };
+const GPU_TAG_CACHE_VECTOR_STENCIL: GpuProfileTag = GpuProfileTag {
+    label: "C_VectorStencil",
+    color: debug_colors::SEAGREEN,
+};
+const GPU_TAG_CACHE_VECTOR_COVER: GpuProfileTag = GpuProfileTag {
+    label: "C_VectorCover",
+    color: debug_colors::MEDIUMSEAGREEN,
+};
 const GPU_TAG_SETUP_TARGET: GpuProfileTag = GpuProfileTag {
     label: "target init",
     color: debug_colors::SLATEGREY,
@@ -265,6 +283,7 @@ impl BatchKind {
                     BrushBatchKind::MixBlend { .. } => "Brush (Composite)",
                     BrushBatchKind::YuvImage(..) => "Brush (YuvImage)",
                     BrushBatchKind::RadialGradient => "Brush (RadialGradient)",
+                    BrushBatchKind::ConicGradient => "Brush (ConicGradient)",
                     BrushBatchKind::LinearGradient => "Brush (LinearGradient)",
                 }
             }
@@ -283,6 +302,7 @@ impl BatchKind {
                     BrushBatchKind::MixBlend { .. } => GPU_TAG_BRUSH_MIXBLEND,
                     BrushBatchKind::YuvImage(..) => GPU_TAG_BRUSH_YUV_IMAGE,
                     BrushBatchKind::RadialGradient => GPU_TAG_BRUSH_RADIAL_GRADIENT,
+                    BrushBatchKind::ConicGradient => GPU_TAG_BRUSH_CONIC_GRADIENT,
                     BrushBatchKind::LinearGradient => GPU_TAG_BRUSH_LINEAR_GRADIENT,
                 }
             }
@@ -722,30 +742,10 @@ pub(crate) mod desc {
                 kind: VertexAttributeKind::F32,
             },
             VertexAttribute {
-                name: "aFromNormal",
-                count: 2,
-                kind: VertexAttributeKind::F32,
-            },
-            VertexAttribute {
-                name: "aCtrlNormal",
-                count: 2,
-                kind: VertexAttributeKind::F32,
-            },
-            VertexAttribute {
-                name: "aToNormal",
-                count: 2,
+                name: "aTaskRect",
+                count: 4,
                 kind: VertexAttributeKind::F32,
             },
-            VertexAttribute {
-                name: "aPathID",
-                count: 1,
-                kind: VertexAttributeKind::U16,
-            },
-            VertexAttribute {
-                name: "aPad",
-                count: 1,
-                kind: VertexAttributeKind::U16,
-            },
         ],
     };
 
@@ -759,24 +759,29 @@ pub(crate) mod desc {
         ],
         instance_attributes: &[
             VertexAttribute {
-                name: "aTargetRect",
+                name: "aTaskRect",
                 count: 4,
+                kind: VertexAttributeKind::F32,
+            },
+            VertexAttribute {
+                name: "aStencilTaskAddress",
+                count: 1,
                 kind: VertexAttributeKind::I32,
             },
             VertexAttribute {
-                name: "aStencilOrigin",
-                count: 2,
+                name: "aFillRule",
+                count: 1,
                 kind: VertexAttributeKind::I32,
             },
             VertexAttribute {
-                name: "aSubpixel",
+                name: "aPaintAddress",
                 count: 1,
-                kind: VertexAttributeKind::U16,
+                kind: VertexAttributeKind::I32,
             },
             VertexAttribute {
-                name: "aPad",
+                name: "aStopsAddress",
                 count: 1,
-                kind: VertexAttributeKind::U16,
+                kind: VertexAttributeKind::I32,
             },
         ],
     };
@@ -1821,6 +1826,8 @@ pub struct RendererVAOs {
     resolve_vao: VAO,
     svg_filter_vao: VAO,
     composite_vao: VAO,
+    vector_stencil_vao: VAO,
+    vector_cover_vao: VAO,
 }
 
 
@@ -1831,6 +1838,8 @@ pub struct RendererVAOs {
 /// one per OS window), and all instances share the same thread.
 pub struct Renderer<B: hal::Backend> {
     result_rx: Receiver<ResultMsg<B>>,
+    /// Used to request the next frame of running scroll animations.
+    api_tx: MsgSender<ApiMsg>,
     debug_server: Box<dyn DebugServer>,
     pub device: Device<B>,
     pending_texture_updates: Vec<TextureUpdateList>,
@@ -1943,6 +1952,12 @@ pub struct Renderer<B: hal::Backend> {
     /// final scene.
     present_config: Option<PresentConfig>,
 
+    /// The native compositor that picture cache tiles are handed to, if any.
+    /// When present, tiles are rendered directly into surfaces owned by the
+    /// compositor instead of the texture cache.
+    #[cfg(feature = "gl")]
+    compositor: Option<Box<dyn Compositor>>,
+
     /// If true, partial present state has been reset and everything needs to
     /// be drawn on the next render.
     force_redraw: bool,
@@ -1954,6 +1969,8 @@ pub enum RendererError {
     Thread(std::io::Error),
     Resource(ResourceCacheError),
     MaxTextureSize,
+    /// A native compositor was supplied without enabling picture caching.
+    CompositorRequiresPictureCaching,
 }
 
 impl From<ShaderError> for RendererError {
@@ -2001,6 +2018,13 @@ impl<B: hal::Backend> Renderer<B> {
     ) -> Result<(Self, RenderApiSender), RendererError> {
         HAS_BEEN_INITIALIZED.store(true, Ordering::SeqCst);
 
+        #[cfg(feature = "gl")]
+        {
+            if options.compositor.is_some() && !options.enable_picture_caching {
+                return Err(RendererError::CompositorRequiresPictureCaching);
+            }
+        }
+
         let (api_tx, api_rx) = channel::msg_channel()?;
         let (payload_tx, payload_rx) = channel::payload_channel()?;
         let (result_tx, result_rx) = channel();
@@ -2193,6 +2217,8 @@ impl<B: hal::Backend> Renderer<B> {
         let resolve_vao = device.create_vao_with_new_instances(&desc::RESOLVE, &prim_vao);
         let svg_filter_vao = device.create_vao_with_new_instances(&desc::SVG_FILTER, &prim_vao);
         let composite_vao = device.create_vao_with_new_instances(&desc::COMPOSITE, &prim_vao);
+        let vector_stencil_vao = device.create_vao_with_new_instances(&desc::VECTOR_STENCIL, &prim_vao);
+        let vector_cover_vao = device.create_vao_with_new_instances(&desc::VECTOR_COVER, &prim_vao);
         let texture_cache_upload_pbo = device.create_pbo();
 
         let texture_resolver = TextureResolver::new(&mut device);
@@ -2218,6 +2244,17 @@ impl<B: hal::Backend> Renderer<B> {
             (false, _) => FontRenderMode::Mono,
         };
 
+        #[cfg(feature = "gl")]
+        let compositor = options.compositor.take();
+        #[cfg(feature = "gl")]
+        let compositor_kind = if compositor.is_some() {
+            CompositorKind::Native
+        } else {
+            CompositorKind::Draw
+        };
+        #[cfg(not(feature = "gl"))]
+        let compositor_kind = CompositorKind::Draw;
+
         let config = FrameBuilderConfig {
             default_font_render_mode,
             dual_source_blending_is_enabled: true,
@@ -2230,6 +2267,8 @@ impl<B: hal::Backend> Renderer<B> {
             advanced_blend_is_coherent: ext_blend_equation_advanced_coherent,
             batch_lookback_count: options.batch_lookback_count,
             background_color: options.clear_color,
+            display_list_limits: options.display_list_limits,
+            compositor_kind,
         };
         info!("WR {:?}", config);
 
@@ -2415,6 +2454,7 @@ impl<B: hal::Backend> Renderer<B> {
 
         let mut renderer = Renderer {
             result_rx,
+            api_tx: api_tx.clone(),
             debug_server,
             device,
             active_documents: Vec::new(),
@@ -2452,6 +2492,8 @@ impl<B: hal::Backend> Renderer<B> {
                 line_vao,
                 svg_filter_vao,
                 composite_vao,
+                vector_stencil_vao,
+                vector_cover_vao,
             },
             transforms_texture,
             prim_header_i_texture,
@@ -2485,6 +2527,8 @@ impl<B: hal::Backend> Renderer<B> {
             shared_texture_cache_cleared: false,
             documents_seen: FastHashSet::default(),
             present_config,
+            #[cfg(feature = "gl")]
+            compositor,
             force_redraw: true,
         };
 
@@ -2816,6 +2860,11 @@ impl<B: hal::Backend> Renderer<B> {
             "Horizontal Blur",
             target.horizontal_blurs.len(),
         );
+        debug_target.add(
+            debug_server::BatchKind::Cache,
+            "Dual Filter Blur",
+            target.dual_filter_blurs.len(),
+        );
         debug_target.add(
             debug_server::BatchKind::Clip,
             "Slow Rectangles [p]",
@@ -2842,6 +2891,12 @@ impl<B: hal::Backend> Renderer<B> {
         for (_, items) in target.clip_batcher.secondary_clips.images.iter() {
             debug_target.add(debug_server::BatchKind::Clip, "Image mask [s]", items.len());
         }
+        for run in target.clip_batcher.primary_clips.composited_images.iter() {
+            debug_target.add(debug_server::BatchKind::Clip, "Composited image mask [p]", run.instances.len());
+        }
+        for run in target.clip_batcher.secondary_clips.composited_images.iter() {
+            debug_target.add(debug_server::BatchKind::Clip, "Composited image mask [s]", run.instances.len());
+        }
 
         debug_target
     }
@@ -2855,6 +2910,11 @@ impl<B: hal::Backend> Renderer<B> {
             "Scalings",
             target.scalings.len(),
         );
+        debug_target.add(
+            debug_server::BatchKind::Cache,
+            "Text Masks",
+            target.text_masks.values().map(|v| v.len()).sum(),
+        );
         debug_target.add(
             debug_server::BatchKind::Cache,
             "Readbacks",
@@ -2870,6 +2930,11 @@ impl<B: hal::Backend> Renderer<B> {
             "Horizontal Blur",
             target.horizontal_blurs.len(),
         );
+        debug_target.add(
+            debug_server::BatchKind::Cache,
+            "Dual Filter Blur",
+            target.dual_filter_blurs.len(),
+        );
         debug_target.add(
             debug_server::BatchKind::Cache,
             "SVG Filters",
@@ -2906,6 +2971,11 @@ impl<B: hal::Backend> Renderer<B> {
             "Horizontal Blur",
             target.horizontal_blurs.len(),
         );
+        debug_target.add(
+            debug_server::BatchKind::Cache,
+            "Dual Filter Blur",
+            target.dual_filter_blurs.len(),
+        );
 
         debug_target
     }
@@ -3137,7 +3207,7 @@ impl<B: hal::Backend> Renderer<B> {
             );
 
             let last_document_index = active_documents.len().max(1) - 1;
-            for (doc_index, (document_id, RenderedDocument { ref mut frame, .. })) in active_documents.iter_mut().enumerate() {
+            for (doc_index, (document_id, RenderedDocument { ref mut frame, ref mut has_scroll_animations, .. })) in active_documents.iter_mut().enumerate() {
                 if self.shared_texture_cache_cleared {
                     assert!(self.documents_seen.contains(&document_id),
                             "Cleared texture cache without sending new document frame.");
@@ -3195,6 +3265,19 @@ impl<B: hal::Backend> Renderer<B> {
                     mem::replace(&mut frame.recorded_dirty_regions, Vec::new());
                 results.recorded_dirty_regions.extend(dirty_regions);
 
+                // Scroll animations advance once per drawn frame, so ask the
+                // backend for the next one without involving the embedder.
+                if *has_scroll_animations {
+                    *has_scroll_animations = false;
+                    let msg = ApiMsg::UpdateDocuments(
+                        vec![*document_id],
+                        vec![TransactionMsg::generate_frame_message()],
+                    );
+                    if self.api_tx.send(msg).is_err() {
+                        warn!("Unable to request the next scroll animation frame");
+                    }
+                }
+
                 // If we're the last document, don't call end_pass here, because we'll
                 // be moving on to drawing the debug overlays. See the comment above
                 // the end_pass call in draw_frame about debug draw overlays
@@ -3439,6 +3522,20 @@ impl<B: hal::Backend> Renderer<B> {
         let mut upload_time = TimeProfileCounter::new("Resource upload time", false);
         upload_time.profile(|| {
             for update_list in pending_texture_updates.drain(..) {
+                // Native surfaces are only requested when there is a compositor.
+                #[cfg(feature = "gl")]
+                for op in update_list.native_surface_updates {
+                    let compositor = self.compositor.as_mut().expect("bug: no native compositor");
+                    match op {
+                        NativeSurfaceOperation::CreateSurface { id, size, is_opaque } => {
+                            compositor.create_surface(id, size, is_opaque);
+                        }
+                        NativeSurfaceOperation::DestroySurface { id } => {
+                            compositor.destroy_surface(id);
+                        }
+                    }
+                }
+
                 for allocation in update_list.allocations {
                     let old = match allocation.kind {
                         TextureCacheAllocationKind::Alloc(ref info) |
@@ -3818,6 +3915,65 @@ impl<B: hal::Backend> Renderer<B> {
         }
     }
 
+    /// Runs the blurs of the bound target through the compute pipeline of the
+    /// device where possible. This has to happen before the render pass of the
+    /// target begins. Returns the vertical and horizontal blurs that still have
+    /// to be drawn with `cs_blur`.
+    #[cfg(not(feature = "gl"))]
+    fn handle_compute_blurs(
+        &mut self,
+        vertical_blurs: &[BlurInstance],
+        horizontal_blurs: &[BlurInstance],
+        render_tasks: &RenderTaskGraph,
+        color_target: bool,
+    ) -> (Vec<BlurInstance>, Vec<BlurInstance>) {
+        if !self.device.supports_compute_blur() {
+            return (vertical_blurs.to_vec(), horizontal_blurs.to_vec());
+        }
+
+        let mut jobs = Vec::new();
+        let mut remaining = (Vec::new(), Vec::new());
+        for (blurs, fallback) in [
+            (vertical_blurs, &mut remaining.0),
+            (horizontal_blurs, &mut remaining.1),
+        ].iter_mut() {
+            for blur in blurs.iter() {
+                let task = &render_tasks.tasks[blur.task_address.0 as usize];
+                let src_task = &render_tasks.tasks[blur.src_task_address.0 as usize];
+                let blur_task = match task.kind {
+                    RenderTaskKind::VerticalBlur(ref info) |
+                    RenderTaskKind::HorizontalBlur(ref info) => info,
+                    _ => unreachable!(),
+                };
+                let (dest_rect, dest_layer) = task.get_target_rect();
+                let (src_rect, src_layer) = src_task.get_target_rect();
+                let job = ComputeBlurJob {
+                    src_rect,
+                    src_layer: src_layer.0 as i32,
+                    blur_region: blur_task.blur_region,
+                    dest_rect,
+                    dest_layer: dest_layer.0 as i32,
+                    direction: blur.blur_direction,
+                    std_deviation: blur_task.blur_std_deviation,
+                };
+                if job.is_supported() {
+                    jobs.push(job);
+                } else {
+                    fallback.push(*blur);
+                }
+            }
+        }
+
+        if !jobs.is_empty() {
+            let _timer = self.gpu_profile.start_timer(GPU_TAG_BLUR);
+            // The blurs must not be overwritten by the clear of the render pass.
+            self.device.clear_rt_if_needed();
+            self.device.dispatch_compute_blurs(&jobs, color_target);
+        }
+
+        remaining
+    }
+
     fn handle_scaling(
         &mut self,
         scalings: &FastHashMap<TextureSource, Vec<ScalingInstance>>,
@@ -3912,6 +4068,15 @@ impl<B: hal::Backend> Renderer<B> {
                     content_origin,
                 )
             });
+            // Native surfaces may share their FBO with other surfaces, so the
+            // clear has to stay within the surface even without a dirty rect.
+            #[cfg(feature = "gl")]
+            let scissor_rect = scissor_rect.or_else(|| match draw_target {
+                DrawTarget::NativeSurface { dimensions, .. } => {
+                    Some(draw_target.build_scissor_rect(Some(dimensions.into()), content_origin))
+                }
+                _ => None,
+            });
 
             #[cfg(feature = "gl")]
             {
@@ -4252,8 +4417,11 @@ impl<B: hal::Backend> Renderer<B> {
                 CompositeTileSurface::Clear => {
                     (TextureSource::Dummy, 0.0, ColorF::BLACK)
                 }
-                CompositeTileSurface::Texture { texture_id, texture_layer } => {
-                    (texture_id, texture_layer as f32, ColorF::WHITE)
+                CompositeTileSurface::Texture { surface: ResolvedSurfaceTexture::TextureCache { texture, layer } } => {
+                    (texture, layer as f32, ColorF::WHITE)
+                }
+                CompositeTileSurface::Texture { surface: ResolvedSurfaceTexture::NativeSurface { .. } } => {
+                    unreachable!("bug: native surfaces are not drawn by the renderer");
                 }
             };
             let textures = BatchTextures::color(texture);
@@ -4331,10 +4499,38 @@ impl<B: hal::Backend> Renderer<B> {
         self.device.enable_depth();
         self.device.enable_depth_write();
 
+        // Accumulate the damage of all documents drawn into the framebuffer.
+        let damage_rect = composite_state.damage_rect();
+        results.damage_rect = if clear_framebuffer {
+            damage_rect
+        } else {
+            match (results.damage_rect, damage_rect) {
+                (Some(a), Some(b)) => Some(a.union(&b)),
+                _ => None,
+            }
+        };
+
         // Determine the partial present mode for this frame, which is used during
         // framebuffer clears and calculating the clip rect for each tile that is drawn.
         let mut partial_present_mode = None;
 
+        // Without a present config, the gfx device keeps track of the contents
+        // of the swapchain images itself, so only the region that changed since
+        // the current image was last presented needs to be redrawn.
+        #[cfg(not(feature = "gl"))]
+        {
+            if self.present_config.is_none() && draw_target.is_default() {
+                let frame_damage = if self.force_redraw { None } else { damage_rect };
+                self.device.add_frame_damage(frame_damage);
+                if let Some(buffer_damage) = frame_damage.and_then(|rect| self.device.buffer_damage(rect)) {
+                    partial_present_mode = Some(PartialPresentMode::Single {
+                        dirty_rect: buffer_damage.to_f32(),
+                    });
+                }
+                self.force_redraw = false;
+            }
+        }
+
         if let Some(PresentConfig::PartialPresent { max_dirty_rects }) = self.present_config {
             // We can only use partial present if we have valid dirty rects and the
             // client hasn't reset partial present state since last frame.
@@ -4384,13 +4580,19 @@ impl<B: hal::Backend> Renderer<B> {
             match partial_present_mode {
                 Some(PartialPresentMode::Single { dirty_rect }) => {
                     // We have a single dirty rect, so clear only that
+                    #[cfg(feature = "gl")]
                     self.device.clear_target(
                         clear_color,
                         Some(1.0),
                         Some(draw_target.to_framebuffer_rect(dirty_rect.to_i32())),
-                        #[cfg(not(feature = "gl"))]
-                        false,
                     );
+                    // The gfx device can only clear rects within a render pass.
+                    #[cfg(not(feature = "gl"))]
+                    {
+                        if !dirty_rect.is_empty() {
+                            clear_rects.push(draw_target.to_framebuffer_rect(dirty_rect.to_i32()));
+                        }
+                    }
                 }
                 Some(PartialPresentMode::Multi) => {
                     // We have a dirty rect per tile, so clear each of them
@@ -4449,6 +4651,12 @@ impl<B: hal::Backend> Renderer<B> {
             let _ = transit_to_present;
         }
 
+        // Only the damaged region of the framebuffer is drawn to.
+        if let Some(PartialPresentMode::Single { dirty_rect }) = partial_present_mode {
+            self.device.enable_scissor();
+            self.device.set_scissor_rect(draw_target.to_framebuffer_rect(dirty_rect.to_i32()));
+        }
+
         // Draw opaque tiles first, front-to-back to get maxmum
         // z-reject efficiency.
         if !composite_state.opaque_tiles.is_empty() {
@@ -4492,6 +4700,11 @@ impl<B: hal::Backend> Renderer<B> {
             );
             self.gpu_profile.finish_sampler(transparent_sampler);
         }
+
+        if let Some(PartialPresentMode::Single { .. }) = partial_present_mode {
+            self.device.disable_scissor();
+        }
+
         #[cfg(not(feature = "gl"))]
         {
             if !transit_to_present {
@@ -4500,6 +4713,39 @@ impl<B: hal::Backend> Renderer<B> {
         }
     }
 
+    /// Hand the picture cache tiles of this frame to the native compositor.
+    /// The tiles have already been rendered into the compositor surfaces, so
+    /// all that's left is to describe where each of them is placed.
+    #[cfg(feature = "gl")]
+    fn composite_native(
+        &mut self,
+        composite_state: &CompositeState,
+        draw_target: DrawTarget,
+        device_size: DeviceIntSize,
+    ) {
+        let _gm = self.gpu_profile.start_marker("native composite");
+
+        // The compositor presents into whatever framebuffer is bound when the
+        // frame ends, which is the default one.
+        self.device.bind_draw_target(draw_target);
+
+        let compositor = self.compositor.as_mut().expect("bug: no native compositor");
+        compositor.begin_frame(device_size);
+
+        // Surfaces are added back to front, so opaque tiles go first.
+        for tile in composite_state.opaque_tiles.iter().chain(composite_state.alpha_tiles.iter()) {
+            if let CompositeTileSurface::Texture { surface: ResolvedSurfaceTexture::NativeSurface { id, .. } } = tile.surface {
+                let transform = CompositorSurfaceTransform::create_translation(
+                    tile.rect.origin.x,
+                    tile.rect.origin.y,
+                );
+                compositor.add_surface(id, transform, tile.clip_rect.round_out().to_i32());
+            }
+        }
+
+        compositor.end_frame();
+    }
+
     fn draw_color_target(
         &mut self,
         draw_target: DrawTarget,
@@ -4566,7 +4812,8 @@ impl<B: hal::Backend> Renderer<B> {
                     // on a mostly-unused last slice of a large texture array).
                     Some(draw_target.to_framebuffer_rect(target.used_rect()))
                 }
-                DrawTarget::Texture { .. } | DrawTarget::External { .. } => {
+                DrawTarget::Texture { .. } | DrawTarget::External { .. } |
+                DrawTarget::NativeSurface { .. } => {
                     None
                 }
             };
@@ -4590,6 +4837,17 @@ impl<B: hal::Backend> Renderer<B> {
         self.handle_blits(
             &target.blits, render_tasks, draw_target, &content_origin,
         );
+
+        #[cfg(not(feature = "gl"))]
+        let (vertical_blurs, horizontal_blurs) = self.handle_compute_blurs(
+            &target.vertical_blurs,
+            &target.horizontal_blurs,
+            render_tasks,
+            true,
+        );
+        #[cfg(feature = "gl")]
+        let (vertical_blurs, horizontal_blurs) = (&target.vertical_blurs[..], &target.horizontal_blurs[..]);
+
         #[cfg(not(feature = "gl"))]
         {
             if target_kind == TargetKind::Main || !target.empty_without_batches() {
@@ -4609,25 +4867,25 @@ impl<B: hal::Backend> Renderer<B> {
         // TODO(gw): In the future, consider having
         //           fast path blur shaders for common
         //           blur radii with fixed weights.
-        if !target.vertical_blurs.is_empty() || !target.horizontal_blurs.is_empty() {
+        if !vertical_blurs.is_empty() || !horizontal_blurs.is_empty() {
             let _timer = self.gpu_profile.start_timer(GPU_TAG_BLUR);
 
             self.set_blend(false, framebuffer_kind);
             self.shaders.borrow_mut().cs_blur_rgba8
                 .bind(&mut self.device, projection, &mut self.renderer_errors);
 
-            if !target.vertical_blurs.is_empty() {
+            if !vertical_blurs.is_empty() {
                 self.draw_instanced_batch(
-                    &target.vertical_blurs,
+                    &vertical_blurs,
                     VertexArrayKind::Blur,
                     &BatchTextures::no_texture(),
                     stats,
                 );
             }
 
-            if !target.horizontal_blurs.is_empty() {
+            if !horizontal_blurs.is_empty() {
                 self.draw_instanced_batch(
-                    &target.horizontal_blurs,
+                    &horizontal_blurs,
                     VertexArrayKind::Blur,
                     &BatchTextures::no_texture(),
                     stats,
@@ -4635,6 +4893,22 @@ impl<B: hal::Backend> Renderer<B> {
             }
         }
 
+        // Draw the levels of any dual filter blur pyramids.
+        if !target.dual_filter_blurs.is_empty() {
+            let _timer = self.gpu_profile.start_timer(GPU_TAG_BLUR);
+
+            self.set_blend(false, framebuffer_kind);
+            self.shaders.borrow_mut().cs_dual_filter_rgba8
+                .bind(&mut self.device, projection, &mut self.renderer_errors);
+
+            self.draw_instanced_batch(
+                &target.dual_filter_blurs,
+                VertexArrayKind::Blur,
+                &BatchTextures::no_texture(),
+                stats,
+            );
+        }
+
         self.handle_scaling(
             &target.scalings,
             projection,
@@ -4649,6 +4923,50 @@ impl<B: hal::Backend> Renderer<B> {
                 stats,
             );
         }
+
+        // Copy the glyphs of any text clips, keeping the highest coverage
+        // where the boxes of neighbouring glyphs overlap.
+        if !target.text_masks.is_empty() {
+            let _timer = self.gpu_profile.start_timer(GPU_TAG_SCALE);
+
+            self.set_blend(true, framebuffer_kind);
+            self.device.set_blend_mode_max();
+
+            self.shaders.borrow_mut().cs_scale
+                .bind(&mut self.device, projection, &mut self.renderer_errors);
+
+            for (source, instances) in &target.text_masks {
+                self.draw_instanced_batch(
+                    instances,
+                    VertexArrayKind::Scale,
+                    &BatchTextures::color(*source),
+                    stats,
+                );
+            }
+
+            self.set_blend(false, framebuffer_kind);
+        }
+
+        // Accumulate the winding of any vector paths. Each segment adds
+        // its signed area on top of the others.
+        if !target.vector_stencils.is_empty() {
+            let _timer = self.gpu_profile.start_timer(GPU_TAG_CACHE_VECTOR_STENCIL);
+
+            self.set_blend(true, framebuffer_kind);
+            self.device.set_blend_mode_additive();
+
+            self.shaders.borrow_mut().pf_vector_stencil
+                .bind(&mut self.device, projection, &mut self.renderer_errors);
+
+            self.draw_instanced_batch(
+                &target.vector_stencils,
+                VertexArrayKind::VectorStencil,
+                &BatchTextures::no_texture(),
+                stats,
+            );
+
+            self.set_blend(false, framebuffer_kind);
+        }
         #[cfg(not(feature = "gl"))]
         {
             if !(transit_to_present && target.alpha_batch_containers.is_empty()) {
@@ -4716,6 +5034,44 @@ impl<B: hal::Backend> Renderer<B> {
             return;
         }
 
+        // draw image masks using composite operators, in order
+        if !list.composited_images.is_empty() {
+            let _gm2 = self.gpu_profile.start_marker("composited clip images");
+            // The blend mode is only switched away from the multiplicative
+            // one set up for the list when an operator asks for it, so that
+            // the first masks, drawn without blending, stay unaffected.
+            let mut current_op = MaskCompositeOperator::Intersect;
+            for run in &list.composited_images {
+                if run.composite_op != current_op {
+                    match run.composite_op {
+                        MaskCompositeOperator::Add => self.device.set_blend_mode_mask_add(),
+                        MaskCompositeOperator::Subtract => self.device.set_blend_mode_mask_subtract(),
+                        MaskCompositeOperator::Intersect => self.device.set_blend_mode_multiply(),
+                        MaskCompositeOperator::Exclude => self.device.set_blend_mode_mask_exclude(),
+                    }
+                    current_op = run.composite_op;
+                }
+                let textures = BatchTextures {
+                    colors: [
+                        run.texture.clone(),
+                        TextureSource::Invalid,
+                        TextureSource::Invalid,
+                    ],
+                };
+                self.shaders.borrow_mut().cs_clip_image
+                    .bind(&mut self.device, projection, &mut self.renderer_errors);
+                self.draw_instanced_batch(
+                    &run.instances,
+                    VertexArrayKind::Clip,
+                    &textures,
+                    stats,
+                );
+            }
+            if current_op != MaskCompositeOperator::Intersect {
+                self.device.set_blend_mode_multiply();
+            }
+        }
+
         // draw rounded cornered rectangles
         if !list.slow_rectangles.is_empty() {
             let _gm2 = self.gpu_profile.start_marker("slow clip rectangles");
@@ -4831,6 +5187,20 @@ impl<B: hal::Backend> Renderer<B> {
             self.device.disable_depth();
             self.device.disable_depth_write();
             self.set_blend(false, FramebufferKind::Other);
+        }
+
+        #[cfg(not(feature = "gl"))]
+        let (vertical_blurs, horizontal_blurs) = self.handle_compute_blurs(
+            &target.vertical_blurs,
+            &target.horizontal_blurs,
+            render_tasks,
+            false,
+        );
+        #[cfg(feature = "gl")]
+        let (vertical_blurs, horizontal_blurs) = (&target.vertical_blurs[..], &target.horizontal_blurs[..]);
+
+        {
+            let _timer = self.gpu_profile.start_timer(GPU_TAG_SETUP_TARGET);
 
             #[cfg(not(feature = "gl"))]
             {
@@ -4907,24 +5277,24 @@ impl<B: hal::Backend> Renderer<B> {
         // TODO(gw): In the future, consider having
         //           fast path blur shaders for common
         //           blur radii with fixed weights.
-        if !target.vertical_blurs.is_empty() || !target.horizontal_blurs.is_empty() {
+        if !vertical_blurs.is_empty() || !horizontal_blurs.is_empty() {
             let _timer = self.gpu_profile.start_timer(GPU_TAG_BLUR);
 
             self.shaders.borrow_mut().cs_blur_a8
                 .bind(&mut self.device, projection, &mut self.renderer_errors);
 
-            if !target.vertical_blurs.is_empty() {
+            if !vertical_blurs.is_empty() {
                 self.draw_instanced_batch(
-                    &target.vertical_blurs,
+                    &vertical_blurs,
                     VertexArrayKind::Blur,
                     &BatchTextures::no_texture(),
                     stats,
                 );
             }
 
-            if !target.horizontal_blurs.is_empty() {
+            if !horizontal_blurs.is_empty() {
                 self.draw_instanced_batch(
-                    &target.horizontal_blurs,
+                    &horizontal_blurs,
                     VertexArrayKind::Blur,
                     &BatchTextures::no_texture(),
                     stats,
@@ -4932,6 +5302,21 @@ impl<B: hal::Backend> Renderer<B> {
             }
         }
 
+        // Draw the levels of any dual filter blur pyramids.
+        if !target.dual_filter_blurs.is_empty() {
+            let _timer = self.gpu_profile.start_timer(GPU_TAG_BLUR);
+
+            self.shaders.borrow_mut().cs_dual_filter_a8
+                .bind(&mut self.device, projection, &mut self.renderer_errors);
+
+            self.draw_instanced_batch(
+                &target.dual_filter_blurs,
+                VertexArrayKind::Blur,
+                &BatchTextures::no_texture(),
+                stats,
+            );
+        }
+
         self.handle_scaling(
             &target.scalings,
             projection,
@@ -5143,6 +5528,26 @@ impl<B: hal::Backend> Renderer<B> {
             );
         }
 
+        // Resolve the coverage of any vector paths for this target.
+        if !target.vector_covers.is_empty() {
+            let _timer = self.gpu_profile.start_timer(GPU_TAG_CACHE_VECTOR_COVER);
+
+            self.set_blend(false, FramebufferKind::Other);
+
+            self.shaders.borrow_mut().pf_vector_cover.bind(
+                &mut self.device,
+                &projection,
+                &mut self.renderer_errors,
+            );
+
+            self.draw_instanced_batch(
+                &target.vector_covers,
+                VertexArrayKind::VectorCover,
+                &BatchTextures::no_texture(),
+                stats,
+            );
+        }
+
         // Draw any blurs for this target.
         if !target.horizontal_blurs.is_empty() {
             let _timer = self.gpu_profile.start_timer(GPU_TAG_BLUR);
@@ -5162,6 +5567,25 @@ impl<B: hal::Backend> Renderer<B> {
                 stats,
             );
         }
+
+        if !target.dual_filter_blurs.is_empty() {
+            let _timer = self.gpu_profile.start_timer(GPU_TAG_BLUR);
+
+            {
+                let mut shaders = self.shaders.borrow_mut();
+                match target.target_kind {
+                    RenderTargetKind::Alpha => &mut shaders.cs_dual_filter_a8,
+                    RenderTargetKind::Color => &mut shaders.cs_dual_filter_rgba8,
+                }.bind(&mut self.device, &projection, &mut self.renderer_errors);
+            }
+
+            self.draw_instanced_batch(
+                &target.dual_filter_blurs,
+                VertexArrayKind::Blur,
+                &BatchTextures::no_texture(),
+                stats,
+            );
+        }
         #[cfg(not(feature = "gl"))]
         self.device.end_render_pass();
     }
@@ -5470,7 +5894,19 @@ impl<B: hal::Backend> Renderer<B> {
                             total_size: device_size * fb_scale,
                         };
 
-                        if self.enable_picture_caching {
+                        #[cfg(feature = "gl")]
+                        let uses_native_compositor = self.compositor.is_some();
+                        #[cfg(not(feature = "gl"))]
+                        let uses_native_compositor = false;
+
+                        if self.enable_picture_caching && uses_native_compositor {
+                            #[cfg(feature = "gl")]
+                            self.composite_native(
+                                &frame.composite_state,
+                                draw_target,
+                                device_size,
+                            );
+                        } else if self.enable_picture_caching {
                             #[cfg(not(feature = "gl"))] {
                                 if self.device.readback_supported {
                                     let draw_calls = results.stats.total_draw_calls;
@@ -5590,16 +6026,42 @@ impl<B: hal::Backend> Renderer<B> {
                         for picture_target in picture_cache {
                             results.stats.color_target_count += 1;
 
-                            let (texture, _) = self.texture_resolver
-                                .resolve(&picture_target.texture)
-                                .expect("bug");
-                            let draw_target = DrawTarget::from_texture(
-                                texture,
-                                picture_target.layer,
-                                true,
+                            let draw_target = match picture_target.surface {
+                                ResolvedSurfaceTexture::TextureCache { ref texture, layer } => {
+                                    let (texture, _) = self.texture_resolver
+                                        .resolve(texture)
+                                        .expect("bug");
+
+                                    DrawTarget::from_texture(
+                                        texture,
+                                        layer as usize,
+                                        true,
+                                        #[cfg(not(feature = "gl"))]
+                                        self.device.frame_id,
+                                    )
+                                }
+                                #[cfg(feature = "gl")]
+                                ResolvedSurfaceTexture::NativeSurface { id, size } => {
+                                    let dirty_rect = picture_target
+                                        .alpha_batch_container
+                                        .task_scissor_rect
+                                        .unwrap_or(size.into());
+                                    let surface_info = self.compositor
+                                        .as_mut()
+                                        .expect("bug: no native compositor")
+                                        .bind(id, dirty_rect);
+
+                                    DrawTarget::NativeSurface {
+                                        offset: surface_info.origin,
+                                        external_fbo_id: surface_info.fbo_id,
+                                        dimensions: size,
+                                    }
+                                }
                                 #[cfg(not(feature = "gl"))]
-                                self.device.frame_id,
-                            );
+                                ResolvedSurfaceTexture::NativeSurface { .. } => {
+                                    unreachable!("bug: native surfaces require the GL device");
+                                }
+                            };
 
                             let projection = create_projection(
                                 0.0,
@@ -5617,6 +6079,13 @@ impl<B: hal::Backend> Renderer<B> {
                                 &frame.render_tasks,
                                 &mut results.stats,
                             );
+
+                            #[cfg(feature = "gl")]
+                            {
+                                if let ResolvedSurfaceTexture::NativeSurface { .. } = picture_target.surface {
+                                    self.compositor.as_mut().unwrap().unbind();
+                                }
+                            }
                         }
                     }
 
@@ -6232,9 +6701,18 @@ impl<B: hal::Backend> Renderer<B> {
         self.device.delete_vao(self.vaos.scale_vao);
         self.device.delete_vao(self.vaos.svg_filter_vao);
         self.device.delete_vao(self.vaos.composite_vao);
+        self.device.delete_vao(self.vaos.vector_stencil_vao);
+        self.device.delete_vao(self.vaos.vector_cover_vao);
 
         self.debug.deinit(&mut self.device);
 
+        #[cfg(feature = "gl")]
+        {
+            if let Some(mut compositor) = self.compositor.take() {
+                compositor.deinit();
+            }
+        }
+
         for (_, target) in self.output_targets {
             self.device.delete_fbo(target.fbo_id);
         }
@@ -6537,6 +7015,15 @@ pub struct RendererOptions {
     pub dump_shader_source: Option<String>,
     /// An optional presentation config for compositor integration.
     pub present_config: Option<PresentConfig>,
+    /// An optional native compositor. If set, picture cache tiles are
+    /// rendered into surfaces owned by the compositor, which is then
+    /// responsible for presenting them. Requires picture caching, or
+    /// `Renderer::new` fails with `CompositorRequiresPictureCaching`.
+    #[cfg(feature = "gl")]
+    pub compositor: Option<Box<dyn Compositor>>,
+    /// Limits enforced on every display list before it is built. Display
+    /// lists that exceed them are dropped.
+    pub display_list_limits: DisplayListLimits,
     #[cfg(not(feature = "gl"))]
     pub heaps_config: HeapsConfig,
     // The size of an instance buffer in bytes
@@ -6599,6 +7086,9 @@ impl Default for RendererOptions {
             start_debug_server: true,
             dump_shader_source: None,
             present_config: None,
+            #[cfg(feature = "gl")]
+            compositor: None,
+            display_list_limits: DisplayListLimits::default(),
             #[cfg(not(feature = "gl"))]
             heaps_config: HeapsConfig {
                 linear: None,
@@ -6679,6 +7169,15 @@ pub struct RenderResults {
     ///           OS compositor support where the dirty rects apply to a
     ///           specific picture cache slice / OS compositor surface).
     pub dirty_rects: Vec<DeviceIntRect>,
+
+    /// The region of the output that changed this frame, computed from the
+    /// dirty rects of the picture cache tiles. `None` if it isn't known, in
+    /// which case the whole output has to be assumed to have changed.
+    ///
+    /// The gfx device only limits drawing into the swapchain image to this
+    /// region. gfx-hal has no incremental present (present regions), so the
+    /// image is still presented in full.
+    pub damage_rect: Option<DeviceIntRect>,
 }
 
 #[cfg(any(feature = "capture", feature = "replay"))]
@@ -7111,7 +7610,8 @@ fn get_vao<'a>(vertex_array_kind: VertexArrayKind,
         VertexArrayKind::Primitive => &vaos.prim_vao,
         VertexArrayKind::Clip => &vaos.clip_vao,
         VertexArrayKind::Blur => &vaos.blur_vao,
-        VertexArrayKind::VectorStencil | VertexArrayKind::VectorCover => unreachable!(),
+        VertexArrayKind::VectorStencil => &vaos.vector_stencil_vao,
+        VertexArrayKind::VectorCover => &vaos.vector_cover_vao,
         VertexArrayKind::Border => &vaos.border_vao,
         VertexArrayKind::Scale => &vaos.scale_vao,
         VertexArrayKind::LineDecoration => &vaos.line_vao,
@@ -7133,6 +7633,7 @@ fn should_skip_batch(kind: &BatchKind, flags: &DebugFlags) -> bool {
             flags.contains(DebugFlags::DISABLE_TEXT_PRIMS)
         }
         BatchKind::Brush(BrushBatchKind::RadialGradient) |
+        BatchKind::Brush(BrushBatchKind::ConicGradient) |
         BatchKind::Brush(BrushBatchKind::LinearGradient) => {
             flags.contains(DebugFlags::DISABLE_GRADIENT_PRIMS)
         }
@@ -17,6 +17,7 @@ use crate::capture::ExternalCaptureImage;
 use crate::capture::PlainExternalImage;
 #[cfg(any(feature = "replay", feature = "png"))]
 use crate::capture::CaptureConfig;
+use crate::composite::{NativeSurfaceId, NativeSurfaceOperation};
 use crate::device::TextureFilter;
 use euclid::{point2, size2};
 use crate::glyph_cache::GlyphCache;
@@ -27,8 +28,10 @@ use crate::gpu_types::UvRectKind;
 use crate::image::{compute_tile_size, compute_tile_rect, compute_tile_range, for_each_tile_in_range};
 use crate::image::compute_valid_tiles_if_bounds_change;
 use crate::internal_types::{FastHashMap, FastHashSet, TextureSource, TextureUpdateList};
+use crate::picture::SnapshotDescriptor;
 use crate::profiler::{ResourceProfileCounters, TextureCacheProfileCounters};
 use crate::render_backend::{FrameId, FrameStamp};
+use crate::render_task::{BlitSource, ClearMode, RenderTask};
 use crate::render_task_graph::{RenderTaskGraph, RenderTaskId};
 use crate::render_task_cache::{RenderTaskCache, RenderTaskCacheKey};
 use crate::render_task_cache::{RenderTaskCacheEntry, RenderTaskCacheEntryHandle};
@@ -49,6 +52,10 @@ use crate::util::drain_filter;
 
 const DEFAULT_TILE_SIZE: TileSize = 512;
 
+/// Stands in for the data of snapshot images in captures.
+#[cfg(any(feature = "capture", feature = "replay"))]
+const SNAPSHOT_IMAGE_PATH: &str = "snapshot";
+
 #[cfg_attr(feature = "capture", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
 pub struct GlyphFetchResult {
@@ -101,6 +108,11 @@ pub enum CachedImageData {
     /// An image owned by the embedding, and referenced by WebRender. This may
     /// take the form of a texture or a heap-allocated buffer.
     External(ExternalImageData),
+    /// An image rendered by WebRender from a stacking context.
+    ///
+    /// The contents are drawn into the texture cache by render tasks, there
+    /// is nothing to upload.
+    Snapshot,
 }
 
 impl From<ImageData> for CachedImageData {
@@ -133,6 +145,16 @@ impl CachedImageData {
             },
             CachedImageData::Blob => true,
             CachedImageData::Raw(_) => true,
+            CachedImageData::Snapshot => true,
+        }
+    }
+
+    /// Returns true if this represents a snapshot.
+    #[inline]
+    pub fn is_snapshot(&self) -> bool {
+        match *self {
+            CachedImageData::Snapshot => true,
+            _ => false,
         }
     }
 }
@@ -508,6 +530,15 @@ pub struct ResourceCache {
     dirty_image_keys: FastHashSet<ImageKey>,
     /// A set of the image keys that are used for render.
     active_image_keys: FastHashSet<ImageKey>,
+    /// The contents of each snapshot image when it was last rendered.
+    snapshots: FastHashMap<ImageKey, SnapshotDescriptor>,
+    /// The tasks rendering snapshot images this frame.
+    snapshot_tasks: FastHashMap<ImageKey, RenderTaskId>,
+
+    /// Native compositor surfaces to create or destroy, sent to the
+    /// renderer with the texture cache updates.
+    pending_native_surface_updates: Vec<NativeSurfaceOperation>,
+    next_native_surface_id: u64,
 }
 
 impl ResourceCache {
@@ -539,6 +570,10 @@ impl ResourceCache {
             deleted_blob_keys: vec![Vec::new(), Vec::new(), Vec::new()].into(),
             dirty_image_keys: FastHashSet::default(),
             active_image_keys: FastHashSet::default(),
+            snapshots: FastHashMap::default(),
+            snapshot_tasks: FastHashMap::default(),
+            pending_native_surface_updates: Vec::new(),
+            next_native_surface_id: 0,
         }
     }
 
@@ -555,6 +590,8 @@ impl ResourceCache {
                 // not make sense to tile them into smaller ones.
                 info.image_type == ExternalImageType::Buffer && size_check
             }
+            // Snapshots are rendered in a single piece.
+            CachedImageData::Snapshot => false,
         }
     }
 
@@ -636,6 +673,9 @@ impl ResourceCache {
                 ResourceUpdate::DeleteImage(img) => {
                     self.delete_image_template(img);
                 }
+                ResourceUpdate::AddSnapshotImage(img) => {
+                    self.add_snapshot_image(img);
+                }
                 ResourceUpdate::DeleteFont(font) => {
                     self.delete_font_template(font);
                 }
@@ -913,6 +953,133 @@ impl ResourceCache {
         self.resources.image_templates.insert(image_key, resource);
     }
 
+    pub fn add_snapshot_image(&mut self, image_key: ImageKey) {
+        // The size is only known once the snapshot has been rendered.
+        let descriptor = ImageDescriptor::new(
+            0,
+            0,
+            self.texture_cache.shared_color_expected_format(),
+            false,
+            false,
+        );
+
+        self.add_image_template(
+            image_key,
+            descriptor,
+            CachedImageData::Snapshot,
+            &DeviceIntRect::zero(),
+            None,
+        );
+    }
+
+    /// Records the current contents of a snapshot image, returning true if
+    /// the snapshot has to be rendered this frame, in which case the images
+    /// showing it are invalidated. A snapshot without a descriptor is
+    /// rendered every frame.
+    pub fn update_snapshot(
+        &mut self,
+        image_key: ImageKey,
+        descriptor: Option<SnapshotDescriptor>,
+    ) -> bool {
+        debug_assert_eq!(self.state, State::AddResources);
+
+        match self.resources.image_templates.get(image_key) {
+            Some(template) if template.data.is_snapshot() => {}
+            _ => {
+                warn!("ERROR: Trying to render a snapshot into a non-snapshot key");
+                debug!("key={:?}", image_key);
+                return false;
+            }
+        }
+
+        let is_allocated = match self.cached_images.try_get(&image_key) {
+            Some(&ImageResult::UntiledAuto(ref entry)) => {
+                self.texture_cache.is_allocated(&entry.texture_cache_handle)
+            }
+            _ => false,
+        };
+
+        if is_allocated && descriptor.is_some() && self.snapshots.get(&image_key) == descriptor.as_ref() {
+            return false;
+        }
+
+        match descriptor {
+            Some(descriptor) => {
+                self.snapshots.insert(image_key, descriptor);
+            }
+            None => {
+                self.snapshots.remove(&image_key);
+            }
+        }
+        self.dirty_image_keys.insert(image_key);
+
+        true
+    }
+
+    /// Copies the output of a render task into the texture cache entry of a
+    /// snapshot image. The returned task has to be rendered before anything
+    /// showing the snapshot this frame, see `get_snapshot_task`.
+    pub fn render_snapshot(
+        &mut self,
+        image_key: ImageKey,
+        size: DeviceIntSize,
+        source_task_id: RenderTaskId,
+        gpu_cache: &mut GpuCache,
+        render_tasks: &mut RenderTaskGraph,
+    ) -> Option<RenderTaskId> {
+        debug_assert_eq!(self.state, State::AddResources);
+
+        let template = match self.resources.image_templates.get_mut(image_key) {
+            Some(template) if template.data.is_snapshot() => template,
+            _ => {
+                warn!("ERROR: Trying to render a snapshot into a non-snapshot key");
+                debug!("key={:?}", image_key);
+                return None;
+            }
+        };
+
+        template.descriptor.size = size;
+        template.visible_rect = size.into();
+
+        let entry = match *self.cached_images.entry(image_key).or_insert_with(|| {
+            ImageResult::UntiledAuto(CachedImageInfo {
+                texture_cache_handle: TextureCacheHandle::invalid(),
+                dirty_rect: DirtyRect::All,
+                manual_eviction: false,
+            })
+        }) {
+            ImageResult::UntiledAuto(ref mut entry) => entry,
+            ImageResult::Multi(..) | ImageResult::Err(..) => unreachable!(),
+        };
+
+        // The picture task covers the whole snapshot, so nothing needs to
+        // be cleared before the copy.
+        let mut task = RenderTask::new_blit(
+            size,
+            BlitSource::RenderTask { task_id: source_task_id },
+        );
+        task.clear_mode = ClearMode::DontCare;
+
+        self.texture_cache.request(&entry.texture_cache_handle, gpu_cache);
+        let task_id = render_tasks.add(task);
+        RenderTaskCache::alloc_render_task(
+            &mut render_tasks[task_id],
+            &mut entry.texture_cache_handle,
+            None,
+            false,
+            gpu_cache,
+            &mut self.texture_cache,
+        );
+        self.snapshot_tasks.insert(image_key, task_id);
+
+        Some(task_id)
+    }
+
+    /// Returns the task rendering a snapshot image this frame, if any.
+    pub fn get_snapshot_task(&self, image_key: ImageKey) -> Option<RenderTaskId> {
+        self.snapshot_tasks.get(&image_key).cloned()
+    }
+
     pub fn update_image_template(
         &mut self,
         image_key: ImageKey,
@@ -1072,6 +1239,7 @@ impl ResourceCache {
         if let Some(mut cached) = self.cached_images.remove(&image_key) {
             cached.drop_from_cache(&mut self.texture_cache);
         }
+        self.snapshots.remove(&image_key);
 
         match value {
             Some(image) => if image.data.is_blob() {
@@ -1131,6 +1299,15 @@ impl ResourceCache {
             return;
         }
 
+        // Snapshots are drawn by the frame builder, referencing them only
+        // keeps the last rendered contents alive.
+        if template.data.is_snapshot() {
+            if let Some(&ImageResult::UntiledAuto(ref entry)) = self.cached_images.try_get(&request.key) {
+                self.texture_cache.request(&entry.texture_cache_handle, gpu_cache);
+            }
+            return;
+        }
+
         let side_size =
             template.tiling.map_or(cmp::max(template.descriptor.size.width, template.descriptor.size.height),
                                    |tile_size| tile_size as i32);
@@ -1511,7 +1688,44 @@ impl ResourceCache {
     }
 
     pub fn pending_updates(&mut self) -> TextureUpdateList {
-        self.texture_cache.pending_updates()
+        let mut updates = self.texture_cache.pending_updates();
+        updates.native_surface_updates = mem::replace(
+            &mut self.pending_native_surface_updates,
+            Vec::new(),
+        );
+        updates
+    }
+
+    /// Requests a new native compositor surface, returning its id.
+    pub fn create_compositor_surface(
+        &mut self,
+        size: DeviceIntSize,
+        is_opaque: bool,
+    ) -> NativeSurfaceId {
+        let id = NativeSurfaceId(self.next_native_surface_id);
+        self.next_native_surface_id += 1;
+
+        self.pending_native_surface_updates.push(
+            NativeSurfaceOperation::CreateSurface {
+                id,
+                size,
+                is_opaque,
+            }
+        );
+
+        id
+    }
+
+    /// Requests the destruction of a native compositor surface.
+    pub fn destroy_compositor_surface(
+        &mut self,
+        id: NativeSurfaceId,
+    ) {
+        self.pending_native_surface_updates.push(
+            NativeSurfaceOperation::DestroySurface {
+                id,
+            }
+        );
     }
 
     pub fn fetch_glyphs<F>(
@@ -1575,6 +1789,41 @@ impl ResourceCache {
         }
     }
 
+    /// Returns the dimensions of a glyph rasterized with the given font. Unlike
+    /// `get_glyph_dimensions`, this isn't limited to the size of the font instance,
+    /// and the result isn't cached.
+    pub fn get_scaled_glyph_dimensions(
+        &mut self,
+        font: &FontInstance,
+        glyph_index: GlyphIndex,
+    ) -> Option<GlyphDimensions> {
+        let mut font = font.clone();
+        self.glyph_rasterizer.prepare_font(&mut font);
+        self.glyph_rasterizer.get_glyph_dimensions(&font, glyph_index)
+    }
+
+    /// Calls `f` with the index and texture cache item of each glyph that
+    /// has been rasterized, for copying the glyphs out of the texture cache.
+    pub fn fetch_glyph_cache_items<F>(
+        &self,
+        mut font: FontInstance,
+        glyph_keys: &[GlyphKey],
+        mut f: F,
+    ) where
+        F: FnMut(usize, CacheItem),
+    {
+        debug_assert_eq!(self.state, State::QueryResources);
+
+        self.glyph_rasterizer.prepare_font(&mut font);
+        let glyph_key_cache = self.cached_glyphs.get_glyph_key_cache_for_font(&font);
+
+        for (index, key) in glyph_keys.iter().enumerate() {
+            if let GlyphCacheEntry::Cached(ref glyph) = *glyph_key_cache.get(key) {
+                f(index, self.texture_cache.get(&glyph.texture_cache_handle));
+            }
+        }
+    }
+
     pub fn get_glyph_index(&mut self, font_key: FontKey, ch: char) -> Option<u32> {
         self.glyph_rasterizer.get_glyph_index(font_key, ch)
     }
@@ -1583,6 +1832,10 @@ impl ResourceCache {
     pub fn get_cached_image(&self, request: ImageRequest) -> Result<CacheItem, ()> {
         debug_assert_eq!(self.state, State::QueryResources);
         let image_info = self.get_image_info(request)?;
+        // Snapshots don't have any contents until they are first rendered.
+        if !self.texture_cache.is_allocated(&image_info.texture_cache_handle) {
+            return Err(());
+        }
         Ok(self.get_texture_cache_item(&image_info.texture_cache_handle))
     }
 
@@ -1597,10 +1850,10 @@ impl ResourceCache {
     fn get_image_info(&self, request: ImageRequest) -> Result<&CachedImageInfo, ()> {
         // TODO(Jerry): add a debug option to visualize the corresponding area for
         // the Err() case of CacheItem.
-        match *self.cached_images.get(&request.key) {
-            ImageResult::UntiledAuto(ref image_info) => Ok(image_info),
-            ImageResult::Multi(ref entries) => Ok(entries.get(&request.into())),
-            ImageResult::Err(_) => Err(()),
+        match self.cached_images.try_get(&request.key) {
+            Some(&ImageResult::UntiledAuto(ref image_info)) => Ok(image_info),
+            Some(&ImageResult::Multi(ref entries)) => Ok(entries.get(&request.into())),
+            Some(&ImageResult::Err(_)) | None => Err(()),
         }
     }
 
@@ -1619,8 +1872,8 @@ impl ResourceCache {
                     // external buffer uses resource_cache.
                     ExternalImageType::Buffer => None,
                 },
-                // raw and blob image are all using resource_cache.
-                CachedImageData::Raw(..) | CachedImageData::Blob => None,
+                // raw, blob and snapshot images are all using resource_cache.
+                CachedImageData::Raw(..) | CachedImageData::Blob | CachedImageData::Snapshot => None,
             };
 
             ImageProperties {
@@ -1757,6 +2010,9 @@ impl ResourceCache {
                         }
                     }
                 }
+                CachedImageData::Snapshot => {
+                    panic!("Snapshots are rendered, not uploaded.");
+                }
             };
 
             for (image_data, blob_rasterized_rect) in updates {
@@ -1853,6 +2109,7 @@ impl ResourceCache {
         self.state = State::Idle;
         self.texture_cache.end_frame(texture_cache_profile);
         self.dirty_image_keys.clear();
+        self.snapshot_tasks.clear();
     }
 
     pub fn set_debug_flags(&mut self, flags: DebugFlags) {
@@ -1926,7 +2183,7 @@ impl ResourceCache {
         for (_, image) in self.resources.image_templates.images.iter() {
             report.images += match image.data {
                 CachedImageData::Raw(ref v) => unsafe { op(v.as_ptr() as *const c_void) },
-                CachedImageData::Blob | CachedImageData::External(..) => 0,
+                CachedImageData::Blob | CachedImageData::External(..) | CachedImageData::Snapshot => 0,
             }
         }
 
@@ -2185,6 +2442,10 @@ impl ResourceCache {
                         .unwrap();
                     other_paths.insert(key, short_path);
                 }
+                CachedImageData::Snapshot => {
+                    // Snapshots are rendered again when the capture is replayed.
+                    other_paths.insert(key, SNAPSHOT_IMAGE_PATH.to_string());
+                }
                 CachedImageData::External(ref ext) => {
                     let short_path = format!("externals/{}", external_images.len() + 1);
                     other_paths.insert(key, short_path.clone());
@@ -2334,6 +2595,16 @@ impl ResourceCache {
         info!("\timage templates...");
         let mut external_images = Vec::new();
         for (key, template) in resources.image_templates {
+            if template.data == SNAPSHOT_IMAGE_PATH {
+                res.image_templates.images.insert(key, ImageResource {
+                    data: CachedImageData::Snapshot,
+                    descriptor: template.descriptor,
+                    tiling: None,
+                    visible_rect: template.descriptor.size.into(),
+                });
+                continue;
+            }
+
             let data = match CaptureConfig::deserialize::<PlainExternalImage, _>(root, &template.data) {
                 Some(plain) => {
                     let ext_data = plain.external;
@@ -7,20 +7,23 @@ use api::{PipelineId, PropertyBinding, PropertyBindingId, MixBlendMode, Stacking
 use api::units::*;
 use crate::clip::{ClipStore, ClipDataStore};
 use crate::clip_scroll_tree::ClipScrollTree;
+use crate::composite::CompositorKind;
 use crate::frame_builder::{ChasePrimitive, FrameBuilderConfig};
 use crate::hit_test::{HitTester, HitTestingScene, HitTestingSceneStats};
 use crate::internal_types::FastHashMap;
 use crate::prim_store::{PrimitiveStore, PrimitiveStoreStats, PictureIndex};
+use crate::scene_building::DisplayListLimits;
 use std::sync::Arc;
 
 /// Stores a map of the animated property bindings for the current display list. These
-/// can be used to animate the transform and/or opacity of a display list without
+/// can be used to animate the transform, opacity and/or colors of a display list without
 /// re-submitting the display list itself.
 #[cfg_attr(feature = "capture", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
 pub struct SceneProperties {
     transform_properties: FastHashMap<PropertyBindingId, LayoutTransform>,
     float_properties: FastHashMap<PropertyBindingId, f32>,
+    color_properties: FastHashMap<PropertyBindingId, ColorF>,
     current_properties: DynamicProperties,
     pending_properties: Option<DynamicProperties>,
 }
@@ -30,6 +33,7 @@ impl SceneProperties {
         SceneProperties {
             transform_properties: FastHashMap::default(),
             float_properties: FastHashMap::default(),
+            color_properties: FastHashMap::default(),
             current_properties: DynamicProperties::default(),
             pending_properties: None,
         }
@@ -48,6 +52,7 @@ impl SceneProperties {
 
         pending_properties.transforms.extend(properties.transforms);
         pending_properties.floats.extend(properties.floats);
+        pending_properties.colors.extend(properties.colors);
 
         self.pending_properties = Some(pending_properties);
     }
@@ -67,6 +72,7 @@ impl SceneProperties {
             if *pending_properties != self.current_properties {
                 self.transform_properties.clear();
                 self.float_properties.clear();
+                self.color_properties.clear();
 
                 for property in &pending_properties.transforms {
                     self.transform_properties
@@ -78,6 +84,11 @@ impl SceneProperties {
                         .insert(property.key.id, property.value);
                 }
 
+                for property in &pending_properties.colors {
+                    self.color_properties
+                        .insert(property.key.id, property.value);
+                }
+
                 self.current_properties = pending_properties.clone();
                 properties_changed = true;
             }
@@ -121,6 +132,26 @@ impl SceneProperties {
     pub fn float_properties(&self) -> &FastHashMap<PropertyBindingId, f32> {
         &self.float_properties
     }
+
+    /// Get the current value for a color property.
+    pub fn resolve_color(
+        &self,
+        property: &PropertyBinding<ColorF>
+    ) -> ColorF {
+        match *property {
+            PropertyBinding::Value(value) => value,
+            PropertyBinding::Binding(ref key, v) => {
+                self.color_properties
+                    .get(&key.id)
+                    .cloned()
+                    .unwrap_or(v)
+            }
+        }
+    }
+
+    pub fn color_properties(&self) -> &FastHashMap<PropertyBindingId, ColorF> {
+        &self.color_properties
+    }
 }
 
 /// A representation of the layout within the display port for a given document or iframe.
@@ -179,6 +210,14 @@ impl Scene {
         self.pipeline_epochs.insert(pipeline_id, epoch);
     }
 
+    /// Drop the display list of a pipeline, as if it had been sent an empty
+    /// list for this epoch. Unlike `remove_pipeline`, the pipeline remains the
+    /// root pipeline if it was one, so a later display list can replace it.
+    pub fn drop_display_list(&mut self, pipeline_id: PipelineId, epoch: Epoch) {
+        self.pipelines.remove(&pipeline_id);
+        self.pipeline_epochs.insert(pipeline_id, epoch);
+    }
+
     pub fn remove_pipeline(&mut self, pipeline_id: PipelineId) {
         if self.root_pipeline_id == Some(pipeline_id) {
             self.root_pipeline_id = None;
@@ -251,6 +290,8 @@ impl BuiltScene {
                 advanced_blend_is_coherent: false,
                 batch_lookback_count: 0,
                 background_color: None,
+                display_list_limits: DisplayListLimits::default(),
+                compositor_kind: CompositorKind::Draw,
             },
         }
     }
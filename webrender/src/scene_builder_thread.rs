@@ -5,6 +5,7 @@
 use api::{AsyncBlobImageRasterizer, BlobImageRequest, BlobImageParams, BlobImageResult};
 use api::{DocumentId, PipelineId, ApiMsg, FrameMsg, ResourceUpdate, ExternalEvent, Epoch};
 use api::{BuiltDisplayList, ColorF, NotificationRequest, Checkpoint, IdNamespace};
+use api::DisplayListPatchOp;
 use api::{ClipIntern, FilterDataIntern, MemoryReport, PrimitiveKeyKind};
 use api::channel::MsgSender;
 use api::units::LayoutSize;
@@ -17,9 +18,10 @@ use crate::internal_types::{FastHashMap, FastHashSet};
 use malloc_size_of::{MallocSizeOf, MallocSizeOfOps};
 use crate::prim_store::backdrop::Backdrop;
 use crate::prim_store::borders::{ImageBorder, NormalBorderPrim};
-use crate::prim_store::gradient::{LinearGradient, RadialGradient};
+use crate::prim_store::gradient::{LinearGradient, RadialGradient, ConicGradient};
 use crate::prim_store::image::{Image, YuvImage};
 use crate::prim_store::line_dec::LineDecoration;
+use crate::prim_store::vector_path::VectorPath;
 use crate::prim_store::picture::Picture;
 use crate::prim_store::text_run::TextRun;
 use crate::resource_cache::{AsyncBlobImageInfo, FontInstanceMap};
@@ -27,6 +29,7 @@ use crate::render_backend::DocumentView;
 use crate::renderer::{PipelineInfo, SceneBuilderHooks};
 use crate::scene::{Scene, BuiltScene, SceneStats};
 use std::iter;
+use std::sync::Arc;
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::mem::replace;
 use time::precise_time_ns;
@@ -39,6 +42,7 @@ use std::time::Duration;
 pub struct Transaction {
     pub document_id: DocumentId,
     pub display_list_updates: Vec<DisplayListUpdate>,
+    pub display_list_patches: Vec<DisplayListPatch>,
     pub removed_pipelines: Vec<(PipelineId, DocumentId)>,
     pub epoch_updates: Vec<(PipelineId, Epoch)>,
     pub request_scene_build: Option<SceneRequest>,
@@ -57,6 +61,7 @@ impl Transaction {
     pub fn can_skip_scene_builder(&self) -> bool {
         self.request_scene_build.is_none() &&
             self.display_list_updates.is_empty() &&
+            self.display_list_patches.is_empty() &&
             self.epoch_updates.is_empty() &&
             self.removed_pipelines.is_empty() &&
             self.blob_requests.is_empty() &&
@@ -65,6 +70,7 @@ impl Transaction {
 
     pub fn should_build_scene(&self) -> bool {
         !self.display_list_updates.is_empty() ||
+            !self.display_list_patches.is_empty() ||
             self.set_root_pipeline.is_some()
     }
 
@@ -108,6 +114,15 @@ pub struct DisplayListUpdate {
     pub content_size: LayoutSize,
 }
 
+/// A patch to the display list of a pipeline, applied after the display list
+/// updates of the same transaction.
+pub struct DisplayListPatch {
+    pub pipeline_id: PipelineId,
+    pub epoch: Epoch,
+    pub patch: BuiltDisplayList,
+    pub ops: Vec<DisplayListPatchOp>,
+}
+
 /// Contains the render backend data needed to build a scene.
 pub struct SceneRequest {
     pub view: DocumentView,
@@ -428,7 +443,17 @@ impl SceneBuilderThread {
                       .or_insert_with(|| Document::new(Scene::new()));
         let scene = &mut doc.scene;
 
+        let mut rejected_display_lists = Vec::new();
         for update in txn.display_list_updates.drain(..) {
+            // Display lists are assumed to be well formed here, clients sending
+            // lists from an untrusted source should validate them first.
+            if let Err(err) = self.config.display_list_limits.check(&update.built_display_list) {
+                warn!("Dropping display list for {:?}: limit exceeded {:?}", update.pipeline_id, err);
+                scene.drop_display_list(update.pipeline_id, update.epoch);
+                rejected_display_lists.push((update.pipeline_id, update.epoch));
+                continue;
+            }
+
             scene.set_display_list(
                 update.pipeline_id,
                 update.epoch,
@@ -439,6 +464,59 @@ impl SceneBuilderThread {
             );
         }
 
+        // Patching saves sending and checking the unchanged items, but the
+        // scene is still built from the whole patched list, which re-walks and
+        // re-interns all of its items. Items left untouched by a patch are
+        // interned to the same handles though, so the interner updates sent to
+        // the data stores stay small.
+        //
+        // A patch that can't be applied is rejected and the pipeline keeps its
+        // previous display list and epoch.
+        for update in txn.display_list_patches.drain(..) {
+            let pipeline = match scene.pipelines.get(&update.pipeline_id) {
+                Some(pipeline) => Arc::clone(pipeline),
+                None => {
+                    warn!("Rejecting display list patch for unknown pipeline {:?}", update.pipeline_id);
+                    rejected_display_lists.push((update.pipeline_id, update.epoch));
+                    continue;
+                }
+            };
+
+            let patched = match pipeline.display_list.apply_patch(&update.patch, &update.ops) {
+                Ok(patched) => patched,
+                Err(err) => {
+                    warn!("Rejecting display list patch for {:?}: {:?}", update.pipeline_id, err);
+                    rejected_display_lists.push((update.pipeline_id, update.epoch));
+                    continue;
+                }
+            };
+
+            // The patch items were built separately from the list they are
+            // spliced into, so check that the result still makes sense. Only
+            // the spliced ranges are walked, unless the patch removed items
+            // that the rest of the list may depend on.
+            if let Err(errors) = patched.validate() {
+                warn!("Rejecting display list patch for {:?}: malformed result {:?}", update.pipeline_id, errors);
+                rejected_display_lists.push((update.pipeline_id, update.epoch));
+                continue;
+            }
+
+            if let Err(err) = self.config.display_list_limits.check_patch(&patched) {
+                warn!("Rejecting display list patch for {:?}: limit exceeded {:?}", update.pipeline_id, err);
+                rejected_display_lists.push((update.pipeline_id, update.epoch));
+                continue;
+            }
+
+            scene.set_display_list(
+                update.pipeline_id,
+                update.epoch,
+                patched.display_list,
+                pipeline.background_color,
+                pipeline.viewport_size,
+                pipeline.content_size,
+            );
+        }
+
         for &(pipeline_id, epoch) in &txn.epoch_updates {
             scene.update_epoch(pipeline_id, epoch);
         }
@@ -480,6 +558,19 @@ impl SceneBuilderThread {
         let is_low_priority = false;
         txn.rasterize_blobs(is_low_priority);
 
+        if !rejected_display_lists.is_empty() {
+            drain_filter(
+                &mut txn.notifications,
+                |n| match n.when() {
+                    Checkpoint::DisplayListRejected { pipeline_id, epoch } => {
+                        rejected_display_lists.contains(&(pipeline_id, epoch))
+                    }
+                    _ => false,
+                },
+                |n| { n.notify(); },
+            );
+        }
+
         drain_filter(
             &mut txn.notifications,
             |n| { n.when() == Checkpoint::SceneBuilt },
@@ -3,14 +3,16 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use api::{AlphaType, BorderDetails, BorderDisplayItem, BuiltDisplayListIter, PrimitiveFlags};
+use api::{BuiltDisplayList, FilterPrimitiveKind, PatchedDisplayList};
 use api::{ClipId, ColorF, CommonItemProperties, ComplexClipRegion, ComponentTransferFuncType, RasterSpace};
 use api::{DisplayItem, DisplayItemRef, ExtendMode, ExternalScrollId, FilterData};
-use api::{FilterOp, FilterPrimitive, FontInstanceKey, GlyphInstance, GlyphOptions, GradientStop};
+use api::{FilterOp, FilterPrimitive, FontInstanceKey, GlyphInstance, GlyphOptions, GradientColorSpace, GradientStop};
 use api::{IframeDisplayItem, ImageKey, ImageRendering, ItemRange, ColorDepth};
-use api::{LineOrientation, LineStyle, NinePatchBorderSource, PipelineId, MixBlendMode};
+use api::{LineOrientation, LineStyle, NinePatchBorderSource, OverscrollBehavior, PipelineId, MixBlendMode};
 use api::{PropertyBinding, ReferenceFrame, ReferenceFrameKind, ScrollFrameDisplayItem, ScrollSensitivity};
 use api::{Shadow, SpaceAndClipInfo, SpatialId, StackingContext, StickyFrameDisplayItem};
 use api::{ClipMode, PrimitiveKeyKind, TransformStyle, YuvColorSpace, ColorRange, YuvData, TempFilterData};
+use api::{ColorU, FillRule, FontRenderMode, PathCommand, PathPaint};
 use api::units::*;
 use crate::clip::{ClipChainId, ClipRegion, ClipItemKey, ClipStore, ClipItemKeyKind, ClipDataHandle, ClipNodeKind};
 use crate::clip_scroll_tree::{ROOT_SPATIAL_NODE_INDEX, ClipScrollTree, SpatialNodeIndex};
@@ -31,10 +33,12 @@ use crate::prim_store::{SpaceSnapper};
 use crate::prim_store::backdrop::Backdrop;
 use crate::prim_store::borders::{ImageBorder, NormalBorderPrim};
 use crate::prim_store::gradient::{GradientStopKey, LinearGradient, RadialGradient, RadialGradientParams};
+use crate::prim_store::gradient::{ConicGradient, ConicGradientParams};
 use crate::prim_store::image::{Image, YuvImage};
 use crate::prim_store::line_dec::{LineDecoration, LineDecorationCacheKey};
 use crate::prim_store::picture::{Picture, PictureCompositeKey, PictureKey};
 use crate::prim_store::text_run::TextRun;
+use crate::prim_store::vector_path::{flatten_path, VectorPath, VectorPathCacheKey, VectorPathPaint};
 use crate::render_backend::{DocumentView};
 use crate::resource_cache::{FontInstanceMap, ImageRequest};
 use crate::scene::{Scene, BuiltScene, SceneStats, StackingContextHelpers};
@@ -295,6 +299,191 @@ impl Slice {
     }
 }
 
+/// Upper bounds on the work a single display list may request. Display lists
+/// exceeding any of them are dropped by the scene builder instead of being built,
+/// which protects the render backend from hostile or buggy content. The default
+/// is to not limit anything.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct DisplayListLimits {
+    /// Maximum number of display items, not counting marker items.
+    pub max_items: usize,
+    /// Maximum nesting depth of stacking contexts and reference frames.
+    pub max_stacking_context_depth: usize,
+    /// Maximum blur radius of filters, box shadows and shadows.
+    pub max_blur_radius: f32,
+    /// Maximum number of glyphs in a single text run.
+    pub max_glyphs_per_text_run: usize,
+    /// Maximum number of stops in a single gradient.
+    pub max_gradient_stops: usize,
+    /// Maximum number of tiles a single repeating image covers.
+    pub max_image_tile_count: usize,
+}
+
+impl Default for DisplayListLimits {
+    fn default() -> Self {
+        DisplayListLimits {
+            max_items: usize::MAX,
+            max_stacking_context_depth: usize::MAX,
+            max_blur_radius: f32::MAX,
+            max_glyphs_per_text_run: usize::MAX,
+            max_gradient_stops: usize::MAX,
+            max_image_tile_count: usize::MAX,
+        }
+    }
+}
+
+/// The first limit a display list was found to exceed, with the offending value.
+#[derive(Debug, PartialEq)]
+pub enum DisplayListLimitError {
+    ItemCount(usize),
+    StackingContextDepth(usize),
+    BlurRadius(f32),
+    GlyphsPerTextRun(usize),
+    GradientStops(usize),
+    ImageTileCount(f32),
+}
+
+impl DisplayListLimits {
+    /// Walk the items of a display list, checking it against these limits.
+    ///
+    /// The display list is assumed to be well formed, since iterating over a
+    /// malformed list panics. Lists from an untrusted source should go through
+    /// `BuiltDisplayList::validate` first.
+    pub fn check(&self, display_list: &BuiltDisplayList) -> Result<(), DisplayListLimitError> {
+        let mut item_count = 0;
+        self.check_items(display_list.iter(), 0, &mut item_count)
+    }
+
+    /// Check a patched display list against these limits. The items kept from
+    /// the previous list were checked along with it, so unless the patch
+    /// changed how they nest only the items spliced in from the patch are
+    /// walked.
+    pub fn check_patch(&self, patched: &PatchedDisplayList) -> Result<(), DisplayListLimitError> {
+        let spliced = match patched.spliced_items() {
+            Some(spliced) => spliced,
+            None => return self.check(&patched.display_list),
+        };
+
+        let mut item_count = patched.retained_item_count();
+        for (iter, depth) in spliced {
+            self.check_items(iter, depth, &mut item_count)?;
+        }
+
+        Ok(())
+    }
+
+    /// Walk the items of `iter`, which start at the given nesting depth of
+    /// stacking contexts and reference frames, adding them to `item_count`.
+    fn check_items(
+        &self,
+        mut iter: BuiltDisplayListIter,
+        mut depth: usize,
+        item_count: &mut usize,
+    ) -> Result<(), DisplayListLimitError> {
+        while let Some(item) = iter.next() {
+            *item_count += 1;
+            if *item_count > self.max_items {
+                return Err(DisplayListLimitError::ItemCount(*item_count));
+            }
+
+            match *item.item() {
+                DisplayItem::PushStackingContext(..) |
+                DisplayItem::PushReferenceFrame(..) => {
+                    depth += 1;
+                    if depth > self.max_stacking_context_depth {
+                        return Err(DisplayListLimitError::StackingContextDepth(depth));
+                    }
+                    self.check_filters(item.filters(), item.filter_primitives())?;
+                }
+                DisplayItem::PopStackingContext |
+                DisplayItem::PopReferenceFrame => {
+                    depth = depth.saturating_sub(1);
+                }
+                DisplayItem::BackdropFilter(..) => {
+                    self.check_filters(item.filters(), item.filter_primitives())?;
+                }
+                DisplayItem::BoxShadow(ref info) => {
+                    self.check_blur_radius(info.blur_radius)?;
+                }
+                DisplayItem::PushShadow(ref info) => {
+                    self.check_blur_radius(info.shadow.blur_radius)?;
+                }
+                DisplayItem::Text(..) => {
+                    let glyph_count = item.glyphs().iter().len();
+                    if glyph_count > self.max_glyphs_per_text_run {
+                        return Err(DisplayListLimitError::GlyphsPerTextRun(glyph_count));
+                    }
+                }
+                DisplayItem::Gradient(..) |
+                DisplayItem::RadialGradient(..) |
+                DisplayItem::ConicGradient(..) => {
+                    let stop_count = item.gradient_stops().iter().len();
+                    if stop_count > self.max_gradient_stops {
+                        return Err(DisplayListLimitError::GradientStops(stop_count));
+                    }
+                }
+                DisplayItem::RepeatingImage(ref info) => {
+                    let tile_size = info.stretch_size + info.tile_spacing;
+                    if tile_size.width > 0.0 && tile_size.height > 0.0 {
+                        let tile_count =
+                            (info.bounds.size.width / tile_size.width).ceil() *
+                            (info.bounds.size.height / tile_size.height).ceil();
+                        if tile_count.is_nan() || tile_count > self.max_image_tile_count as f32 {
+                            return Err(DisplayListLimitError::ImageTileCount(tile_count));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_filters(
+        &self,
+        filters: ItemRange<FilterOp>,
+        filter_primitives: ItemRange<FilterPrimitive>,
+    ) -> Result<(), DisplayListLimitError> {
+        for filter in filters {
+            match filter {
+                FilterOp::Blur(width, height) => {
+                    self.check_blur_radius(width)?;
+                    self.check_blur_radius(height)?
+                }
+                FilterOp::DropShadow(shadow) => self.check_blur_radius(shadow.blur_radius)?,
+                _ => {}
+            }
+        }
+
+        for primitive in filter_primitives {
+            match primitive.kind {
+                FilterPrimitiveKind::Blur(ref blur) => {
+                    self.check_blur_radius(blur.width)?;
+                    self.check_blur_radius(blur.height)?
+                }
+                FilterPrimitiveKind::DropShadow(ref drop_shadow) => {
+                    self.check_blur_radius(drop_shadow.shadow.blur_radius)?
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_blur_radius(&self, radius: f32) -> Result<(), DisplayListLimitError> {
+        // Written so that NaN radii are rejected too.
+        if radius <= self.max_blur_radius {
+            Ok(())
+        } else {
+            Err(DisplayListLimitError::BlurRadius(radius))
+        }
+    }
+}
+
 /// A structure that converts a serialized display list into a form that WebRender
 /// can use to later build a frame. This structure produces a BuiltScene. Public
 /// members are typically those that are destructured into the BuiltScene.
@@ -429,6 +618,7 @@ impl<'a> SceneBuilder<'a> {
             ClipChainId::NONE,
             RasterSpace::Screen,
             /* is_backdrop_root = */ true,
+            /* snapshot = */ None,
             device_pixel_scale,
         );
 
@@ -743,6 +933,8 @@ impl<'a> SceneBuilder<'a> {
             info.clip_rect,
             item.complex_clip().iter(),
             info.image_mask,
+            None,
+            None,
             &current_offset,
         );
         // Just use clip rectangle as the frame rect for this scroll frame.
@@ -762,6 +954,7 @@ impl<'a> SceneBuilder<'a> {
             &frame_rect,
             &content_size,
             info.scroll_sensitivity,
+            info.overscroll_behavior,
             ScrollFrameKind::Explicit,
             info.external_scroll_offset,
         );
@@ -840,6 +1033,7 @@ impl<'a> SceneBuilder<'a> {
             clip_chain_id,
             stacking_context.raster_space,
             stacking_context.is_backdrop_root,
+            stacking_context.snapshot,
             self.sc_stack.last().unwrap().snap_to_device.device_pixel_scale,
         );
 
@@ -929,6 +1123,7 @@ impl<'a> SceneBuilder<'a> {
             &iframe_rect,
             &content_size,
             ScrollSensitivity::ScriptAndInputEvents,
+            OverscrollBehavior::Auto,
             ScrollFrameKind::PipelineRoot,
             LayoutVector2D::zero(),
         );
@@ -1132,7 +1327,7 @@ impl<'a> SceneBuilder<'a> {
                 self.add_solid_rectangle(
                     clip_and_scroll,
                     &layout,
-                    ColorF::TRANSPARENT,
+                    PropertyBinding::Value(ColorF::TRANSPARENT),
                 );
             }
             DisplayItem::ClearRectangle(ref info) => {
@@ -1181,6 +1376,7 @@ impl<'a> SceneBuilder<'a> {
                     info.gradient.end_point,
                     item.gradient_stops(),
                     info.gradient.extend_mode,
+                    info.gradient.color_space,
                     tile_size,
                     info.tile_spacing,
                     None,
@@ -1214,6 +1410,7 @@ impl<'a> SceneBuilder<'a> {
                     info.gradient.radius.width / info.gradient.radius.height,
                     item.gradient_stops(),
                     info.gradient.extend_mode,
+                    info.gradient.color_space,
                     tile_size,
                     info.tile_spacing,
                     None,
@@ -1226,6 +1423,55 @@ impl<'a> SceneBuilder<'a> {
                     prim_key_kind,
                 );
             }
+            DisplayItem::ConicGradient(ref info) => {
+                let (layout, unsnapped_rect, clip_and_scroll) = self.process_common_properties_with_bounds(
+                    &info.common,
+                    &info.bounds,
+                    apply_pipeline_clip,
+                );
+
+                let tile_size = process_repeat_size(
+                    &layout.rect,
+                    &unsnapped_rect,
+                    info.tile_size,
+                );
+
+                let prim_key_kind = self.create_conic_gradient_prim(
+                    &layout,
+                    info.gradient.center,
+                    info.gradient.angle,
+                    info.gradient.start_offset,
+                    info.gradient.end_offset,
+                    item.gradient_stops(),
+                    info.gradient.extend_mode,
+                    tile_size,
+                    info.tile_spacing,
+                    None,
+                );
+
+                self.add_nonshadowable_primitive(
+                    clip_and_scroll,
+                    &layout,
+                    Vec::new(),
+                    prim_key_kind,
+                );
+            }
+            DisplayItem::Path(ref info) => {
+                let (layout, _, clip_and_scroll) = self.process_common_properties_with_bounds(
+                    &info.common,
+                    &info.bounds,
+                    apply_pipeline_clip,
+                );
+
+                self.add_path(
+                    clip_and_scroll,
+                    &layout,
+                    info.fill_rule,
+                    &info.paint,
+                    item.path_commands(),
+                    item.gradient_stops(),
+                );
+            }
             DisplayItem::BoxShadow(ref info) => {
                 let (layout, _, clip_and_scroll) = self.process_common_properties_with_bounds(
                     &info.common,
@@ -1298,10 +1544,19 @@ impl<'a> SceneBuilder<'a> {
             DisplayItem::Clip(ref info) => {
                 let parent_space = self.get_space(&info.parent_space_and_clip.spatial_id);
                 let current_offset = self.current_offset(parent_space);
+                let path_clip = info.path_clip.map(|path_clip| {
+                    let segments = flatten_path(item.path_commands().iter(), info.clip_rect.origin);
+                    (path_clip, segments)
+                });
+                let text_clip = info.text_clip.and_then(|text_clip| {
+                    self.create_text_clip(&text_clip.font_key, item.glyphs(), info.clip_rect.origin)
+                });
                 let clip_region = ClipRegion::create_for_clip_node(
                     info.clip_rect,
                     item.complex_clip().iter(),
                     info.image_mask,
+                    path_clip,
+                    text_clip,
                     &current_offset,
                 );
                 self.add_clip_node(info.id, &info.parent_space_and_clip, clip_region);
@@ -1412,7 +1667,8 @@ impl<'a> SceneBuilder<'a> {
             DisplayItem::SetGradientStops |
             DisplayItem::SetFilterOps |
             DisplayItem::SetFilterData |
-            DisplayItem::SetFilterPrimitives => {}
+            DisplayItem::SetFilterPrimitives |
+            DisplayItem::SetItemKey(..) => {}
 
             DisplayItem::PopReferenceFrame |
             DisplayItem::PopStackingContext => {
@@ -1674,6 +1930,7 @@ impl<'a> SceneBuilder<'a> {
         clip_chain_id: ClipChainId,
         requested_raster_space: RasterSpace,
         is_backdrop_root: bool,
+        snapshot: Option<ImageKey>,
         device_pixel_scale: DevicePixelScale,
     ) {
         // Check if this stacking context is the root of a pipeline, and the caller
@@ -1781,6 +2038,10 @@ impl<'a> SceneBuilder<'a> {
             current_clip_chain_id = clip_chain_node.parent_clip_chain_id;
         }
 
+        if snapshot.is_some() {
+            blit_reason |= BlitReason::SNAPSHOT;
+        }
+
         let snap_to_device = self.sc_stack.last().map_or(
             SpaceSnapper::new(
                 ROOT_SPATIAL_NODE_INDEX,
@@ -1805,6 +2066,7 @@ impl<'a> SceneBuilder<'a> {
             context_3d,
             create_tile_cache,
             is_backdrop_root,
+            snapshot,
             snap_to_device,
         });
     }
@@ -1932,6 +2194,10 @@ impl<'a> SceneBuilder<'a> {
             ))
         );
 
+        // The snapshot holds the contents of the stacking context, before any
+        // filters or mix-blend-mode are applied to them.
+        self.prim_store.pictures[leaf_pic_index.0].snapshot = stacking_context.snapshot;
+
         // Create a chain of pictures based on presence of filters,
         // mix-blend-mode and/or 3d rendering context containers.
 
@@ -2188,6 +2454,7 @@ impl<'a> SceneBuilder<'a> {
             &viewport_rect,
             &content_size,
             ScrollSensitivity::ScriptAndInputEvents,
+            OverscrollBehavior::Auto,
             ScrollFrameKind::PipelineRoot,
             LayoutVector2D::zero(),
         );
@@ -2289,6 +2556,55 @@ impl<'a> SceneBuilder<'a> {
             clip_count += 1;
         }
 
+        if let Some((path_clip, segments)) = clip_region.path_clip {
+            let item = ClipItemKey {
+                kind: ClipItemKeyKind::path(
+                    snapped_clip_rect,
+                    segments,
+                    path_clip.fill_rule,
+                    path_clip.mode,
+                ),
+                spatial_node_index,
+            };
+
+            let handle = self
+                .interners
+                .clip
+                .intern(&item, || ClipNodeKind::Complex);
+
+            parent_clip_chain_index = self
+                .clip_store
+                .add_clip_chain_node(
+                    handle,
+                    parent_clip_chain_index,
+                );
+            clip_count += 1;
+        }
+
+        if let Some((font, glyphs)) = clip_region.text_clip {
+            let item = ClipItemKey {
+                kind: ClipItemKeyKind::text(
+                    snapped_clip_rect,
+                    font,
+                    glyphs,
+                ),
+                spatial_node_index,
+            };
+
+            let handle = self
+                .interners
+                .clip
+                .intern(&item, || ClipNodeKind::Complex);
+
+            parent_clip_chain_index = self
+                .clip_store
+                .add_clip_chain_node(
+                    handle,
+                    parent_clip_chain_index,
+                );
+            clip_count += 1;
+        }
+
         // Map the supplied ClipId -> clip chain id.
         self.id_to_index_mapper.add_clip_chain(
             new_node_id,
@@ -2308,6 +2624,7 @@ impl<'a> SceneBuilder<'a> {
         frame_rect: &LayoutRect,
         content_size: &LayoutSize,
         scroll_sensitivity: ScrollSensitivity,
+        overscroll_behavior: OverscrollBehavior,
         frame_kind: ScrollFrameKind,
         external_scroll_offset: LayoutVector2D,
     ) -> SpatialNodeIndex {
@@ -2318,6 +2635,7 @@ impl<'a> SceneBuilder<'a> {
             frame_rect,
             content_size,
             scroll_sensitivity,
+            overscroll_behavior,
             frame_kind,
             external_scroll_offset,
         );
@@ -2434,7 +2752,7 @@ impl<'a> SceneBuilder<'a> {
                         // blur radius is 0, the code in Picture::prepare_for_render will
                         // detect this and mark the picture to be drawn directly into the
                         // parent picture, which avoids an intermediate surface and blur.
-                        let mut blur_filter = Filter::Blur(std_deviation);
+                        let mut blur_filter = Filter::Blur(std_deviation, std_deviation);
                         blur_filter.sanitize();
                         let composite_mode = PictureCompositeMode::Filter(blur_filter);
                         let composite_mode_key = Some(composite_mode.clone()).into();
@@ -2620,13 +2938,19 @@ impl<'a> SceneBuilder<'a> {
         &mut self,
         clip_and_scroll: ScrollNodeAndClipChain,
         info: &LayoutPrimitiveInfo,
-        color: ColorF,
+        color: PropertyBinding<ColorF>,
     ) {
-        if color.a == 0.0 {
-            // Don't add transparent rectangles to the draw list, but do consider them for hit
-            // testing. This allows specifying invisible hit testing areas.
-            self.add_primitive_to_hit_testing_list(info, clip_and_scroll);
-            return;
+        match color {
+            PropertyBinding::Value(value) => {
+                if value.a == 0.0 {
+                    // Don't add transparent rectangles to the draw list,
+                    // but do consider them for hit testing. This allows
+                    // specifying invisible hit testing areas.
+                    self.add_primitive_to_hit_testing_list(info, clip_and_scroll);
+                    return;
+                }
+            },
+            PropertyBinding::Binding(..) => {},
         }
 
         self.add_primitive(
@@ -2724,6 +3048,54 @@ impl<'a> SceneBuilder<'a> {
         );
     }
 
+    pub fn add_path(
+        &mut self,
+        clip_and_scroll: ScrollNodeAndClipChain,
+        info: &LayoutPrimitiveInfo,
+        fill_rule: FillRule,
+        paint: &PathPaint,
+        commands: ItemRange<PathCommand>,
+        stops: ItemRange<GradientStop>,
+    ) {
+        // The outline is flattened relative to the primitive origin, so that
+        // the same path at different positions shares a render task.
+        let segments = flatten_path(commands.iter(), info.rect.origin);
+
+        let paint = match *paint {
+            PathPaint::Color(color) => VectorPathPaint::Color(color.into()),
+            PathPaint::Gradient(ref gradient) => {
+                let stops = stops.iter().map(|stop| {
+                    GradientStopKey {
+                        offset: stop.offset,
+                        color: stop.color.into(),
+                        midpoint: stop.midpoint,
+                    }
+                }).collect();
+
+                VectorPathPaint::LinearGradient {
+                    start_point: gradient.start_point.into(),
+                    end_point: gradient.end_point.into(),
+                    extend_mode: gradient.extend_mode,
+                    stops,
+                }
+            }
+        };
+
+        self.add_nonshadowable_primitive(
+            clip_and_scroll,
+            info,
+            Vec::new(),
+            VectorPath {
+                cache_key: VectorPathCacheKey {
+                    segments,
+                    fill_rule,
+                    paint,
+                    size: info.rect.size.into(),
+                },
+            },
+        );
+    }
+
     pub fn add_border(
         &mut self,
         clip_and_scroll: ScrollNodeAndClipChain,
@@ -2769,6 +3141,7 @@ impl<'a> SceneBuilder<'a> {
                             gradient.end_point,
                             gradient_stops,
                             gradient.extend_mode,
+                            gradient.color_space,
                             LayoutSize::new(border.height as f32, border.width as f32),
                             LayoutSize::zero(),
                             Some(Box::new(nine_patch)),
@@ -2793,6 +3166,7 @@ impl<'a> SceneBuilder<'a> {
                             gradient.radius.width / gradient.radius.height,
                             gradient_stops,
                             gradient.extend_mode,
+                            gradient.color_space,
                             LayoutSize::new(border.height as f32, border.width as f32),
                             LayoutSize::zero(),
                             Some(Box::new(nine_patch)),
@@ -2825,6 +3199,7 @@ impl<'a> SceneBuilder<'a> {
         end_point: LayoutPoint,
         stops: ItemRange<GradientStop>,
         extend_mode: ExtendMode,
+        color_space: GradientColorSpace,
         stretch_size: LayoutSize,
         mut tile_spacing: LayoutSize,
         nine_patch: Option<Box<NinePatchDescriptor>>,
@@ -2839,6 +3214,7 @@ impl<'a> SceneBuilder<'a> {
             GradientStopKey {
                 offset: stop.offset,
                 color: stop.color.into(),
+                midpoint: stop.midpoint,
             }
         }).collect();
 
@@ -2868,6 +3244,7 @@ impl<'a> SceneBuilder<'a> {
 
         Some(LinearGradient {
             extend_mode,
+            color_space,
             start_point: sp.into(),
             end_point: ep.into(),
             stretch_size: stretch_size.into(),
@@ -2887,6 +3264,7 @@ impl<'a> SceneBuilder<'a> {
         ratio_xy: f32,
         stops: ItemRange<GradientStop>,
         extend_mode: ExtendMode,
+        color_space: GradientColorSpace,
         stretch_size: LayoutSize,
         mut tile_spacing: LayoutSize,
         nine_patch: Option<Box<NinePatchDescriptor>>,
@@ -2904,10 +3282,53 @@ impl<'a> SceneBuilder<'a> {
             GradientStopKey {
                 offset: stop.offset,
                 color: stop.color.into(),
+                midpoint: stop.midpoint,
             }
         }).collect();
 
         RadialGradient {
+            extend_mode,
+            color_space,
+            center: center.into(),
+            params,
+            stretch_size: stretch_size.into(),
+            tile_spacing: tile_spacing.into(),
+            nine_patch,
+            stops,
+        }
+    }
+
+    pub fn create_conic_gradient_prim(
+        &mut self,
+        info: &LayoutPrimitiveInfo,
+        center: LayoutPoint,
+        angle: f32,
+        start_offset: f32,
+        end_offset: f32,
+        stops: ItemRange<GradientStop>,
+        extend_mode: ExtendMode,
+        stretch_size: LayoutSize,
+        mut tile_spacing: LayoutSize,
+        nine_patch: Option<Box<NinePatchDescriptor>>,
+    ) -> ConicGradient {
+        let mut prim_rect = info.rect;
+        simplify_repeated_primitive(&stretch_size, &mut tile_spacing, &mut prim_rect);
+
+        let params = ConicGradientParams {
+            angle,
+            start_offset,
+            end_offset,
+        };
+
+        let stops = stops.iter().map(|stop| {
+            GradientStopKey {
+                offset: stop.offset,
+                color: stop.color.into(),
+                midpoint: stop.midpoint,
+            }
+        }).collect();
+
+        ConicGradient {
             extend_mode,
             center: center.into(),
             params,
@@ -2994,6 +3415,55 @@ impl<'a> SceneBuilder<'a> {
         );
     }
 
+    /// Resolves the font of a text clip, and places its glyphs relative
+    /// to the origin of the clip rect.
+    fn create_text_clip(
+        &self,
+        font_instance_key: &FontInstanceKey,
+        glyph_range: ItemRange<GlyphInstance>,
+        origin: LayoutPoint,
+    ) -> Option<(FontInstance, Vec<GlyphInstance>)> {
+        let instance_map = self.font_instances.read().unwrap();
+        let font_instance = match instance_map.get(font_instance_key) {
+            Some(instance) => instance,
+            None => {
+                warn!("Unknown font instance key");
+                debug!("key={:?}", font_instance_key);
+                return None;
+            }
+        };
+
+        if font_instance.size.0 <= 0 {
+            return None;
+        }
+
+        // The coverage of the glyphs is all that matters, so they are
+        // rasterized in white, with grayscale AA and snapped to pixels.
+        let render_mode = self.config
+            .default_font_render_mode
+            .limit_by(font_instance.render_mode)
+            .limit_by(FontRenderMode::Alpha);
+        let mut font = FontInstance::new(
+            Arc::clone(font_instance),
+            ColorU::new(255, 255, 255, 255),
+            render_mode,
+            font_instance.flags,
+        );
+        font.disable_subpixel_position();
+
+        let glyphs = glyph_range
+            .iter()
+            .map(|glyph| {
+                GlyphInstance {
+                    index: glyph.index,
+                    point: glyph.point - origin.to_vector(),
+                }
+            })
+            .collect();
+
+        Some((font, glyphs))
+    }
+
     pub fn add_image(
         &mut self,
         clip_and_scroll: ScrollNodeAndClipChain,
@@ -3503,6 +3973,10 @@ struct FlattenedStackingContext {
     /// True if this stacking context is a backdrop root.
     is_backdrop_root: bool,
 
+    /// If set, the leaf picture of this stacking context is also
+    /// rendered into this snapshot image.
+    snapshot: Option<ImageKey>,
+
     /// A helper struct to snap local rects in device space. During frame
     /// building we may establish new raster roots, however typically that is in
     /// cases where we won't be applying snapping (e.g. has perspective), or in
@@ -3945,3 +4419,234 @@ fn add_clips(
         current_clip_chain_id = clip_chain_node.parent_clip_chain_id;
     }
 }
+
+#[cfg(test)]
+fn check_limits<F>(limits: DisplayListLimits, build: F) -> Result<(), DisplayListLimitError>
+where
+    F: FnOnce(&mut api::DisplayListBuilder, &CommonItemProperties),
+{
+    let pipeline_id = PipelineId::dummy();
+    let mut builder = api::DisplayListBuilder::new(pipeline_id, LayoutSize::new(100.0, 100.0));
+    let common = CommonItemProperties::new(
+        LayoutRect::new(LayoutPoint::zero(), LayoutSize::new(100.0, 100.0)),
+        SpaceAndClipInfo::root_scroll(pipeline_id),
+    );
+    build(&mut builder, &common);
+    limits.check(&builder.finalize().2)
+}
+
+#[test]
+fn test_display_list_limits_item_count() {
+    let limits = DisplayListLimits { max_items: 2, ..DisplayListLimits::default() };
+    let push_rects = |count| move |builder: &mut api::DisplayListBuilder, common: &CommonItemProperties| {
+        for _ in 0 .. count {
+            builder.push_rect(common, ColorF::WHITE);
+        }
+    };
+
+    assert_eq!(check_limits(limits, push_rects(2)), Ok(()));
+    assert_eq!(check_limits(limits, push_rects(3)), Err(DisplayListLimitError::ItemCount(3)));
+}
+
+#[test]
+fn test_display_list_limits_stacking_context_depth() {
+    let limits = DisplayListLimits { max_stacking_context_depth: 1, ..DisplayListLimits::default() };
+
+    // Depth is the number of open scopes, not the total number of them.
+    let result = check_limits(limits, |builder, common| {
+        for _ in 0 .. 2 {
+            builder.push_simple_stacking_context(LayoutPoint::zero(), common.spatial_id, PrimitiveFlags::empty());
+            builder.pop_stacking_context();
+        }
+    });
+    assert_eq!(result, Ok(()));
+
+    let result = check_limits(limits, |builder, common| {
+        let spatial_id = builder.push_reference_frame(
+            LayoutPoint::zero(),
+            common.spatial_id,
+            TransformStyle::Flat,
+            PropertyBinding::Value(LayoutTransform::identity()),
+            ReferenceFrameKind::Transform,
+        );
+        builder.push_simple_stacking_context(LayoutPoint::zero(), spatial_id, PrimitiveFlags::empty());
+        builder.pop_stacking_context();
+        builder.pop_reference_frame();
+    });
+    assert_eq!(result, Err(DisplayListLimitError::StackingContextDepth(2)));
+}
+
+#[test]
+fn test_display_list_limits_patch() {
+    let limits = DisplayListLimits {
+        max_items: 3,
+        max_stacking_context_depth: 1,
+        ..DisplayListLimits::default()
+    };
+    let pipeline_id = PipelineId::dummy();
+    let content_size = LayoutSize::new(100.0, 100.0);
+    let common = CommonItemProperties::new(
+        LayoutRect::new(LayoutPoint::zero(), content_size),
+        SpaceAndClipInfo::root_scroll(pipeline_id),
+    );
+
+    // Range 2 is nested in the stacking context of ranges 1 and 3.
+    let mut builder = api::DisplayListBuilder::new(pipeline_id, content_size);
+    builder.push_item_key(api::ItemKey(1));
+    builder.push_simple_stacking_context(LayoutPoint::zero(), common.spatial_id, PrimitiveFlags::empty());
+    builder.push_item_key(api::ItemKey(2));
+    builder.push_rect(&common, ColorF::WHITE);
+    builder.push_item_key(api::ItemKey(3));
+    builder.pop_stacking_context();
+    let list = builder.finalize().2;
+
+    let check_patch = |build: &dyn Fn(&mut api::DisplayListBuilder)| {
+        let mut builder = api::DisplayListBuilder::new_patch(pipeline_id, content_size, list.descriptor());
+        builder.push_item_key(api::ItemKey(2));
+        build(&mut builder);
+        let patch = builder.finalize().2;
+        let patched = list.apply_patch(&patch, &[api::DisplayListPatchOp::Replace(api::ItemKey(2))]).unwrap();
+        assert!(patched.spliced_items().is_some());
+        limits.check_patch(&patched)
+    };
+
+    // The items kept from the previous list count towards the limits.
+    assert_eq!(check_patch(&|builder| builder.push_rect(&common, ColorF::BLACK)), Ok(()));
+    assert_eq!(
+        check_patch(&|builder| {
+            builder.push_rect(&common, ColorF::BLACK);
+            builder.push_rect(&common, ColorF::BLACK);
+        }),
+        Err(DisplayListLimitError::ItemCount(4)),
+    );
+    assert_eq!(
+        check_patch(&|builder| {
+            builder.push_simple_stacking_context(LayoutPoint::zero(), common.spatial_id, PrimitiveFlags::empty());
+            builder.pop_stacking_context();
+        }),
+        Err(DisplayListLimitError::StackingContextDepth(2)),
+    );
+}
+
+#[test]
+fn test_display_list_limits_blur_radius() {
+    let limits = DisplayListLimits { max_blur_radius: 10.0, ..DisplayListLimits::default() };
+    let shadow = |blur_radius| Shadow {
+        offset: LayoutVector2D::zero(),
+        color: ColorF::BLACK,
+        blur_radius,
+    };
+    let push_filter = |filter| move |builder: &mut api::DisplayListBuilder, common: &CommonItemProperties| {
+        builder.push_simple_stacking_context_with_filters(
+            LayoutPoint::zero(),
+            common.spatial_id,
+            PrimitiveFlags::empty(),
+            &[filter],
+            &[],
+            &[],
+        );
+        builder.pop_stacking_context();
+    };
+
+    assert_eq!(check_limits(limits, push_filter(FilterOp::Blur(10.0, 10.0))), Ok(()));
+    assert_eq!(
+        check_limits(limits, push_filter(FilterOp::Blur(5.0, 20.0))),
+        Err(DisplayListLimitError::BlurRadius(20.0)),
+    );
+    assert_eq!(
+        check_limits(limits, push_filter(FilterOp::DropShadow(shadow(20.0)))),
+        Err(DisplayListLimitError::BlurRadius(20.0)),
+    );
+
+    let result = check_limits(limits, |builder, common| {
+        builder.push_box_shadow(
+            common,
+            common.clip_rect,
+            LayoutVector2D::zero(),
+            ColorF::BLACK,
+            20.0,
+            0.0,
+            api::BorderRadius::zero(),
+            api::BoxShadowClipMode::Outset,
+        );
+    });
+    assert_eq!(result, Err(DisplayListLimitError::BlurRadius(20.0)));
+
+    let result = check_limits(limits, |builder, common| {
+        let space_and_clip = SpaceAndClipInfo { spatial_id: common.spatial_id, clip_id: common.clip_id };
+        builder.push_shadow(&space_and_clip, shadow(20.0), true);
+        builder.pop_all_shadows();
+    });
+    assert_eq!(result, Err(DisplayListLimitError::BlurRadius(20.0)));
+
+    // NaN radii are rejected too.
+    match check_limits(limits, push_filter(FilterOp::Blur(f32::NAN, 1.0))) {
+        Err(DisplayListLimitError::BlurRadius(radius)) => assert!(radius.is_nan()),
+        result => panic!("unexpected result {:?}", result),
+    }
+}
+
+#[test]
+fn test_display_list_limits_glyphs_per_text_run() {
+    let limits = DisplayListLimits { max_glyphs_per_text_run: 2, ..DisplayListLimits::default() };
+    let push_text = |count| move |builder: &mut api::DisplayListBuilder, common: &CommonItemProperties| {
+        let glyphs: Vec<_> = (0 .. count)
+            .map(|index| GlyphInstance { index, point: LayoutPoint::zero() })
+            .collect();
+        builder.push_text(
+            common,
+            common.clip_rect,
+            &glyphs,
+            FontInstanceKey::new(api::IdNamespace(0), 0),
+            ColorF::BLACK,
+            None,
+        );
+    };
+
+    assert_eq!(check_limits(limits, push_text(2)), Ok(()));
+    assert_eq!(check_limits(limits, push_text(3)), Err(DisplayListLimitError::GlyphsPerTextRun(3)));
+}
+
+#[test]
+fn test_display_list_limits_gradient_stops() {
+    let limits = DisplayListLimits { max_gradient_stops: 2, ..DisplayListLimits::default() };
+    let push_gradient = |count| move |builder: &mut api::DisplayListBuilder, common: &CommonItemProperties| {
+        let stops = (0 .. count)
+            .map(|i| GradientStop { offset: i as f32, color: ColorF::WHITE, midpoint: None })
+            .collect();
+        let gradient = builder.create_gradient(
+            LayoutPoint::zero(),
+            LayoutPoint::new(100.0, 0.0),
+            stops,
+            ExtendMode::Clamp,
+        );
+        builder.push_gradient(common, common.clip_rect, gradient, common.clip_rect.size, LayoutSize::zero());
+    };
+
+    assert_eq!(check_limits(limits, push_gradient(2)), Ok(()));
+    assert_eq!(check_limits(limits, push_gradient(3)), Err(DisplayListLimitError::GradientStops(3)));
+}
+
+#[test]
+fn test_display_list_limits_image_tile_count() {
+    let limits = DisplayListLimits { max_image_tile_count: 99, ..DisplayListLimits::default() };
+    let push_image = |stretch_size| move |builder: &mut api::DisplayListBuilder, common: &CommonItemProperties| {
+        builder.push_repeating_image(
+            common,
+            common.clip_rect,
+            stretch_size,
+            LayoutSize::zero(),
+            ImageRendering::Auto,
+            AlphaType::PremultipliedAlpha,
+            ImageKey::DUMMY,
+            ColorF::WHITE,
+        );
+    };
+
+    // 9 x 10 and 10 x 10 tiles over the 100 x 100 bounds.
+    assert_eq!(check_limits(limits, push_image(LayoutSize::new(12.0, 10.0))), Ok(()));
+    assert_eq!(
+        check_limits(limits, push_image(LayoutSize::new(10.0, 10.0))),
+        Err(DisplayListLimitError::ImageTileCount(100.0)),
+    );
+}
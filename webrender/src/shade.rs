@@ -391,12 +391,16 @@ pub struct Shaders<B: hal::Backend> {
     // of these shaders are then used by the primitive shaders.
     pub cs_blur_a8: LazilyCompiledShader<B>,
     pub cs_blur_rgba8: LazilyCompiledShader<B>,
+    pub cs_dual_filter_a8: LazilyCompiledShader<B>,
+    pub cs_dual_filter_rgba8: LazilyCompiledShader<B>,
     pub cs_border_segment: LazilyCompiledShader<B>,
     pub cs_border_solid: LazilyCompiledShader<B>,
     pub cs_scale: LazilyCompiledShader<B>,
     pub cs_line_decoration: LazilyCompiledShader<B>,
     pub cs_gradient: LazilyCompiledShader<B>,
     pub cs_svg_filter: LazilyCompiledShader<B>,
+    pub pf_vector_stencil: LazilyCompiledShader<B>,
+    pub pf_vector_cover: LazilyCompiledShader<B>,
 
     // Brush shaders
     brush_solid: BrushShader<B>,
@@ -406,6 +410,7 @@ pub struct Shaders<B: hal::Backend> {
     brush_mix_blend: BrushShader<B>,
     brush_yuv_image: Vec<Option<BrushShader<B>>>,
     brush_radial_gradient: BrushShader<B>,
+    brush_conic_gradient: BrushShader<B>,
     brush_linear_gradient: BrushShader<B>,
 
     /// These are "cache clip shaders". These shaders are used to
@@ -496,6 +501,20 @@ impl<B: hal::Backend> Shaders<B> {
             use_pixel_local_storage,
         )?;
 
+        let brush_conic_gradient = BrushShader::new(
+            "brush_conic_gradient",
+            device,
+            if options.enable_dithering {
+               &[DITHERING_FEATURE]
+            } else {
+               &[]
+            },
+            options.precache_flags,
+            false /* advanced blend */,
+            false /* dual source */,
+            use_pixel_local_storage,
+        )?;
+
         let brush_linear_gradient = BrushShader::new(
             "brush_linear_gradient",
             device,
@@ -526,6 +545,22 @@ impl<B: hal::Backend> Shaders<B> {
             options.precache_flags,
         )?;
 
+        let cs_dual_filter_a8 = LazilyCompiledShader::new(
+            ShaderKind::Cache(VertexArrayKind::Blur),
+            "cs_dual_filter",
+            &["ALPHA_TARGET"],
+            device,
+            options.precache_flags,
+        )?;
+
+        let cs_dual_filter_rgba8 = LazilyCompiledShader::new(
+            ShaderKind::Cache(VertexArrayKind::Blur),
+            "cs_dual_filter",
+            &["COLOR_TARGET"],
+            device,
+            options.precache_flags,
+        )?;
+
         let cs_svg_filter = LazilyCompiledShader::new(
             ShaderKind::Cache(VertexArrayKind::SvgFilter),
             "cs_svg_filter",
@@ -723,6 +758,22 @@ impl<B: hal::Backend> Shaders<B> {
             options.precache_flags,
         )?;
 
+        let pf_vector_stencil = LazilyCompiledShader::new(
+            ShaderKind::VectorStencil,
+            "pf_vector_stencil",
+            &[],
+            device,
+            options.precache_flags,
+        )?;
+
+        let pf_vector_cover = LazilyCompiledShader::new(
+            ShaderKind::VectorCover,
+            "pf_vector_cover",
+            &[],
+            device,
+            options.precache_flags,
+        )?;
+
         let cs_border_segment = LazilyCompiledShader::new(
             ShaderKind::Cache(VertexArrayKind::Border),
             "cs_border_segment",
@@ -750,12 +801,16 @@ impl<B: hal::Backend> Shaders<B> {
         Ok(Shaders {
             cs_blur_a8,
             cs_blur_rgba8,
+            cs_dual_filter_a8,
+            cs_dual_filter_rgba8,
             cs_border_segment,
             cs_line_decoration,
             cs_gradient,
             cs_border_solid,
             cs_scale,
             cs_svg_filter,
+            pf_vector_stencil,
+            pf_vector_cover,
             brush_solid,
             brush_image,
             brush_fast_image,
@@ -763,6 +818,7 @@ impl<B: hal::Backend> Shaders<B> {
             brush_mix_blend,
             brush_yuv_image,
             brush_radial_gradient,
+            brush_conic_gradient,
             brush_linear_gradient,
             cs_clip_rectangle_slow,
             cs_clip_rectangle_fast,
@@ -814,6 +870,9 @@ impl<B: hal::Backend> Shaders<B> {
                     BrushBatchKind::RadialGradient => {
                         &mut self.brush_radial_gradient
                     }
+                    BrushBatchKind::ConicGradient => {
+                        &mut self.brush_conic_gradient
+                    }
                     BrushBatchKind::LinearGradient => {
                         &mut self.brush_linear_gradient
                     }
@@ -841,11 +900,14 @@ impl<B: hal::Backend> Shaders<B> {
         self.cs_scale.deinit(device);
         self.cs_blur_a8.deinit(device);
         self.cs_blur_rgba8.deinit(device);
+        self.cs_dual_filter_a8.deinit(device);
+        self.cs_dual_filter_rgba8.deinit(device);
         self.cs_svg_filter.deinit(device);
         self.brush_solid.deinit(device);
         self.brush_blend.deinit(device);
         self.brush_mix_blend.deinit(device);
         self.brush_radial_gradient.deinit(device);
+        self.brush_conic_gradient.deinit(device);
         self.brush_linear_gradient.deinit(device);
         self.cs_clip_rectangle_slow.deinit(device);
         self.cs_clip_rectangle_fast.deinit(device);
@@ -873,6 +935,8 @@ impl<B: hal::Backend> Shaders<B> {
         self.cs_border_solid.deinit(device);
         self.cs_gradient.deinit(device);
         self.cs_line_decoration.deinit(device);
+        self.pf_vector_stencil.deinit(device);
+        self.pf_vector_cover.deinit(device);
         self.cs_border_segment.deinit(device);
         self.ps_split_composite.deinit(device);
         self.composite.deinit(device);
@@ -4,13 +4,18 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use api::{ExternalScrollId, PipelineId, PropertyBinding, PropertyBindingId, ReferenceFrameKind, ScrollClamping, ScrollLocation};
-use api::{TransformStyle, ScrollSensitivity, StickyOffsetBounds};
+use api::{OverscrollBehavior, TransformStyle, ScrollEasing, ScrollSensitivity, StickyOffsetBounds};
 use api::units::*;
 use crate::clip_scroll_tree::{CoordinateSystem, CoordinateSystemId, SpatialNodeIndex, TransformUpdateState};
 use euclid::{Scale, SideOffsets2D};
 use crate::scene::SceneProperties;
 use crate::util::{LayoutFastTransform, MatrixHelpers, ScaleOffset, TransformedRectKind, VectorHelpers};
 
+/// Flings slower than this, in layout pixels per second, come to a stop.
+const MIN_FLING_VELOCITY: f32 = 10.0;
+/// Lower bound of the fling friction, so that every fling eventually stops.
+const MIN_FLING_FRICTION: f32 = 0.1;
+
 #[derive(Clone, Debug)]
 pub enum SpatialNodeType {
     /// A special kind of node that adjusts its position based on the position
@@ -152,12 +157,14 @@ impl SpatialNode {
         frame_rect: &LayoutRect,
         content_size: &LayoutSize,
         scroll_sensitivity: ScrollSensitivity,
+        overscroll_behavior: OverscrollBehavior,
         frame_kind: ScrollFrameKind,
         external_scroll_offset: LayoutVector2D,
     ) -> Self {
         let node_type = SpatialNodeType::ScrollFrame(ScrollFrameInfo::new(
                 *frame_rect,
                 scroll_sensitivity,
+                overscroll_behavior,
                 LayoutSize::new(
                     (content_size.width - frame_rect.size.width).max(0.0),
                     (content_size.height - frame_rect.size.height).max(0.0)
@@ -222,27 +229,14 @@ impl SpatialNode {
             }
         };
 
-        let normalized_offset = match clamp {
-            ScrollClamping::ToContentBounds => {
-                let scrollable_size = scrolling.scrollable_size;
-                let scrollable_width = scrollable_size.width;
-                let scrollable_height = scrollable_size.height;
-
-                if scrollable_height <= 0. && scrollable_width <= 0. {
-                    return false;
-                }
+        // An explicit scroll position wins over any running animation.
+        scrolling.animation = None;
 
-                let origin = LayoutPoint::new(origin.x.max(0.0), origin.y.max(0.0));
-                LayoutVector2D::new(
-                    (-origin.x).max(-scrollable_width).min(0.0).round(),
-                    (-origin.y).max(-scrollable_height).min(0.0).round(),
-                )
-            }
-            ScrollClamping::NoClamping => LayoutPoint::zero() - *origin,
+        let new_offset = match scrolling.offset_for_origin(origin, clamp) {
+            Some(offset) => offset,
+            None => return false,
         };
 
-        let new_offset = normalized_offset - scrolling.external_scroll_offset;
-
         if new_offset == scrolling.offset {
             return false;
         }
@@ -589,7 +583,7 @@ impl SpatialNode {
         }
     }
 
-    pub fn scroll(&mut self, scroll_location: ScrollLocation) -> bool {
+    pub fn scroll(&mut self, scroll_location: ScrollLocation, now: u64) -> bool {
         // TODO(gw): This scroll method doesn't currently support
         //           scroll nodes with non-zero external scroll
         //           offsets. However, it's never used by Gecko,
@@ -601,8 +595,15 @@ impl SpatialNode {
             _ => return false,
         };
 
-        let delta = match scroll_location {
-            ScrollLocation::Delta(delta) => delta,
+        // Any new scroll interrupts a running animation.
+        scrolling.animation = None;
+
+        match scroll_location {
+            ScrollLocation::Delta(delta) => {
+                let original_layer_scroll_offset = scrolling.offset;
+                scrolling.scroll_by(delta);
+                scrolling.offset != original_layer_scroll_offset
+            }
             ScrollLocation::Start => {
                 if scrolling.offset.y.round() >= 0.0 {
                     // Nothing to do on this layer.
@@ -610,7 +611,7 @@ impl SpatialNode {
                 }
 
                 scrolling.offset.y = 0.0;
-                return true;
+                true
             }
             ScrollLocation::End => {
                 let end_pos = -scrolling.scrollable_size.height;
@@ -620,29 +621,51 @@ impl SpatialNode {
                 }
 
                 scrolling.offset.y = end_pos;
-                return true;
+                true
             }
-        };
+            ScrollLocation::SmoothTo { origin, duration_ms, easing, clamp } => {
+                let clamp = scrolling.input_clamping(clamp);
+                let target = match scrolling.offset_for_origin(&origin, clamp) {
+                    Some(offset) => scrolling.origin_for_offset(offset),
+                    None => return false,
+                };
+                scrolling.start_animation(ScrollAnimationKind::Smooth {
+                    target,
+                    duration_ns: duration_ms as u64 * 1_000_000,
+                    easing,
+                }, clamp, now);
+                true
+            }
+            ScrollLocation::Fling { velocity, friction, clamp } => {
+                let clamp = scrolling.input_clamping(clamp);
+                scrolling.start_animation(ScrollAnimationKind::Fling {
+                    velocity,
+                    friction: friction.max(MIN_FLING_FRICTION),
+                }, clamp, now);
+                true
+            }
+        }
+    }
 
-        let scrollable_width = scrolling.scrollable_size.width;
-        let scrollable_height = scrolling.scrollable_size.height;
-        let original_layer_scroll_offset = scrolling.offset;
+    /// Scrolls this node by `delta` within its content bounds. Returns whether
+    /// the offset changed, and the part of the delta to pass on to the
+    /// enclosing scroll frames.
+    pub fn scroll_by_delta(&mut self, delta: LayoutVector2D) -> (bool, LayoutVector2D) {
+        let scrolling = match self.node_type {
+            SpatialNodeType::ScrollFrame(ref mut scrolling) => scrolling,
+            _ => return (false, delta),
+        };
 
-        if scrollable_width > 0. {
-            scrolling.offset.x = (scrolling.offset.x + delta.x)
-                .min(0.0)
-                .max(-scrollable_width)
-                .round();
-        }
+        scrolling.animation = None;
 
-        if scrollable_height > 0. {
-            scrolling.offset.y = (scrolling.offset.y + delta.y)
-                .min(0.0)
-                .max(-scrollable_height)
-                .round();
-        }
+        let original_layer_scroll_offset = scrolling.offset;
+        let remaining = scrolling.scroll_by(delta);
+        let remaining = match scrolling.overscroll_behavior {
+            OverscrollBehavior::Auto => remaining,
+            OverscrollBehavior::Contain | OverscrollBehavior::None => LayoutVector2D::zero(),
+        };
 
-        scrolling.offset != original_layer_scroll_offset
+        (scrolling.offset != original_layer_scroll_offset, remaining)
     }
 
     pub fn scroll_offset(&self) -> LayoutVector2D {
@@ -739,6 +762,10 @@ pub struct ScrollFrameInfo {
 
     pub scroll_sensitivity: ScrollSensitivity,
 
+    /// Whether scroll deltas this frame can't absorb propagate to the
+    /// enclosing scroll frames.
+    pub overscroll_behavior: OverscrollBehavior,
+
     /// Amount that this ScrollFrame can scroll in both directions.
     pub scrollable_size: LayoutSize,
 
@@ -762,6 +789,9 @@ pub struct ScrollFrameInfo {
 
     /// The current offset of this scroll node.
     pub offset: LayoutVector2D,
+
+    /// The smooth scroll or fling currently moving this node, if any.
+    pub animation: Option<ScrollAnimation>,
 }
 
 /// Manages scrolling offset.
@@ -769,6 +799,7 @@ impl ScrollFrameInfo {
     pub fn new(
         viewport_rect: LayoutRect,
         scroll_sensitivity: ScrollSensitivity,
+        overscroll_behavior: OverscrollBehavior,
         scrollable_size: LayoutSize,
         external_id: Option<ExternalScrollId>,
         frame_kind: ScrollFrameKind,
@@ -778,10 +809,12 @@ impl ScrollFrameInfo {
             viewport_rect,
             offset: -external_scroll_offset,
             scroll_sensitivity,
+            overscroll_behavior,
             scrollable_size,
             external_id,
             frame_kind,
             external_scroll_offset,
+            animation: None,
         }
     }
 
@@ -805,10 +838,183 @@ impl ScrollFrameInfo {
             viewport_rect: self.viewport_rect,
             offset,
             scroll_sensitivity: self.scroll_sensitivity,
+            overscroll_behavior: self.overscroll_behavior,
             scrollable_size: self.scrollable_size,
             external_id: self.external_id,
             frame_kind: self.frame_kind,
             external_scroll_offset: self.external_scroll_offset,
+            animation: old_scroll_info.animation,
+        }
+    }
+
+    /// Returns the scroll origin, as passed to `set_scroll_origin`, that
+    /// corresponds to `offset`.
+    pub fn origin_for_offset(&self, offset: LayoutVector2D) -> LayoutPoint {
+        LayoutPoint::zero() - (offset + self.external_scroll_offset)
+    }
+
+    /// Returns the offset of this node when scrolled to `origin`, or None if
+    /// it can't scroll at all within the requested clamping.
+    pub fn offset_for_origin(
+        &self,
+        origin: &LayoutPoint,
+        clamp: ScrollClamping,
+    ) -> Option<LayoutVector2D> {
+        let normalized_offset = match clamp {
+            ScrollClamping::ToContentBounds => {
+                let scrollable_width = self.scrollable_size.width;
+                let scrollable_height = self.scrollable_size.height;
+
+                if scrollable_height <= 0. && scrollable_width <= 0. {
+                    return None;
+                }
+
+                let origin = LayoutPoint::new(origin.x.max(0.0), origin.y.max(0.0));
+                LayoutVector2D::new(
+                    (-origin.x).max(-scrollable_width).min(0.0).round(),
+                    (-origin.y).max(-scrollable_height).min(0.0).round(),
+                )
+            }
+            ScrollClamping::NoClamping => LayoutPoint::zero() - *origin,
+        };
+
+        Some(normalized_offset - self.external_scroll_offset)
+    }
+
+    /// Applies `delta` to the offset, clamped to the content bounds. Returns
+    /// the part of the delta that went past the bounds.
+    fn scroll_by(&mut self, delta: LayoutVector2D) -> LayoutVector2D {
+        let scrollable_width = self.scrollable_size.width;
+        let scrollable_height = self.scrollable_size.height;
+        let mut remaining = delta;
+
+        if scrollable_width > 0. {
+            let x = self.offset.x + delta.x;
+            let clamped_x = x.min(0.0).max(-scrollable_width);
+            self.offset.x = clamped_x.round();
+            remaining.x = x - clamped_x;
+        }
+
+        if scrollable_height > 0. {
+            let y = self.offset.y + delta.y;
+            let clamped_y = y.min(0.0).max(-scrollable_height);
+            self.offset.y = clamped_y.round();
+            remaining.y = y - clamped_y;
+        }
+
+        remaining
+    }
+
+    /// Input scrolls never move frames with `OverscrollBehavior::None` past
+    /// their content bounds.
+    fn input_clamping(&self, clamp: ScrollClamping) -> ScrollClamping {
+        match self.overscroll_behavior {
+            OverscrollBehavior::None => ScrollClamping::ToContentBounds,
+            OverscrollBehavior::Auto | OverscrollBehavior::Contain => clamp,
+        }
+    }
+
+    fn start_animation(&mut self, kind: ScrollAnimationKind, clamp: ScrollClamping, now: u64) {
+        self.animation = Some(ScrollAnimation {
+            start_time: now,
+            start_origin: self.origin_for_offset(self.offset),
+            kind,
+            clamp,
+        });
+    }
+
+    /// Moves the node to where its animation is at `now`, and drops the
+    /// animation once it is over. Returns true if the node was animating.
+    pub fn tick_animation(&mut self, now: u64) -> bool {
+        let animation = match self.animation {
+            Some(animation) => animation,
+            None => return false,
+        };
+
+        let (origin, mut finished) = animation.sample(now);
+
+        if animation.clamp == ScrollClamping::ToContentBounds {
+            if let ScrollAnimationKind::Fling { velocity, .. } = animation.kind {
+                // A fling stops once it has hit the content bounds on every
+                // axis it moves along. The origin moves against the velocity.
+                let max_origin = self.scrollable_size.max(LayoutSize::zero());
+                let pinned_x = (velocity.x > 0.0 && origin.x <= 0.0) ||
+                    (velocity.x < 0.0 && origin.x >= max_origin.width) ||
+                    velocity.x == 0.0;
+                let pinned_y = (velocity.y > 0.0 && origin.y <= 0.0) ||
+                    (velocity.y < 0.0 && origin.y >= max_origin.height) ||
+                    velocity.y == 0.0;
+                finished |= pinned_x && pinned_y;
+            }
+        }
+
+        match self.offset_for_origin(&origin, animation.clamp) {
+            Some(offset) => self.offset = offset,
+            None => finished = true,
+        }
+
+        if finished {
+            self.animation = None;
+        }
+
+        true
+    }
+}
+
+/// A scroll animation ticked by the render backend each time it builds a frame.
+/// It is expressed in scroll origins, so that it survives changes of the
+/// external scroll offset between scenes.
+#[derive(Copy, Clone, Debug)]
+pub struct ScrollAnimation {
+    /// Time at which the animation started, in nanoseconds.
+    pub start_time: u64,
+    pub start_origin: LayoutPoint,
+    pub kind: ScrollAnimationKind,
+    pub clamp: ScrollClamping,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum ScrollAnimationKind {
+    /// Interpolates towards `target` along the easing curve.
+    Smooth {
+        target: LayoutPoint,
+        duration_ns: u64,
+        easing: ScrollEasing,
+    },
+    /// Moves with a velocity that decays by `friction` per second, so the
+    /// distance covered after t seconds is velocity / friction * (1 - e^(-friction * t)).
+    Fling {
+        velocity: LayoutVector2D,
+        friction: f32,
+    },
+}
+
+impl ScrollAnimation {
+    /// Returns the unclamped scroll origin at `now`, and whether the
+    /// animation is over.
+    pub fn sample(&self, now: u64) -> (LayoutPoint, bool) {
+        let elapsed_ns = now.saturating_sub(self.start_time);
+
+        match self.kind {
+            ScrollAnimationKind::Smooth { target, duration_ns, easing } => {
+                if elapsed_ns >= duration_ns {
+                    return (target, true);
+                }
+                let progress = easing.apply(elapsed_ns as f32 / duration_ns as f32);
+                (self.start_origin.lerp(target, progress), false)
+            }
+            ScrollAnimationKind::Fling { velocity, friction } => {
+                let speed = velocity.length();
+                let stop_time = if speed > MIN_FLING_VELOCITY {
+                    (speed / MIN_FLING_VELOCITY).ln() / friction
+                } else {
+                    0.0
+                };
+                let elapsed = (elapsed_ns as f64 / 1_000_000_000.0) as f32;
+                let t = elapsed.min(stop_time);
+                let distance = velocity * ((1.0 - (-friction * t).exp()) / friction);
+                (self.start_origin - distance, elapsed >= stop_time)
+            }
         }
     }
 }
@@ -897,6 +1103,7 @@ fn test_cst_perspective_relative_scroll() {
         &LayoutRect::new(LayoutPoint::zero(), LayoutSize::new(100.0, 100.0)),
         &LayoutSize::new(100.0, 500.0),
         ScrollSensitivity::Script,
+        OverscrollBehavior::Auto,
         ScrollFrameKind::Explicit,
         LayoutVector2D::zero(),
     );
@@ -908,6 +1115,7 @@ fn test_cst_perspective_relative_scroll() {
         &LayoutRect::new(LayoutPoint::zero(), LayoutSize::new(100.0, 100.0)),
         &LayoutSize::new(100.0, 500.0),
         ScrollSensitivity::Script,
+        OverscrollBehavior::Auto,
         ScrollFrameKind::Explicit,
         LayoutVector2D::new(0.0, 50.0),
     );
@@ -934,3 +1142,74 @@ fn test_cst_perspective_relative_scroll() {
     assert!(scroll_offset.x.approx_eq(&0.0));
     assert!(scroll_offset.y.approx_eq(&0.0));
 }
+
+#[test]
+fn test_scroll_animations() {
+    use crate::clip_scroll_tree::ClipScrollTree;
+
+    let mut cst = ClipScrollTree::new();
+    let pipeline_id = PipelineId::dummy();
+    let ext_scroll_id = ExternalScrollId(1, pipeline_id);
+
+    let root = cst.add_reference_frame(
+        None,
+        TransformStyle::Flat,
+        PropertyBinding::Value(LayoutTransform::identity()),
+        ReferenceFrameKind::Transform,
+        LayoutVector2D::zero(),
+        pipeline_id,
+    );
+
+    let scroll_frame = cst.add_scroll_frame(
+        root,
+        Some(ext_scroll_id),
+        pipeline_id,
+        &LayoutRect::new(LayoutPoint::zero(), LayoutSize::new(100.0, 100.0)),
+        &LayoutSize::new(100.0, 500.0),
+        ScrollSensitivity::ScriptAndInputEvents,
+        OverscrollBehavior::Auto,
+        ScrollFrameKind::Explicit,
+        LayoutVector2D::zero(),
+    );
+
+    let ms = 1_000_000;
+    let offset_y = |cst: &ClipScrollTree| cst.spatial_nodes[scroll_frame.0 as usize].scroll_offset().y;
+
+    // A smooth scroll reaches the middle of the distance halfway through
+    // an ease-in-out curve, and stops on the target.
+    assert!(cst.scroll_nearest_scrolling_ancestor(
+        ScrollLocation::SmoothTo {
+            origin: LayoutPoint::new(0.0, 200.0),
+            duration_ms: 100,
+            easing: ScrollEasing::EaseInOut,
+            clamp: ScrollClamping::ToContentBounds,
+        },
+        Some(scroll_frame),
+        0,
+    ));
+    assert!(cst.tick_scroll_animations(50 * ms));
+    assert_eq!(offset_y(&cst), -100.0);
+    assert!(cst.get_scroll_node_state()[0].is_animating);
+    assert!(cst.tick_scroll_animations(100 * ms));
+    assert_eq!(offset_y(&cst), -200.0);
+    assert!(!cst.has_scroll_animations());
+    assert!(!cst.tick_scroll_animations(150 * ms));
+
+    // A fling decelerates and stops at the end of the content.
+    assert!(cst.scroll_nearest_scrolling_ancestor(
+        ScrollLocation::Fling {
+            velocity: LayoutVector2D::new(0.0, -1000.0),
+            friction: 1.0,
+            clamp: ScrollClamping::ToContentBounds,
+        },
+        Some(scroll_frame),
+        0,
+    ));
+    assert!(cst.tick_scroll_animations(100 * ms));
+    assert_eq!(offset_y(&cst), -295.0);
+    assert!(cst.has_scroll_animations());
+    assert!(cst.tick_scroll_animations(1000 * ms));
+    assert_eq!(offset_y(&cst), -400.0);
+    assert!(!cst.has_scroll_animations());
+    assert!(!cst.get_scroll_node_state()[0].is_animating);
+}
@@ -0,0 +1,424 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! A reference implementation of the `Compositor` interface.
+//!
+//! WebRender draws the picture cache tiles into GL framebuffers owned by
+//! the compositor, which copies their contents back into CPU memory as
+//! they are unbound. Composition itself happens entirely on the CPU, and
+//! the resulting frame is presented by blitting it into the framebuffer
+//! that is bound when the frame ends. Nothing here depends on the
+//! windowing system, so it can be exercised in headless configurations
+//! (e.g. by wrench with `--sw-compositor`), and serves as an example for
+//! embedders integrating with an OS compositor.
+
+use api::ColorF;
+use api::units::*;
+use gleam::gl;
+use crate::composite::{Compositor, CompositorSurfaceTransform, NativeSurfaceId, NativeSurfaceInfo};
+use crate::internal_types::FastHashMap;
+use std::rc::Rc;
+
+const BYTES_PER_PIXEL: usize = 4;
+
+/// A surface created through the compositor interface.
+struct SwSurface {
+    size: DeviceIntSize,
+    is_opaque: bool,
+    fbo: gl::GLuint,
+    color_rbo: gl::GLuint,
+    depth_rbo: gl::GLuint,
+    /// The contents of the surface, as premultiplied RGBA with the first
+    /// row at the top.
+    pixels: Vec<u8>,
+}
+
+/// A surface placed in the visual tree of the current frame.
+struct SwVisual {
+    id: NativeSurfaceId,
+    transform: CompositorSurfaceTransform,
+    clip_rect: DeviceIntRect,
+}
+
+/// The surface currently bound for drawing by WebRender.
+struct BoundSurface {
+    id: NativeSurfaceId,
+    dirty_rect: DeviceIntRect,
+}
+
+/// The GL objects the composited frame is uploaded to before presenting.
+struct PresentTarget {
+    size: DeviceIntSize,
+    texture: gl::GLuint,
+    fbo: gl::GLuint,
+}
+
+/// A compositor that composites the native surfaces on the CPU.
+pub struct SwCompositor {
+    gl: Rc<dyn gl::Gl>,
+    clear_color: ColorF,
+    surfaces: FastHashMap<NativeSurfaceId, SwSurface>,
+    visuals: Vec<SwVisual>,
+    bound_surface: Option<BoundSurface>,
+    device_size: DeviceIntSize,
+    /// The most recently composited frame, as premultiplied RGBA with the
+    /// first row at the top.
+    frame: Vec<u8>,
+    present_target: Option<PresentTarget>,
+}
+
+impl SwCompositor {
+    pub fn new(gl: Rc<dyn gl::Gl>, clear_color: ColorF) -> Self {
+        SwCompositor {
+            gl,
+            clear_color,
+            surfaces: FastHashMap::default(),
+            visuals: Vec::new(),
+            bound_surface: None,
+            device_size: DeviceIntSize::zero(),
+            frame: Vec::new(),
+            present_target: None,
+        }
+    }
+
+    /// The size of the most recently composited frame.
+    pub fn frame_size(&self) -> DeviceIntSize {
+        self.device_size
+    }
+
+    /// The pixels of the most recently composited frame, as premultiplied
+    /// RGBA with the first row at the top.
+    pub fn frame_pixels(&self) -> &[u8] {
+        &self.frame
+    }
+
+    fn get_integer(&self, name: gl::GLenum) -> gl::GLuint {
+        let mut value = [0];
+        unsafe {
+            self.gl.get_integer_v(name, &mut value);
+        }
+        value[0] as gl::GLuint
+    }
+
+    /// Composite the visual tree of the current frame into `self.frame`.
+    fn composite(&mut self) {
+        let width = self.device_size.width.max(0) as usize;
+        let height = self.device_size.height.max(0) as usize;
+
+        let clear = self.clear_color.premultiplied().to_array();
+        let clear = [
+            to_u8(clear[0]),
+            to_u8(clear[1]),
+            to_u8(clear[2]),
+            to_u8(clear[3]),
+        ];
+        self.frame.clear();
+        self.frame.reserve(width * height * BYTES_PER_PIXEL);
+        for _ in 0 .. width * height {
+            self.frame.extend_from_slice(&clear);
+        }
+
+        let device_rect = DeviceIntRect::new(DeviceIntPoint::zero(), self.device_size);
+
+        for visual in &self.visuals {
+            let surface = match self.surfaces.get(&visual.id) {
+                Some(surface) => surface,
+                None => continue,
+            };
+            let inverse = match visual.transform.inverse() {
+                Some(inverse) => inverse,
+                None => continue,
+            };
+
+            let surface_rect = DeviceRect::new(DevicePoint::zero(), surface.size.to_f32());
+            let bounds = visual.transform
+                .transform_rect(&surface_rect)
+                .round_out()
+                .to_i32();
+            let rect = match bounds
+                .intersection(&visual.clip_rect)
+                .and_then(|rect| rect.intersection(&device_rect))
+            {
+                Some(rect) => rect,
+                None => continue,
+            };
+
+            let surface_width = surface.size.width as usize;
+
+            for y in rect.min_y() .. rect.max_y() {
+                for x in rect.min_x() .. rect.max_x() {
+                    // Sample the surface at the center of the device pixel.
+                    let p = inverse.transform_point(DevicePoint::new(x as f32 + 0.5, y as f32 + 0.5));
+                    let sx = p.x.floor() as i32;
+                    let sy = p.y.floor() as i32;
+                    if sx < 0 || sy < 0 || sx >= surface.size.width || sy >= surface.size.height {
+                        continue;
+                    }
+
+                    let src_offset = (sy as usize * surface_width + sx as usize) * BYTES_PER_PIXEL;
+                    let dst_offset = (y as usize * width + x as usize) * BYTES_PER_PIXEL;
+                    let src = &surface.pixels[src_offset .. src_offset + BYTES_PER_PIXEL];
+                    let dst = &mut self.frame[dst_offset .. dst_offset + BYTES_PER_PIXEL];
+
+                    if surface.is_opaque {
+                        dst.copy_from_slice(src);
+                    } else {
+                        // Premultiplied alpha "over".
+                        let inv_alpha = 255 - src[3] as u32;
+                        for (d, s) in dst.iter_mut().zip(src) {
+                            *d = (*s as u32 + (*d as u32 * inv_alpha + 127) / 255).min(255) as u8;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Blit the composited frame into the framebuffer bound for drawing.
+    fn present(&mut self) {
+        let size = self.device_size;
+        if size.width <= 0 || size.height <= 0 {
+            return;
+        }
+
+        let gl = Rc::clone(&self.gl);
+        let draw_fbo = self.get_integer(gl::DRAW_FRAMEBUFFER_BINDING);
+        let read_fbo = self.get_integer(gl::READ_FRAMEBUFFER_BINDING);
+        let texture_binding = self.get_integer(gl::TEXTURE_BINDING_2D);
+        let unpack_buffer = self.get_integer(gl::PIXEL_UNPACK_BUFFER_BINDING);
+        let scissor_enabled = gl.is_enabled(gl::SCISSOR_TEST) != 0;
+
+        if self.present_target.as_ref().map_or(false, |target| target.size != size) {
+            let target = self.present_target.take().unwrap();
+            gl.delete_framebuffers(&[target.fbo]);
+            gl.delete_textures(&[target.texture]);
+        }
+
+        let target = match self.present_target {
+            Some(ref target) => target,
+            None => {
+                let texture = gl.gen_textures(1)[0];
+                gl.bind_texture(gl::TEXTURE_2D, texture);
+                gl.tex_parameter_i(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as gl::GLint);
+                gl.tex_parameter_i(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as gl::GLint);
+                gl.tex_image_2d(
+                    gl::TEXTURE_2D,
+                    0,
+                    gl::RGBA8 as gl::GLint,
+                    size.width,
+                    size.height,
+                    0,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    None,
+                );
+
+                let fbo = gl.gen_framebuffers(1)[0];
+                gl.bind_framebuffer(gl::READ_FRAMEBUFFER, fbo);
+                gl.framebuffer_texture_2d(
+                    gl::READ_FRAMEBUFFER,
+                    gl::COLOR_ATTACHMENT0,
+                    gl::TEXTURE_2D,
+                    texture,
+                    0,
+                );
+
+                self.present_target = Some(PresentTarget { size, texture, fbo });
+                self.present_target.as_ref().unwrap()
+            }
+        };
+
+        gl.bind_buffer(gl::PIXEL_UNPACK_BUFFER, 0);
+        gl.bind_texture(gl::TEXTURE_2D, target.texture);
+        gl.tex_sub_image_2d(
+            gl::TEXTURE_2D,
+            0,
+            0,
+            0,
+            size.width,
+            size.height,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            &self.frame,
+        );
+
+        // The first row of the frame is at the top, but GL framebuffers
+        // start at the bottom, so flip while blitting.
+        gl.bind_framebuffer(gl::READ_FRAMEBUFFER, target.fbo);
+        gl.disable(gl::SCISSOR_TEST);
+        gl.blit_framebuffer(
+            0,
+            0,
+            size.width,
+            size.height,
+            0,
+            size.height,
+            size.width,
+            0,
+            gl::COLOR_BUFFER_BIT,
+            gl::NEAREST,
+        );
+
+        if scissor_enabled {
+            gl.enable(gl::SCISSOR_TEST);
+        }
+        gl.bind_texture(gl::TEXTURE_2D, texture_binding);
+        gl.bind_buffer(gl::PIXEL_UNPACK_BUFFER, unpack_buffer);
+        gl.bind_framebuffer(gl::READ_FRAMEBUFFER, read_fbo);
+        gl.bind_framebuffer(gl::DRAW_FRAMEBUFFER, draw_fbo);
+    }
+}
+
+impl Compositor for SwCompositor {
+    fn create_surface(
+        &mut self,
+        id: NativeSurfaceId,
+        size: DeviceIntSize,
+        is_opaque: bool,
+    ) {
+        let gl = Rc::clone(&self.gl);
+        let draw_fbo = self.get_integer(gl::DRAW_FRAMEBUFFER_BINDING);
+        let renderbuffer_binding = self.get_integer(gl::RENDERBUFFER_BINDING);
+
+        let rbos = gl.gen_renderbuffers(2);
+        let (color_rbo, depth_rbo) = (rbos[0], rbos[1]);
+        gl.bind_renderbuffer(gl::RENDERBUFFER, color_rbo);
+        gl.renderbuffer_storage(gl::RENDERBUFFER, gl::RGBA8, size.width, size.height);
+        gl.bind_renderbuffer(gl::RENDERBUFFER, depth_rbo);
+        gl.renderbuffer_storage(gl::RENDERBUFFER, gl::DEPTH_COMPONENT24, size.width, size.height);
+
+        let fbo = gl.gen_framebuffers(1)[0];
+        gl.bind_framebuffer(gl::DRAW_FRAMEBUFFER, fbo);
+        gl.framebuffer_renderbuffer(
+            gl::DRAW_FRAMEBUFFER,
+            gl::COLOR_ATTACHMENT0,
+            gl::RENDERBUFFER,
+            color_rbo,
+        );
+        gl.framebuffer_renderbuffer(
+            gl::DRAW_FRAMEBUFFER,
+            gl::DEPTH_ATTACHMENT,
+            gl::RENDERBUFFER,
+            depth_rbo,
+        );
+
+        gl.bind_renderbuffer(gl::RENDERBUFFER, renderbuffer_binding);
+        gl.bind_framebuffer(gl::DRAW_FRAMEBUFFER, draw_fbo);
+
+        let pixel_count = (size.width.max(0) * size.height.max(0)) as usize;
+        let old = self.surfaces.insert(id, SwSurface {
+            size,
+            is_opaque,
+            fbo,
+            color_rbo,
+            depth_rbo,
+            pixels: vec![0; pixel_count * BYTES_PER_PIXEL],
+        });
+        assert!(old.is_none(), "bug: native surface {:?} created twice", id);
+    }
+
+    fn destroy_surface(
+        &mut self,
+        id: NativeSurfaceId,
+    ) {
+        let surface = self.surfaces.remove(&id).expect("bug: unknown native surface");
+        self.gl.delete_framebuffers(&[surface.fbo]);
+        self.gl.delete_renderbuffers(&[surface.color_rbo, surface.depth_rbo]);
+    }
+
+    fn bind(
+        &mut self,
+        id: NativeSurfaceId,
+        dirty_rect: DeviceIntRect,
+    ) -> NativeSurfaceInfo {
+        assert!(self.bound_surface.is_none(), "bug: native surface already bound");
+        let surface = &self.surfaces[&id];
+        self.bound_surface = Some(BoundSurface { id, dirty_rect });
+
+        NativeSurfaceInfo {
+            origin: DeviceIntPoint::zero(),
+            fbo_id: surface.fbo,
+        }
+    }
+
+    fn unbind(&mut self) {
+        let bound = self.bound_surface.take().expect("bug: no native surface bound");
+        let gl = Rc::clone(&self.gl);
+        let read_fbo = self.get_integer(gl::READ_FRAMEBUFFER_BINDING);
+        let pack_buffer = self.get_integer(gl::PIXEL_PACK_BUFFER_BINDING);
+
+        let surface = self.surfaces.get_mut(&bound.id).expect("bug: unknown native surface");
+        let surface_rect = DeviceIntRect::new(DeviceIntPoint::zero(), surface.size);
+        let rect = match bound.dirty_rect.intersection(&surface_rect) {
+            Some(rect) => rect,
+            None => return,
+        };
+
+        // Copy the updated region back into the CPU side copy. Rows are
+        // read in the order WebRender drew them, so the first row read is
+        // the top of the dirty rect.
+        let row_size = rect.size.width as usize * BYTES_PER_PIXEL;
+        let mut data = vec![0; row_size * rect.size.height as usize];
+        gl.bind_buffer(gl::PIXEL_PACK_BUFFER, 0);
+        gl.bind_framebuffer(gl::READ_FRAMEBUFFER, surface.fbo);
+        gl.read_pixels_into_buffer(
+            rect.origin.x,
+            rect.origin.y,
+            rect.size.width,
+            rect.size.height,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            &mut data,
+        );
+        gl.bind_framebuffer(gl::READ_FRAMEBUFFER, read_fbo);
+        gl.bind_buffer(gl::PIXEL_PACK_BUFFER, pack_buffer);
+
+        let stride = surface.size.width as usize * BYTES_PER_PIXEL;
+        for (row, src) in data.chunks(row_size).enumerate() {
+            let offset = (rect.origin.y as usize + row) * stride +
+                rect.origin.x as usize * BYTES_PER_PIXEL;
+            surface.pixels[offset .. offset + row_size].copy_from_slice(src);
+        }
+    }
+
+    fn begin_frame(&mut self, device_size: DeviceIntSize) {
+        self.device_size = device_size;
+        self.visuals.clear();
+    }
+
+    fn add_surface(
+        &mut self,
+        id: NativeSurfaceId,
+        transform: CompositorSurfaceTransform,
+        clip_rect: DeviceIntRect,
+    ) {
+        self.visuals.push(SwVisual {
+            id,
+            transform,
+            clip_rect,
+        });
+    }
+
+    fn end_frame(&mut self) {
+        self.composite();
+        self.present();
+    }
+
+    fn deinit(&mut self) {
+        assert!(self.bound_surface.is_none(), "bug: native surface still bound");
+        let ids: Vec<NativeSurfaceId> = self.surfaces.keys().cloned().collect();
+        for id in ids {
+            self.destroy_surface(id);
+        }
+        if let Some(target) = self.present_target.take() {
+            self.gl.delete_framebuffers(&[target.fbo]);
+            self.gl.delete_textures(&[target.texture]);
+        }
+        self.visuals.clear();
+    }
+}
+
+fn to_u8(value: f32) -> u8 {
+    (value.max(0.0).min(1.0) * 255.0).round() as u8
+}
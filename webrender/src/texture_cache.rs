@@ -1849,6 +1849,9 @@ impl TextureCacheUpdate {
             CachedImageData::Blob => {
                 panic!("The vector image should have been rasterized.");
             }
+            CachedImageData::Snapshot => {
+                panic!("Snapshot images are rendered, not uploaded.");
+            }
             CachedImageData::External(ext_image) => match ext_image.image_type {
                 ExternalImageType::TextureHandle(_) => {
                     panic!("External texture handle should not go through texture_cache.");
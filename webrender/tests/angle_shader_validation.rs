@@ -50,6 +50,10 @@ const SHADERS: &[Shader] = &[
         name: "cs_blur",
         features: &[ "ALPHA_TARGET", "COLOR_TARGET" ],
     },
+    Shader {
+        name: "cs_dual_filter",
+        features: &[ "ALPHA_TARGET", "COLOR_TARGET" ],
+    },
     Shader {
         name: "cs_border_segment",
         features: CACHE_FEATURES,
@@ -70,6 +74,14 @@ const SHADERS: &[Shader] = &[
         name: "cs_svg_filter",
         features: CACHE_FEATURES,
     },
+    Shader {
+        name: "pf_vector_stencil",
+        features: CACHE_FEATURES,
+    },
+    Shader {
+        name: "pf_vector_cover",
+        features: CACHE_FEATURES,
+    },
     // Prim shaders
     Shader {
         name: "ps_split_composite",
@@ -111,6 +123,10 @@ const SHADERS: &[Shader] = &[
         name: "brush_radial_gradient",
         features: GRADIENT_FEATURES,
     },
+    Shader {
+        name: "brush_conic_gradient",
+        features: GRADIENT_FEATURES,
+    },
     Shader {
         name: "brush_linear_gradient",
         features: GRADIENT_FEATURES,
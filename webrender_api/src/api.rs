@@ -16,7 +16,7 @@ use std::u32;
 // local imports
 use crate::{display_item as di, font};
 use crate::color::{ColorU, ColorF};
-use crate::display_list::{BuiltDisplayList, BuiltDisplayListDescriptor};
+use crate::display_list::{BuiltDisplayList, BuiltDisplayListDescriptor, DisplayListPatchOp};
 use crate::image::{BlobImageData, BlobImageKey, ImageData, ImageDescriptor, ImageKey};
 use crate::units::*;
 
@@ -32,6 +32,7 @@ pub enum ResourceUpdate {
     AddBlobImage(AddBlobImage),
     UpdateBlobImage(UpdateBlobImage),
     DeleteImage(ImageKey),
+    AddSnapshotImage(ImageKey),
     SetBlobImageVisibleArea(BlobImageKey, DeviceIntRect),
     AddFont(AddFont),
     DeleteFont(font::FontKey),
@@ -59,6 +60,7 @@ impl fmt::Debug for ResourceUpdate {
                 &i.descriptor.size
             )),
             ResourceUpdate::DeleteImage(..) => f.write_str("ResourceUpdate::DeleteImage"),
+            ResourceUpdate::AddSnapshotImage(..) => f.write_str("ResourceUpdate::AddSnapshotImage"),
             ResourceUpdate::SetBlobImageVisibleArea(..) => f.write_str("ResourceUpdate::SetBlobImageVisibleArea"),
             ResourceUpdate::AddFont(..) => f.write_str("ResourceUpdate::AddFont"),
             ResourceUpdate::DeleteFont(..) => f.write_str("ResourceUpdate::DeleteFont"),
@@ -210,6 +212,37 @@ impl Transaction {
         self.payloads.push(Payload { epoch, pipeline_id, display_list_data });
     }
 
+    /// Patch the display list previously set for a pipeline, instead of
+    /// sending it again in full. The new items of the ops are taken from
+    /// `patch`, see `BuiltDisplayList::apply_patch`. The background, viewport
+    /// and content size of the pipeline are left unchanged.
+    ///
+    /// This saves sending and checking the items that didn't change, but the
+    /// scene is still built again from the whole patched display list.
+    ///
+    /// If the patch can't be applied, or the patched list is malformed, the
+    /// patch is rejected and `Checkpoint::DisplayListRejected` is notified for
+    /// this pipeline and epoch. The pipeline keeps its previous display list
+    /// and epoch, and the client should send the full display list again.
+    pub fn update_display_list(
+        &mut self,
+        epoch: Epoch,
+        pipeline_id: PipelineId,
+        patch: BuiltDisplayList,
+        ops: Vec<DisplayListPatchOp>,
+    ) {
+        let (display_list_data, list_descriptor) = patch.into_data();
+        self.scene_ops.push(
+            SceneMsg::UpdateDisplayList {
+                epoch,
+                pipeline_id,
+                list_descriptor,
+                ops,
+            }
+        );
+        self.payloads.push(Payload { epoch, pipeline_id, display_list_data });
+    }
+
     pub fn update_resources(&mut self, resources: Vec<ResourceUpdate>) {
         self.merge(resources);
     }
@@ -372,6 +405,13 @@ impl Transaction {
         self.resource_updates.push(ResourceUpdate::DeleteImage(key));
     }
 
+    /// Registers an image whose contents are rendered by WebRender, from the
+    /// stacking contexts that name it as their snapshot. The image can be used
+    /// by display items of any pipeline and is removed with `delete_image`.
+    pub fn add_snapshot_image(&mut self, key: ImageKey) {
+        self.resource_updates.push(ResourceUpdate::AddSnapshotImage(key));
+    }
+
     pub fn add_blob_image(
         &mut self,
         key: BlobImageKey,
@@ -556,6 +596,20 @@ impl TransactionMsg {
             low_priority: false,
         }
     }
+
+    /// A transaction that only asks for a new frame to be generated.
+    pub fn generate_frame_message() -> Self {
+        TransactionMsg {
+            scene_ops: Vec::new(),
+            frame_ops: Vec::new(),
+            resource_updates: Vec::new(),
+            notifications: Vec::new(),
+            generate_frame: true,
+            invalidate_rendered_frame: false,
+            use_scene_builder_thread: false,
+            low_priority: false,
+        }
+    }
 }
 
 #[derive(Clone, Deserialize, Serialize)]
@@ -662,6 +716,12 @@ pub enum SceneMsg {
         content_size: LayoutSize,
         preserve_frame_state: bool,
     },
+    UpdateDisplayList {
+        list_descriptor: BuiltDisplayListDescriptor,
+        epoch: Epoch,
+        pipeline_id: PipelineId,
+        ops: Vec<DisplayListPatchOp>,
+    },
     SetDocumentView {
         device_rect: DeviceIntRect,
         device_pixel_ratio: f32,
@@ -688,6 +748,7 @@ impl fmt::Debug for SceneMsg {
         f.write_str(match *self {
             SceneMsg::UpdateEpoch(..) => "SceneMsg::UpdateEpoch",
             SceneMsg::SetDisplayList { .. } => "SceneMsg::SetDisplayList",
+            SceneMsg::UpdateDisplayList { .. } => "SceneMsg::UpdateDisplayList",
             SceneMsg::SetPageZoom(..) => "SceneMsg::SetPageZoom",
             SceneMsg::RemovePipeline(..) => "SceneMsg::RemovePipeline",
             SceneMsg::EnableFrameOutput(..) => "SceneMsg::EnableFrameOutput",
@@ -916,7 +977,7 @@ pub enum PrimitiveKeyKind {
     /// Clear an existing rect, used for special effects on some platforms.
     Clear,
     Rectangle {
-        color: ColorU,
+        color: PropertyBinding<ColorU>,
     },
 }
 
@@ -939,6 +1000,8 @@ macro_rules! enumerate_interners {
             line_decoration: LineDecoration,
             linear_grad: LinearGradient,
             radial_grad: RadialGradient,
+            conic_grad: ConicGradient,
+            vector_path: VectorPath,
             picture: Picture,
             text_run: TextRun,
             filter_data: FilterDataIntern,
@@ -1036,7 +1099,7 @@ impl ExternalEvent {
     }
 }
 
-#[derive(Clone, Deserialize, Serialize)]
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
 pub enum ScrollClamping {
     ToContentBounds,
     NoClamping,
@@ -1463,6 +1526,8 @@ impl Drop for RenderApi {
 pub struct ScrollNodeState {
     pub id: di::ExternalScrollId,
     pub scroll_offset: LayoutVector2D,
+    /// True while a smooth scroll or fling animation is moving this node.
+    pub is_animating: bool,
 }
 
 #[derive(Clone, Copy, Debug, Deserialize, Serialize)]
@@ -1473,6 +1538,54 @@ pub enum ScrollLocation {
     Start,
     /// Scroll to very bottom of element.
     End,
+    /// Animate the scroll origin of the element to `origin` over `duration_ms`.
+    SmoothTo {
+        origin: LayoutPoint,
+        duration_ms: u32,
+        easing: ScrollEasing,
+        clamp: ScrollClamping,
+    },
+    /// Keep scrolling the element with an initial velocity, in layout pixels per
+    /// second and in the same direction as `Delta`, that decays exponentially.
+    /// `friction` is the decay rate per second, higher values stop sooner.
+    Fling {
+        velocity: LayoutVector2D,
+        friction: f32,
+        clamp: ScrollClamping,
+    },
+}
+
+/// The timing curve of a `ScrollLocation::SmoothTo` animation.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum ScrollEasing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl ScrollEasing {
+    /// Maps the elapsed fraction of the animation, in [0, 1], to the
+    /// fraction of the distance covered.
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.max(0.0).min(1.0);
+        match *self {
+            ScrollEasing::Linear => t,
+            ScrollEasing::EaseIn => t * t * t,
+            ScrollEasing::EaseOut => {
+                let u = 1.0 - t;
+                1.0 - u * u * u
+            }
+            ScrollEasing::EaseInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    let u = 2.0 - 2.0 * t;
+                    1.0 - 0.5 * u * u * u
+                }
+            }
+        }
+    }
 }
 
 /// Represents a zoom factor.
@@ -1510,7 +1623,7 @@ impl PropertyBindingId {
 /// A unique key that is used for connecting animated property
 /// values to bindings in the display list.
 #[repr(C)]
-#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, MallocSizeOf, PartialEq, Serialize, PeekPoke)]
 pub struct PropertyBindingKey<T> {
     pub id: PropertyBindingId,
     _phantom: PhantomData<T>,
@@ -1539,7 +1652,7 @@ impl<T> PropertyBindingKey<T> {
 /// used for the case where the animation is still in-delay phase
 /// (i.e. the animation doesn't produce any animation values).
 #[repr(C)]
-#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize, PeekPoke)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, MallocSizeOf, PartialEq, Serialize, PeekPoke)]
 pub enum PropertyBinding<T> {
     Value(T),
     Binding(PropertyBindingKey<T>, T),
@@ -1557,6 +1670,30 @@ impl<T> From<T> for PropertyBinding<T> {
     }
 }
 
+impl From<PropertyBinding<ColorU>> for PropertyBinding<ColorF> {
+    fn from(value: PropertyBinding<ColorU>) -> PropertyBinding<ColorF> {
+        match value {
+            PropertyBinding::Value(value) => PropertyBinding::Value(value.into()),
+            PropertyBinding::Binding(k, v) => {
+                let key = PropertyBindingKey { id: k.id, _phantom: PhantomData };
+                PropertyBinding::Binding(key, v.into())
+            }
+        }
+    }
+}
+
+impl From<PropertyBinding<ColorF>> for PropertyBinding<ColorU> {
+    fn from(value: PropertyBinding<ColorF>) -> PropertyBinding<ColorU> {
+        match value {
+            PropertyBinding::Value(value) => PropertyBinding::Value(value.into()),
+            PropertyBinding::Binding(k, v) => {
+                let key = PropertyBindingKey { id: k.id, _phantom: PhantomData };
+                PropertyBinding::Binding(key, v.into())
+            }
+        }
+    }
+}
+
 /// The current value of an animated property. This is
 /// supplied by the calling code.
 #[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
@@ -1572,6 +1709,7 @@ pub struct PropertyValue<T> {
 pub struct DynamicProperties {
     pub transforms: Vec<PropertyValue<LayoutTransform>>,
     pub floats: Vec<PropertyValue<f32>>,
+    pub colors: Vec<PropertyValue<ColorF>>,
 }
 
 pub trait RenderNotifier: Send {
@@ -1594,6 +1732,11 @@ pub enum Checkpoint {
     /// NotificationRequests get notified with this if they get dropped without having been
     /// notified. This provides the guarantee that if a request is created it will get notified.
     TransactionDropped,
+    /// Notified during scene building if the display list set for this pipeline and epoch in
+    /// the transaction exceeded the renderer's `DisplayListLimits` and was dropped, or if the
+    /// display list patch for them was rejected. Otherwise, requests for
+    /// this checkpoint are notified with `TransactionDropped`.
+    DisplayListRejected { pipeline_id: PipelineId, epoch: Epoch },
 }
 
 pub trait NotificationHandler : Send + Sync {
@@ -16,6 +16,28 @@ use crate::units::*;
 // Taken from nsCSSRendering.cpp in Gecko.
 pub const MAX_BLUR_RADIUS: f32 = 300.;
 
+/// Maximum radius of a morphology filter primitive. The radius is clamped to
+/// it again once scaled to device pixels, since the shader samples a bounded
+/// number of pixels.
+// Must match MAX_MORPHOLOGY_RADIUS in cs_svg_filter.glsl.
+pub const MAX_MORPHOLOGY_RADIUS: f32 = 100.;
+
+/// Maximum number of columns or rows of a convolve matrix kernel.
+pub const MAX_CONVOLVE_MATRIX_ORDER: u32 = 5;
+
+/// Maximum number of octaves of a turbulence filter primitive. Each octave
+/// contributes half as much as the previous one, so past this point they
+/// are below the precision of an 8 bit color channel.
+// Must match MAX_TURBULENCE_OCTAVES in cs_svg_filter.glsl.
+pub const MAX_TURBULENCE_OCTAVES: u32 = 10;
+
+/// Range of the specular exponent of the specular lighting filter primitive.
+pub const MIN_SPECULAR_EXPONENT: f32 = 1.;
+pub const MAX_SPECULAR_EXPONENT: f32 = 128.;
+
+/// Maximum number of inputs of a merge filter primitive.
+pub const MAX_MERGE_INPUTS: usize = 16;
+
 // ******************************************************************
 // * NOTE: some of these structs have an "IMPLICIT" comment.        *
 // * This indicates that the BuiltDisplayList will have serialized  *
@@ -125,6 +147,8 @@ pub enum DisplayItem {
     PushShadow(PushShadowDisplayItem),
     Gradient(GradientDisplayItem),
     RadialGradient(RadialGradientDisplayItem),
+    ConicGradient(ConicGradientDisplayItem),
+    Path(PathDisplayItem),
     Image(ImageDisplayItem),
     RepeatingImage(RepeatingImageDisplayItem),
     YuvImage(YuvImageDisplayItem),
@@ -148,6 +172,10 @@ pub enum DisplayItem {
     SetFilterData,
     SetFilterPrimitives,
 
+    // This marker item starts a range of items that can be patched in place,
+    // extending up to the next SetItemKey or the end of the list.
+    SetItemKey(ItemKey),
+
     // These marker items terminate a scope introduced by a previous item.
     PopReferenceFrame,
     PopStackingContext,
@@ -170,12 +198,14 @@ pub enum DebugDisplayItem {
     PushShadow(PushShadowDisplayItem),
     Gradient(GradientDisplayItem),
     RadialGradient(RadialGradientDisplayItem),
+    ConicGradient(ConicGradientDisplayItem),
+    Path(PathDisplayItem, Vec<PathCommand>),
     Image(ImageDisplayItem),
     RepeatingImage(RepeatingImageDisplayItem),
     YuvImage(YuvImageDisplayItem),
     BackdropFilter(BackdropFilterDisplayItem),
 
-    Clip(ClipDisplayItem, Vec<ComplexClipRegion>),
+    Clip(ClipDisplayItem, Vec<ComplexClipRegion>, Vec<PathCommand>, Vec<font::GlyphInstance>),
     ClipChain(ClipChainItem, Vec<ClipId>),
 
     ScrollFrame(ScrollFrameDisplayItem, Vec<ComplexClipRegion>),
@@ -188,6 +218,7 @@ pub enum DebugDisplayItem {
     SetFilterOps(Vec<FilterOp>),
     SetFilterData(FilterData),
     SetFilterPrimitives(Vec<FilterPrimitive>),
+    SetItemKey(ItemKey),
 
     PopReferenceFrame,
     PopStackingContext,
@@ -200,7 +231,9 @@ pub struct ClipDisplayItem {
     pub parent_space_and_clip: SpaceAndClipInfo,
     pub clip_rect: LayoutRect,
     pub image_mask: Option<ImageMask>,
-} // IMPLICIT: complex_clips: Vec<ComplexClipRegion>
+    pub path_clip: Option<PathClip>,
+    pub text_clip: Option<TextClip>,
+} // IMPLICIT: complex_clips: Vec<ComplexClipRegion>, path_commands: Vec<PathCommand>, glyphs: Vec<font::GlyphInstance>
 
 /// The minimum and maximum allowable offset for a sticky frame in a single dimension.
 #[repr(C)]
@@ -260,6 +293,19 @@ pub enum ScrollSensitivity {
     Script,
 }
 
+/// Whether scrolling that reaches the bounds of a scroll frame carries on in
+/// the enclosing scroll frames, like the CSS `overscroll-behavior` property.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize, PeekPoke)]
+pub enum OverscrollBehavior {
+    /// Leftover scroll deltas propagate to the enclosing scroll frame.
+    Auto,
+    /// Scrolling never propagates past this scroll frame.
+    Contain,
+    /// Like `Contain`, and input scroll animations can't move this scroll
+    /// frame past its content bounds either.
+    None,
+}
+
 #[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
 pub struct ScrollFrameDisplayItem {
     /// The id of the clip this scroll frame creates
@@ -274,6 +320,7 @@ pub struct ScrollFrameDisplayItem {
     pub external_id: Option<ExternalScrollId>,
     pub image_mask: Option<ImageMask>,
     pub scroll_sensitivity: ScrollSensitivity,
+    pub overscroll_behavior: OverscrollBehavior,
     /// The amount this scrollframe has already been scrolled by, in the caller.
     /// This means that all the display items that are inside the scrollframe
     /// will have their coordinates shifted by this amount, and this offset
@@ -286,7 +333,7 @@ pub struct ScrollFrameDisplayItem {
 #[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
 pub struct RectangleDisplayItem {
     pub common: CommonItemProperties,
-    pub color: ColorF,
+    pub color: PropertyBinding<ColorF>,
 }
 
 /// Clears all colors from the area, making it possible to cut holes in the window.
@@ -579,11 +626,24 @@ pub enum ExtendMode {
     Repeat,
 }
 
+/// The color space colors are interpolated in between gradient stops.
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, Hash, Eq, MallocSizeOf, PartialEq, Serialize, Deserialize, PeekPoke)]
+pub enum GradientColorSpace {
+    /// Premultiplied sRGB, the legacy CSS behavior.
+    Srgb,
+    LinearSrgb,
+    Oklab,
+    /// Polar Oklab, with hues interpolated along the shorter arc.
+    Oklch,
+}
+
 #[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
 pub struct Gradient {
     pub start_point: LayoutPoint,
     pub end_point: LayoutPoint,
     pub extend_mode: ExtendMode,
+    pub color_space: GradientColorSpace,
 } // IMPLICIT: stops: Vec<GradientStop>
 
 /// The area
@@ -607,6 +667,9 @@ pub struct GradientDisplayItem {
 pub struct GradientStop {
     pub offset: f32,
     pub color: ColorF,
+    /// Position of the color hint between the previous stop and this one, as
+    /// a fraction of the distance between them. None is the same as 0.5.
+    pub midpoint: Option<f32>,
 }
 
 #[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
@@ -616,6 +679,18 @@ pub struct RadialGradient {
     pub start_offset: f32,
     pub end_offset: f32,
     pub extend_mode: ExtendMode,
+    pub color_space: GradientColorSpace,
+} // IMPLICIT stops: Vec<GradientStop>
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub struct ConicGradient {
+    pub center: LayoutPoint,
+    /// The angle (in radians) at which the gradient line starts, measured
+    /// clockwise from the top.
+    pub angle: f32,
+    pub start_offset: f32,
+    pub end_offset: f32,
+    pub extend_mode: ExtendMode,
 } // IMPLICIT stops: Vec<GradientStop>
 
 /// Just an abstraction for bundling up a bunch of clips into a "super clip".
@@ -637,6 +712,58 @@ pub struct RadialGradientDisplayItem {
     pub tile_spacing: LayoutSize,
 }
 
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub struct ConicGradientDisplayItem {
+    pub common: CommonItemProperties,
+    /// The area to tile the gradient over (first tile starts at origin of this rect)
+    // FIXME: this should ideally just be `tile_origin` here, with the clip_rect
+    // defining the bounds of the item. Needs non-trivial backend changes.
+    pub bounds: LayoutRect,
+    pub gradient: ConicGradient,
+    pub tile_size: LayoutSize,
+    pub tile_spacing: LayoutSize,
+}
+
+/// The rule used to decide which parts of a path are inside it.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, MallocSizeOf, PartialEq, Serialize, PeekPoke)]
+pub enum FillRule {
+    NonZero,
+    EvenOdd,
+}
+
+/// A single command of a path outline, in the same coordinate space as the
+/// item bounds. Subpaths that are not explicitly closed are implicitly closed
+/// when filled.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub enum PathCommand {
+    MoveTo(LayoutPoint),
+    LineTo(LayoutPoint),
+    QuadTo(LayoutPoint, LayoutPoint),
+    CubicTo(LayoutPoint, LayoutPoint, LayoutPoint),
+    Close,
+}
+
+/// How the inside of a path is painted.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub enum PathPaint {
+    Color(ColorF),
+    /// A linear gradient with its points relative to the origin of the item
+    /// bounds, using the most recently pushed gradient stops.
+    Gradient(Gradient),
+}
+
+/// Fills an arbitrary path made of lines and bezier curves.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub struct PathDisplayItem {
+    pub common: CommonItemProperties,
+    /// The area the path outline is contained in. Parts of the path outside
+    /// of these bounds are cut off.
+    pub bounds: LayoutRect,
+    pub fill_rule: FillRule,
+    pub paint: PathPaint,
+} // IMPLICIT: commands: Vec<PathCommand>
+
 /// Renders a filtered region of its backdrop
 #[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
 pub struct BackdropFilterDisplayItem {
@@ -687,6 +814,10 @@ pub struct StackingContext {
     pub cache_tiles: bool,
     /// True if this stacking context is a backdrop root.
     pub is_backdrop_root: bool,
+    /// If set, the contents of this stacking context are also rendered into
+    /// this snapshot image, whether they are visible on screen or not, see
+    /// `Transaction::add_snapshot_image`.
+    pub snapshot: Option<ImageKey>,
 }
 // IMPLICIT: filters: Vec<FilterOp>, filter_datas: Vec<FilterData>, filter_primitives: Vec<FilterPrimitive>
 
@@ -782,6 +913,88 @@ impl CompositeOperator {
     }
 }
 
+/// Available operators for the morphology filter primitive
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, MallocSizeOf, PartialEq, Serialize, PeekPoke)]
+pub enum MorphologyOperator {
+    Erode,
+    Dilate,
+}
+
+impl MorphologyOperator {
+    // This must stay in sync with the morphology operator defines in cs_svg_filter.glsl
+    pub fn as_int(&self) -> u32 {
+        match self {
+            MorphologyOperator::Erode => 0,
+            MorphologyOperator::Dilate => 1,
+        }
+    }
+}
+
+/// How the convolve matrix filter primitive samples outside of its input.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, MallocSizeOf, PartialEq, Serialize, PeekPoke)]
+pub enum ConvolveEdgeMode {
+    /// Repeat the pixels at the edge of the input.
+    Duplicate,
+    /// Take pixels from the opposite edge of the input.
+    Wrap,
+    /// Use transparent black.
+    None,
+}
+
+impl ConvolveEdgeMode {
+    // This must stay in sync with the edge mode defines in cs_svg_filter.glsl
+    pub fn as_int(&self) -> u32 {
+        match self {
+            ConvolveEdgeMode::Duplicate => 0,
+            ConvolveEdgeMode::Wrap => 1,
+            ConvolveEdgeMode::None => 2,
+        }
+    }
+}
+
+/// The noise function of the turbulence filter primitive.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, MallocSizeOf, PartialEq, Serialize, PeekPoke)]
+pub enum TurbulenceType {
+    FractalNoise,
+    Turbulence,
+}
+
+impl TurbulenceType {
+    // This must stay in sync with the turbulence type defines in cs_svg_filter.glsl
+    pub fn as_int(&self) -> u32 {
+        match self {
+            TurbulenceType::FractalNoise => 0,
+            TurbulenceType::Turbulence => 1,
+        }
+    }
+}
+
+/// A color channel, used to select which channel of an input a filter
+/// primitive reads from.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, MallocSizeOf, PartialEq, Serialize, PeekPoke)]
+pub enum ColorChannel {
+    Red,
+    Green,
+    Blue,
+    Alpha,
+}
+
+impl ColorChannel {
+    // This must stay in sync with the component index used in cs_svg_filter.glsl
+    pub fn as_int(&self) -> u32 {
+        match self {
+            ColorChannel::Red => 0,
+            ColorChannel::Green => 1,
+            ColorChannel::Blue => 2,
+            ColorChannel::Alpha => 3,
+        }
+    }
+}
+
 /// An input to a SVG filter primitive.
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, MallocSizeOf, PartialEq, Serialize, PeekPoke)]
@@ -833,12 +1046,16 @@ impl FloodPrimitive {
 #[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
 pub struct BlurPrimitive {
     pub input: FilterPrimitiveInput,
-    pub radius: f32,
+    /// The standard deviation along the x axis.
+    pub width: f32,
+    /// The standard deviation along the y axis.
+    pub height: f32,
 }
 
 impl BlurPrimitive {
     pub fn sanitize(&mut self) {
-        self.radius = self.radius.min(MAX_BLUR_RADIUS);
+        self.width = self.width.min(MAX_BLUR_RADIUS);
+        self.height = self.height.min(MAX_BLUR_RADIUS);
     }
 }
 
@@ -904,6 +1121,233 @@ pub struct CompositePrimitive {
     pub operator: CompositeOperator,
 }
 
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub struct MorphologyPrimitive {
+    pub input: FilterPrimitiveInput,
+    pub radius_x: f32,
+    pub radius_y: f32,
+    pub operator: MorphologyOperator,
+}
+
+impl MorphologyPrimitive {
+    pub fn sanitize(&mut self) {
+        self.radius_x = self.radius_x.min(MAX_MORPHOLOGY_RADIUS);
+        self.radius_y = self.radius_y.min(MAX_MORPHOLOGY_RADIUS);
+    }
+}
+
+/// cbindgen:derive-eq=false
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub struct ConvolveMatrixPrimitive {
+    pub input: FilterPrimitiveInput,
+    /// The number of columns and rows of the kernel, at most
+    /// `MAX_CONVOLVE_MATRIX_ORDER` each.
+    pub order: [u32; 2],
+    /// The kernel values, in row major order. Only the first
+    /// `order[0] * order[1]` values are used.
+    pub kernel: [f32; 25],
+    /// The sum of the kernel values is used when zero.
+    pub divisor: f32,
+    pub bias: f32,
+    /// The column and row of the kernel that is centered on each pixel.
+    pub target: [u32; 2],
+    pub edge_mode: ConvolveEdgeMode,
+    pub preserve_alpha: bool,
+}
+
+impl ConvolveMatrixPrimitive {
+    pub fn sanitize(&mut self) {
+        for i in 0 .. 2 {
+            self.order[i] = self.order[i].max(1).min(MAX_CONVOLVE_MATRIX_ORDER);
+            self.target[i] = self.target[i].min(self.order[i] - 1);
+        }
+
+        if self.divisor == 0.0 {
+            let count = (self.order[0] * self.order[1]) as usize;
+            let sum: f32 = self.kernel[.. count].iter().sum();
+            self.divisor = if sum == 0.0 { 1.0 } else { sum };
+        }
+    }
+}
+
+/// Generates Perlin noise, as specified by feTurbulence. The noise is
+/// computed in the local space of the filtered content.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub struct TurbulencePrimitive {
+    pub base_frequency_x: f32,
+    pub base_frequency_y: f32,
+    pub num_octaves: u32,
+    pub seed: i32,
+    /// Adjusts the base frequencies so that the noise tiles seamlessly
+    /// across the filter region.
+    pub stitch_tiles: bool,
+    pub noise_type: TurbulenceType,
+}
+
+impl TurbulencePrimitive {
+    pub fn sanitize(&mut self) {
+        self.base_frequency_x = self.base_frequency_x.max(0.0);
+        self.base_frequency_y = self.base_frequency_y.max(0.0);
+        self.num_octaves = self.num_octaves.min(MAX_TURBULENCE_OCTAVES);
+    }
+}
+
+/// Moves the pixels of `input1` by an amount read from the selected channels
+/// of `input2`, scaled so that a channel value of 0.5 doesn't move the pixel.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub struct DisplacementMapPrimitive {
+    pub input1: FilterPrimitiveInput,
+    pub input2: FilterPrimitiveInput,
+    pub scale: f32,
+    pub x_channel_selector: ColorChannel,
+    pub y_channel_selector: ColorChannel,
+}
+
+/// The light source of a lighting filter primitive. Positions are in the
+/// local space of the filtered content, with z pointing towards the viewer.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub enum LightSource {
+    /// A light infinitely far away. The angles are in degrees.
+    Distant {
+        azimuth: f32,
+        elevation: f32,
+    },
+    Point {
+        position: LayoutPoint3D,
+    },
+    Spot {
+        position: LayoutPoint3D,
+        points_at: LayoutPoint3D,
+        specular_exponent: f32,
+        /// The angle in degrees around `points_at` outside of which the
+        /// light doesn't shine. Angles of 90 degrees or more don't limit it.
+        limiting_cone_angle: f32,
+    },
+}
+
+impl LightSource {
+    // This must stay in sync with the light source defines in cs_svg_filter.glsl
+    pub fn as_int(&self) -> u32 {
+        match self {
+            LightSource::Distant { .. } => 0,
+            LightSource::Point { .. } => 1,
+            LightSource::Spot { .. } => 2,
+        }
+    }
+}
+
+/// Lights the input using its alpha channel as a bump map, with a diffuse
+/// (Lambertian) lighting model. The result is opaque.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub struct DiffuseLightingPrimitive {
+    pub input: FilterPrimitiveInput,
+    pub surface_scale: f32,
+    pub diffuse_constant: f32,
+    pub lighting_color: ColorF,
+    pub light: LightSource,
+}
+
+impl DiffuseLightingPrimitive {
+    pub fn sanitize(&mut self) {
+        self.diffuse_constant = self.diffuse_constant.max(0.0);
+        self.lighting_color.r = self.lighting_color.r.min(1.0).max(0.0);
+        self.lighting_color.g = self.lighting_color.g.min(1.0).max(0.0);
+        self.lighting_color.b = self.lighting_color.b.min(1.0).max(0.0);
+        self.lighting_color.a = self.lighting_color.a.min(1.0).max(0.0);
+    }
+}
+
+/// Lights the input using its alpha channel as a bump map, with a specular
+/// (Phong) lighting model. The result is transparent where the light isn't
+/// reflected, so that it can be composited over a texture.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub struct SpecularLightingPrimitive {
+    pub input: FilterPrimitiveInput,
+    pub surface_scale: f32,
+    pub specular_constant: f32,
+    pub specular_exponent: f32,
+    pub lighting_color: ColorF,
+    pub light: LightSource,
+}
+
+impl SpecularLightingPrimitive {
+    pub fn sanitize(&mut self) {
+        self.specular_constant = self.specular_constant.max(0.0);
+        self.specular_exponent = self.specular_exponent
+            .max(MIN_SPECULAR_EXPONENT)
+            .min(MAX_SPECULAR_EXPONENT);
+        self.lighting_color.r = self.lighting_color.r.min(1.0).max(0.0);
+        self.lighting_color.g = self.lighting_color.g.min(1.0).max(0.0);
+        self.lighting_color.b = self.lighting_color.b.min(1.0).max(0.0);
+        self.lighting_color.a = self.lighting_color.a.min(1.0).max(0.0);
+    }
+}
+
+/// Draws an image in a rectangle of the filter region, and leaves the rest
+/// of it transparent.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub struct ImagePrimitive {
+    pub image_key: ImageKey,
+    pub rect: LayoutRect,
+}
+
+/// Fills the filter region with copies of a rectangle of the input.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub struct TilePrimitive {
+    pub input: FilterPrimitiveInput,
+    /// The rectangle of the input that is repeated, which is usually the
+    /// region the input primitive was restricted to.
+    pub source_rect: LayoutRect,
+}
+
+impl TilePrimitive {
+    pub fn sanitize(&mut self) {
+        self.source_rect.size.width = self.source_rect.size.width.max(0.0);
+        self.source_rect.size.height = self.source_rect.size.height.max(0.0);
+    }
+}
+
+/// Composites its inputs over each other, with the first input at the bottom.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub struct MergePrimitive {
+    /// The inputs are stored inline so that filter primitives keep a fixed
+    /// size in the display list. Only the first `input_count` are used.
+    pub inputs: [FilterPrimitiveInput; MAX_MERGE_INPUTS],
+    pub input_count: u32,
+}
+
+impl MergePrimitive {
+    /// Creates a merge of the given inputs, of which there can be at most
+    /// `MAX_MERGE_INPUTS`.
+    pub fn new(inputs: &[FilterPrimitiveInput]) -> Self {
+        assert!(inputs.len() <= MAX_MERGE_INPUTS, "Too many merge inputs");
+        let mut primitive = MergePrimitive {
+            input_count: inputs.len() as u32,
+            ..MergePrimitive::default()
+        };
+        primitive.inputs[.. inputs.len()].copy_from_slice(inputs);
+        primitive
+    }
+
+    pub fn inputs(&self) -> &[FilterPrimitiveInput] {
+        &self.inputs[.. self.input_count as usize]
+    }
+
+    pub fn sanitize(&mut self) {
+        self.input_count = self.input_count.min(MAX_MERGE_INPUTS as u32);
+    }
+}
+
 /// See: https://github.com/eqrion/cbindgen/issues/9
 /// cbindgen:derive-eq=false
 #[repr(C)]
@@ -921,6 +1365,16 @@ pub enum FilterPrimitiveKind {
     ComponentTransfer(ComponentTransferPrimitive),
     Offset(OffsetPrimitive),
     Composite(CompositePrimitive),
+    Morphology(MorphologyPrimitive),
+    /// cbindgen:derive-eq=false
+    ConvolveMatrix(ConvolveMatrixPrimitive),
+    Turbulence(TurbulencePrimitive),
+    DisplacementMap(DisplacementMapPrimitive),
+    DiffuseLighting(DiffuseLightingPrimitive),
+    SpecularLighting(SpecularLightingPrimitive),
+    Image(ImagePrimitive),
+    Tile(TilePrimitive),
+    Merge(MergePrimitive),
 }
 
 impl Default for FilterPrimitiveKind {
@@ -936,6 +1390,13 @@ impl FilterPrimitiveKind {
             FilterPrimitiveKind::Blur(blur) => blur.sanitize(),
             FilterPrimitiveKind::Opacity(opacity) => opacity.sanitize(),
             FilterPrimitiveKind::DropShadow(drop_shadow) => drop_shadow.sanitize(),
+            FilterPrimitiveKind::Morphology(morphology) => morphology.sanitize(),
+            FilterPrimitiveKind::ConvolveMatrix(convolve_matrix) => convolve_matrix.sanitize(),
+            FilterPrimitiveKind::Turbulence(turbulence) => turbulence.sanitize(),
+            FilterPrimitiveKind::DiffuseLighting(lighting) => lighting.sanitize(),
+            FilterPrimitiveKind::SpecularLighting(lighting) => lighting.sanitize(),
+            FilterPrimitiveKind::Tile(tile) => tile.sanitize(),
+            FilterPrimitiveKind::Merge(merge) => merge.sanitize(),
 
             // No sanitization needed.
             FilterPrimitiveKind::Identity(..) |
@@ -943,6 +1404,8 @@ impl FilterPrimitiveKind {
             FilterPrimitiveKind::ColorMatrix(..) |
             FilterPrimitiveKind::Offset(..) |
             FilterPrimitiveKind::Composite(..) |
+            FilterPrimitiveKind::DisplacementMap(..) |
+            FilterPrimitiveKind::Image(..) |
             // Component transfer's filter data is sanitized separately.
             FilterPrimitiveKind::ComponentTransfer(..) => {}
         }
@@ -972,7 +1435,8 @@ pub enum FilterOp {
     /// Filter that does no transformation of the colors, needed for
     /// debug purposes only.
     Identity,
-    Blur(f32),
+    /// Blurs with separate standard deviations along the x and y axes.
+    Blur(f32, f32),
     Brightness(f32),
     Contrast(f32),
     Grayscale(f32),
@@ -987,6 +1451,11 @@ pub enum FilterOp {
     LinearToSrgb,
     ComponentTransfer,
     Flood(ColorF),
+    /// Maps colors through a 3D lookup table with trilinear interpolation.
+    /// The table is stored as `size` slices of `size` x `size` texels laid
+    /// out horizontally, for an image that is `size * size` wide and `size`
+    /// high. Blue selects the slice, red the column and green the row.
+    ColorLut3D { lut: ImageKey, size: u32 },
 }
 
 #[repr(u8)]
@@ -1207,12 +1676,61 @@ impl YuvFormat {
     }
 }
 
+/// Which channel of an image mask is used as the coverage of the mask
+/// (CSS `mask-mode`).
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, MallocSizeOf, PartialEq, Serialize, PeekPoke)]
+pub enum MaskMode {
+    /// The alpha channel of the image.
+    Alpha = 0,
+    /// The luminance of the (premultiplied) color channels of the image.
+    Luminance = 1,
+}
+
+/// How an image mask is combined with the image masks applied before it
+/// (CSS `mask-composite`). Clips are applied starting from the clip chain
+/// node a primitive refers to, towards the root of the chain. The operator of
+/// the first image mask of a primitive has no effect, and all other clips
+/// intersect the combined masks.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, MallocSizeOf, PartialEq, Serialize, PeekPoke)]
+pub enum MaskCompositeOperator {
+    /// The union of both masks.
+    Add = 0,
+    /// The parts of this mask outside of the preceding ones.
+    Subtract = 1,
+    /// The intersection of both masks.
+    Intersect = 2,
+    /// The parts of either mask that don't overlap.
+    Exclude = 3,
+}
+
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
 pub struct ImageMask {
     pub image: ImageKey,
     pub rect: LayoutRect,
     pub repeat: bool,
+    pub mode: MaskMode,
+    pub composite_op: MaskCompositeOperator,
+}
+
+/// Clips to the area filled by a path outline. The path commands are in the
+/// same coordinate space as the clip rect, and are cut off by it.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub struct PathClip {
+    pub fill_rule: FillRule,
+    pub mode: ClipMode,
+}
+
+/// Clips to the coverage of a run of glyphs, like CSS `background-clip: text`.
+/// The glyphs are positioned in the same coordinate space as the clip rect,
+/// and are cut off by it.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub struct TextClip {
+    pub font_key: font::FontInstanceKey,
 }
 
 impl ImageMask {
@@ -1415,6 +1933,12 @@ impl SpatialId {
     }
 }
 
+/// A client chosen identifier for a range of display items, used to patch a
+/// display list that was previously sent. See `DisplayListPatchOp`.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize, PeekPoke)]
+#[repr(C)]
+pub struct ItemKey(pub u64);
+
 /// An external identifier that uniquely identifies a scroll frame independent of its ClipId, which
 /// may change from frame to frame. This should be unique within a pipeline. WebRender makes no
 /// attempt to ensure uniqueness. The zero value is reserved for use by the root scroll node of
@@ -1459,7 +1983,10 @@ impl DisplayItem {
             DisplayItem::SetFilterOps => "set_filter_ops",
             DisplayItem::SetFilterData => "set_filter_data",
             DisplayItem::SetFilterPrimitives => "set_filter_primitives",
+            DisplayItem::SetItemKey(..) => "set_item_key",
             DisplayItem::RadialGradient(..) => "radial_gradient",
+            DisplayItem::ConicGradient(..) => "conic_gradient",
+            DisplayItem::Path(..) => "path",
             DisplayItem::Rectangle(..) => "rectangle",
             DisplayItem::ScrollFrame(..) => "scroll_frame",
             DisplayItem::SetGradientStops => "set_gradient_stops",
@@ -1486,6 +2013,7 @@ macro_rules! impl_default_for_enums {
 impl_default_for_enums! {
     DisplayItem => PopStackingContext,
     ScrollSensitivity => ScriptAndInputEvents,
+    OverscrollBehavior => Auto,
     LineOrientation => Vertical,
     LineStyle => Solid,
     RepeatMode => Stretch,
@@ -1495,9 +2023,12 @@ impl_default_for_enums! {
     BorderStyle => None,
     BoxShadowClipMode => Outset,
     ExtendMode => Clamp,
+    GradientColorSpace => Srgb,
     FilterOp => Identity,
     ComponentTransferFuncType => Identity,
     ClipMode => Clip,
+    MaskMode => Alpha,
+    MaskCompositeOperator => Intersect,
     ClipId => ClipId::invalid(),
     ReferenceFrameKind => Transform,
     TransformStyle => Flat,
@@ -1511,5 +2042,13 @@ impl_default_for_enums! {
     YuvFormat => NV12,
     FilterPrimitiveInput => Original,
     ColorSpace => Srgb,
-    CompositeOperator => Over
+    CompositeOperator => Over,
+    MorphologyOperator => Erode,
+    ConvolveEdgeMode => Duplicate,
+    TurbulenceType => Turbulence,
+    ColorChannel => Alpha,
+    LightSource => Distant { azimuth: 0.0, elevation: 0.0 },
+    FillRule => NonZero,
+    PathCommand => Close,
+    PathPaint => Color(ColorF::default())
 }
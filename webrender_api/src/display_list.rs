@@ -4,7 +4,7 @@
 
 use euclid::SideOffsets2D;
 use peek_poke::{ensure_red_zone, peek_from_slice, poke_extend_vec};
-use peek_poke::{poke_inplace_slice, poke_into_vec, Poke};
+use peek_poke::{poke_inplace_slice, poke_into_vec, try_peek_from_slice, Poke};
 #[cfg(feature = "deserialize")]
 use serde::de::Deserializer;
 #[cfg(feature = "serialize")]
@@ -14,7 +14,7 @@ use std::io::{stdout, Write};
 use std::marker::PhantomData;
 use std::ops::Range;
 use std::mem;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use time::precise_time_ns;
 // local imports
 use crate::display_item as di;
@@ -38,6 +38,12 @@ const FIRST_SPATIAL_NODE_INDEX: usize = 2;
 // See ROOT_SCROLL_NODE_SPATIAL_ID
 const FIRST_CLIP_NODE_INDEX: usize = 1;
 
+/// The version of the display list encoding: how items and their trailing
+/// arrays are laid out in the data. Changes to the display item types
+/// themselves are caught by the layout hash instead, so this only needs to be
+/// bumped when the framing changes.
+pub const DISPLAY_LIST_FORMAT_VERSION: u32 = 1;
+
 #[repr(C)]
 #[derive(Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub struct ItemRange<'a, T> {
@@ -109,6 +115,12 @@ pub struct BuiltDisplayList {
 #[repr(C)]
 #[derive(Copy, Clone, Default, Deserialize, Serialize)]
 pub struct BuiltDisplayListDescriptor {
+    /// The `DISPLAY_LIST_FORMAT_VERSION` of the sender. This and `layout_hash`
+    /// come first, so that they stay at the same offset when other fields
+    /// change.
+    format_version: u32,
+    /// The `display_list_layout_hash()` of the sender.
+    layout_hash: u64,
     /// The first IPC time stamp: before any work has been done
     builder_start_time: u64,
     /// The second IPC time stamp: after serialization
@@ -119,6 +131,95 @@ pub struct BuiltDisplayListDescriptor {
     total_clip_nodes: usize,
     /// The amount of spatial nodes created while building this display list.
     total_spatial_nodes: usize,
+    /// The amount of clip chains created while building this display list.
+    total_clip_chains: u64,
+}
+
+/// Why `BuiltDisplayList::from_data` rejected a display list.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DisplayListFormatError {
+    /// The list was encoded with a different `DISPLAY_LIST_FORMAT_VERSION`.
+    VersionMismatch { expected: u32, found: u32 },
+    /// The list was encoded with different display item definitions.
+    LayoutMismatch { expected: u64, found: u64 },
+}
+
+lazy_static! {
+    static ref DISPLAY_LIST_LAYOUT_HASH: u64 = compute_display_list_layout_hash();
+}
+
+/// A hash of the layout of all the types serialized in a display list, as
+/// computed by `peek_poke::Poke::layout_hash`. It differs between webrender_api
+/// revisions that don't agree on how display lists are encoded.
+pub fn display_list_layout_hash() -> u64 {
+    *DISPLAY_LIST_LAYOUT_HASH
+}
+
+fn compute_display_list_layout_hash() -> u64 {
+    use peek_poke::hash_layout;
+
+    let hash = di::DisplayItem::layout_hash();
+    let hash = hash_layout(hash, di::GradientStop::layout_hash());
+    let hash = hash_layout(hash, GlyphInstance::layout_hash());
+    let hash = hash_layout(hash, di::PathCommand::layout_hash());
+    let hash = hash_layout(hash, di::FilterOp::layout_hash());
+    let hash = hash_layout(hash, di::ComponentTransferFuncType::layout_hash());
+    let hash = hash_layout(hash, f32::layout_hash());
+    let hash = hash_layout(hash, di::FilterPrimitive::layout_hash());
+    let hash = hash_layout(hash, di::ClipId::layout_hash());
+    hash_layout(hash, di::ComplexClipRegion::layout_hash())
+}
+
+/// A problem found by `BuiltDisplayList::validate`. The `index` of each error
+/// is the position of the offending item in the list, counting marker items.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DisplayListError {
+    /// The array data (glyphs, gradient stops, complex clips, etc) following
+    /// an item extends past the end of the display list.
+    TruncatedAuxData { index: usize },
+    /// A `PopStackingContext` without a matching `PushStackingContext`.
+    UnbalancedPopStackingContext { index: usize },
+    /// A `PopReferenceFrame` without a matching `PushReferenceFrame`.
+    UnbalancedPopReferenceFrame { index: usize },
+    /// A `PushStackingContext` that is never popped.
+    UnclosedStackingContext { index: usize },
+    /// A `PushReferenceFrame` that is never popped.
+    UnclosedReferenceFrame { index: usize },
+    /// A clip or clip chain that isn't defined earlier in the list.
+    UndefinedClipId { index: usize, id: di::ClipId },
+    /// A spatial node that isn't defined earlier in the list.
+    UndefinedSpatialId { index: usize, id: di::SpatialId },
+    /// A rect with a NaN or infinite coordinate.
+    NonFiniteRect { index: usize },
+    /// A filter with a NaN or infinite value.
+    NonFiniteFilterValue { index: usize },
+    /// An item, or an element of the array data following it, that can't be
+    /// decoded, such as an enum with an unknown tag.
+    MalformedItem { index: usize },
+}
+
+/// An edit of a display list that was previously sent for a pipeline, see
+/// `BuiltDisplayList::apply_patch`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum DisplayListPatchOp {
+    /// Insert the range with this key from the patch after the range keyed
+    /// `after`, or at the start of the display list.
+    Insert { after: Option<di::ItemKey>, key: di::ItemKey },
+    /// Replace the items of a range with the ones from the patch.
+    Replace(di::ItemKey),
+    /// Remove a range.
+    Remove(di::ItemKey),
+}
+
+/// Why `BuiltDisplayList::apply_patch` rejected a patch.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DisplayListPatchError {
+    /// An op refers to a range that isn't in the display list.
+    UnknownKey(di::ItemKey),
+    /// An `Insert` of a key that is already in the display list.
+    DuplicateKey(di::ItemKey),
+    /// The patch has no range for the key of an `Insert` or `Replace`.
+    MissingPatchItems(di::ItemKey),
 }
 
 pub struct BuiltDisplayListIter<'a> {
@@ -127,12 +228,15 @@ pub struct BuiltDisplayListIter<'a> {
     cur_item: di::DisplayItem,
     cur_stops: ItemRange<'a, di::GradientStop>,
     cur_glyphs: ItemRange<'a, GlyphInstance>,
+    cur_path_commands: ItemRange<'a, di::PathCommand>,
     cur_filters: ItemRange<'a, di::FilterOp>,
     cur_filter_data: Vec<TempFilterData<'a>>,
     cur_filter_primitives: ItemRange<'a, di::FilterPrimitive>,
     cur_clip_chain_items: ItemRange<'a, di::ClipId>,
     cur_complex_clip: ItemRange<'a, di::ComplexClipRegion>,
     peeking: Peek,
+    /// The iteration stops once this many bytes of `data` are left.
+    end_len: usize,
     /// Should just be initialized but never populated in release builds
     debug_stats: DebugStats,
 }
@@ -224,8 +328,29 @@ pub struct AuxIter<'a, T> {
 impl BuiltDisplayListDescriptor {}
 
 impl BuiltDisplayList {
-    pub fn from_data(data: Vec<u8>, descriptor: BuiltDisplayListDescriptor) -> Self {
-        BuiltDisplayList { data, descriptor }
+    /// Reconstruct a display list from the data and descriptor sent by
+    /// `into_data`. Fails if the sender was built from a webrender_api
+    /// revision that encodes display lists differently.
+    pub fn from_data(
+        data: Vec<u8>,
+        descriptor: BuiltDisplayListDescriptor,
+    ) -> Result<Self, DisplayListFormatError> {
+        if descriptor.format_version != DISPLAY_LIST_FORMAT_VERSION {
+            return Err(DisplayListFormatError::VersionMismatch {
+                expected: DISPLAY_LIST_FORMAT_VERSION,
+                found: descriptor.format_version,
+            });
+        }
+
+        let layout_hash = display_list_layout_hash();
+        if descriptor.layout_hash != layout_hash {
+            return Err(DisplayListFormatError::LayoutMismatch {
+                expected: layout_hash,
+                found: descriptor.layout_hash,
+            });
+        }
+
+        Ok(BuiltDisplayList { data, descriptor })
     }
 
     pub fn into_data(mut self) -> (Vec<u8>, BuiltDisplayListDescriptor) {
@@ -265,6 +390,670 @@ impl BuiltDisplayList {
     pub fn iter(&self) -> BuiltDisplayListIter {
         BuiltDisplayListIter::new(self)
     }
+
+    /// Check that the list is well formed, without panicking on malformed
+    /// input. Scene building assumes that:
+    ///
+    /// * All array data attached to items fits within the list.
+    /// * Stacking contexts and reference frames are properly nested.
+    /// * Every `ClipId` and `SpatialId` is defined before it is used.
+    /// * All rects and filter values are finite.
+    ///
+    /// Lists received from an untrusted source should be validated before
+    /// being sent to WebRender. All errors are reported, except that the walk
+    /// stops at an item that can't be decoded or at truncated array data,
+    /// since the items after it can't be found.
+    pub fn validate(&self) -> Result<(), Vec<DisplayListError>> {
+        let mut validator = DisplayListValidator::default();
+        let mut index = 0;
+        validator.validate_items(self.item_slice(), di::DisplayItem::max_size(), &mut index);
+        validator.finish()
+    }
+
+    /// Apply a patch to this display list, returning the patched list.
+    ///
+    /// The list is split into ranges of items by the `SetItemKey` markers
+    /// pushed with `DisplayListBuilder::push_item_key`. Items that precede the
+    /// first marker aren't part of any range and can't be patched. The items
+    /// of inserted and replaced ranges are taken from the range with the same
+    /// key in `patch`, which should be built with
+    /// `DisplayListBuilder::new_patch` so that its ids don't collide with the
+    /// ones of this list.
+    ///
+    /// The returned list keeps track of the ranges that were spliced in from
+    /// the patch, so that only those need to be checked again, see
+    /// `PatchedDisplayList::validate`.
+    pub fn apply_patch(
+        &self,
+        patch: &BuiltDisplayList,
+        ops: &[DisplayListPatchOp],
+    ) -> Result<PatchedDisplayList, DisplayListPatchError> {
+        // The ranges of the patched list, along with whether they come from
+        // the patch.
+        let mut ranges: Vec<(KeyedRange, bool)> = self.keyed_ranges()
+            .into_iter()
+            .map(|range| (range, false))
+            .collect();
+        let patch_ranges = patch.keyed_ranges();
+        let mut self_contained = true;
+
+        let find = |ranges: &[(KeyedRange, bool)], key| {
+            ranges.iter().position(|(range, _)| range.key == Some(key))
+        };
+        let patch_range = |key| {
+            patch_ranges
+                .iter()
+                .find(|range| range.key == Some(key))
+                .cloned()
+                .ok_or(DisplayListPatchError::MissingPatchItems(key))
+        };
+
+        for op in ops {
+            match *op {
+                DisplayListPatchOp::Insert { after, key } => {
+                    if find(&ranges, key).is_some() {
+                        return Err(DisplayListPatchError::DuplicateKey(key));
+                    }
+                    let index = match after {
+                        Some(after) => find(&ranges, after)
+                            .ok_or(DisplayListPatchError::UnknownKey(after))? + 1,
+                        None => 0,
+                    };
+                    let range = patch_range(key)?;
+                    self_contained &= range.summary.is_balanced();
+                    ranges.insert(index, (range, true));
+                }
+                DisplayListPatchOp::Replace(key) => {
+                    let index = find(&ranges, key).ok_or(DisplayListPatchError::UnknownKey(key))?;
+                    let range = patch_range(key)?;
+                    self_contained &= range.summary.is_balanced();
+                    self_contained &= ranges[index].0.summary.can_be_dropped();
+                    ranges[index] = (range, true);
+                }
+                DisplayListPatchOp::Remove(key) => {
+                    let index = find(&ranges, key).ok_or(DisplayListPatchError::UnknownKey(key))?;
+                    self_contained &= ranges[index].0.summary.can_be_dropped();
+                    ranges.remove(index);
+                }
+            }
+        }
+
+        let len = ranges.iter().map(|(range, _)| range.data.len()).sum::<usize>();
+        let mut data = Vec::with_capacity(len + di::DisplayItem::max_size());
+        let mut patched_ranges = Vec::with_capacity(ranges.len());
+        for (range, spliced) in ranges {
+            let start = data.len();
+            data.extend_from_slice(range.data);
+            patched_ranges.push(PatchedRange {
+                bytes: start .. data.len(),
+                summary: range.summary,
+                spliced,
+            });
+        }
+        ensure_red_zone::<di::DisplayItem>(&mut data);
+
+        let display_list = BuiltDisplayList {
+            data,
+            descriptor: BuiltDisplayListDescriptor {
+                format_version: self.descriptor.format_version,
+                layout_hash: self.descriptor.layout_hash,
+                builder_start_time: patch.descriptor.builder_start_time,
+                builder_finish_time: patch.descriptor.builder_finish_time,
+                send_start_time: patch.descriptor.send_start_time,
+                total_clip_nodes: self.descriptor.total_clip_nodes
+                    .max(patch.descriptor.total_clip_nodes),
+                total_spatial_nodes: self.descriptor.total_spatial_nodes
+                    .max(patch.descriptor.total_spatial_nodes),
+                total_clip_chains: self.descriptor.total_clip_chains
+                    .max(patch.descriptor.total_clip_chains),
+            },
+        };
+
+        Ok(PatchedDisplayList {
+            display_list,
+            ranges: patched_ranges,
+            self_contained,
+        })
+    }
+
+    /// Iterate over the items in a byte range of `item_slice`, which has to
+    /// start and end at item boundaries.
+    fn iter_range(&self, range: Range<usize>) -> BuiltDisplayListIter {
+        let data = self.item_slice();
+        let mut iter = BuiltDisplayListIter::new_with_list_and_data(self, &data[range.start ..]);
+        iter.end_len = data.len() - range.end;
+        iter
+    }
+
+    /// Split the items of the list at each `SetItemKey` marker. Each marker
+    /// is included in the range that it starts.
+    fn keyed_ranges(&self) -> Vec<KeyedRange<'_>> {
+        let data = self.item_slice();
+        let mut ranges = Vec::new();
+        let mut key = None;
+        let mut start = 0;
+        let mut summary = RangeSummary::new(0);
+
+        let mut iter = self.iter();
+        loop {
+            let offset = data.len() - iter.data.len();
+            let next_key = match iter.next_raw() {
+                Some(item) => match *item.item() {
+                    di::DisplayItem::SetItemKey(next_key) => Some(next_key),
+                    ref item => {
+                        summary.add_item(item);
+                        continue;
+                    }
+                },
+                None => None,
+            };
+
+            if key.is_some() || offset > start {
+                let depth = summary.end_depth;
+                ranges.push(KeyedRange { key, data: &data[start .. offset], summary });
+                summary = RangeSummary::new(depth);
+            }
+
+            match next_key {
+                Some(next_key) => {
+                    key = Some(next_key);
+                    start = offset;
+                    summary.add_item(&di::DisplayItem::SetItemKey(next_key));
+                }
+                None => return ranges,
+            }
+        }
+    }
+}
+
+/// A range of items of a display list, see `BuiltDisplayList::keyed_ranges`.
+#[derive(Clone)]
+struct KeyedRange<'a> {
+    /// The key of the range, or `None` for the items before the first key.
+    key: Option<di::ItemKey>,
+    data: &'a [u8],
+    summary: RangeSummary,
+}
+
+/// What `BuiltDisplayList::keyed_ranges` found out about the items of a
+/// range while splitting the list.
+#[derive(Clone)]
+struct RangeSummary {
+    /// Number of items, including marker items.
+    raw_items: usize,
+    /// Number of display items, not counting marker items.
+    items: usize,
+    /// Nesting depth of stacking contexts and reference frames at the start
+    /// and at the end of the range.
+    start_depth: usize,
+    end_depth: usize,
+    /// Whether the range pops stacking contexts or reference frames that were
+    /// pushed before it.
+    pops_outer_scopes: bool,
+    /// Clip and spatial ids defined by the items of the range.
+    clip_ids: Vec<di::ClipId>,
+    spatial_ids: Vec<di::SpatialId>,
+}
+
+impl RangeSummary {
+    fn new(depth: usize) -> Self {
+        RangeSummary {
+            raw_items: 0,
+            items: 0,
+            start_depth: depth,
+            end_depth: depth,
+            pops_outer_scopes: false,
+            clip_ids: Vec::new(),
+            spatial_ids: Vec::new(),
+        }
+    }
+
+    fn add_item(&mut self, item: &di::DisplayItem) {
+        use crate::DisplayItem::*;
+
+        self.raw_items += 1;
+        match *item {
+            SetGradientStops |
+            SetFilterOps |
+            SetFilterData |
+            SetFilterPrimitives |
+            SetItemKey(..) => return,
+            _ => self.items += 1,
+        }
+
+        match *item {
+            Clip(ref info) => self.clip_ids.push(info.id),
+            ClipChain(ref info) => self.clip_ids.push(di::ClipId::ClipChain(info.id)),
+            ScrollFrame(ref info) => {
+                self.clip_ids.push(info.clip_id);
+                self.spatial_ids.push(info.scroll_frame_id);
+            }
+            StickyFrame(ref info) => self.spatial_ids.push(info.id),
+            PushReferenceFrame(ref info) => {
+                self.spatial_ids.push(info.reference_frame.id);
+                self.end_depth += 1;
+            }
+            PushStackingContext(..) => self.end_depth += 1,
+            PopReferenceFrame |
+            PopStackingContext => {
+                if self.end_depth == self.start_depth {
+                    self.pops_outer_scopes = true;
+                }
+                self.end_depth = self.end_depth.saturating_sub(1);
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether every stacking context and reference frame pushed by the range
+    /// is popped by it, and no others.
+    fn is_balanced(&self) -> bool {
+        !self.pops_outer_scopes && self.end_depth == self.start_depth
+    }
+
+    /// Whether removing the range leaves the rest of the list as well formed
+    /// as it was.
+    fn can_be_dropped(&self) -> bool {
+        self.is_balanced() && self.clip_ids.is_empty() && self.spatial_ids.is_empty()
+    }
+}
+
+/// A display list with a patch applied, see `BuiltDisplayList::apply_patch`.
+pub struct PatchedDisplayList {
+    pub display_list: BuiltDisplayList,
+    ranges: Vec<PatchedRange>,
+    /// Whether the ranges the patch spliced in are balanced, and the ones it
+    /// dropped are balanced and don't define any ids. If so the items from
+    /// the previous list are left as they were, and only the spliced ranges
+    /// need to be checked. Otherwise the whole list does.
+    self_contained: bool,
+}
+
+/// A range of items of a `PatchedDisplayList`.
+struct PatchedRange {
+    /// Where the items are in the item data of the patched list.
+    bytes: Range<usize>,
+    summary: RangeSummary,
+    /// Whether the items come from the patch rather than the previous list.
+    spliced: bool,
+}
+
+impl PatchedDisplayList {
+    /// Check that the patched list is well formed, see
+    /// `BuiltDisplayList::validate`.
+    ///
+    /// The items kept from the previous list are assumed to be well formed,
+    /// so unless the patch changed how they nest or removed ids, only the
+    /// items spliced in from the patch are walked. Those can use the ids
+    /// defined before them, but have to close the stacking contexts and
+    /// reference frames they open.
+    pub fn validate(&self) -> Result<(), Vec<DisplayListError>> {
+        if !self.self_contained {
+            return self.display_list.validate();
+        }
+
+        let mut validator = DisplayListValidator::default();
+        let data = self.display_list.item_slice();
+        let mut index = 0;
+
+        for range in &self.ranges {
+            if !range.spliced {
+                validator.clip_ids.extend(range.summary.clip_ids.iter().cloned());
+                validator.spatial_ids.extend(range.summary.spatial_ids.iter().cloned());
+                index += range.summary.raw_items;
+                continue;
+            }
+
+            let end_len = data.len() - range.bytes.end;
+            if !validator.validate_items(&data[range.bytes.start ..], end_len, &mut index) {
+                break;
+            }
+            validator.close_scopes();
+        }
+
+        validator.finish()
+    }
+
+    /// The items spliced in from the patch, along with the nesting depth of
+    /// stacking contexts and reference frames at the start of each range. The
+    /// ranges are balanced. Returns None if the patch changed the items kept
+    /// from the previous list in a way that requires walking the whole list
+    /// again.
+    pub fn spliced_items(&self) -> Option<Vec<(BuiltDisplayListIter, usize)>> {
+        if !self.self_contained {
+            return None;
+        }
+
+        let mut depth = 0;
+        let mut spliced = Vec::new();
+        for range in &self.ranges {
+            if range.spliced {
+                spliced.push((self.display_list.iter_range(range.bytes.clone()), depth));
+            } else {
+                depth = range.summary.end_depth;
+            }
+        }
+
+        Some(spliced)
+    }
+
+    /// The number of display items kept from the previous list, not counting
+    /// marker items.
+    pub fn retained_item_count(&self) -> usize {
+        self.ranges
+            .iter()
+            .filter(|range| !range.spliced)
+            .map(|range| range.summary.items)
+            .sum()
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum ValidatorScope {
+    StackingContext,
+    ReferenceFrame,
+}
+
+/// State tracked while walking the items of a display list in `validate`.
+#[derive(Default)]
+struct DisplayListValidator {
+    errors: Vec<DisplayListError>,
+    /// The currently open stacking contexts and reference frames, along with
+    /// the index of the item that pushed them.
+    scopes: Vec<(ValidatorScope, usize)>,
+    clip_ids: HashSet<di::ClipId>,
+    spatial_ids: HashSet<di::SpatialId>,
+}
+
+impl DisplayListValidator {
+    /// Validate the items of `data` until only `end_len` bytes are left,
+    /// counting them in `index`. Returns false if an item can't be decoded,
+    /// in which case the items after it can't be found.
+    fn validate_items(&mut self, mut data: &[u8], end_len: usize, index: &mut usize) -> bool {
+        // Same end of list condition as `BuiltDisplayListIter::next_raw`.
+        while data.len() > end_len {
+            let mut item = di::DisplayItem::PopStackingContext;
+            data = match try_peek_from_slice(data, &mut item) {
+                Some(data) => data,
+                None => {
+                    self.errors.push(DisplayListError::MalformedItem { index: *index });
+                    return false;
+                }
+            };
+
+            if let Err(error) = self.validate_item(*index, &item, &mut data) {
+                self.errors.push(error);
+                return false;
+            }
+            *index += 1;
+        }
+
+        true
+    }
+
+    /// Validate a single item, advancing `data` past any arrays following it.
+    /// Returns an error if those arrays can't be decoded, in which case the
+    /// rest of the list can't be walked.
+    fn validate_item(
+        &mut self,
+        index: usize,
+        item: &di::DisplayItem,
+        data: &mut &[u8],
+    ) -> Result<(), DisplayListError> {
+        use crate::DisplayItem::*;
+
+        match *item {
+            Rectangle(ref info) => self.check_common(index, &info.common),
+            ClearRectangle(ref info) => self.check_common(index, &info.common),
+            HitTest(ref info) => self.check_common(index, &info.common),
+            BackdropFilter(ref info) => self.check_common(index, &info.common),
+            Text(ref info) => {
+                validate_slice::<GlyphInstance>(index, data)?;
+                self.check_common(index, &info.common);
+                self.check_rect(index, &info.bounds);
+            }
+            Line(ref info) => {
+                self.check_common(index, &info.common);
+                self.check_rect(index, &info.area);
+            }
+            Border(ref info) => {
+                self.check_common(index, &info.common);
+                self.check_rect(index, &info.bounds);
+            }
+            BoxShadow(ref info) => {
+                self.check_common(index, &info.common);
+                self.check_rect(index, &info.box_bounds);
+            }
+            Gradient(ref info) => {
+                self.check_common(index, &info.common);
+                self.check_rect(index, &info.bounds);
+            }
+            RadialGradient(ref info) => {
+                self.check_common(index, &info.common);
+                self.check_rect(index, &info.bounds);
+            }
+            ConicGradient(ref info) => {
+                self.check_common(index, &info.common);
+                self.check_rect(index, &info.bounds);
+            }
+            Path(ref info) => {
+                validate_slice::<di::PathCommand>(index, data)?;
+                self.check_common(index, &info.common);
+                self.check_rect(index, &info.bounds);
+            }
+            Image(ref info) => {
+                self.check_common(index, &info.common);
+                self.check_rect(index, &info.bounds);
+            }
+            RepeatingImage(ref info) => {
+                self.check_common(index, &info.common);
+                self.check_rect(index, &info.bounds);
+            }
+            YuvImage(ref info) => {
+                self.check_common(index, &info.common);
+                self.check_rect(index, &info.bounds);
+            }
+            PushShadow(ref info) => {
+                self.check_space_and_clip(index, &info.space_and_clip);
+            }
+            Clip(ref info) => {
+                let complex_clips = validate_slice::<di::ComplexClipRegion>(index, data)?;
+                validate_slice::<di::PathCommand>(index, data)?;
+                validate_slice::<GlyphInstance>(index, data)?;
+                self.check_space_and_clip(index, &info.parent_space_and_clip);
+                self.check_rect(index, &info.clip_rect);
+                for complex_clip in complex_clips.iter() {
+                    self.check_rect(index, &complex_clip.rect);
+                }
+                if let Some(ref image_mask) = info.image_mask {
+                    self.check_rect(index, &image_mask.rect);
+                }
+                self.clip_ids.insert(info.id);
+            }
+            ClipChain(ref info) => {
+                let clip_ids = validate_slice::<di::ClipId>(index, data)?;
+                for clip_id in clip_ids.iter() {
+                    self.check_clip_id(index, clip_id);
+                }
+                if let Some(parent) = info.parent {
+                    self.check_clip_id(index, di::ClipId::ClipChain(parent));
+                }
+                self.clip_ids.insert(di::ClipId::ClipChain(info.id));
+            }
+            ScrollFrame(ref info) => {
+                let complex_clips = validate_slice::<di::ComplexClipRegion>(index, data)?;
+                self.check_space_and_clip(index, &info.parent_space_and_clip);
+                self.check_rect(index, &info.content_rect);
+                self.check_rect(index, &info.clip_rect);
+                for complex_clip in complex_clips.iter() {
+                    self.check_rect(index, &complex_clip.rect);
+                }
+                if let Some(ref image_mask) = info.image_mask {
+                    self.check_rect(index, &image_mask.rect);
+                }
+                self.clip_ids.insert(info.clip_id);
+                self.spatial_ids.insert(info.scroll_frame_id);
+            }
+            StickyFrame(ref info) => {
+                self.check_spatial_id(index, info.parent_spatial_id);
+                self.check_rect(index, &info.bounds);
+                self.spatial_ids.insert(info.id);
+            }
+            Iframe(ref info) => {
+                self.check_space_and_clip(index, &info.space_and_clip);
+                self.check_rect(index, &info.bounds);
+                self.check_rect(index, &info.clip_rect);
+            }
+            PushReferenceFrame(ref info) => {
+                self.check_spatial_id(index, info.parent_spatial_id);
+                self.spatial_ids.insert(info.reference_frame.id);
+                self.scopes.push((ValidatorScope::ReferenceFrame, index));
+            }
+            PushStackingContext(ref info) => {
+                self.check_spatial_id(index, info.spatial_id);
+                if let Some(clip_id) = info.stacking_context.clip_id {
+                    self.check_clip_id(index, clip_id);
+                }
+                self.scopes.push((ValidatorScope::StackingContext, index));
+            }
+            SetGradientStops => {
+                validate_slice::<di::GradientStop>(index, data)?;
+            }
+            SetFilterOps => {
+                let filters = validate_slice::<di::FilterOp>(index, data)?;
+                for filter in filters.iter() {
+                    self.check_filter(index, &filter);
+                }
+            }
+            SetFilterData => {
+                validate_slice::<di::ComponentTransferFuncType>(index, data)?;
+                validate_slice::<f32>(index, data)?;
+                validate_slice::<f32>(index, data)?;
+                validate_slice::<f32>(index, data)?;
+                validate_slice::<f32>(index, data)?;
+            }
+            SetFilterPrimitives => {
+                validate_slice::<di::FilterPrimitive>(index, data)?;
+            }
+            PopReferenceFrame => {
+                self.pop_scope(
+                    ValidatorScope::ReferenceFrame,
+                    DisplayListError::UnbalancedPopReferenceFrame { index },
+                );
+            }
+            PopStackingContext => {
+                self.pop_scope(
+                    ValidatorScope::StackingContext,
+                    DisplayListError::UnbalancedPopStackingContext { index },
+                );
+            }
+            SetItemKey(..) |
+            PopAllShadows => {}
+        }
+
+        Ok(())
+    }
+
+    fn pop_scope(&mut self, scope: ValidatorScope, error: DisplayListError) {
+        match self.scopes.last() {
+            Some(&(current, _)) if current == scope => {
+                self.scopes.pop();
+            }
+            _ => self.errors.push(error),
+        }
+    }
+
+    fn check_common(&mut self, index: usize, common: &di::CommonItemProperties) {
+        self.check_rect(index, &common.clip_rect);
+        self.check_clip_id(index, common.clip_id);
+        self.check_spatial_id(index, common.spatial_id);
+    }
+
+    fn check_space_and_clip(&mut self, index: usize, space_and_clip: &di::SpaceAndClipInfo) {
+        self.check_clip_id(index, space_and_clip.clip_id);
+        self.check_spatial_id(index, space_and_clip.spatial_id);
+    }
+
+    fn check_clip_id(&mut self, index: usize, id: di::ClipId) {
+        if !id.is_root() && !self.clip_ids.contains(&id) {
+            self.errors.push(DisplayListError::UndefinedClipId { index, id });
+        }
+    }
+
+    fn check_spatial_id(&mut self, index: usize, id: di::SpatialId) {
+        if !id.is_root_reference_frame() &&
+           !id.is_root_scroll_node() &&
+           !self.spatial_ids.contains(&id) {
+            self.errors.push(DisplayListError::UndefinedSpatialId { index, id });
+        }
+    }
+
+    fn check_rect(&mut self, index: usize, rect: &LayoutRect) {
+        let is_finite = rect.origin.x.is_finite() &&
+            rect.origin.y.is_finite() &&
+            rect.size.width.is_finite() &&
+            rect.size.height.is_finite();
+        if !is_finite {
+            self.errors.push(DisplayListError::NonFiniteRect { index });
+        }
+    }
+
+    fn check_filter(&mut self, index: usize, filter: &di::FilterOp) {
+        use crate::FilterOp::*;
+
+        let is_finite = match *filter {
+            Blur(width, height) => width.is_finite() && height.is_finite(),
+            Brightness(value) |
+            Contrast(value) |
+            Grayscale(value) |
+            HueRotate(value) |
+            Invert(value) |
+            Saturate(value) |
+            Sepia(value) => value.is_finite(),
+            Opacity(ref binding, value) => {
+                let bound = match *binding {
+                    PropertyBinding::Value(value) => value.is_finite(),
+                    PropertyBinding::Binding(_, value) => value.is_finite(),
+                };
+                bound && value.is_finite()
+            }
+            DropShadow(ref shadow) => {
+                shadow.offset.x.is_finite() &&
+                    shadow.offset.y.is_finite() &&
+                    shadow.blur_radius.is_finite()
+            }
+            ColorMatrix(ref matrix) => matrix.iter().all(|value| value.is_finite()),
+            Identity |
+            SrgbToLinear |
+            LinearToSrgb |
+            ComponentTransfer |
+            Flood(..) |
+            ColorLut3D { .. } => true,
+        };
+        if !is_finite {
+            self.errors.push(DisplayListError::NonFiniteFilterValue { index });
+        }
+    }
+
+    /// Report the stacking contexts and reference frames that are still open.
+    fn close_scopes(&mut self) {
+        for (scope, index) in self.scopes.drain(..) {
+            self.errors.push(match scope {
+                ValidatorScope::StackingContext => DisplayListError::UnclosedStackingContext { index },
+                ValidatorScope::ReferenceFrame => DisplayListError::UnclosedReferenceFrame { index },
+            });
+        }
+    }
+
+    fn finish(mut self) -> Result<(), Vec<DisplayListError>> {
+        self.close_scopes();
+
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(self.errors)
+        }
+    }
 }
 
 /// Returns the byte-range the slice occupied.
@@ -282,6 +1071,46 @@ fn skip_slice<'a, T: peek_poke::Peek>(data: &mut &'a [u8]) -> ItemRange<'a, T> {
     }
 }
 
+/// Like `skip_slice`, but returns an error instead of panicking if the slice,
+/// or any of the items in it, don't fit in `data` or can't be decoded.
+fn validate_slice<'a, T>(
+    index: usize,
+    data: &mut &'a [u8],
+) -> Result<ItemRange<'a, T>, DisplayListError>
+where
+    T: Default + peek_poke::Peek,
+{
+    let truncated = DisplayListError::TruncatedAuxData { index };
+    let mut skip_offset = 0usize;
+    let rest = try_peek_from_slice(data, &mut skip_offset).ok_or(truncated.clone())?;
+    if skip_offset > rest.len() {
+        return Err(truncated);
+    }
+    let (bytes, rest) = rest.split_at(skip_offset);
+
+    // Read the items the same way `AuxIter` does, checking the remaining
+    // length and the encoding of each item.
+    if !bytes.is_empty() {
+        let mut count = 0usize;
+        let mut items = try_peek_from_slice(bytes, &mut count).ok_or(truncated.clone())?;
+        let mut item = T::default();
+        for _ in 0 .. count {
+            if items.len() <= T::max_size() {
+                return Err(truncated);
+            }
+            items = try_peek_from_slice(items, &mut item)
+                .ok_or(DisplayListError::MalformedItem { index })?;
+        }
+    }
+
+    *data = rest;
+
+    Ok(ItemRange {
+        bytes,
+        _boo: PhantomData,
+    })
+}
+
 impl<'a> BuiltDisplayListIter<'a> {
     pub fn new(list: &'a BuiltDisplayList) -> Self {
         Self::new_with_list_and_data(list, list.item_slice())
@@ -294,12 +1123,14 @@ impl<'a> BuiltDisplayListIter<'a> {
             cur_item: di::DisplayItem::PopStackingContext,
             cur_stops: ItemRange::default(),
             cur_glyphs: ItemRange::default(),
+            cur_path_commands: ItemRange::default(),
             cur_filters: ItemRange::default(),
             cur_filter_data: Vec::new(),
             cur_filter_primitives: ItemRange::default(),
             cur_clip_chain_items: ItemRange::default(),
             cur_complex_clip: ItemRange::default(),
             peeking: Peek::NotPeeking,
+            end_len: di::DisplayItem::max_size(),
             debug_stats: DebugStats {
                 last_addr: data.as_ptr() as usize,
                 stats: HashMap::default(),
@@ -328,6 +1159,7 @@ impl<'a> BuiltDisplayListIter<'a> {
         // Don't let these bleed into another item
         self.cur_stops = ItemRange::default();
         self.cur_complex_clip = ItemRange::default();
+        self.cur_path_commands = ItemRange::default();
         self.cur_clip_chain_items = ItemRange::default();
         self.cur_filters = ItemRange::default();
         self.cur_filter_primitives = ItemRange::default();
@@ -339,8 +1171,9 @@ impl<'a> BuiltDisplayListIter<'a> {
                 SetGradientStops |
                 SetFilterOps |
                 SetFilterData |
-                SetFilterPrimitives => {
-                    // These are marker items for populating other display items, don't yield them.
+                SetFilterPrimitives |
+                SetItemKey(..) => {
+                    // These are marker items for populating or keying other display items, don't yield them.
                     continue;
                 }
                 _ => {
@@ -360,8 +1193,9 @@ impl<'a> BuiltDisplayListIter<'a> {
 
         // A "red zone" of DisplayItem::max_size() bytes has been added to the
         // end of the serialized display list. If this amount, or less, is
-        // remaining then we've reached the end of the display list.
-        if self.data.len() <= di::DisplayItem::max_size() {
+        // remaining then we've reached the end of the display list. Iterators
+        // over a range of the list stop earlier, see `end_len`.
+        if self.data.len() <= self.end_len {
             return None;
         }
 
@@ -401,19 +1235,26 @@ impl<'a> BuiltDisplayListIter<'a> {
                 self.cur_clip_chain_items = skip_slice::<di::ClipId>(&mut self.data);
                 self.debug_stats.log_slice("clip_chain.clip_ids", &self.cur_clip_chain_items);
             }
-            Clip(_) | ScrollFrame(_) => {
+            Clip(_) => {
                 self.cur_complex_clip = skip_slice::<di::ComplexClipRegion>(&mut self.data);
-                let name = if let Clip(_) = self.cur_item {
-                    "clip.complex_clips"
-                } else {
-                    "scroll_frame.complex_clips"
-                };
-                self.debug_stats.log_slice(name, &self.cur_complex_clip);
+                self.debug_stats.log_slice("clip.complex_clips", &self.cur_complex_clip);
+                self.cur_path_commands = skip_slice::<di::PathCommand>(&mut self.data);
+                self.debug_stats.log_slice("clip.path_commands", &self.cur_path_commands);
+                self.cur_glyphs = skip_slice::<GlyphInstance>(&mut self.data);
+                self.debug_stats.log_slice("clip.glyphs", &self.cur_glyphs);
+            }
+            ScrollFrame(_) => {
+                self.cur_complex_clip = skip_slice::<di::ComplexClipRegion>(&mut self.data);
+                self.debug_stats.log_slice("scroll_frame.complex_clips", &self.cur_complex_clip);
             }
             Text(_) => {
                 self.cur_glyphs = skip_slice::<GlyphInstance>(&mut self.data);
                 self.debug_stats.log_slice("text.glyphs", &self.cur_glyphs);
             }
+            Path(_) => {
+                self.cur_path_commands = skip_slice::<di::PathCommand>(&mut self.data);
+                self.debug_stats.log_slice("path.commands", &self.cur_path_commands);
+            }
             _ => { /* do nothing */ }
         }
 
@@ -499,6 +1340,10 @@ impl<'a, 'b> DisplayItemRef<'a, 'b> {
         self.iter.cur_glyphs
     }
 
+    pub fn path_commands(&self) -> ItemRange<di::PathCommand> {
+        self.iter.cur_path_commands
+    }
+
     pub fn filters(&self) -> ItemRange<di::FilterOp> {
         self.iter.cur_filters
     }
@@ -574,7 +1419,9 @@ impl Serialize for BuiltDisplayList {
             let serial_di = match *item.item() {
                 Real::Clip(v) => Debug::Clip(
                     v,
-                    item.iter.cur_complex_clip.iter().collect()
+                    item.iter.cur_complex_clip.iter().collect(),
+                    item.iter.cur_path_commands.iter().collect(),
+                    item.iter.cur_glyphs.iter().collect()
                 ),
                 Real::ClipChain(v) => Debug::ClipChain(
                     v,
@@ -588,6 +1435,10 @@ impl Serialize for BuiltDisplayList {
                     v,
                     item.iter.cur_glyphs.iter().collect()
                 ),
+                Real::Path(v) => Debug::Path(
+                    v,
+                    item.iter.cur_path_commands.iter().collect()
+                ),
                 Real::SetFilterOps => Debug::SetFilterOps(
                     item.iter.cur_filters.iter().collect()
                 ),
@@ -617,6 +1468,7 @@ impl Serialize for BuiltDisplayList {
                 Real::SetGradientStops => Debug::SetGradientStops(
                     item.iter.cur_stops.iter().collect()
                 ),
+                Real::SetItemKey(key) => Debug::SetItemKey(key),
                 Real::StickyFrame(v) => Debug::StickyFrame(v),
                 Real::Rectangle(v) => Debug::Rectangle(v),
                 Real::ClearRectangle(v) => Debug::ClearRectangle(v),
@@ -629,6 +1481,7 @@ impl Serialize for BuiltDisplayList {
                 Real::BoxShadow(v) => Debug::BoxShadow(v),
                 Real::Gradient(v) => Debug::Gradient(v),
                 Real::RadialGradient(v) => Debug::RadialGradient(v),
+                Real::ConicGradient(v) => Debug::ConicGradient(v),
                 Real::Iframe(v) => Debug::Iframe(v),
                 Real::PushReferenceFrame(v) => Debug::PushReferenceFrame(v),
                 Real::PushStackingContext(v) => Debug::PushStackingContext(v),
@@ -664,14 +1517,18 @@ impl<'de> Deserialize<'de> for BuiltDisplayList {
         let mut temp = Vec::new();
         let mut total_clip_nodes = FIRST_CLIP_NODE_INDEX;
         let mut total_spatial_nodes = FIRST_SPATIAL_NODE_INDEX;
+        let mut total_clip_chains = 0;
         for complete in list {
             let item = match complete {
-                Debug::Clip(v, complex_clips) => {
+                Debug::Clip(v, complex_clips, path_commands, glyphs) => {
                     total_clip_nodes += 1;
                     DisplayListBuilder::push_iter_impl(&mut temp, complex_clips);
+                    DisplayListBuilder::push_iter_impl(&mut temp, path_commands);
+                    DisplayListBuilder::push_iter_impl(&mut temp, glyphs);
                     Real::Clip(v)
                 },
                 Debug::ClipChain(v, clip_chain_ids) => {
+                    total_clip_chains += 1;
                     DisplayListBuilder::push_iter_impl(&mut temp, clip_chain_ids);
                     Real::ClipChain(v)
                 }
@@ -689,6 +1546,10 @@ impl<'de> Deserialize<'de> for BuiltDisplayList {
                     DisplayListBuilder::push_iter_impl(&mut temp, glyphs);
                     Real::Text(v)
                 },
+                Debug::Path(v, commands) => {
+                    DisplayListBuilder::push_iter_impl(&mut temp, commands);
+                    Real::Path(v)
+                },
                 Debug::Iframe(v) => {
                     total_clip_nodes += 1;
                     Real::Iframe(v)
@@ -722,6 +1583,7 @@ impl<'de> Deserialize<'de> for BuiltDisplayList {
                     DisplayListBuilder::push_iter_impl(&mut temp, stops);
                     Real::SetGradientStops
                 },
+                Debug::SetItemKey(key) => Real::SetItemKey(key),
 
                 Debug::Rectangle(v) => Real::Rectangle(v),
                 Debug::ClearRectangle(v) => Real::ClearRectangle(v),
@@ -734,6 +1596,7 @@ impl<'de> Deserialize<'de> for BuiltDisplayList {
                 Debug::BoxShadow(v) => Real::BoxShadow(v),
                 Debug::Gradient(v) => Real::Gradient(v),
                 Debug::RadialGradient(v) => Real::RadialGradient(v),
+                Debug::ConicGradient(v) => Real::ConicGradient(v),
                 Debug::PushStackingContext(v) => Real::PushStackingContext(v),
                 Debug::PushShadow(v) => Real::PushShadow(v),
                 Debug::BackdropFilter(v) => Real::BackdropFilter(v),
@@ -755,11 +1618,14 @@ impl<'de> Deserialize<'de> for BuiltDisplayList {
         Ok(BuiltDisplayList {
             data,
             descriptor: BuiltDisplayListDescriptor {
+                format_version: DISPLAY_LIST_FORMAT_VERSION,
+                layout_hash: display_list_layout_hash(),
                 builder_start_time: 0,
                 builder_finish_time: 1,
                 send_start_time: 0,
                 total_clip_nodes,
                 total_spatial_nodes,
+                total_clip_chains,
             },
         })
     }
@@ -812,6 +1678,22 @@ impl DisplayListBuilder {
         }
     }
 
+    /// Create a builder for the items of a patch to a display list, see
+    /// `BuiltDisplayList::apply_patch`. `previous` is the descriptor of the
+    /// list being patched, or of the last patch applied to it, so that the
+    /// ids allocated by this builder don't collide with the ones in use.
+    pub fn new_patch(
+        pipeline_id: PipelineId,
+        content_size: LayoutSize,
+        previous: &BuiltDisplayListDescriptor,
+    ) -> Self {
+        let mut builder = Self::new(pipeline_id, content_size);
+        builder.next_clip_index = previous.total_clip_nodes;
+        builder.next_spatial_index = previous.total_spatial_nodes;
+        builder.next_clip_chain_id = previous.total_clip_chains;
+        builder
+    }
+
     /// Return the content size for this display list
     pub fn content_size(&self) -> LayoutSize {
         self.content_size
@@ -956,7 +1838,21 @@ impl DisplayListBuilder {
     ) {
         let item = di::DisplayItem::Rectangle(di::RectangleDisplayItem {
             common: *common,
-            color
+            color: PropertyBinding::Value(color),
+        });
+        self.push_item(&item);
+    }
+
+    /// Pushes a rectangle whose color can be animated through the
+    /// `colors` of `DynamicProperties`, without building a new display list.
+    pub fn push_rect_with_animation(
+        &mut self,
+        common: &di::CommonItemProperties,
+        color: PropertyBinding<ColorF>,
+    ) {
+        let item = di::DisplayItem::Rectangle(di::RectangleDisplayItem {
+            common: *common,
+            color,
         });
         self.push_item(&item);
     }
@@ -1124,6 +2020,21 @@ impl DisplayListBuilder {
         gradient
     }
 
+    /// NOTE: gradients must be pushed in the order they're created
+    /// because create_gradient stores the stops in anticipation.
+    pub fn create_conic_gradient(
+        &mut self,
+        center: LayoutPoint,
+        angle: f32,
+        stops: Vec<di::GradientStop>,
+        extend_mode: di::ExtendMode,
+    ) -> di::ConicGradient {
+        let mut builder = GradientBuilder::with_stops(stops);
+        let gradient = builder.conic_gradient(center, angle, extend_mode);
+        self.push_stops(builder.stops());
+        gradient
+    }
+
     pub fn push_border(
         &mut self,
         common: &di::CommonItemProperties,
@@ -1221,6 +2132,52 @@ impl DisplayListBuilder {
         self.push_item(&item);
     }
 
+    /// Pushes a conic gradient to be displayed.
+    ///
+    /// See [`push_gradient`](#method.push_gradient) for explanation.
+    pub fn push_conic_gradient(
+        &mut self,
+        common: &di::CommonItemProperties,
+        bounds: LayoutRect,
+        gradient: di::ConicGradient,
+        tile_size: LayoutSize,
+        tile_spacing: LayoutSize,
+    ) {
+        let item = di::DisplayItem::ConicGradient(di::ConicGradientDisplayItem {
+            common: *common,
+            bounds,
+            gradient,
+            tile_size,
+            tile_spacing,
+        });
+
+        self.push_item(&item);
+    }
+
+    /// Pushes a filled path to be displayed.
+    ///
+    /// When painting with a gradient, it must have been created with
+    /// [`create_gradient`](#method.create_gradient) just before this call so
+    /// that its stops are picked up.
+    pub fn push_path(
+        &mut self,
+        common: &di::CommonItemProperties,
+        bounds: LayoutRect,
+        fill_rule: di::FillRule,
+        paint: di::PathPaint,
+        commands: &[di::PathCommand],
+    ) {
+        let item = di::DisplayItem::Path(di::PathDisplayItem {
+            common: *common,
+            bounds,
+            fill_rule,
+            paint,
+        });
+
+        self.push_item(&item);
+        self.push_iter(commands);
+    }
+
     pub fn push_reference_frame(
         &mut self,
         origin: LayoutPoint,
@@ -1264,6 +2221,7 @@ impl DisplayListBuilder {
         raster_space: di::RasterSpace,
         cache_tiles: bool,
         is_backdrop_root: bool,
+        snapshot: Option<ImageKey>,
     ) {
         self.push_filters(filters, filter_datas, filter_primitives);
 
@@ -1278,6 +2236,7 @@ impl DisplayListBuilder {
                 raster_space,
                 cache_tiles,
                 is_backdrop_root,
+                snapshot,
             },
         });
 
@@ -1324,6 +2283,7 @@ impl DisplayListBuilder {
             di::RasterSpace::Screen,
             /* cache_tiles = */ false,
             /* is_backdrop_root = */ false,
+            /* snapshot = */ None,
         );
     }
 
@@ -1407,6 +2367,7 @@ impl DisplayListBuilder {
         complex_clips: I,
         image_mask: Option<di::ImageMask>,
         scroll_sensitivity: di::ScrollSensitivity,
+        overscroll_behavior: di::OverscrollBehavior,
         external_scroll_offset: LayoutVector2D,
     ) -> di::SpaceAndClipInfo
     where
@@ -1424,6 +2385,7 @@ impl DisplayListBuilder {
             external_id,
             image_mask,
             scroll_sensitivity,
+            overscroll_behavior,
             external_scroll_offset,
         });
 
@@ -1468,10 +2430,68 @@ impl DisplayListBuilder {
             parent_space_and_clip: *parent_space_and_clip,
             clip_rect,
             image_mask,
+            path_clip: None,
+            text_clip: None,
         });
 
         self.push_item(&item);
         self.push_iter(complex_clips);
+        self.push_iter(&[] as &[di::PathCommand]);
+        self.push_iter(&[] as &[GlyphInstance]);
+        id
+    }
+
+    /// Defines a clip to the area of the path described by `commands`,
+    /// filled according to `path_clip`. Only the parts of the path within
+    /// `clip_rect` are kept.
+    pub fn define_path_clip(
+        &mut self,
+        parent_space_and_clip: &di::SpaceAndClipInfo,
+        clip_rect: LayoutRect,
+        path_clip: di::PathClip,
+        commands: &[di::PathCommand],
+    ) -> di::ClipId {
+        let id = self.generate_clip_index();
+        let item = di::DisplayItem::Clip(di::ClipDisplayItem {
+            id,
+            parent_space_and_clip: *parent_space_and_clip,
+            clip_rect,
+            image_mask: None,
+            path_clip: Some(path_clip),
+            text_clip: None,
+        });
+
+        self.push_item(&item);
+        self.push_iter(&[] as &[di::ComplexClipRegion]);
+        self.push_iter(commands);
+        self.push_iter(&[] as &[GlyphInstance]);
+        id
+    }
+
+    /// Defines a clip to the coverage of the given glyphs, rasterized with
+    /// the font instance the same way a text run would be. Only the parts
+    /// of the glyphs within `clip_rect` are kept.
+    pub fn define_text_clip(
+        &mut self,
+        parent_space_and_clip: &di::SpaceAndClipInfo,
+        clip_rect: LayoutRect,
+        font_key: FontInstanceKey,
+        glyphs: &[GlyphInstance],
+    ) -> di::ClipId {
+        let id = self.generate_clip_index();
+        let item = di::DisplayItem::Clip(di::ClipDisplayItem {
+            id,
+            parent_space_and_clip: *parent_space_and_clip,
+            clip_rect,
+            image_mask: None,
+            path_clip: None,
+            text_clip: Some(di::TextClip { font_key }),
+        });
+
+        self.push_item(&item);
+        self.push_iter(&[] as &[di::ComplexClipRegion]);
+        self.push_iter(&[] as &[di::PathCommand]);
+        self.push_iter(glyphs);
         id
     }
 
@@ -1535,6 +2555,13 @@ impl DisplayListBuilder {
         self.push_item(&di::DisplayItem::PopAllShadows);
     }
 
+    /// Start a range of items that can later be inserted, replaced or removed
+    /// by a display list patch. The range extends to the next key, so scopes
+    /// such as stacking contexts should not straddle two ranges.
+    pub fn push_item_key(&mut self, key: di::ItemKey) {
+        self.push_item(&di::DisplayItem::SetItemKey(key));
+    }
+
     pub fn finalize(mut self) -> (PipelineId, LayoutSize, BuiltDisplayList) {
         assert!(self.save_state.is_none(), "Finalized DisplayListBuilder with a pending save");
 
@@ -1550,14 +2577,596 @@ impl DisplayListBuilder {
             self.content_size,
             BuiltDisplayList {
                 descriptor: BuiltDisplayListDescriptor {
+                    format_version: DISPLAY_LIST_FORMAT_VERSION,
+                    layout_hash: display_list_layout_hash(),
                     builder_start_time: self.builder_start_time,
                     builder_finish_time: end_time,
                     send_start_time: 0,
                     total_clip_nodes: self.next_clip_index,
                     total_spatial_nodes: self.next_spatial_index,
+                    total_clip_chains: self.next_clip_chain_id,
                 },
                 data: self.data,
             },
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::IdNamespace;
+
+    /// The items of a list, identified by key or by the red channel of the
+    /// rect color.
+    #[derive(Debug, PartialEq)]
+    enum Item {
+        Key(u64),
+        Rect(f32),
+        Other,
+    }
+
+    fn items(list: &BuiltDisplayList) -> Vec<Item> {
+        let mut iter = list.iter();
+        let mut items = Vec::new();
+        while let Some(item) = iter.next_raw() {
+            items.push(match *item.item() {
+                di::DisplayItem::SetItemKey(key) => Item::Key(key.0),
+                di::DisplayItem::Rectangle(ref rect) => match rect.color {
+                    PropertyBinding::Value(color) => Item::Rect(color.r),
+                    PropertyBinding::Binding(..) => Item::Other,
+                },
+                _ => Item::Other,
+            });
+        }
+        items
+    }
+
+    fn push_rect(builder: &mut DisplayListBuilder, id: f32) {
+        let pipeline_id = PipelineId::dummy();
+        let common = di::CommonItemProperties::new(
+            LayoutRect::new(LayoutPoint::zero(), LayoutSize::new(10.0, 10.0)),
+            di::SpaceAndClipInfo::root_scroll(pipeline_id),
+        );
+        builder.push_rect(&common, ColorF::new(id, 0.0, 0.0, 1.0));
+    }
+
+    /// A list of an unkeyed rect followed by ranges 1 and 2 with one rect
+    /// each.
+    fn build_keyed_list() -> BuiltDisplayList {
+        let mut builder = DisplayListBuilder::new(PipelineId::dummy(), LayoutSize::new(100.0, 100.0));
+        push_rect(&mut builder, 0.0);
+        builder.push_item_key(di::ItemKey(1));
+        push_rect(&mut builder, 0.1);
+        builder.push_item_key(di::ItemKey(2));
+        push_rect(&mut builder, 0.2);
+        builder.finalize().2
+    }
+
+    fn build_patch(list: &BuiltDisplayList, keys: &[u64]) -> BuiltDisplayList {
+        let mut builder = DisplayListBuilder::new_patch(
+            PipelineId::dummy(),
+            LayoutSize::new(100.0, 100.0),
+            list.descriptor(),
+        );
+        for &key in keys {
+            builder.push_item_key(di::ItemKey(key));
+            push_rect(&mut builder, 0.5 + key as f32 / 10.0);
+        }
+        builder.finalize().2
+    }
+
+    #[test]
+    fn test_keyed_ranges() {
+        let list = build_keyed_list();
+        let ranges = list.keyed_ranges();
+        let keys: Vec<_> = ranges.iter().map(|range| range.key).collect();
+        assert_eq!(keys, vec![None, Some(di::ItemKey(1)), Some(di::ItemKey(2))]);
+
+        let len = ranges.iter().map(|range| range.data.len()).sum::<usize>();
+        assert_eq!(len + di::DisplayItem::max_size(), list.data().len());
+    }
+
+    #[test]
+    fn test_apply_patch() {
+        let list = build_keyed_list();
+        let patch = build_patch(&list, &[2, 3]);
+        let ops = [
+            DisplayListPatchOp::Replace(di::ItemKey(2)),
+            DisplayListPatchOp::Insert { after: Some(di::ItemKey(1)), key: di::ItemKey(3) },
+            DisplayListPatchOp::Remove(di::ItemKey(1)),
+        ];
+        let patched = list.apply_patch(&patch, &ops).unwrap();
+
+        assert_eq!(items(&patched.display_list), vec![
+            Item::Rect(0.0),
+            Item::Key(3),
+            Item::Rect(0.8),
+            Item::Key(2),
+            Item::Rect(0.7),
+        ]);
+        assert_eq!(patched.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_apply_patch_insert_at_start() {
+        let list = build_keyed_list();
+        let patch = build_patch(&list, &[3]);
+        let ops = [DisplayListPatchOp::Insert { after: None, key: di::ItemKey(3) }];
+        let patched = list.apply_patch(&patch, &ops).unwrap();
+
+        assert_eq!(items(&patched.display_list), vec![
+            Item::Key(3),
+            Item::Rect(0.8),
+            Item::Rect(0.0),
+            Item::Key(1),
+            Item::Rect(0.1),
+            Item::Key(2),
+            Item::Rect(0.2),
+        ]);
+    }
+
+    #[test]
+    fn test_apply_patch_errors() {
+        let list = build_keyed_list();
+        let patch = build_patch(&list, &[2, 3]);
+
+        let unknown = [
+            DisplayListPatchOp::Replace(di::ItemKey(4)),
+            DisplayListPatchOp::Remove(di::ItemKey(4)),
+            DisplayListPatchOp::Insert { after: Some(di::ItemKey(4)), key: di::ItemKey(3) },
+        ];
+        for op in &unknown {
+            assert_eq!(
+                list.apply_patch(&patch, std::slice::from_ref(op)).err(),
+                Some(DisplayListPatchError::UnknownKey(di::ItemKey(4))),
+            );
+        }
+
+        assert_eq!(
+            list.apply_patch(&patch, &[
+                DisplayListPatchOp::Insert { after: None, key: di::ItemKey(2) },
+            ]).err(),
+            Some(DisplayListPatchError::DuplicateKey(di::ItemKey(2))),
+        );
+        assert_eq!(
+            list.apply_patch(&patch, &[DisplayListPatchOp::Replace(di::ItemKey(1))]).err(),
+            Some(DisplayListPatchError::MissingPatchItems(di::ItemKey(1))),
+        );
+
+        // A removed key can't be used by later ops of the same patch.
+        assert_eq!(
+            list.apply_patch(&patch, &[
+                DisplayListPatchOp::Remove(di::ItemKey(2)),
+                DisplayListPatchOp::Remove(di::ItemKey(2)),
+            ]).err(),
+            Some(DisplayListPatchError::UnknownKey(di::ItemKey(2))),
+        );
+    }
+
+    #[test]
+    fn test_apply_patch_descriptor_counts() {
+        let pipeline_id = PipelineId::dummy();
+        let root = di::SpaceAndClipInfo::root_scroll(pipeline_id);
+        let clip_rect = LayoutRect::new(LayoutPoint::zero(), LayoutSize::new(10.0, 10.0));
+
+        let mut builder = DisplayListBuilder::new(pipeline_id, LayoutSize::new(100.0, 100.0));
+        builder.push_item_key(di::ItemKey(1));
+        builder.define_clip(&root, clip_rect, None, None);
+        let list = builder.finalize().2;
+
+        let mut builder = DisplayListBuilder::new_patch(
+            pipeline_id,
+            LayoutSize::new(100.0, 100.0),
+            list.descriptor(),
+        );
+        builder.push_item_key(di::ItemKey(1));
+        let clip_id = builder.define_clip(&root, clip_rect, None, None);
+        builder.define_clip_chain(None, vec![clip_id]);
+        builder.push_reference_frame(
+            LayoutPoint::zero(),
+            root.spatial_id,
+            di::TransformStyle::Flat,
+            PropertyBinding::Value(LayoutTransform::identity()),
+            di::ReferenceFrameKind::Transform,
+        );
+        builder.pop_reference_frame();
+        let patch = builder.finalize().2;
+
+        let patched = list
+            .apply_patch(&patch, &[DisplayListPatchOp::Replace(di::ItemKey(1))])
+            .unwrap();
+        assert_eq!(patched.display_list.total_clip_nodes(), FIRST_CLIP_NODE_INDEX + 2);
+        assert_eq!(patched.display_list.total_spatial_nodes(), FIRST_SPATIAL_NODE_INDEX + 1);
+        assert_eq!(patched.display_list.descriptor().total_clip_chains, 1);
+        assert_eq!(patched.validate(), Ok(()));
+
+        // Removing items doesn't release their ids, so that a later patch
+        // built against the patched list doesn't reuse them.
+        let removed = patched
+            .display_list
+            .apply_patch(&list, &[DisplayListPatchOp::Remove(di::ItemKey(1))])
+            .unwrap();
+        assert_eq!(removed.display_list.total_clip_nodes(), FIRST_CLIP_NODE_INDEX + 2);
+        assert_eq!(removed.display_list.total_spatial_nodes(), FIRST_SPATIAL_NODE_INDEX + 1);
+        assert_eq!(removed.display_list.descriptor().total_clip_chains, 1);
+        assert_eq!(items(&removed.display_list), vec![]);
+    }
+
+    #[test]
+    fn test_validate_patch() {
+        let pipeline_id = PipelineId::dummy();
+        let root = di::SpaceAndClipInfo::root_scroll(pipeline_id);
+
+        // Range 1 defines a clip that range 2 uses.
+        let mut builder = new_builder();
+        builder.push_item_key(di::ItemKey(1));
+        let clip_id = builder.define_clip(&root, test_rect(), None, None);
+        builder.push_item_key(di::ItemKey(2));
+        let clipped = di::SpaceAndClipInfo { spatial_id: root.spatial_id, clip_id };
+        builder.push_rect(&di::CommonItemProperties::new(test_rect(), clipped), ColorF::WHITE);
+        let list = builder.finalize().2;
+
+        let undefined_clip_id = di::ClipId::Clip(100, pipeline_id);
+        let mut builder = DisplayListBuilder::new_patch(
+            pipeline_id,
+            LayoutSize::new(100.0, 100.0),
+            list.descriptor(),
+        );
+        builder.push_item_key(di::ItemKey(2));
+        builder.push_rect(&di::CommonItemProperties::new(test_rect(), clipped), ColorF::BLACK);
+        builder.push_item_key(di::ItemKey(3));
+        builder.push_rect(
+            &di::CommonItemProperties::new(
+                test_rect(),
+                di::SpaceAndClipInfo { spatial_id: root.spatial_id, clip_id: undefined_clip_id },
+            ),
+            ColorF::BLACK,
+        );
+        let patch = builder.finalize().2;
+
+        // Only the spliced items are checked, and they can use the clip kept
+        // from the previous list.
+        let patched = list
+            .apply_patch(&patch, &[DisplayListPatchOp::Replace(di::ItemKey(2))])
+            .unwrap();
+        assert_eq!(patched.spliced_items().map(|spliced| spliced.len()), Some(1));
+        assert_eq!(patched.retained_item_count(), 1);
+        assert_eq!(patched.validate(), Ok(()));
+
+        // Errors in spliced items are reported at their index in the patched list.
+        let patched = list
+            .apply_patch(&patch, &[
+                DisplayListPatchOp::Insert { after: Some(di::ItemKey(2)), key: di::ItemKey(3) },
+            ])
+            .unwrap();
+        assert_eq!(patched.validate(), Err(vec![
+            DisplayListError::UndefinedClipId { index: 5, id: undefined_clip_id },
+        ]));
+
+        // Removing the range that defines the clip has the whole list checked
+        // again, which finds range 2 still using it.
+        let patched = list
+            .apply_patch(&patch, &[DisplayListPatchOp::Remove(di::ItemKey(1))])
+            .unwrap();
+        assert!(patched.spliced_items().is_none());
+        assert_eq!(patched.validate(), Err(vec![
+            DisplayListError::UndefinedClipId { index: 1, id: clip_id },
+        ]));
+    }
+
+    #[test]
+    fn test_validate_patch_scopes() {
+        let pipeline_id = PipelineId::dummy();
+        let root = di::SpaceAndClipInfo::root_scroll(pipeline_id);
+        let common = di::CommonItemProperties::new(test_rect(), root);
+
+        // Range 2 is nested in a stacking context opened by range 1 and closed
+        // by range 3.
+        let mut builder = new_builder();
+        builder.push_item_key(di::ItemKey(1));
+        builder.push_simple_stacking_context(LayoutPoint::zero(), root.spatial_id, di::PrimitiveFlags::empty());
+        builder.push_item_key(di::ItemKey(2));
+        builder.push_rect(&common, ColorF::WHITE);
+        builder.push_item_key(di::ItemKey(3));
+        builder.pop_stacking_context();
+        let list = builder.finalize().2;
+
+        let mut builder = DisplayListBuilder::new_patch(
+            pipeline_id,
+            LayoutSize::new(100.0, 100.0),
+            list.descriptor(),
+        );
+        builder.push_item_key(di::ItemKey(2));
+        builder.push_simple_stacking_context(LayoutPoint::zero(), root.spatial_id, di::PrimitiveFlags::empty());
+        builder.push_rect(&common, ColorF::BLACK);
+        builder.pop_stacking_context();
+        builder.push_item_key(di::ItemKey(4));
+        builder.push_simple_stacking_context(LayoutPoint::zero(), root.spatial_id, di::PrimitiveFlags::empty());
+        let patch = builder.finalize().2;
+
+        let patched = list
+            .apply_patch(&patch, &[DisplayListPatchOp::Replace(di::ItemKey(2))])
+            .unwrap();
+        let depths: Vec<_> = patched.spliced_items().unwrap().iter().map(|&(_, depth)| depth).collect();
+        assert_eq!(depths, vec![1]);
+        assert_eq!(patched.validate(), Ok(()));
+
+        // A range that leaves a stacking context open has the whole list
+        // checked again.
+        let patched = list
+            .apply_patch(&patch, &[
+                DisplayListPatchOp::Insert { after: Some(di::ItemKey(2)), key: di::ItemKey(4) },
+            ])
+            .unwrap();
+        assert!(patched.spliced_items().is_none());
+        assert!(patched.validate().is_err());
+    }
+
+    fn new_builder() -> DisplayListBuilder {
+        DisplayListBuilder::new(PipelineId::dummy(), LayoutSize::new(100.0, 100.0))
+    }
+
+    fn test_rect() -> LayoutRect {
+        LayoutRect::new(LayoutPoint::zero(), LayoutSize::new(10.0, 10.0))
+    }
+
+    #[test]
+    fn test_validate_well_formed() {
+        let pipeline_id = PipelineId::dummy();
+        let root = di::SpaceAndClipInfo::root_scroll(pipeline_id);
+        let mut builder = new_builder();
+
+        let spatial_id = builder.push_reference_frame(
+            LayoutPoint::zero(),
+            root.spatial_id,
+            di::TransformStyle::Flat,
+            PropertyBinding::Value(LayoutTransform::identity()),
+            di::ReferenceFrameKind::Transform,
+        );
+        builder.push_simple_stacking_context_with_filters(
+            LayoutPoint::zero(),
+            spatial_id,
+            di::PrimitiveFlags::empty(),
+            &[di::FilterOp::Blur(2.0, 2.0), di::FilterOp::Opacity(PropertyBinding::Value(0.5), 0.5)],
+            &[],
+            &[],
+        );
+        let space_and_clip = di::SpaceAndClipInfo { spatial_id, clip_id: root.clip_id };
+        let clip_id = builder.define_clip(&space_and_clip, test_rect(), None, None);
+        let clip_chain_id = builder.define_clip_chain(None, vec![clip_id]);
+        builder.push_rect(
+            &di::CommonItemProperties::new(
+                test_rect(),
+                di::SpaceAndClipInfo { spatial_id, clip_id: di::ClipId::ClipChain(clip_chain_id) },
+            ),
+            ColorF::WHITE,
+        );
+        builder.pop_stacking_context();
+        builder.pop_reference_frame();
+
+        assert_eq!(builder.finalize().2.validate(), Ok(()));
+        assert_eq!(new_builder().finalize().2.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_define_path_clip() {
+        let root = di::SpaceAndClipInfo::root_scroll(PipelineId::dummy());
+        let path_clip = di::PathClip { fill_rule: di::FillRule::EvenOdd, mode: di::ClipMode::Clip };
+        let commands = [
+            di::PathCommand::MoveTo(LayoutPoint::new(0.0, 0.0)),
+            di::PathCommand::LineTo(LayoutPoint::new(10.0, 0.0)),
+            di::PathCommand::LineTo(LayoutPoint::new(0.0, 10.0)),
+            di::PathCommand::Close,
+        ];
+        let mut builder = new_builder();
+        builder.define_clip(&root, test_rect(), None, None);
+        builder.define_path_clip(&root, test_rect(), path_clip, &commands);
+        let list = builder.finalize().2;
+        assert_eq!(list.validate(), Ok(()));
+
+        let mut iter = list.iter();
+        let mut clips = Vec::new();
+        while let Some(item) = iter.next_raw() {
+            if let di::DisplayItem::Clip(ref info) = *item.item() {
+                let commands: Vec<_> = item.path_commands().iter().collect();
+                clips.push((info.path_clip, commands));
+            }
+        }
+        assert_eq!(clips, vec![(None, vec![]), (Some(path_clip), commands.to_vec())]);
+    }
+
+    #[test]
+    fn test_validate_truncated() {
+        let glyphs: Vec<_> = (0 .. 200)
+            .map(|index| GlyphInstance { index, point: LayoutPoint::zero() })
+            .collect();
+        let mut builder = new_builder();
+        builder.push_text(
+            &di::CommonItemProperties::new(test_rect(), di::SpaceAndClipInfo::root_scroll(PipelineId::dummy())),
+            test_rect(),
+            &glyphs,
+            FontInstanceKey::new(IdNamespace(0), 0),
+            ColorF::BLACK,
+            None,
+        );
+        let mut list = builder.finalize().2;
+
+        // Cut the glyphs short, past the red zone of the list.
+        let len = list.data.len() - di::DisplayItem::max_size() - 1000;
+        list.data.truncate(len);
+        ensure_red_zone::<di::DisplayItem>(&mut list.data);
+
+        assert_eq!(list.validate(), Err(vec![DisplayListError::TruncatedAuxData { index: 0 }]));
+    }
+
+    #[test]
+    fn test_validate_unbalanced() {
+        let pipeline_id = PipelineId::dummy();
+        let spatial_id = di::SpatialId::root_scroll_node(pipeline_id);
+
+        let mut builder = new_builder();
+        builder.pop_stacking_context();
+        builder.pop_reference_frame();
+        assert_eq!(builder.finalize().2.validate(), Err(vec![
+            DisplayListError::UnbalancedPopStackingContext { index: 0 },
+            DisplayListError::UnbalancedPopReferenceFrame { index: 1 },
+        ]));
+
+        let mut builder = new_builder();
+        builder.push_simple_stacking_context(LayoutPoint::zero(), spatial_id, di::PrimitiveFlags::empty());
+        assert_eq!(builder.finalize().2.validate(), Err(vec![
+            DisplayListError::UnclosedStackingContext { index: 0 },
+        ]));
+
+        // A reference frame popped by a stacking context pop.
+        let mut builder = new_builder();
+        builder.push_reference_frame(
+            LayoutPoint::zero(),
+            spatial_id,
+            di::TransformStyle::Flat,
+            PropertyBinding::Value(LayoutTransform::identity()),
+            di::ReferenceFrameKind::Transform,
+        );
+        builder.pop_stacking_context();
+        assert_eq!(builder.finalize().2.validate(), Err(vec![
+            DisplayListError::UnbalancedPopStackingContext { index: 1 },
+            DisplayListError::UnclosedReferenceFrame { index: 0 },
+        ]));
+    }
+
+    #[test]
+    fn test_validate_undefined_ids() {
+        let pipeline_id = PipelineId::dummy();
+        let root = di::SpaceAndClipInfo::root_scroll(pipeline_id);
+        let clip_id = di::ClipId::Clip(5, pipeline_id);
+        let spatial_id = di::SpatialId::new(5, pipeline_id);
+
+        let mut builder = new_builder();
+        builder.push_rect(
+            &di::CommonItemProperties::new(test_rect(), di::SpaceAndClipInfo { spatial_id: root.spatial_id, clip_id }),
+            ColorF::WHITE,
+        );
+        builder.push_rect(
+            &di::CommonItemProperties::new(test_rect(), di::SpaceAndClipInfo { spatial_id, clip_id: root.clip_id }),
+            ColorF::WHITE,
+        );
+        builder.define_clip_chain(None, vec![clip_id]);
+        assert_eq!(builder.finalize().2.validate(), Err(vec![
+            DisplayListError::UndefinedClipId { index: 0, id: clip_id },
+            DisplayListError::UndefinedSpatialId { index: 1, id: spatial_id },
+            DisplayListError::UndefinedClipId { index: 2, id: clip_id },
+        ]));
+
+        // Ids must be defined before they are used.
+        let mut builder = new_builder();
+        builder.push_rect(
+            &di::CommonItemProperties::new(
+                test_rect(),
+                di::SpaceAndClipInfo { spatial_id: root.spatial_id, clip_id: di::ClipId::Clip(1, pipeline_id) },
+            ),
+            ColorF::WHITE,
+        );
+        builder.define_clip(&root, test_rect(), None, None);
+        assert_eq!(builder.finalize().2.validate(), Err(vec![
+            DisplayListError::UndefinedClipId { index: 0, id: di::ClipId::Clip(1, pipeline_id) },
+        ]));
+    }
+
+    #[test]
+    fn test_validate_non_finite() {
+        let pipeline_id = PipelineId::dummy();
+        let root = di::SpaceAndClipInfo::root_scroll(pipeline_id);
+        let bad_rect = LayoutRect::new(LayoutPoint::new(std::f32::NAN, 0.0), LayoutSize::new(10.0, 10.0));
+
+        let mut builder = new_builder();
+        builder.push_rect(&di::CommonItemProperties::new(bad_rect, root), ColorF::WHITE);
+        builder.define_clip(&root, test_rect(), None, None);
+        builder.define_clip(&root, LayoutRect::new(LayoutPoint::zero(), LayoutSize::new(std::f32::INFINITY, 1.0)), None, None);
+        assert_eq!(builder.finalize().2.validate(), Err(vec![
+            DisplayListError::NonFiniteRect { index: 0 },
+            DisplayListError::NonFiniteRect { index: 2 },
+        ]));
+
+        let filters = [
+            di::FilterOp::Blur(1.0, 1.0),
+            di::FilterOp::Blur(std::f32::NAN, 1.0),
+            di::FilterOp::Opacity(PropertyBinding::Value(std::f32::INFINITY), 1.0),
+        ];
+        let mut builder = new_builder();
+        builder.push_simple_stacking_context_with_filters(
+            LayoutPoint::zero(),
+            root.spatial_id,
+            di::PrimitiveFlags::empty(),
+            &filters,
+            &[],
+            &[],
+        );
+        builder.pop_stacking_context();
+        assert_eq!(builder.finalize().2.validate(), Err(vec![
+            DisplayListError::NonFiniteFilterValue { index: 0 },
+            DisplayListError::NonFiniteFilterValue { index: 0 },
+        ]));
+    }
+
+    #[test]
+    fn test_validate_bad_enum_tag() {
+        let root = di::SpaceAndClipInfo::root_scroll(PipelineId::dummy());
+        let common = di::CommonItemProperties::new(test_rect(), root);
+
+        // The tag of the item itself.
+        let mut builder = new_builder();
+        push_rect(&mut builder, 0.0);
+        let mut list = builder.finalize().2;
+        list.data[0] = 0xff;
+        assert_eq!(list.validate(), Err(vec![DisplayListError::MalformedItem { index: 0 }]));
+
+        // The tag of the `ClipId` of a rect, which follows the item tag and
+        // the clip rect.
+        let mut builder = new_builder();
+        builder.push_rect(&common, ColorF::WHITE);
+        builder.push_rect(&common, ColorF::WHITE);
+        let mut list = builder.finalize().2;
+        let offset = 1 + LayoutRect::max_size();
+        assert_eq!(list.data[offset], 0);
+        list.data[offset] = 0xff;
+        assert_eq!(list.validate(), Err(vec![DisplayListError::MalformedItem { index: 0 }]));
+
+        // The tag of a `FilterOp`, which follows the item tag, and the byte
+        // size and item count of the array.
+        let mut builder = new_builder();
+        builder.push_filters(&[di::FilterOp::Blur(1.0, 1.0)], &[], &[]);
+        builder.push_rect(&common, ColorF::WHITE);
+        let mut list = builder.finalize().2;
+        let offset = 1 + 2 * mem::size_of::<usize>();
+        assert_eq!(list.data[offset], 1);
+        list.data[offset] = 0xff;
+        assert_eq!(list.validate(), Err(vec![DisplayListError::MalformedItem { index: 0 }]));
+    }
+
+    #[test]
+    fn test_from_data_format_checks() {
+        let (data, descriptor) = build_keyed_list().into_data();
+        assert!(BuiltDisplayList::from_data(data.clone(), descriptor).is_ok());
+
+        let mut bad_version = descriptor;
+        bad_version.format_version += 1;
+        assert_eq!(
+            BuiltDisplayList::from_data(data.clone(), bad_version).err(),
+            Some(DisplayListFormatError::VersionMismatch {
+                expected: DISPLAY_LIST_FORMAT_VERSION,
+                found: DISPLAY_LIST_FORMAT_VERSION + 1,
+            }),
+        );
+
+        let mut bad_layout = descriptor;
+        bad_layout.layout_hash ^= 1;
+        assert_eq!(
+            BuiltDisplayList::from_data(data, bad_layout).err(),
+            Some(DisplayListFormatError::LayoutMismatch {
+                expected: display_list_layout_hash(),
+                found: display_list_layout_hash() ^ 1,
+            }),
+        );
+    }
+}
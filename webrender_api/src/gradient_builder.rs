@@ -11,6 +11,7 @@ use crate::units::*;
 /// Each gradient needs at least two stops.
 pub struct GradientBuilder {
     stops: Vec<di::GradientStop>,
+    color_space: di::GradientColorSpace,
 }
 
 impl GradientBuilder {
@@ -18,12 +19,16 @@ impl GradientBuilder {
     pub fn new() -> Self {
         GradientBuilder {
             stops: Vec::new(),
+            color_space: di::GradientColorSpace::Srgb,
         }
     }
 
     /// Create a gradient builder with a list of stops.
     pub fn with_stops(stops: Vec<di::GradientStop>) -> GradientBuilder {
-        GradientBuilder { stops }
+        GradientBuilder {
+            stops,
+            color_space: di::GradientColorSpace::Srgb,
+        }
     }
 
     /// Push an additional stop for the gradient.
@@ -31,6 +36,11 @@ impl GradientBuilder {
         self.stops.push(stop);
     }
 
+    /// Set the color space the produced gradients interpolate in.
+    pub fn set_color_space(&mut self, color_space: di::GradientColorSpace) {
+        self.color_space = color_space;
+    }
+
     /// Get a reference to the list of stops.
     pub fn stops(&self) -> &[di::GradientStop] {
         self.stops.as_ref()
@@ -55,6 +65,7 @@ impl GradientBuilder {
             start_point: start_point + start_to_end * start_offset,
             end_point: start_point + start_to_end * end_offset,
             extend_mode,
+            color_space: self.color_space,
         }
     }
 
@@ -75,8 +86,8 @@ impl GradientBuilder {
             let last_color = self.stops.last().unwrap().color;
 
             self.stops.clear();
-            self.stops.push(di::GradientStop { offset: 0.0, color: last_color, });
-            self.stops.push(di::GradientStop { offset: 1.0, color: last_color, });
+            self.stops.push(di::GradientStop { offset: 0.0, color: last_color, midpoint: None, });
+            self.stops.push(di::GradientStop { offset: 1.0, color: last_color, midpoint: None, });
 
             return di::RadialGradient {
                 center,
@@ -84,6 +95,7 @@ impl GradientBuilder {
                 start_offset: 0.0,
                 end_offset: 1.0,
                 extend_mode,
+                color_space: self.color_space,
             };
         }
 
@@ -96,6 +108,26 @@ impl GradientBuilder {
             start_offset,
             end_offset,
             extend_mode,
+            color_space: self.color_space,
+        }
+    }
+
+    /// Produce a conic gradient, normalize the stops.
+    pub fn conic_gradient(
+        &mut self,
+        center: LayoutPoint,
+        angle: f32,
+        extend_mode: di::ExtendMode,
+    ) -> di::ConicGradient {
+        let (start_offset, end_offset) =
+            self.normalize(extend_mode);
+
+        di::ConicGradient {
+            center,
+            angle,
+            start_offset,
+            end_offset,
+            extend_mode,
         }
     }
 
@@ -106,6 +138,8 @@ impl GradientBuilder {
     /// of the gradient line to where stop[0] and the end of the gradient line
     /// to stop[n-1]. this function adjusts the stops in place, and returns
     /// the amount to adjust the gradient line start and stop.
+    /// Midpoints are relative to their surrounding stops, so they are left
+    /// untouched.
     fn normalize(&mut self, extend_mode: di::ExtendMode) -> (f32, f32) {
         let stops = &mut self.stops;
         assert!(stops.len() >= 2);
@@ -134,10 +168,10 @@ impl GradientBuilder {
                     // This gradient is two colors split at the offset of the stops,
                     // so create a gradient with two colors split at 0.5 and adjust
                     // the gradient line so 0.5 is at the offset of the stops
-                    stops.push(di::GradientStop { color: first.color, offset: 0.0, });
-                    stops.push(di::GradientStop { color: first.color, offset: 0.5, });
-                    stops.push(di::GradientStop { color: last.color, offset: 0.5, });
-                    stops.push(di::GradientStop { color: last.color, offset: 1.0, });
+                    stops.push(di::GradientStop { color: first.color, offset: 0.0, midpoint: None, });
+                    stops.push(di::GradientStop { color: first.color, offset: 0.5, midpoint: None, });
+                    stops.push(di::GradientStop { color: last.color, offset: 0.5, midpoint: None, });
+                    stops.push(di::GradientStop { color: last.color, offset: 1.0, midpoint: None, });
 
                     let offset = last.offset;
 
@@ -148,8 +182,8 @@ impl GradientBuilder {
                     // position should just display the last color. I believe the
                     // spec says that it should be the average color of the gradient,
                     // but this matches what Gecko and Blink does
-                    stops.push(di::GradientStop { color: last.color, offset: 0.0, });
-                    stops.push(di::GradientStop { color: last.color, offset: 1.0, });
+                    stops.push(di::GradientStop { color: last.color, offset: 0.0, midpoint: None, });
+                    stops.push(di::GradientStop { color: last.color, offset: 1.0, midpoint: None, });
 
                     (0.0, 1.0)
                 }
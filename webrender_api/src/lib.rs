@@ -30,6 +30,8 @@ pub extern crate euclid;
 #[cfg(feature = "ipc")]
 extern crate ipc_channel;
 #[macro_use]
+extern crate lazy_static;
+#[macro_use]
 extern crate malloc_size_of_derive;
 extern crate serde;
 #[macro_use]
@@ -99,7 +99,7 @@ impl JsonFrameWriter {
         let payload = Payload::from_data(data);
         let dl_desc = self.dl_descriptor.take().unwrap();
 
-        let dl = BuiltDisplayList::from_data(payload.display_list_data, dl_desc);
+        let dl = BuiltDisplayList::from_data(payload.display_list_data, dl_desc).unwrap();
 
         let mut frame_file_name = self.frame_base.clone();
         let current_shown_frame = unsafe { CURRENT_FRAME_NUMBER };
@@ -162,6 +162,7 @@ impl JsonFrameWriter {
                 ResourceUpdate::DeleteImage(img) => {
                     self.images.remove(&img);
                 }
+                ResourceUpdate::AddSnapshotImage(_) => {}
                 ResourceUpdate::AddFont(ref font) => match font {
                     &AddFont::Raw(key, ref bytes, index) => {
                         self.fonts
@@ -718,6 +718,7 @@ fn main() {
         dump_shader_source,
         notifier,
         init,
+        args.is_present("sw_compositor"),
     );
 
     if let Some(window_title) = wrench.take_title() {
@@ -2,7 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use euclid::{point2, size2, rect};
+use euclid::{point2, size2, rect, Scale};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicIsize, Ordering};
 use std::sync::mpsc::Receiver;
@@ -47,6 +47,7 @@ impl<'a> RawtestHarness<'a> {
         self.test_capture();
         self.test_zero_height_window();
         self.test_clear_cache();
+        self.test_damage_rect();
     }
 
     fn render_and_get_pixels(&mut self, window_rect: FramebufferIntRect) -> Vec<u8> {
@@ -1402,6 +1403,35 @@ impl<'a> RawtestHarness<'a> {
             ColorF::new(1.0, 1.0, 1.0, 1.0),
         );
 
+        // Add a rectangle that is clipped to its top left half by a path clip.
+        let rect = LayoutRect::new(LayoutPoint::new(0., 200.), LayoutSize::new(100., 100.));
+        let triangle = [
+            PathCommand::MoveTo(LayoutPoint::new(0., 200.)),
+            PathCommand::LineTo(LayoutPoint::new(100., 200.)),
+            PathCommand::LineTo(LayoutPoint::new(0., 300.)),
+            PathCommand::Close,
+        ];
+        let path_clip = PathClip {
+            fill_rule: FillRule::NonZero,
+            mode: ClipMode::Clip,
+        };
+        let clip_id = builder.define_path_clip(
+            &space_and_clip,
+            rect,
+            path_clip,
+            &triangle,
+        );
+        builder.push_rect(
+            &CommonItemProperties {
+                hit_info: Some((0, 6)),
+                clip_rect: rect,
+                clip_id,
+                spatial_id: space_and_clip.spatial_id,
+                flags: PrimitiveFlags::default(),
+            },
+            ColorF::new(1.0, 1.0, 1.0, 1.0),
+        );
+
         let mut epoch = Epoch(0);
         let txn = Transaction::new();
         self.submit_dl(&mut epoch, layout_size, builder, &txn.resource_updates);
@@ -1458,6 +1488,10 @@ impl<'a> RawtestHarness<'a> {
 
         test_rounded_rectangle(WorldPoint::new(100., 100.), WorldSize::new(100., 100.), (0, 4));
         test_rounded_rectangle(WorldPoint::new(200., 100.), WorldSize::new(100., 100.), (0, 5));
+
+        // Only the part of the rectangle inside the path clip should be hit.
+        assert_hit_test(WorldPoint::new(20., 220.), vec![(0, 6), (0, 1)]);
+        assert_hit_test(WorldPoint::new(80., 280.), vec![(0, 1)]);
     }
 
     fn test_clear_cache(&mut self) {
@@ -1475,4 +1509,36 @@ impl<'a> RawtestHarness<'a> {
         self.rx.recv().unwrap();
         self.wrench.render();
     }
+
+    fn test_damage_rect(&mut self) {
+        println!("\tdamage rect...");
+
+        let layout_size = LayoutSize::new(400., 400.);
+        let changing_rect = rect(200., 200., 50., 50.);
+        let dp_ratio = self.wrench.device_pixel_ratio;
+
+        let mut do_test = |color| {
+            let mut builder = DisplayListBuilder::new(self.wrench.root_pipeline_id, layout_size);
+            let info = self.make_common_properties(rect(10., 10., 50., 50.));
+            builder.push_rect(&info, ColorF::new(1.0, 0.0, 0.0, 1.0));
+            let info = self.make_common_properties(changing_rect);
+            builder.push_rect(&info, color);
+
+            self.submit_dl(&mut Epoch(0), layout_size, builder, &[]);
+            self.rx.recv().unwrap();
+            self.wrench.render().damage_rect.expect("damage rect should be known")
+        };
+
+        let blue = ColorF::new(0.0, 0.0, 1.0, 1.0);
+        do_test(blue);
+
+        // Nothing changed, so nothing is damaged.
+        let damage_rect = do_test(blue);
+        assert!(damage_rect.is_empty());
+
+        // Only the tiles covering the rect that changed are damaged.
+        let damage_rect = do_test(ColorF::new(0.0, 1.0, 0.0, 1.0));
+        let changed_rect: DeviceIntRect = (changing_rect * Scale::new(dp_ratio)).round().to_i32();
+        assert!(damage_rect.contains_rect(&changed_rect));
+    }
 }
@@ -8,12 +8,13 @@ use webrender::api::{PropertyBinding, PropertyBindingId, DynamicProperties};
 use webrender::api::units::{LayoutSize, LayoutTransform};
 
 /// Stores a map of the animated property bindings for the current display list. These
-/// can be used to animate the transform and/or opacity of a display list without
+/// can be used to animate the transform, opacity and/or color of a display list without
 /// re-submitting the display list itself.
 #[derive(Default)]
 pub struct SceneProperties {
     transform_properties: HashMap<PropertyBindingId, LayoutTransform>,
     float_properties: HashMap<PropertyBindingId, f32>,
+    color_properties: HashMap<PropertyBindingId, ColorF>,
 }
 
 impl SceneProperties {
@@ -21,6 +22,7 @@ impl SceneProperties {
     pub fn set_properties(&mut self, properties: &DynamicProperties) {
         self.transform_properties.clear();
         self.float_properties.clear();
+        self.color_properties.clear();
 
         for property in &properties.transforms {
             self.transform_properties
@@ -31,6 +33,11 @@ impl SceneProperties {
             self.float_properties
                 .insert(property.key.id, property.value);
         }
+
+        for property in &properties.colors {
+            self.color_properties
+                .insert(property.key.id, property.value);
+        }
     }
 
     /// Get the current value for a transform property.
@@ -57,6 +64,17 @@ impl SceneProperties {
                 .unwrap_or(v),
         }
     }
+
+    /// Get the current value for a color property.
+    pub fn resolve_color(&self, property: &PropertyBinding<ColorF>) -> ColorF {
+        match *property {
+            PropertyBinding::Value(value) => value,
+            PropertyBinding::Binding(ref key, v) => self.color_properties
+                .get(&key.id)
+                .cloned()
+                .unwrap_or(v),
+        }
+    }
 }
 
 /// A representation of the layout within the display port for a given document or iframe.
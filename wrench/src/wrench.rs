@@ -184,6 +184,7 @@ impl Wrench {
         dump_shader_source: Option<String>,
         notifier: Option<Box<dyn RenderNotifier>>,
         init: webrender::DeviceInit<back::Backend>,
+        sw_compositor: bool,
     ) -> Self {
         println!("Shader override path: {:?}", shader_override_path);
 
@@ -221,6 +222,18 @@ impl Wrench {
             ron::de::from_str(&source).expect("Unable to parse HeapsConfig")
         };
 
+        // The native compositor interface hands out GL framebuffers, so
+        // there is nothing to plug it into on the gfx backends.
+        #[cfg(feature = "gl")]
+        let compositor = if sw_compositor {
+            let compositor = webrender::SwCompositor::new(init.gl.clone(), ColorF::WHITE);
+            Some(Box::new(compositor) as Box<dyn webrender::Compositor>)
+        } else {
+            None
+        };
+        #[cfg(feature = "gfx")]
+        assert!(!sw_compositor, "--sw-compositor requires the GL backend");
+
         let opts = webrender::RendererOptions {
             device_pixel_ratio: dp_ratio,
             resource_override_path: shader_override_path,
@@ -238,6 +251,8 @@ impl Wrench {
             allow_dual_source_blending: !disable_dual_source_blending,
             allow_advanced_blend_equation: false, //true,
             dump_shader_source,
+            #[cfg(feature = "gl")]
+            compositor,
             #[cfg(feature = "gfx")]
             heaps_config,
             #[cfg(feature = "gfx")]
@@ -181,6 +181,17 @@ fn broadcast<T: Clone>(base_vals: &[T], num_items: usize) -> Vec<T> {
     vals
 }
 
+// The optional "midpoints" of a gradient hold the color hint between each
+// pair of consecutive stops, relative to the two stops.
+fn set_gradient_midpoints(stops: &mut [GradientStop], item: &Yaml) {
+    if let Some(midpoints) = item["midpoints"].as_vec_f32() {
+        assert_eq!(midpoints.len() + 1, stops.len(), "gradient needs a midpoint per pair of stops");
+        for (stop, midpoint) in stops[1 ..].iter_mut().zip(midpoints) {
+            stop.midpoint = Some(midpoint);
+        }
+    }
+}
+
 enum CheckerboardKind {
     BlackGrey,
     BlackTransparent,
@@ -325,6 +336,9 @@ pub struct YamlFrameReader {
 
     image_map: HashMap<(PathBuf, Option<i64>), (ImageKey, LayoutSize)>,
 
+    /// Snapshot images, by the name they are given in YAML.
+    snapshot_map: HashMap<String, ImageKey>,
+
     fonts: HashMap<FontDescriptor, FontKey>,
     font_instances: HashMap<(FontKey, Au, FontInstanceFlags, Option<ColorU>, SyntheticItalics), FontInstanceKey>,
     font_render_mode: Option<FontRenderMode>,
@@ -364,6 +378,7 @@ impl YamlFrameReader {
             font_render_mode: None,
             allow_mipmaps: false,
             image_map: HashMap::new(),
+            snapshot_map: HashMap::new(),
             user_clip_id_map: HashMap::new(),
             user_spatial_id_map: HashMap::new(),
             clip_id_stack: Vec::new(),
@@ -773,6 +788,20 @@ impl YamlFrameReader {
         val
     }
 
+    fn get_or_create_snapshot(&mut self, name: &str, wrench: &mut Wrench) -> ImageKey {
+        if let Some(key) = self.snapshot_map.get(name) {
+            return *key;
+        }
+
+        let image_key = wrench.api.generate_image_key();
+        let mut txn = Transaction::new();
+        txn.add_snapshot_image(image_key);
+        wrench.api.update_resources(txn.resource_updates);
+
+        self.snapshot_map.insert(name.to_owned(), image_key);
+        image_key
+    }
+
     fn get_or_create_font(&mut self, desc: FontDescriptor, wrench: &mut Wrench) -> FontKey {
         let list_resources = self.list_resources;
         *self.fonts
@@ -859,17 +888,38 @@ impl YamlFrameReader {
             .as_rect()
             .unwrap_or(LayoutRect::new(LayoutPoint::zero(), image_dims));
         let image_repeat = item["repeat"].as_bool().expect("Expected boolean");
+        let mode = item["mask-mode"].as_mask_mode().unwrap_or(MaskMode::Alpha);
+        let composite_op = item["mask-composite"]
+            .as_mask_composite_operator()
+            .unwrap_or(MaskCompositeOperator::Intersect);
         Some(ImageMask {
             image: image_key,
             rect: image_rect,
             repeat: image_repeat,
+            mode,
+            composite_op,
         })
     }
 
+    fn to_path_clip(&self, item: &Yaml) -> Option<(PathClip, Vec<PathCommand>)> {
+        if item.as_hash().is_none() {
+            return None;
+        }
+
+        let commands = item["commands"]
+            .as_path_commands()
+            .expect("path clip must have commands");
+        let path_clip = PathClip {
+            fill_rule: item["fill-rule"].as_fill_rule().unwrap_or(FillRule::NonZero),
+            mode: item["clip-mode"].as_clip_mode().unwrap_or(ClipMode::Clip),
+        };
+        Some((path_clip, commands))
+    }
+
     fn to_gradient(&mut self, dl: &mut DisplayListBuilder, item: &Yaml) -> Gradient {
         let start = item["start"].as_point().expect("gradient must have start");
         let end = item["end"].as_point().expect("gradient must have end");
-        let stops = item["stops"]
+        let mut stops = item["stops"]
             .as_vec()
             .expect("gradient must have stops")
             .chunks(2)
@@ -881,22 +931,32 @@ impl YamlFrameReader {
                     color: chunk[1]
                         .as_colorf()
                         .expect("gradient stop color is not color"),
+                    midpoint: None,
                 }
             })
             .collect::<Vec<_>>();
+        set_gradient_midpoints(&mut stops, item);
         let extend_mode = if item["repeat"].as_bool().unwrap_or(false) {
             ExtendMode::Repeat
         } else {
             ExtendMode::Clamp
         };
 
-        dl.create_gradient(start, end, stops, extend_mode)
+        let color_space = item["color-space"]
+            .as_gradient_color_space()
+            .unwrap_or(GradientColorSpace::Srgb);
+
+        let mut builder = GradientBuilder::with_stops(stops);
+        builder.set_color_space(color_space);
+        let gradient = builder.gradient(start, end, extend_mode);
+        dl.push_stops(builder.stops());
+        gradient
     }
 
     fn to_radial_gradient(&mut self, dl: &mut DisplayListBuilder, item: &Yaml) -> RadialGradient {
         let center = item["center"].as_point().expect("radial gradient must have center");
         let radius = item["radius"].as_size().expect("radial gradient must have a radius");
-        let stops = item["stops"]
+        let mut stops = item["stops"]
             .as_vec()
             .expect("radial gradient must have stops")
             .chunks(2)
@@ -908,16 +968,55 @@ impl YamlFrameReader {
                     color: chunk[1]
                         .as_colorf()
                         .expect("gradient stop color is not color"),
+                    midpoint: None,
+                }
+            })
+            .collect::<Vec<_>>();
+        set_gradient_midpoints(&mut stops, item);
+        let extend_mode = if item["repeat"].as_bool().unwrap_or(false) {
+            ExtendMode::Repeat
+        } else {
+            ExtendMode::Clamp
+        };
+
+        let color_space = item["color-space"]
+            .as_gradient_color_space()
+            .unwrap_or(GradientColorSpace::Srgb);
+
+        let mut builder = GradientBuilder::with_stops(stops);
+        builder.set_color_space(color_space);
+        let gradient = builder.radial_gradient(center, radius, extend_mode);
+        dl.push_stops(builder.stops());
+        gradient
+    }
+
+    fn to_conic_gradient(&mut self, dl: &mut DisplayListBuilder, item: &Yaml) -> ConicGradient {
+        let center = item["center"].as_point().expect("conic gradient must have center");
+        let angle = item["angle"].as_force_f32().unwrap_or(0.0).to_radians();
+        let mut stops = item["stops"]
+            .as_vec()
+            .expect("conic gradient must have stops")
+            .chunks(2)
+            .map(|chunk| {
+                GradientStop {
+                    offset: chunk[0]
+                        .as_force_f32()
+                        .expect("gradient stop offset is not f32"),
+                    color: chunk[1]
+                        .as_colorf()
+                        .expect("gradient stop color is not color"),
+                    midpoint: None,
                 }
             })
             .collect::<Vec<_>>();
+        set_gradient_midpoints(&mut stops, item);
         let extend_mode = if item["repeat"].as_bool().unwrap_or(false) {
             ExtendMode::Repeat
         } else {
             ExtendMode::Clamp
         };
 
-        dl.create_radial_gradient(center, radius, stops, extend_mode)
+        dl.create_conic_gradient(center, angle, stops, extend_mode)
     }
 
     fn handle_rect(
@@ -1085,6 +1184,65 @@ impl YamlFrameReader {
         );
     }
 
+    fn handle_conic_gradient(
+        &mut self,
+        dl: &mut DisplayListBuilder,
+        item: &Yaml,
+        info: &mut CommonItemProperties,
+    ) {
+        let bounds_key = if item["type"].is_badvalue() {
+            "conic-gradient"
+        } else {
+            "bounds"
+        };
+        let bounds = item[bounds_key]
+            .as_rect()
+            .expect("conic gradient must have bounds");
+        let gradient = self.to_conic_gradient(dl, item);
+        let tile_size = item["tile-size"].as_size().unwrap_or(bounds.size);
+        let tile_spacing = item["tile-spacing"].as_size().unwrap_or(LayoutSize::zero());
+
+        dl.push_conic_gradient(
+            &info,
+            bounds,
+            gradient,
+            tile_size,
+            tile_spacing,
+        );
+    }
+
+    fn handle_path(
+        &mut self,
+        dl: &mut DisplayListBuilder,
+        item: &Yaml,
+        info: &mut CommonItemProperties,
+    ) {
+        let bounds = item["bounds"]
+            .as_rect()
+            .expect("path must have bounds");
+        let commands = item["commands"]
+            .as_path_commands()
+            .expect("path must have commands");
+        let fill_rule = item["fill-rule"]
+            .as_fill_rule()
+            .unwrap_or(FillRule::NonZero);
+
+        // A path is filled with a gradient if it has stops.
+        let paint = if item["stops"].is_badvalue() {
+            PathPaint::Color(item["color"].as_colorf().unwrap_or(ColorF::BLACK))
+        } else {
+            PathPaint::Gradient(self.to_gradient(dl, item))
+        };
+
+        dl.push_path(
+            &info,
+            bounds,
+            fill_rule,
+            paint,
+            &commands,
+        );
+    }
+
     fn handle_border(
         &mut self,
         dl: &mut DisplayListBuilder,
@@ -1361,15 +1519,20 @@ impl YamlFrameReader {
         item: &Yaml,
         info: &mut CommonItemProperties,
     ) {
-        let filename = &item[if item["type"].is_badvalue() {
-                                 "image"
-                             } else {
-                                 "src"
-                             }];
-        let tiling = item["tile-size"].as_i64();
-        let file = rsrc_path(filename, &self.aux_dir);
-        let (image_key, image_dims) =
-            self.add_or_get_image(&file, tiling, item, wrench);
+        // Snapshots have no intrinsic size, so their bounds need a size.
+        let (image_key, image_dims) = match item["snapshot"].as_str() {
+            Some(name) => (self.get_or_create_snapshot(name, wrench), LayoutSize::zero()),
+            None => {
+                let filename = &item[if item["type"].is_badvalue() {
+                                         "image"
+                                     } else {
+                                         "src"
+                                     }];
+                let tiling = item["tile-size"].as_i64();
+                let file = rsrc_path(filename, &self.aux_dir);
+                self.add_or_get_image(&file, tiling, item, wrench)
+            }
+        };
 
         let bounds_raws = item["bounds"].as_vec_f32().unwrap();
         let bounds = if bounds_raws.len() == 2 {
@@ -1434,8 +1597,36 @@ impl YamlFrameReader {
         item: &Yaml,
         info: &mut CommonItemProperties,
     ) {
-        let size = item["size"].as_pt_to_au().unwrap_or(Au::from_f32_px(16.0));
         let color = item["color"].as_colorf().unwrap_or(ColorF::BLACK);
+
+        assert!(
+            item["blur-radius"].is_badvalue(),
+            "text no longer has a blur radius, use PushShadow and PopAllShadows"
+        );
+
+        let (font_instance_key, glyphs, rect) = self.to_text_run(item, wrench);
+        // TODO(gw): We could optionally use the WR API to query glyph dimensions
+        //           here and calculate the bounding region here if we want to.
+        let rect = rect.expect("Text items with glyphs require bounds [for now]");
+
+        dl.push_text(
+            &info,
+            rect,
+            &glyphs,
+            font_instance_key,
+            color,
+            None,
+        );
+    }
+
+    /// Reads the font and glyphs of a text item or text clip, along with
+    /// the bounds of the text if they are known.
+    fn to_text_run(
+        &mut self,
+        item: &Yaml,
+        wrench: &mut Wrench,
+    ) -> (FontInstanceKey, Vec<GlyphInstance>, Option<LayoutRect>) {
+        let size = item["size"].as_pt_to_au().unwrap_or(Au::from_f32_px(16.0));
         let bg_color = item["bg-color"].as_colorf().map(|c| c.into());
         let synthetic_italics = if let Some(angle) = item["synthetic-italics"].as_f32() {
             SyntheticItalics::from_degrees(angle)
@@ -1462,11 +1653,6 @@ impl YamlFrameReader {
             flags |= FontInstanceFlags::FLIP_Y;
         }
 
-        assert!(
-            item["blur-radius"].is_badvalue(),
-            "text no longer has a blur radius, use PushShadow and PopAllShadows"
-        );
-
         let desc = FontDescriptor::from_yaml(item, &self.aux_dir);
         let font_key = self.get_or_create_font(desc, wrench);
         let font_instance_key = self.get_or_create_font_instance(font_key,
@@ -1505,12 +1691,7 @@ impl YamlFrameReader {
                     }
                 })
                 .collect::<Vec<_>>();
-            // TODO(gw): We could optionally use the WR API to query glyph dimensions
-            //           here and calculate the bounding region here if we want to.
-            let rect = item["bounds"]
-                .as_rect()
-                .expect("Text items with glyphs require bounds [for now]");
-            (glyphs, rect)
+            (glyphs, item["bounds"].as_rect())
         } else {
             let text = item["text"].as_str().unwrap();
             let origin = item["origin"]
@@ -1536,17 +1717,10 @@ impl YamlFrameReader {
                     gi
                 })
                 .collect::<Vec<_>>();
-            (glyphs, bounds)
+            (glyphs, Some(bounds))
         };
 
-        dl.push_text(
-            &info,
-            rect,
-            &glyphs,
-            font_instance_key,
-            color,
-            None,
-        );
+        (font_instance_key, glyphs, rect)
     }
 
     fn handle_iframe(
@@ -1686,6 +1860,8 @@ impl YamlFrameReader {
                 "border" => self.handle_border(dl, wrench, item, &mut info),
                 "gradient" => self.handle_gradient(dl, item, &mut info),
                 "radial-gradient" => self.handle_radial_gradient(dl, item, &mut info),
+                "conic-gradient" => self.handle_conic_gradient(dl, item, &mut info),
+                "path" => self.handle_path(dl, item, &mut info),
                 "box-shadow" => self.handle_box_shadow(dl, item, &mut info),
                 "iframe" => self.handle_iframe(dl, item, &mut info),
                 "stacking-context" => {
@@ -1694,7 +1870,7 @@ impl YamlFrameReader {
                 "reference-frame" => self.handle_reference_frame(dl, wrench, item),
                 "shadow" => self.handle_push_shadow(dl, item, &mut info),
                 "pop-all-shadows" => self.handle_pop_all_shadows(dl),
-                "backdrop-filter" => self.handle_backdrop_filter(dl, item, &mut info),
+                "backdrop-filter" => self.handle_backdrop_filter(dl, wrench, item, &mut info),
                 _ => println!("Skipping unknown item type: {:?}", item),
             }
 
@@ -1722,6 +1898,9 @@ impl YamlFrameReader {
         let content_size = yaml["content-size"].as_size().unwrap_or(clip_rect.size);
         let content_rect = LayoutRect::new(clip_rect.origin, content_size);
         let external_scroll_offset = yaml["external-scroll-offset"].as_vector().unwrap_or(LayoutVector2D::zero());
+        let overscroll_behavior = yaml["overscroll-behavior"]
+            .as_overscroll_behavior()
+            .unwrap_or(OverscrollBehavior::Auto);
 
         let numeric_id = yaml["id"].as_i64().map(|id| id as u64);
 
@@ -1742,6 +1921,7 @@ impl YamlFrameReader {
             complex_clips,
             image_mask,
             ScrollSensitivity::ScriptAndInputEvents,
+            overscroll_behavior,
             external_scroll_offset,
         );
         if let Some(numeric_id) = numeric_id {
@@ -1889,14 +2069,40 @@ impl YamlFrameReader {
         let numeric_id = yaml["id"].as_i64();
         let complex_clips = self.to_complex_clip_regions(&yaml["complex"]);
         let image_mask = self.to_image_mask(&yaml["image-mask"], wrench);
+        let path_clip = self.to_path_clip(&yaml["path-clip"]);
 
         let space_and_clip = self.top_space_and_clip();
-        let real_id = dl.define_clip(
-            &space_and_clip,
-            clip_rect,
-            complex_clips,
-            image_mask,
-        );
+        let real_id = if yaml["text-clip"].as_hash().is_some() {
+            assert!(
+                complex_clips.is_empty() && image_mask.is_none() && path_clip.is_none(),
+                "text clips can't be combined with other clips in the same node"
+            );
+            let (font_instance_key, glyphs, _) = self.to_text_run(&yaml["text-clip"], wrench);
+            dl.define_text_clip(
+                &space_and_clip,
+                clip_rect,
+                font_instance_key,
+                &glyphs,
+            )
+        } else if let Some((path_clip, commands)) = path_clip {
+            assert!(
+                complex_clips.is_empty() && image_mask.is_none(),
+                "path clips can't be combined with other clips in the same node"
+            );
+            dl.define_path_clip(
+                &space_and_clip,
+                clip_rect,
+                path_clip,
+                &commands,
+            )
+        } else {
+            dl.define_clip(
+                &space_and_clip,
+                clip_rect,
+                complex_clips,
+                image_mask,
+            )
+        };
         if let Some(numeric_id) = numeric_id {
             self.add_clip_id_mapping(numeric_id as u64, real_id);
             self.add_spatial_id_mapping(numeric_id as u64, space_and_clip.spatial_id);
@@ -2031,6 +2237,7 @@ impl YamlFrameReader {
             .unwrap_or(RasterSpace::Screen);
         let cache_tiles = yaml["cache"].as_bool().unwrap_or(false);
         let is_backdrop_root = yaml["backdrop-root"].as_bool().unwrap_or(false);
+        let snapshot = yaml["snapshot"].as_str().map(|name| self.get_or_create_snapshot(name, wrench));
 
         if is_root {
             if let Some(size) = yaml["scroll-offset"].as_point() {
@@ -2039,9 +2246,9 @@ impl YamlFrameReader {
             }
         }
 
-        let filters = yaml["filters"].as_vec_filter_op().unwrap_or(vec![]);
+        let filters = self.to_filter_ops(yaml, wrench);
         let filter_datas = yaml["filter-datas"].as_vec_filter_data().unwrap_or(vec![]);
-        let filter_primitives = yaml["filter-primitives"].as_vec_filter_primitive().unwrap_or(vec![]);
+        let filter_primitives = self.to_filter_primitives(&yaml["filter-primitives"], wrench);
 
         dl.push_stacking_context(
             bounds.origin,
@@ -2056,6 +2263,7 @@ impl YamlFrameReader {
             raster_space,
             cache_tiles,
             is_backdrop_root,
+            snapshot,
         );
 
         if !yaml["items"].is_badvalue() {
@@ -2070,9 +2278,44 @@ impl YamlFrameReader {
         }
     }
 
+    fn to_filter_ops(&mut self, item: &Yaml, wrench: &mut Wrench) -> Vec<FilterOp> {
+        let mut filters = item["filters"].as_vec_filter_op().unwrap_or(vec![]);
+
+        // Lookup tables are loaded from files, in the order of the filters using them.
+        let color_luts = item["color-luts"].as_vec().cloned().unwrap_or(vec![]);
+        let lut_filters = filters.iter_mut().filter_map(|filter| match *filter {
+            FilterOp::ColorLut3D { ref mut lut, .. } => Some(lut),
+            _ => None,
+        });
+        for (lut, lut_item) in lut_filters.zip(color_luts.iter()) {
+            let file = rsrc_path(lut_item, &self.aux_dir);
+            let (image_key, _) = self.add_or_get_image(&file, None, item, wrench);
+            *lut = image_key;
+        }
+
+        filters
+    }
+
+    fn to_filter_primitives(&mut self, item: &Yaml, wrench: &mut Wrench) -> Vec<FilterPrimitive> {
+        let mut filter_primitives = item.as_vec_filter_primitive().unwrap_or(vec![]);
+
+        // Image primitives are loaded from files, like image items.
+        let primitive_items = item.as_vec().map_or(vec![item], |items| items.iter().collect());
+        for (primitive, primitive_item) in filter_primitives.iter_mut().zip(primitive_items) {
+            if let FilterPrimitiveKind::Image(ref mut image) = primitive.kind {
+                let file = rsrc_path(&primitive_item["src"], &self.aux_dir);
+                let (image_key, _) = self.add_or_get_image(&file, None, primitive_item, wrench);
+                image.image_key = image_key;
+            }
+        }
+
+        filter_primitives
+    }
+
     fn handle_backdrop_filter(
         &mut self,
         dl: &mut DisplayListBuilder,
+        wrench: &mut Wrench,
         item: &Yaml,
         info: &mut CommonItemProperties,
     ) {
@@ -2081,9 +2324,9 @@ impl YamlFrameReader {
             &info.clip_rect
         );
 
-        let filters = item["filters"].as_vec_filter_op().unwrap_or(vec![]);
+        let filters = self.to_filter_ops(item, wrench);
         let filter_datas = item["filter-datas"].as_vec_filter_data().unwrap_or(vec![]);
-        let filter_primitives = item["filter-primitives"].as_vec_filter_primitive().unwrap_or(vec![]);
+        let filter_primitives = self.to_filter_primitives(&item["filter-primitives"], wrench);
 
         dl.push_backdrop_filter(
             &info,
@@ -8,7 +8,7 @@ use euclid::{Point2D, Rect, Size2D, Transform3D, Vector2D};
 use image::{save_buffer, ColorType};
 use crate::premultiply::unpremultiply;
 use crate::scene::{Scene, SceneProperties};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::{fmt, fs};
@@ -47,9 +47,12 @@ fn str_node(parent: &mut Table, key: &str, value: &str) {
     yaml_node(parent, key, Yaml::String(value.to_owned()));
 }
 
+fn path_to_string(value: &Path) -> String {
+    value.to_str().unwrap().to_owned().replace("\\", "/")
+}
+
 fn path_node(parent: &mut Table, key: &str, value: &Path) {
-    let pstr = value.to_str().unwrap().to_owned().replace("\\", "/");
-    yaml_node(parent, key, Yaml::String(pstr));
+    yaml_node(parent, key, Yaml::String(path_to_string(value)));
 }
 
 fn enum_node<E: StringEnum>(parent: &mut Table, key: &str, value: E) {
@@ -266,12 +269,15 @@ fn write_filters(
     name: &str,
     filter_iter: impl IntoIterator<Item = FilterOp>,
     properties: &SceneProperties,
+    mut path_for_image: impl FnMut(ImageKey) -> Option<PathBuf>,
 ) {
     let mut filters = vec![];
+    let mut color_luts = vec![];
     for filter in filter_iter {
         match filter {
             FilterOp::Identity => { filters.push(Yaml::String("identity".into())) }
-            FilterOp::Blur(x) => { filters.push(Yaml::String(format!("blur({})", x))) }
+            FilterOp::Blur(x, y) if x == y => { filters.push(Yaml::String(format!("blur({})", x))) }
+            FilterOp::Blur(x, y) => { filters.push(Yaml::String(format!("blur({}, {})", x, y))) }
             FilterOp::Brightness(x) => { filters.push(Yaml::String(format!("brightness({})", x))) }
             FilterOp::Contrast(x) => { filters.push(Yaml::String(format!("contrast({})", x))) }
             FilterOp::Grayscale(x) => { filters.push(Yaml::String(format!("grayscale({})", x))) }
@@ -304,10 +310,19 @@ fn write_filters(
             FilterOp::Flood(color) => {
                 filters.push(Yaml::String(format!("flood({})", color_to_string(color))))
             }
+            FilterOp::ColorLut3D { lut, size } => {
+                filters.push(Yaml::String(format!("color-lut-3d({})", size)));
+                if let Some(path) = path_for_image(lut) {
+                    color_luts.push(Yaml::String(path_to_string(&path)));
+                }
+            }
         }
     }
 
     yaml_node(parent, name, Yaml::Array(filters));
+    if !color_luts.is_empty() {
+        yaml_node(parent, "color-luts", Yaml::Array(color_luts));
+    }
 }
 
 fn write_filter_datas(
@@ -352,10 +367,34 @@ fn write_filter_datas(
     yaml_node(parent, name, Yaml::Array(filter_datas));
 }
 
+fn light_source_node(parent: &mut Table, key: &str, value: LightSource) {
+    let mut table = new_table();
+    match value {
+        LightSource::Distant { azimuth, elevation } => {
+            str_node(&mut table, "type", "distant");
+            f32_node(&mut table, "azimuth", azimuth);
+            f32_node(&mut table, "elevation", elevation);
+        }
+        LightSource::Point { position } => {
+            str_node(&mut table, "type", "point");
+            f32_vec_node(&mut table, "position", &position.to_array());
+        }
+        LightSource::Spot { position, points_at, specular_exponent, limiting_cone_angle } => {
+            str_node(&mut table, "type", "spot");
+            f32_vec_node(&mut table, "position", &position.to_array());
+            f32_vec_node(&mut table, "points-at", &points_at.to_array());
+            f32_node(&mut table, "specular-exponent", specular_exponent);
+            f32_node(&mut table, "limiting-cone-angle", limiting_cone_angle);
+        }
+    }
+    table_node(parent, key, table);
+}
+
 fn write_filter_primitives(
     parent: &mut Table,
     name: &str,
     filter_primitive_iter: impl IntoIterator<Item = FilterPrimitive>,
+    mut path_for_image: impl FnMut(ImageKey) -> Option<PathBuf>,
 ) {
     let mut filter_primitives = vec![];
     for filter_primitive in filter_primitive_iter {
@@ -378,7 +417,7 @@ fn write_filter_primitives(
             FilterPrimitiveKind::Blur(blur_primitive) => {
                 yaml_node(&mut table, "type", Yaml::String("blur".into()));
                 filter_input_node(&mut table, "in", blur_primitive.input);
-                f32_node(&mut table, "radius", blur_primitive.radius);
+                yaml_node(&mut table, "radius", f32_vec_yaml(&[blur_primitive.width, blur_primitive.height], true));
             }
             FilterPrimitiveKind::Opacity(opacity_primitive) => {
                 yaml_node(&mut table, "type", Yaml::String("opacity".into()));
@@ -426,6 +465,75 @@ fn write_filter_primitives(
                     f32_vec_node(&mut table, "k-values", &k_vals);
                 }
             }
+            FilterPrimitiveKind::Morphology(info) => {
+                yaml_node(&mut table, "type", Yaml::String("morphology".into()));
+                filter_input_node(&mut table, "in", info.input);
+                f32_node(&mut table, "radius-x", info.radius_x);
+                f32_node(&mut table, "radius-y", info.radius_y);
+                enum_node(&mut table, "operator", info.operator);
+            }
+            FilterPrimitiveKind::ConvolveMatrix(info) => {
+                yaml_node(&mut table, "type", Yaml::String("convolve-matrix".into()));
+                filter_input_node(&mut table, "in", info.input);
+                u32_vec_node(&mut table, "order", &info.order);
+                let kernel_len = (info.order[0] * info.order[1]) as usize;
+                f32_vec_node(&mut table, "kernel", &info.kernel[.. kernel_len]);
+                f32_node(&mut table, "divisor", info.divisor);
+                f32_node(&mut table, "bias", info.bias);
+                u32_vec_node(&mut table, "target", &info.target);
+                enum_node(&mut table, "edge-mode", info.edge_mode);
+                bool_node(&mut table, "preserve-alpha", info.preserve_alpha);
+            }
+            FilterPrimitiveKind::Turbulence(info) => {
+                yaml_node(&mut table, "type", Yaml::String("turbulence".into()));
+                f32_vec_node(&mut table, "base-frequency", &[info.base_frequency_x, info.base_frequency_y]);
+                u32_node(&mut table, "num-octaves", info.num_octaves);
+                i32_node(&mut table, "seed", info.seed);
+                bool_node(&mut table, "stitch-tiles", info.stitch_tiles);
+                enum_node(&mut table, "noise-type", info.noise_type);
+            }
+            FilterPrimitiveKind::DisplacementMap(info) => {
+                yaml_node(&mut table, "type", Yaml::String("displacement-map".into()));
+                filter_input_node(&mut table, "in1", info.input1);
+                filter_input_node(&mut table, "in2", info.input2);
+                f32_node(&mut table, "scale", info.scale);
+                enum_node(&mut table, "x-channel-selector", info.x_channel_selector);
+                enum_node(&mut table, "y-channel-selector", info.y_channel_selector);
+            }
+            FilterPrimitiveKind::DiffuseLighting(info) => {
+                yaml_node(&mut table, "type", Yaml::String("diffuse-lighting".into()));
+                filter_input_node(&mut table, "in", info.input);
+                f32_node(&mut table, "surface-scale", info.surface_scale);
+                f32_node(&mut table, "diffuse-constant", info.diffuse_constant);
+                color_node(&mut table, "lighting-color", info.lighting_color);
+                light_source_node(&mut table, "light", info.light);
+            }
+            FilterPrimitiveKind::SpecularLighting(info) => {
+                yaml_node(&mut table, "type", Yaml::String("specular-lighting".into()));
+                filter_input_node(&mut table, "in", info.input);
+                f32_node(&mut table, "surface-scale", info.surface_scale);
+                f32_node(&mut table, "specular-constant", info.specular_constant);
+                f32_node(&mut table, "specular-exponent", info.specular_exponent);
+                color_node(&mut table, "lighting-color", info.lighting_color);
+                light_source_node(&mut table, "light", info.light);
+            }
+            FilterPrimitiveKind::Image(info) => {
+                yaml_node(&mut table, "type", Yaml::String("image".into()));
+                if let Some(path) = path_for_image(info.image_key) {
+                    path_node(&mut table, "src", &path);
+                }
+                rect_node(&mut table, "rect", &info.rect);
+            }
+            FilterPrimitiveKind::Tile(info) => {
+                yaml_node(&mut table, "type", Yaml::String("tile".into()));
+                filter_input_node(&mut table, "in", info.input);
+                rect_node(&mut table, "source-rect", &info.source_rect);
+            }
+            FilterPrimitiveKind::Merge(info) => {
+                yaml_node(&mut table, "type", Yaml::String("merge".into()));
+                let inputs = info.inputs().iter().map(|&input| filter_input_to_string(input)).collect::<Vec<_>>();
+                yaml_node(&mut table, "inputs", string_vec_yaml(&inputs, false));
+            }
         }
         enum_node(&mut table, "color-space", filter_primitive.color_space);
         filter_primitives.push(Yaml::Hash(table));
@@ -441,6 +549,7 @@ fn write_stacking_context(
     filter_iter: impl IntoIterator<Item = FilterOp>,
     filter_data_iter: &[TempFilterData],
     filter_primitive_iter: impl IntoIterator<Item = FilterPrimitive>,
+    mut path_for_image: impl FnMut(ImageKey) -> Option<PathBuf>,
 ) {
     enum_node(parent, "transform-style", sc.transform_style);
 
@@ -459,9 +568,17 @@ fn write_stacking_context(
         enum_node(parent, "mix-blend-mode", sc.mix_blend_mode)
     }
 
-    write_filters(parent, "filters", filter_iter, properties);
+    write_filters(parent, "filters", filter_iter, properties, &mut path_for_image);
     write_filter_datas(parent, "filter-datas", filter_data_iter);
-    write_filter_primitives(parent, "filter-primitives", filter_primitive_iter);
+    write_filter_primitives(parent, "filter-primitives", filter_primitive_iter, path_for_image);
+
+    if let Some(key) = sc.snapshot {
+        str_node(parent, "snapshot", &snapshot_name(key));
+    }
+}
+
+fn snapshot_name(key: ImageKey) -> String {
+    format!("snapshot-{}", key.1)
 }
 
 #[cfg(target_os = "macos")]
@@ -503,6 +620,23 @@ fn native_font_handle_to_yaml(
     }
 }
 
+fn gradient_midpoints_node(parent: &mut Table, stops_range: ItemRange<GradientStop>) {
+    if stops_range.iter().any(|stop| stop.midpoint.is_some()) {
+        let midpoints: Vec<f32> = stops_range
+            .iter()
+            .skip(1)
+            .map(|stop| stop.midpoint.unwrap_or(0.5))
+            .collect();
+        f32_vec_node(parent, "midpoints", &midpoints);
+    }
+}
+
+fn gradient_color_space_node(parent: &mut Table, color_space: GradientColorSpace) {
+    if color_space != GradientColorSpace::Srgb {
+        enum_node(parent, "color-space", color_space);
+    }
+}
+
 fn radial_gradient_to_yaml(
     table: &mut Table,
     gradient: &webrender::api::RadialGradient,
@@ -523,9 +657,53 @@ fn radial_gradient_to_yaml(
         denormalized_stops.push(Yaml::String(color_to_string(stop.color)));
     }
     yaml_node(table, "stops", Yaml::Array(denormalized_stops));
+    gradient_midpoints_node(table, stops_range);
+    bool_node(table, "repeat", gradient.extend_mode == ExtendMode::Repeat);
+    gradient_color_space_node(table, gradient.color_space);
+}
+
+fn conic_gradient_to_yaml(
+    table: &mut Table,
+    gradient: &webrender::api::ConicGradient,
+    stops_range: ItemRange<GradientStop>,
+) {
+    point_node(table, "center", &gradient.center);
+    f32_node(table, "angle", gradient.angle.to_degrees());
+
+    let first_offset = gradient.start_offset;
+    let last_offset = gradient.end_offset;
+    let stops_delta = last_offset - first_offset;
+    assert!(first_offset <= last_offset);
+
+    let mut denormalized_stops = vec![];
+    for stop in stops_range {
+        let denormalized_stop = (stop.offset * stops_delta) + first_offset;
+        denormalized_stops.push(Yaml::Real(denormalized_stop.to_string()));
+        denormalized_stops.push(Yaml::String(color_to_string(stop.color)));
+    }
+    yaml_node(table, "stops", Yaml::Array(denormalized_stops));
+    gradient_midpoints_node(table, stops_range);
     bool_node(table, "repeat", gradient.extend_mode == ExtendMode::Repeat);
 }
 
+fn path_commands_to_string(commands: ItemRange<PathCommand>) -> String {
+    let mut parts = vec![];
+    for command in commands {
+        match command {
+            PathCommand::MoveTo(p) => parts.push(format!("M {} {}", p.x, p.y)),
+            PathCommand::LineTo(p) => parts.push(format!("L {} {}", p.x, p.y)),
+            PathCommand::QuadTo(c, p) => {
+                parts.push(format!("Q {} {} {} {}", c.x, c.y, p.x, p.y))
+            }
+            PathCommand::CubicTo(c1, c2, p) => {
+                parts.push(format!("C {} {} {} {} {} {}", c1.x, c1.y, c2.x, c2.y, p.x, p.y))
+            }
+            PathCommand::Close => parts.push("Z".to_owned()),
+        }
+    }
+    parts.join(" ")
+}
+
 enum CachedFont {
     Native(NativeFontHandle, Option<PathBuf>),
     Raw(Option<Vec<u8>>, u32, Option<PathBuf>),
@@ -571,6 +749,7 @@ pub struct YamlFrameWriter {
     frame_base: PathBuf,
     rsrc_gen: ResourceGenerator,
     images: HashMap<ImageKey, CachedImage>,
+    snapshots: HashSet<ImageKey>,
     fonts: HashMap<FontKey, CachedFont>,
     font_instances: HashMap<FontInstanceKey, CachedFontInstance>,
 
@@ -656,6 +835,7 @@ impl YamlFrameWriter {
                 next_num: 1,
             },
             images: HashMap::new(),
+            snapshots: HashSet::new(),
             fonts: HashMap::new(),
             font_instances: HashMap::new(),
 
@@ -694,7 +874,7 @@ impl YamlFrameWriter {
 
         let payload = Payload::from_data(data);
 
-        let dl = BuiltDisplayList::from_data(payload.display_list_data, dl_desc);
+        let dl = BuiltDisplayList::from_data(payload.display_list_data, dl_desc).unwrap();
 
         let mut root_dl_table = new_table();
         {
@@ -811,6 +991,10 @@ impl YamlFrameWriter {
                 }
                 ResourceUpdate::DeleteImage(img) => {
                     self.images.remove(&img);
+                    self.snapshots.remove(&img);
+                }
+                ResourceUpdate::AddSnapshotImage(img) => {
+                    self.snapshots.insert(img);
                 }
                 ResourceUpdate::AddFont(ref font) => match font {
                     &AddFont::Raw(key, ref bytes, index) => {
@@ -939,6 +1123,49 @@ impl YamlFrameWriter {
         Some(Yaml::Array(complex_items))
     }
 
+    fn write_font(&mut self, v: &mut Table, font_key: FontInstanceKey) {
+        let instance = self.font_instances.entry(font_key).or_insert_with(|| {
+            println!("Warning: font instance key not found in font instances table!");
+            CachedFontInstance {
+                font_key: FontKey::new(IdNamespace(0), 0),
+                glyph_size: Au::from_px(16),
+            }
+        });
+
+        f32_node(
+            v,
+            "size",
+            instance.glyph_size.to_f32_px() * 12.0 / 16.0,
+        );
+
+        let entry = self.fonts.entry(instance.font_key).or_insert_with(|| {
+            println!("Warning: font key not found in fonts table!");
+            CachedFont::Raw(Some(vec![]), 0, None)
+        });
+
+        match entry {
+            &mut CachedFont::Native(ref handle, ref mut path_opt) => {
+                native_font_handle_to_yaml(&mut self.rsrc_gen, handle, v, path_opt);
+            }
+            &mut CachedFont::Raw(ref mut bytes_opt, index, ref mut path_opt) => {
+                if let Some(bytes) = bytes_opt.take() {
+                    let (path_file, path) = self.rsrc_gen.next_rsrc_paths(
+                        "font",
+                        "ttf",
+                    );
+                    let mut file = fs::File::create(&path_file).unwrap();
+                    file.write_all(&bytes).unwrap();
+                    *path_opt = Some(path);
+                }
+
+                path_node(v, "font", path_opt.as_ref().unwrap());
+                if index != 0 {
+                    u32_node(v, "font-index", index);
+                }
+            }
+        }
+    }
+
     fn make_clip_mask_image_node(&mut self, image_mask: &Option<ImageMask>) -> Option<Yaml> {
         let mask = match image_mask {
             &Some(ref mask) => mask,
@@ -951,6 +1178,12 @@ impl YamlFrameWriter {
         }
         rect_node(&mut mask_table, "rect", &mask.rect);
         bool_node(&mut mask_table, "repeat", mask.repeat);
+        if mask.mode != MaskMode::Alpha {
+            enum_node(&mut mask_table, "mask-mode", mask.mode);
+        }
+        if mask.composite_op != MaskCompositeOperator::Intersect {
+            enum_node(&mut mask_table, "mask-composite", mask.composite_op);
+        }
         Some(Yaml::Hash(mask_table))
     }
 
@@ -978,7 +1211,7 @@ impl YamlFrameWriter {
                 DisplayItem::Rectangle(item) => {
                     str_node(&mut v, "type", "rect");
                     common_node(&mut v, clip_id_mapper, &item.common);
-                    color_node(&mut v, "color", item.color);
+                    color_node(&mut v, "color", scene.properties.resolve_color(&item.color));
                 }
                 DisplayItem::HitTest(item) => {
                     str_node(&mut v, "type", "hit-test");
@@ -1011,53 +1244,15 @@ impl YamlFrameWriter {
                     rect_node(&mut v, "bounds", &item.bounds);
                     u32_vec_node(&mut v, "glyphs", &indices);
                     f32_vec_node(&mut v, "offsets", &offsets);
-
-                    let instance = self.font_instances.entry(item.font_key).or_insert_with(|| {
-                        println!("Warning: font instance key not found in font instances table!");
-                        CachedFontInstance {
-                            font_key: FontKey::new(IdNamespace(0), 0),
-                            glyph_size: Au::from_px(16),
-                        }
-                    });
-
-                    f32_node(
-                        &mut v,
-                        "size",
-                        instance.glyph_size.to_f32_px() * 12.0 / 16.0,
-                    );
+                    self.write_font(&mut v, item.font_key);
                     color_node(&mut v, "color", item.color);
-
-                    let entry = self.fonts.entry(instance.font_key).or_insert_with(|| {
-                        println!("Warning: font key not found in fonts table!");
-                        CachedFont::Raw(Some(vec![]), 0, None)
-                    });
-
-                    match entry {
-                        &mut CachedFont::Native(ref handle, ref mut path_opt) => {
-                            native_font_handle_to_yaml(&mut self.rsrc_gen, handle, &mut v, path_opt);
-                        }
-                        &mut CachedFont::Raw(ref mut bytes_opt, index, ref mut path_opt) => {
-                            if let Some(bytes) = bytes_opt.take() {
-                                let (path_file, path) = self.rsrc_gen.next_rsrc_paths(
-                                    "font",
-                                    "ttf",
-                                );
-                                let mut file = fs::File::create(&path_file).unwrap();
-                                file.write_all(&bytes).unwrap();
-                                *path_opt = Some(path);
-                            }
-
-                            path_node(&mut v, "font", path_opt.as_ref().unwrap());
-                            if index != 0 {
-                                u32_node(&mut v, "font-index", index);
-                            }
-                        }
-                    }
                 }
                 DisplayItem::Image(item) => {
                     common_node(&mut v, clip_id_mapper, &item.common);
                     rect_node(&mut v, "bounds", &item.bounds);
-                    if let Some(path) = self.path_for_image(item.image_key) {
+                    if self.snapshots.contains(&item.image_key) {
+                        str_node(&mut v, "snapshot", &snapshot_name(item.image_key));
+                    } else if let Some(path) = self.path_for_image(item.image_key) {
                         path_node(&mut v, "image", &path);
                     }
                     if let Some(&CachedImage {
@@ -1181,7 +1376,9 @@ impl YamlFrameWriter {
                                         stops.push(Yaml::String(color_to_string(stop.color)));
                                     }
                                     yaml_node(&mut v, "stops", Yaml::Array(stops));
+                                    gradient_midpoints_node(&mut v, base.gradient_stops());
                                     bool_node(&mut v, "repeat", gradient.extend_mode == ExtendMode::Repeat);
+                                    gradient_color_space_node(&mut v, gradient.color_space);
                                 }
                                 NinePatchBorderSource::RadialGradient(gradient) => {
                                     str_node(&mut v, "border-type", "radial-gradient");
@@ -1255,11 +1452,13 @@ impl YamlFrameWriter {
                         stops.push(Yaml::String(color_to_string(stop.color)));
                     }
                     yaml_node(&mut v, "stops", Yaml::Array(stops));
+                    gradient_midpoints_node(&mut v, base.gradient_stops());
                     bool_node(
                         &mut v,
                         "repeat",
                         item.gradient.extend_mode == ExtendMode::Repeat,
                     );
+                    gradient_color_space_node(&mut v, item.gradient.color_space);
                 }
                 DisplayItem::RadialGradient(item) => {
                     str_node(&mut v, "type", "radial-gradient");
@@ -1273,6 +1472,46 @@ impl YamlFrameWriter {
                         base.gradient_stops(),
                     );
                 }
+                DisplayItem::ConicGradient(item) => {
+                    str_node(&mut v, "type", "conic-gradient");
+                    rect_node(&mut v, "bounds", &item.bounds);
+                    common_node(&mut v, clip_id_mapper, &item.common);
+                    size_node(&mut v, "tile-size", &item.tile_size);
+                    size_node(&mut v, "tile-spacing", &item.tile_spacing);
+                    conic_gradient_to_yaml(
+                        &mut v,
+                        &item.gradient,
+                        base.gradient_stops(),
+                    );
+                }
+                DisplayItem::Path(item) => {
+                    str_node(&mut v, "type", "path");
+                    rect_node(&mut v, "bounds", &item.bounds);
+                    common_node(&mut v, clip_id_mapper, &item.common);
+                    str_node(&mut v, "fill-rule", item.fill_rule.as_str());
+                    str_node(&mut v, "commands", &path_commands_to_string(base.path_commands()));
+                    match item.paint {
+                        PathPaint::Color(color) => {
+                            color_node(&mut v, "color", color);
+                        }
+                        PathPaint::Gradient(gradient) => {
+                            point_node(&mut v, "start", &gradient.start_point);
+                            point_node(&mut v, "end", &gradient.end_point);
+                            let mut stops = vec![];
+                            for stop in base.gradient_stops() {
+                                stops.push(Yaml::Real(stop.offset.to_string()));
+                                stops.push(Yaml::String(color_to_string(stop.color)));
+                            }
+                            yaml_node(&mut v, "stops", Yaml::Array(stops));
+                            gradient_midpoints_node(&mut v, base.gradient_stops());
+                            bool_node(
+                                &mut v,
+                                "repeat",
+                                gradient.extend_mode == ExtendMode::Repeat,
+                            );
+                        }
+                    }
+                }
                 DisplayItem::Iframe(item) => {
                     str_node(&mut v, "type", "iframe");
                     rect_node(&mut v, "bounds", &item.bounds);
@@ -1314,6 +1553,7 @@ impl YamlFrameWriter {
                         base.filters(),
                         base.filter_datas(),
                         base.filter_primitives(),
+                        |key| self.path_for_image(key),
                     );
 
                     let mut sub_iter = base.sub_iter();
@@ -1352,6 +1592,29 @@ impl YamlFrameWriter {
                     if let Some(mask_yaml) = self.make_clip_mask_image_node(&item.image_mask) {
                         yaml_node(&mut v, "image-mask", mask_yaml);
                     }
+
+                    if let Some(path_clip) = item.path_clip {
+                        let mut path_table = new_table();
+                        str_node(&mut path_table, "commands", &path_commands_to_string(base.path_commands()));
+                        str_node(&mut path_table, "fill-rule", path_clip.fill_rule.as_str());
+                        enum_node(&mut path_table, "clip-mode", path_clip.mode);
+                        yaml_node(&mut v, "path-clip", Yaml::Hash(path_table));
+                    }
+
+                    if let Some(text_clip) = item.text_clip {
+                        let mut indices: Vec<u32> = vec![];
+                        let mut offsets: Vec<f32> = vec![];
+                        for g in base.glyphs() {
+                            indices.push(g.index);
+                            offsets.push(g.point.x);
+                            offsets.push(g.point.y);
+                        }
+                        let mut text_table = new_table();
+                        u32_vec_node(&mut text_table, "glyphs", &indices);
+                        f32_vec_node(&mut text_table, "offsets", &offsets);
+                        self.write_font(&mut text_table, text_clip.font_key);
+                        yaml_node(&mut v, "text-clip", Yaml::Hash(text_table));
+                    }
                 }
                 DisplayItem::ClipChain(item) => {
                     str_node(&mut v, "type", "clip-chain");
@@ -1375,6 +1638,9 @@ impl YamlFrameWriter {
                     size_node(&mut v, "content-size", &item.content_rect.size);
                     rect_node(&mut v, "bounds", &item.clip_rect);
                     vector_node(&mut v, "external-scroll-offset", &item.external_scroll_offset);
+                    if item.overscroll_behavior != OverscrollBehavior::Auto {
+                        enum_node(&mut v, "overscroll-behavior", item.overscroll_behavior);
+                    }
 
                     if let Some(complex) = self.make_complex_clips_node(
                         base.complex_clip()
@@ -1426,9 +1692,20 @@ impl YamlFrameWriter {
                     str_node(&mut v, "type", "backdrop-filter");
                     common_node(&mut v, clip_id_mapper, &item.common);
 
-                    write_filters(&mut v, "filters", base.filters(), &scene.properties);
+                    write_filters(
+                        &mut v,
+                        "filters",
+                        base.filters(),
+                        &scene.properties,
+                        |key| self.path_for_image(key),
+                    );
                     write_filter_datas(&mut v, "filter-datas", base.filter_datas());
-                    write_filter_primitives(&mut v, "filter-primitives", base.filter_primitives());
+                    write_filter_primitives(
+                        &mut v,
+                        "filter-primitives",
+                        base.filter_primitives(),
+                        |key| self.path_for_image(key),
+                    );
                 }
 
                 DisplayItem::PopReferenceFrame |
@@ -1437,7 +1714,8 @@ impl YamlFrameWriter {
                 DisplayItem::SetGradientStops |
                 DisplayItem::SetFilterOps |
                 DisplayItem::SetFilterData |
-                DisplayItem::SetFilterPrimitives => panic!("dummy item yielded?"),
+                DisplayItem::SetFilterPrimitives |
+                DisplayItem::SetItemKey(..) => panic!("dummy item yielded?"),
 
                 DisplayItem::PushShadow(item) => {
                     str_node(&mut v, "type", "shadow");
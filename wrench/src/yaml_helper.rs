@@ -41,7 +41,14 @@ pub trait YamlHelper {
     fn as_filter_input(&self) -> Option<FilterPrimitiveInput>;
     fn as_filter_primitive(&self) -> Option<FilterPrimitive>;
     fn as_vec_filter_primitive(&self) -> Option<Vec<FilterPrimitive>>;
+    fn as_light_source(&self) -> Option<LightSource>;
     fn as_color_space(&self) -> Option<ColorSpace>;
+    fn as_gradient_color_space(&self) -> Option<GradientColorSpace>;
+    fn as_fill_rule(&self) -> Option<FillRule>;
+    fn as_mask_mode(&self) -> Option<MaskMode>;
+    fn as_mask_composite_operator(&self) -> Option<MaskCompositeOperator>;
+    fn as_overscroll_behavior(&self) -> Option<OverscrollBehavior>;
+    fn as_path_commands(&self) -> Option<Vec<PathCommand>>;
 }
 
 fn string_to_color(color: &str) -> Option<ColorF> {
@@ -145,6 +152,25 @@ define_string_enum!(
 
 define_string_enum!(ClipMode, [Clip = "clip", ClipOut = "clip-out"]);
 
+define_string_enum!(FillRule, [NonZero = "nonzero", EvenOdd = "evenodd"]);
+
+define_string_enum!(MaskMode, [Alpha = "alpha", Luminance = "luminance"]);
+
+define_string_enum!(
+    OverscrollBehavior,
+    [Auto = "auto", Contain = "contain", None = "none"]
+);
+
+define_string_enum!(
+    MaskCompositeOperator,
+    [
+        Add = "add",
+        Subtract = "subtract",
+        Intersect = "intersect",
+        Exclude = "exclude"
+    ]
+);
+
 define_string_enum!(
     ComponentTransferFuncType,
     [
@@ -164,6 +190,45 @@ define_string_enum!(
     ]
 );
 
+define_string_enum!(
+    GradientColorSpace,
+    [
+        Srgb = "srgb",
+        LinearSrgb = "linear-srgb",
+        Oklab = "oklab",
+        Oklch = "oklch"
+    ]
+);
+
+define_string_enum!(MorphologyOperator, [Erode = "erode", Dilate = "dilate"]);
+
+define_string_enum!(
+    ColorChannel,
+    [
+        Red = "r",
+        Green = "g",
+        Blue = "b",
+        Alpha = "a"
+    ]
+);
+
+define_string_enum!(
+    TurbulenceType,
+    [
+        FractalNoise = "fractal-noise",
+        Turbulence = "turbulence"
+    ]
+);
+
+define_string_enum!(
+    ConvolveEdgeMode,
+    [
+        Duplicate = "duplicate",
+        Wrap = "wrap",
+        None = "none"
+    ]
+);
+
 // Rotate around `axis` by `degrees` angle
 fn make_rotation(
     origin: &LayoutPoint,
@@ -580,7 +645,11 @@ impl YamlHelper for Yaml {
                     Some(FilterOp::ComponentTransfer)
                 }
                 ("blur", ref args, _) if args.len() == 1 => {
-                    Some(FilterOp::Blur(args[0].parse().unwrap()))
+                    let radius = args[0].parse().unwrap();
+                    Some(FilterOp::Blur(radius, radius))
+                }
+                ("blur", ref args, _) if args.len() == 2 => {
+                    Some(FilterOp::Blur(args[0].parse().unwrap(), args[1].parse().unwrap()))
                 }
                 ("brightness", ref args, _) if args.len() == 1 => {
                     Some(FilterOp::Brightness(args[0].parse().unwrap()))
@@ -631,6 +700,14 @@ impl YamlHelper for Yaml {
                     let yaml = yaml_doc.pop().unwrap();
                     Some(FilterOp::Flood(yaml["color"].as_colorf().unwrap()))
                 }
+                ("color-lut-3d", ref args, _) if args.len() == 1 => {
+                    // The table itself is listed under `color-luts` and resolved by the
+                    // frame reader.
+                    Some(FilterOp::ColorLut3D {
+                        lut: ImageKey::DUMMY,
+                        size: args[0].parse().unwrap(),
+                    })
+                }
                 (_, _, _) => None,
             }
         } else {
@@ -733,9 +810,11 @@ impl YamlHelper for Yaml {
                     })
                 }
                 "blur" => {
+                    let radius = self["radius"].as_vec_f32().unwrap();
                     FilterPrimitiveKind::Blur(BlurPrimitive {
                         input: self["in"].as_filter_input().unwrap(),
-                        radius: self["radius"].as_f32().unwrap(),
+                        width: radius[0],
+                        height: *radius.get(1).unwrap_or(&radius[0]),
                     })
                 }
                 "opacity" => {
@@ -797,6 +876,115 @@ impl YamlHelper for Yaml {
                         operator,
                     })
                 }
+                "morphology" => {
+                    FilterPrimitiveKind::Morphology(MorphologyPrimitive {
+                        input: self["in"].as_filter_input().unwrap(),
+                        radius_x: self["radius-x"].as_f32().unwrap(),
+                        radius_y: self["radius-y"].as_f32().unwrap(),
+                        operator: self["operator"].as_str()
+                            .and_then(|x| StringEnum::from_str(x))
+                            .unwrap_or(MorphologyOperator::Erode),
+                    })
+                }
+                "convolve-matrix" => {
+                    let order = self["order"].as_vec_u32().unwrap();
+                    assert!(order.len() == 2, "Must be 2 values for convolve matrix order");
+                    let k: Vec<f32> = self["kernel"].as_vec_f32().unwrap();
+                    assert!(
+                        k.len() as u32 == order[0] * order[1],
+                        "Convolve matrix kernel size must match its order",
+                    );
+                    let mut kernel = [0.0; 25];
+                    kernel[.. k.len()].clone_from_slice(&k);
+                    let target = match self["target"].as_vec_u32() {
+                        Some(target) => [target[0], target[1]],
+                        None => [order[0] / 2, order[1] / 2],
+                    };
+
+                    FilterPrimitiveKind::ConvolveMatrix(ConvolveMatrixPrimitive {
+                        input: self["in"].as_filter_input().unwrap(),
+                        order: [order[0], order[1]],
+                        kernel,
+                        divisor: self["divisor"].as_f32().unwrap_or(0.0),
+                        bias: self["bias"].as_f32().unwrap_or(0.0),
+                        target,
+                        edge_mode: self["edge-mode"].as_str()
+                            .and_then(|x| StringEnum::from_str(x))
+                            .unwrap_or(ConvolveEdgeMode::Duplicate),
+                        preserve_alpha: self["preserve-alpha"].as_bool().unwrap_or(false),
+                    })
+                }
+                "displacement-map" => {
+                    FilterPrimitiveKind::DisplacementMap(DisplacementMapPrimitive {
+                        input1: self["in1"].as_filter_input().unwrap(),
+                        input2: self["in2"].as_filter_input().unwrap(),
+                        scale: self["scale"].as_f32().unwrap_or(0.0),
+                        x_channel_selector: self["x-channel-selector"].as_str()
+                            .and_then(|x| StringEnum::from_str(x))
+                            .unwrap_or(ColorChannel::Alpha),
+                        y_channel_selector: self["y-channel-selector"].as_str()
+                            .and_then(|x| StringEnum::from_str(x))
+                            .unwrap_or(ColorChannel::Alpha),
+                    })
+                }
+                "image" => {
+                    // The image key is filled in by the frame reader, which
+                    // loads the image from the "src" path.
+                    FilterPrimitiveKind::Image(ImagePrimitive {
+                        image_key: ImageKey::DUMMY,
+                        rect: self["rect"].as_rect().unwrap(),
+                    })
+                }
+                "tile" => {
+                    FilterPrimitiveKind::Tile(TilePrimitive {
+                        input: self["in"].as_filter_input().unwrap(),
+                        source_rect: self["source-rect"].as_rect().unwrap(),
+                    })
+                }
+                "merge" => {
+                    let inputs: Vec<_> = self["inputs"]
+                        .as_vec()
+                        .unwrap()
+                        .iter()
+                        .map(|input| input.as_filter_input().unwrap())
+                        .collect();
+                    FilterPrimitiveKind::Merge(MergePrimitive::new(&inputs))
+                }
+                "diffuse-lighting" => {
+                    FilterPrimitiveKind::DiffuseLighting(DiffuseLightingPrimitive {
+                        input: self["in"].as_filter_input().unwrap(),
+                        surface_scale: self["surface-scale"].as_f32().unwrap_or(1.0),
+                        diffuse_constant: self["diffuse-constant"].as_f32().unwrap_or(1.0),
+                        lighting_color: self["lighting-color"].as_colorf().unwrap_or(ColorF::WHITE),
+                        light: self["light"].as_light_source().unwrap(),
+                    })
+                }
+                "specular-lighting" => {
+                    FilterPrimitiveKind::SpecularLighting(SpecularLightingPrimitive {
+                        input: self["in"].as_filter_input().unwrap(),
+                        surface_scale: self["surface-scale"].as_f32().unwrap_or(1.0),
+                        specular_constant: self["specular-constant"].as_f32().unwrap_or(1.0),
+                        specular_exponent: self["specular-exponent"].as_f32().unwrap_or(1.0),
+                        lighting_color: self["lighting-color"].as_colorf().unwrap_or(ColorF::WHITE),
+                        light: self["light"].as_light_source().unwrap(),
+                    })
+                }
+                "turbulence" => {
+                    // A single base frequency applies to both axes.
+                    let base_frequency = self["base-frequency"].as_vec_f32().unwrap();
+                    let base_frequency_y = *base_frequency.get(1).unwrap_or(&base_frequency[0]);
+
+                    FilterPrimitiveKind::Turbulence(TurbulencePrimitive {
+                        base_frequency_x: base_frequency[0],
+                        base_frequency_y,
+                        num_octaves: self["num-octaves"].as_i64().unwrap_or(1) as u32,
+                        seed: self["seed"].as_i64().unwrap_or(0) as i32,
+                        stitch_tiles: self["stitch-tiles"].as_bool().unwrap_or(false),
+                        noise_type: self["noise-type"].as_str()
+                            .and_then(|x| StringEnum::from_str(x))
+                            .unwrap_or(TurbulenceType::Turbulence),
+                    })
+                }
                 _ => return None,
             };
 
@@ -817,7 +1005,86 @@ impl YamlHelper for Yaml {
         }
     }
 
+    fn as_light_source(&self) -> Option<LightSource> {
+        let as_point = |yaml: &Yaml| {
+            let v = yaml.as_vec_f32().unwrap();
+            assert!(v.len() == 3, "Light positions must have 3 values");
+            LayoutPoint3D::new(v[0], v[1], v[2])
+        };
+
+        let light = match self["type"].as_str()? {
+            "distant" => LightSource::Distant {
+                azimuth: self["azimuth"].as_f32().unwrap_or(0.0),
+                elevation: self["elevation"].as_f32().unwrap_or(0.0),
+            },
+            "point" => LightSource::Point {
+                position: as_point(&self["position"]),
+            },
+            "spot" => LightSource::Spot {
+                position: as_point(&self["position"]),
+                points_at: as_point(&self["points-at"]),
+                specular_exponent: self["specular-exponent"].as_f32().unwrap_or(1.0),
+                limiting_cone_angle: self["limiting-cone-angle"].as_f32().unwrap_or(90.0),
+            },
+            _ => return None,
+        };
+        Some(light)
+    }
+
     fn as_color_space(&self) -> Option<ColorSpace> {
         self.as_str().and_then(|x| StringEnum::from_str(x))
     }
+
+    fn as_gradient_color_space(&self) -> Option<GradientColorSpace> {
+        self.as_str().and_then(|x| StringEnum::from_str(x))
+    }
+
+    fn as_fill_rule(&self) -> Option<FillRule> {
+        self.as_str().and_then(|x| StringEnum::from_str(x))
+    }
+
+    fn as_mask_mode(&self) -> Option<MaskMode> {
+        self.as_str().and_then(|x| StringEnum::from_str(x))
+    }
+
+    fn as_mask_composite_operator(&self) -> Option<MaskCompositeOperator> {
+        self.as_str().and_then(|x| StringEnum::from_str(x))
+    }
+
+    fn as_overscroll_behavior(&self) -> Option<OverscrollBehavior> {
+        self.as_str().and_then(|x| StringEnum::from_str(x))
+    }
+
+    // Parses a subset of the SVG path syntax, with absolute M, L, Q, C
+    // and Z commands separated by whitespace, e.g. "M 0 0 L 10 0 Z".
+    fn as_path_commands(&self) -> Option<Vec<PathCommand>> {
+        let mut tokens = self.as_str()?.split_whitespace();
+        let mut commands = Vec::new();
+
+        fn point<'a, I: Iterator<Item = &'a str>>(tokens: &mut I) -> Option<LayoutPoint> {
+            let x = f32::from_str(tokens.next()?).ok()?;
+            let y = f32::from_str(tokens.next()?).ok()?;
+            Some(LayoutPoint::new(x, y))
+        }
+
+        while let Some(command) = tokens.next() {
+            commands.push(match command {
+                "M" => PathCommand::MoveTo(point(&mut tokens)?),
+                "L" => PathCommand::LineTo(point(&mut tokens)?),
+                "Q" => PathCommand::QuadTo(point(&mut tokens)?, point(&mut tokens)?),
+                "C" => PathCommand::CubicTo(
+                    point(&mut tokens)?,
+                    point(&mut tokens)?,
+                    point(&mut tokens)?,
+                ),
+                "Z" => PathCommand::Close,
+                _ => {
+                    println!("Unrecognized path command '{}'", command);
+                    return None;
+                }
+            });
+        }
+
+        Some(commands)
+    }
 }